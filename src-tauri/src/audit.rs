@@ -0,0 +1,137 @@
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use crate::warn;
+
+/// 审计日志的文件名，与数据文件放在同一目录
+const AUDIT_FILE_NAME: &str = "audit.jsonl";
+
+/// 审计记录的操作类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AuditAction {
+    Add,
+    Update,
+    Delete,
+    /// 解密密码、备注、附件或两步验证密钥
+    Decrypt,
+    /// 导出备份或明文数据
+    Export,
+    Import,
+}
+
+/// 一条审计记录，只包含操作类型和条目id，不包含密码、密钥等敏感内容
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AuditEvent {
+    pub timestamp: DateTime<Utc>,
+    pub action: AuditAction,
+    /// 涉及多个条目的操作（如导出）为 `None`
+    pub entry_id: Option<String>,
+}
+
+/// 数据文件所在目录下的审计日志路径
+pub fn audit_path(data_path: &Path) -> PathBuf {
+    data_path.with_file_name(AUDIT_FILE_NAME)
+}
+
+/// 只追加的审计日志，每行一条JSON格式的 `AuditEvent`
+pub struct AuditLog {
+    path: PathBuf,
+    // 保证多个操作同时写入时每行完整
+    write_lock: Mutex<()>,
+}
+
+impl AuditLog {
+    pub fn new(path: PathBuf) -> Self {
+        Self {
+            path,
+            write_lock: Mutex::new(()),
+        }
+    }
+
+    /// 追加一条记录
+    pub fn record(&self, action: AuditAction, entry_id: Option<&str>) -> io::Result<()> {
+        let event = AuditEvent {
+            timestamp: Utc::now(),
+            action,
+            entry_id: entry_id.map(str::to_string),
+        };
+        let mut line = serde_json::to_string(&event)?;
+        line.push('\n');
+
+        let _guard = self.write_lock.lock().unwrap();
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)?
+            .write_all(line.as_bytes())
+    }
+
+    /// 最近的 `limit` 条记录，按时间从旧到新
+    ///
+    /// 文件不存在时返回空列表，无法解析的行会被跳过
+    pub fn recent(&self, limit: usize) -> Result<Vec<AuditEvent>> {
+        let content = match fs::read_to_string(&self.path) {
+            Ok(content) => content,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(e.into()),
+        };
+
+        let events: Vec<AuditEvent> = content
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .filter_map(|line| match serde_json::from_str(line) {
+                Ok(event) => Some(event),
+                Err(e) => {
+                    warn!("跳过无法解析的审计记录：{}", e);
+                    None
+                }
+            })
+            .collect();
+
+        let skip = events.len().saturating_sub(limit);
+        Ok(events.into_iter().skip(skip).collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::store::mock_store;
+
+    #[test]
+    fn recent_returns_latest_in_order() {
+        let path = mock_store::temp_dir().join(AUDIT_FILE_NAME);
+        let log = AuditLog::new(path.clone());
+        assert!(log.recent(10).unwrap().is_empty());
+
+        log.record(AuditAction::Add, Some("a")).unwrap();
+        log.record(AuditAction::Export, None).unwrap();
+        fs::OpenOptions::new()
+            .append(true)
+            .open(&path)
+            .unwrap()
+            .write_all(b"not json\n")
+            .unwrap();
+        log.record(AuditAction::Delete, Some("a")).unwrap();
+
+        let actions: Vec<AuditAction> = log.recent(10).unwrap().iter().map(|e| e.action).collect();
+        assert_eq!(
+            actions,
+            [AuditAction::Add, AuditAction::Export, AuditAction::Delete]
+        );
+
+        let latest = log.recent(2).unwrap();
+        assert_eq!(latest[0].action, AuditAction::Export);
+        assert_eq!(latest[0].entry_id, None);
+        assert_eq!(latest[1].entry_id.as_deref(), Some("a"));
+    }
+}
@@ -0,0 +1,59 @@
+use anyhow::{Result, anyhow};
+
+use crate::crypto::{self, EncryptedData};
+use crate::store::StorageData;
+
+/// 备份文件头：4字节魔数 + 1字节版本号
+const BACKUP_MAGIC: &[u8; 4] = b"PWBK";
+const BACKUP_VERSION: u8 = 1;
+const HEADER_LEN: usize = BACKUP_MAGIC.len() + 1;
+
+/// 把存储数据序列化并用 `passphrase` 整体加密为备份文件
+pub fn encode_backup(data: &StorageData, passphrase: &str) -> Result<Vec<u8>> {
+    let json = serde_json::to_string(data)?;
+    let encrypted = crypto::encrypt_with_password(&json, passphrase)?;
+
+    let mut bytes = Vec::with_capacity(HEADER_LEN);
+    bytes.extend_from_slice(BACKUP_MAGIC);
+    bytes.push(BACKUP_VERSION);
+    serde_json::to_writer(&mut bytes, &encrypted)?;
+
+    Ok(bytes)
+}
+
+/// 校验文件头并解密备份文件
+pub fn decode_backup(bytes: &[u8], passphrase: &str) -> Result<StorageData> {
+    if bytes.len() < HEADER_LEN || &bytes[..BACKUP_MAGIC.len()] != BACKUP_MAGIC {
+        return Err(anyhow!("不是有效的备份文件"));
+    }
+
+    let version = bytes[BACKUP_MAGIC.len()];
+    if version != BACKUP_VERSION {
+        return Err(anyhow!("不支持的备份版本：{}", version));
+    }
+
+    let encrypted: EncryptedData =
+        serde_json::from_slice(&bytes[HEADER_LEN..]).map_err(|_| anyhow!("备份文件已损坏"))?;
+
+    // 不区分具体原因，避免泄露解密失败的细节
+    let json = crypto::decrypt_with_password(&encrypted, passphrase)
+        .map_err(|_| anyhow!("备份密码错误或文件已损坏"))?;
+
+    serde_json::from_str(&json).map_err(|e| anyhow!("备份内容格式错误：{}", e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rejects_bad_header() {
+        assert!(decode_backup(b"", "pw").is_err());
+        assert!(decode_backup(b"NOPE\x01{}", "pw").is_err());
+
+        let mut bytes = encode_backup(&StorageData::new(), "pw").unwrap();
+        bytes[BACKUP_MAGIC.len()] = 99;
+        let err = decode_backup(&bytes, "pw").unwrap_err();
+        assert!(err.to_string().contains("99"));
+    }
+}
@@ -0,0 +1,224 @@
+use anyhow::{Result, anyhow};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+
+use crate::crypto;
+use crate::csv::RowError;
+use crate::password::{Password, PasswordCreateRequest};
+use crate::totp;
+
+/// Bitwarden 条目类型：登录
+pub const ITEM_TYPE_LOGIN: u8 = 1;
+
+/// Bitwarden 未加密导出文件（1Password 也可导入该格式）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BitwardenExport {
+    #[serde(default)]
+    pub encrypted: bool,
+    #[serde(default)]
+    pub folders: Vec<BitwardenFolder>,
+    pub items: Vec<BitwardenItem>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BitwardenFolder {
+    pub id: String,
+    pub name: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BitwardenItem {
+    #[serde(default)]
+    pub id: Option<String>,
+    #[serde(default)]
+    pub folder_id: Option<String>,
+    #[serde(rename = "type")]
+    pub item_type: u8,
+    pub name: String,
+    #[serde(default)]
+    pub notes: Option<String>,
+    #[serde(default)]
+    pub favorite: bool,
+    #[serde(default)]
+    pub login: Option<BitwardenLogin>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BitwardenLogin {
+    #[serde(default)]
+    pub username: Option<String>,
+    #[serde(default)]
+    pub password: Option<String>,
+    #[serde(default)]
+    pub uris: Vec<BitwardenUri>,
+    #[serde(default)]
+    pub totp: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BitwardenUri {
+    pub uri: String,
+}
+
+/// 把条目解密后转换为 Bitwarden 导出格式的JSON，结果是明文
+///
+/// 第一个标签作为所在文件夹（Bitwarden 每个条目只能属于一个文件夹），
+/// 描述和解密后的备注合并为 `notes`。任何条目无法用 `key` 解密时返回错误，
+/// 避免导出不完整的数据
+pub fn export_bitwarden(passwords: &[Password], key: &str) -> Result<String> {
+    // 文件夹按名称排序，id 使用序号，保证同样的数据导出结果相同
+    let folder_ids: BTreeMap<&str, String> = passwords
+        .iter()
+        .filter_map(|p| p.tags.first().map(String::as_str))
+        .collect::<BTreeSet<_>>()
+        .into_iter()
+        .enumerate()
+        .map(|(i, name)| (name, format!("folder-{}", i + 1)))
+        .collect();
+
+    let folders = folder_ids
+        .iter()
+        .map(|(name, id)| BitwardenFolder {
+            id: id.clone(),
+            name: name.to_string(),
+        })
+        .collect();
+
+    let mut items = Vec::with_capacity(passwords.len());
+    for p in passwords {
+        let decrypt = |data| {
+            crypto::decrypt_with_password(data, key)
+                .map_err(|e| anyhow!("无法解密条目「{}」: {}", p.title, e))
+        };
+
+        let notes = match &p.encrypted_notes {
+            Some(notes) => Some(decrypt(notes)?),
+            None => None,
+        };
+        let notes = [Some(p.description.clone()), notes]
+            .into_iter()
+            .flatten()
+            .filter(|s| !s.is_empty())
+            .collect::<Vec<_>>()
+            .join("\n\n");
+
+        items.push(BitwardenItem {
+            id: Some(p.id.clone()),
+            folder_id: p.tags.first().map(|tag| folder_ids[tag.as_str()].clone()),
+            item_type: ITEM_TYPE_LOGIN,
+            name: p.title.clone(),
+            notes: (!notes.is_empty()).then_some(notes),
+            favorite: p.favorite,
+            login: Some(BitwardenLogin {
+                username: Some(p.username.clone()),
+                password: Some(decrypt(&p.encrypted_password)?),
+                uris: p
+                    .url
+                    .iter()
+                    .map(|uri| BitwardenUri { uri: uri.clone() })
+                    .collect(),
+                totp: match &p.totp_secret {
+                    Some(secret) => Some(decrypt(secret)?),
+                    None => None,
+                },
+            }),
+        });
+    }
+
+    let export = BitwardenExport {
+        encrypted: false,
+        folders,
+        items,
+    };
+    Ok(serde_json::to_string_pretty(&export)?)
+}
+
+/// 解析 Bitwarden 未加密导出的JSON，把登录条目转换为创建请求
+///
+/// 网址取第一个 `uri`，所在文件夹作为标签。没有密码的条目（安全笔记、支付卡等）
+/// 和两步验证密钥无效的条目会被跳过，跳过记录的 `line` 为条目在 `items` 中的
+/// 序号（从1开始）
+pub fn bitwarden_to_requests(
+    json: &str,
+    key: &str,
+) -> Result<(Vec<PasswordCreateRequest>, Vec<RowError>)> {
+    let export: BitwardenExport =
+        serde_json::from_str(json).map_err(|e| anyhow!("无法解析Bitwarden导出文件: {}", e))?;
+    if export.encrypted {
+        return Err(anyhow!(
+            "不支持加密的Bitwarden导出文件，请导出为未加密的JSON"
+        ));
+    }
+
+    let folders: BTreeMap<&str, &str> = export
+        .folders
+        .iter()
+        .map(|f| (f.id.as_str(), f.name.as_str()))
+        .collect();
+
+    let mut requests = Vec::new();
+    let mut skipped = Vec::new();
+
+    for (index, item) in export.items.into_iter().enumerate() {
+        let tag = item
+            .folder_id
+            .as_deref()
+            .and_then(|id| folders.get(id))
+            .map(|name| name.to_string());
+
+        match item_to_request(item, tag, key) {
+            Ok(request) => requests.push(request),
+            Err(e) => skipped.push(RowError {
+                line: index + 1,
+                reason: e.to_string(),
+            }),
+        }
+    }
+
+    Ok((requests, skipped))
+}
+
+fn item_to_request(
+    item: BitwardenItem,
+    tag: Option<String>,
+    key: &str,
+) -> Result<PasswordCreateRequest> {
+    let title = item.name.trim();
+    if title.is_empty() {
+        return Err(anyhow!("标题为空"));
+    }
+    let login = match item.login {
+        Some(login) if item.item_type == ITEM_TYPE_LOGIN => login,
+        _ => return Err(anyhow!("「{}」不是登录条目", title)),
+    };
+    let password = login
+        .password
+        .filter(|p| !p.is_empty())
+        .ok_or_else(|| anyhow!("「{}」没有密码", title))?;
+    let totp_secret = login.totp.filter(|t| !t.is_empty());
+    if let Some(secret) = &totp_secret {
+        totp::decode_base32(secret)
+            .map_err(|e| anyhow!("「{}」的两步验证密钥无效: {}", title, e))?;
+    }
+
+    Ok(PasswordCreateRequest {
+        title: title.to_string(),
+        description: String::new(),
+        tags: tag.into_iter().collect(),
+        username: login.username.unwrap_or_default().trim().to_string(),
+        password,
+        url: login
+            .uris
+            .into_iter()
+            .map(|u| u.uri.trim().to_string())
+            .find(|u| !u.is_empty()),
+        key: key.to_string(),
+        expires_at: None,
+        totp_secret,
+        notes: item.notes.filter(|n| !n.is_empty()),
+        client_id: None,
+        kdf_strength: crypto::KdfStrength::Fast,
+    })
+}
@@ -0,0 +1,63 @@
+use sha1::{Digest, Sha1};
+
+/// HIBP range API 使用的前缀长度
+pub const PREFIX_LEN: usize = 5;
+
+/// 明文的 SHA-1，大写十六进制
+///
+/// 完整的哈希只在本地使用，不能发送或写入日志
+pub fn sha1_hex(plaintext: &str) -> String {
+    Sha1::digest(plaintext.as_bytes())
+        .iter()
+        .map(|b| format!("{:02X}", b))
+        .collect()
+}
+
+/// 哈希的前5位，用于向 HIBP range API 查询（k-匿名）
+pub fn sha1_prefix(sha1: &str) -> String {
+    sha1[..PREFIX_LEN].to_ascii_uppercase()
+}
+
+/// 判断条目是否出现在泄露列表中
+///
+/// `full_sha1_suffix_list` 是 range API 对该前缀返回的后缀列表，
+/// 每项可以是 `SUFFIX` 或原始响应中的 `SUFFIX:COUNT`，忽略大小写
+pub fn matches_breach(full_sha1_suffix_list: &[String], entry_sha1: &str) -> bool {
+    let Some(suffix) = entry_sha1.get(PREFIX_LEN..) else {
+        return false;
+    };
+
+    full_sha1_suffix_list.iter().any(|line| {
+        let candidate = line.split(':').next().unwrap_or_default().trim();
+        candidate.eq_ignore_ascii_case(suffix)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // "password" 的 SHA-1
+    const PASSWORD_SHA1: &str = "5BAA61E4C9B93F3F0682250B6CF8331B7EE68FD8";
+
+    #[test]
+    fn sha1_of_password() {
+        assert_eq!(sha1_hex("password"), PASSWORD_SHA1);
+        assert_eq!(sha1_prefix(PASSWORD_SHA1), "5BAA6");
+    }
+
+    #[test]
+    fn matches_range_response() {
+        let suffixes = vec![
+            "0018A45C4D1DEF81644B54AB7F969B88D65:1".to_string(),
+            "1e4c9b93f3f0682250b6cf8331b7ee68fd8:9545824".to_string(),
+        ];
+        assert!(matches_breach(&suffixes, PASSWORD_SHA1));
+        assert!(matches_breach(
+            &["1E4C9B93F3F0682250B6CF8331B7EE68FD8".to_string()],
+            PASSWORD_SHA1
+        ));
+        assert!(!matches_breach(&suffixes, &sha1_hex("correct horse")));
+        assert!(!matches_breach(&[], PASSWORD_SHA1));
+    }
+}
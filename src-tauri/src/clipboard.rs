@@ -0,0 +1,55 @@
+use anyhow::{Result, anyhow};
+use std::time::Duration;
+use tauri_plugin_clipboard_manager::ClipboardExt;
+
+use crate::error;
+
+/// 写入剪贴板，并在 `clear_after` 后清空
+///
+/// `clear_after` 为0时不自动清空
+pub fn copy_with_timeout(
+    app: &tauri::AppHandle,
+    text: String,
+    clear_after: Duration,
+) -> Result<()> {
+    app.clipboard()
+        .write_text(text.clone())
+        .map_err(|e| anyhow!("写入剪贴板失败：{}", e))?;
+
+    if clear_after.is_zero() {
+        return Ok(());
+    }
+
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        tokio::time::sleep(clear_after).await;
+
+        let current = app.clipboard().read_text().ok();
+        if should_clear(current.as_deref(), &text)
+            && let Err(e) = app.clipboard().clear()
+        {
+            error!("清空剪贴板失败：{}", e);
+        }
+    });
+
+    Ok(())
+}
+
+/// 只有剪贴板内容仍是我们写入的值时才清空，避免覆盖用户之后复制的内容
+pub fn should_clear(current: Option<&str>, written: &str) -> bool {
+    current == Some(written)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn clears_only_unchanged_content() {
+        assert!(should_clear(Some("s3cret"), "s3cret"));
+        assert!(!should_clear(Some("something else"), "s3cret"));
+        assert!(!should_clear(Some(""), "s3cret"));
+        // 读取失败或剪贴板中不是文本
+        assert!(!should_clear(None, "s3cret"));
+    }
+}
@@ -1,30 +1,333 @@
 use anyhow::{Result, anyhow};
 use serde::{Deserialize, Serialize};
 use std::fs;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
 use tauri::Manager;
 use tauri::path::BaseDirectory;
 
+use crate::crypto::CipherAlgo;
+use crate::log::{self, LogLevel};
+use crate::store::github_store::GITHUB_API_URL;
+use crate::store::{StorageId, StorageTarget};
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(from = "StorageConfigFile")]
 pub struct StorageConfig {
-    pub local_storage: Option<LocalStorageConfig>,
-    pub github_storage: Option<GithubStorageConfig>,
+    pub local_storages: Vec<LocalStorageConfig>,
+    pub github_storages: Vec<GithubStorageConfig>,
+    pub s3_storages: Vec<S3StorageConfig>,
+}
+
+// 配置文件中的存储配置
+// 兼容旧版本只有一个 local_storage / github_storage 的格式
+#[derive(Deserialize)]
+struct StorageConfigFile {
+    #[serde(default)]
+    local_storages: Vec<LocalStorageConfig>,
+    #[serde(default)]
+    github_storages: Vec<GithubStorageConfig>,
+    #[serde(default)]
+    s3_storages: Vec<S3StorageConfig>,
+    #[serde(default)]
+    local_storage: Option<LocalStorageConfig>,
+    #[serde(default)]
+    github_storage: Option<GithubStorageConfig>,
+}
+
+impl From<StorageConfigFile> for StorageConfig {
+    fn from(file: StorageConfigFile) -> Self {
+        Self {
+            local_storages: file
+                .local_storage
+                .into_iter()
+                .chain(file.local_storages)
+                .collect(),
+            github_storages: file
+                .github_storage
+                .into_iter()
+                .chain(file.github_storages)
+                .collect(),
+            s3_storages: file.s3_storages,
+        }
+    }
+}
+
+/// 单个存储点的配置
+#[derive(Debug, Clone, Copy)]
+pub enum StorageEntry<'a> {
+    Local(&'a LocalStorageConfig),
+    GitHub(&'a GithubStorageConfig),
+    S3(&'a S3StorageConfig),
+}
+
+impl StorageEntry<'_> {
+    pub fn target(&self) -> StorageTarget {
+        match self {
+            StorageEntry::Local(_) => StorageTarget::Local,
+            StorageEntry::GitHub(_) => StorageTarget::GitHub,
+            StorageEntry::S3(_) => StorageTarget::S3,
+        }
+    }
+}
+
+impl StorageConfig {
+    /// 按配置顺序列出所有启用的存储点及其id
+    ///
+    /// 未命名的存储点以类型名（`local`/`github`/`s3`）为id，同类型的后续存储点加上序号，
+    /// 如 `local-2`；id重复时报错
+    pub fn enabled_entries(&self) -> Result<Vec<(StorageId, StorageEntry<'_>)>> {
+        let locals = self
+            .local_storages
+            .iter()
+            .enumerate()
+            .map(|(i, c)| (c.enabled, &c.name, i, StorageEntry::Local(c)));
+        let githubs = self
+            .github_storages
+            .iter()
+            .enumerate()
+            .map(|(i, c)| (c.enabled, &c.name, i, StorageEntry::GitHub(c)));
+        let s3s = self
+            .s3_storages
+            .iter()
+            .enumerate()
+            .map(|(i, c)| (c.enabled, &c.name, i, StorageEntry::S3(c)));
+
+        let mut entries: Vec<(StorageId, StorageEntry)> = Vec::new();
+        for (enabled, name, index, entry) in locals.chain(githubs).chain(s3s) {
+            if !enabled {
+                continue;
+            }
+
+            let id = match name.trim() {
+                "" if index == 0 => StorageId::from(entry.target().name()),
+                "" => StorageId(format!("{}-{}", entry.target().name(), index + 1)),
+                name => StorageId::from(name),
+            };
+            if entries.iter().any(|(existing, _)| *existing == id) {
+                return Err(anyhow!("Duplicate storage name: {}", id));
+            }
+            entries.push((id, entry));
+        }
+
+        Ok(entries)
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct LocalStorageConfig {
+    /// 存储点名称，为空时按类型自动命名
+    #[serde(default)]
+    pub name: String,
     pub enabled: bool,
-    // pub data_path: PathBuf,
+    /// 数据文件路径，相对路径基于应用数据目录；为空时使用默认路径
+    #[serde(default)]
+    pub data_path: Option<PathBuf>,
+    /// 数据文件格式
+    #[serde(default)]
+    pub format: LocalFormat,
+    /// 保存前保留的历史版本数量，0 表示不备份
+    #[serde(default = "default_max_backups")]
+    pub max_backups: usize,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+/// 本地存储的数据文件格式
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LocalFormat {
+    /// 整个数据保存为一个JSON文件
+    #[default]
+    Json,
+    /// SQLite数据库，每个条目单独一行，保存时只写入变化的条目
+    Sqlite,
+}
+
+fn default_max_backups() -> usize {
+    3
+}
+
+impl Default for LocalStorageConfig {
+    fn default() -> Self {
+        Self {
+            name: String::new(),
+            enabled: true,
+            data_path: None,
+            format: LocalFormat::default(),
+            max_backups: default_max_backups(),
+        }
+    }
+}
+
+#[derive(Clone, Serialize, Deserialize)]
 pub struct GithubStorageConfig {
+    /// 存储点名称，为空时按类型自动命名
+    #[serde(default)]
+    pub name: String,
     pub enabled: bool,
     pub owner: String,
     pub repo: String,
     pub branch: String,
     pub token: String,
     pub file_path: String,
+    /// 请求失败（限流、5xx、网络错误）时最多尝试的次数
+    #[serde(default = "default_max_attempts")]
+    pub max_attempts: u32,
+    /// API 根地址，GitHub Enterprise 使用，如 `https://github.mycorp.com/api/v3`
+    /// 为空时使用公共 API
+    #[serde(default)]
+    pub base_url: Option<String>,
+    /// 单个请求的超时时间（秒），避免网络卡住时读写一直不返回
+    #[serde(default = "default_timeout_secs")]
+    pub timeout_secs: u64,
+}
+
+fn default_max_attempts() -> u32 {
+    3
+}
+
+fn default_timeout_secs() -> u64 {
+    30
+}
+
+// token不能出现在日志中
+impl std::fmt::Debug for GithubStorageConfig {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("GithubStorageConfig")
+            .field("name", &self.name)
+            .field("enabled", &self.enabled)
+            .field("owner", &self.owner)
+            .field("repo", &self.repo)
+            .field("branch", &self.branch)
+            .field("token", &log::redact(&self.token))
+            .field("file_path", &self.file_path)
+            .field("max_attempts", &self.max_attempts)
+            .field("base_url", &self.base_url)
+            .field("timeout_secs", &self.timeout_secs)
+            .finish()
+    }
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct S3StorageConfig {
+    /// 存储点名称，为空时按类型自动命名
+    #[serde(default)]
+    pub name: String,
+    pub enabled: bool,
+    /// 服务地址，如 `https://s3.us-east-1.amazonaws.com` 或 MinIO 的地址
+    pub endpoint: String,
+    pub region: String,
+    pub bucket: String,
+    /// 对象路径，如 `passwd/passwords.json`
+    pub key: String,
+    pub access_key_id: String,
+    pub secret_access_key: String,
+}
+
+// 访问密钥不能出现在日志中
+impl std::fmt::Debug for S3StorageConfig {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("S3StorageConfig")
+            .field("name", &self.name)
+            .field("enabled", &self.enabled)
+            .field("endpoint", &self.endpoint)
+            .field("region", &self.region)
+            .field("bucket", &self.bucket)
+            .field("key", &self.key)
+            .field("access_key_id", &log::redact(&self.access_key_id))
+            .field("secret_access_key", &log::redact(&self.secret_access_key))
+            .finish()
+    }
+}
+
+/// 覆盖GitHub token的环境变量
+pub const ENV_GITHUB_TOKEN: &str = "GITHUB_TOKEN";
+/// 覆盖GitHub仓库所有者的环境变量
+pub const ENV_GITHUB_OWNER: &str = "PASSWD_GITHUB_OWNER";
+/// 覆盖GitHub仓库名的环境变量
+pub const ENV_GITHUB_REPO: &str = "PASSWD_GITHUB_REPO";
+
+impl GithubStorageConfig {
+    /// 返回应用环境变量覆盖后的配置，原配置（及配置文件）保持不变
+    ///
+    /// 优先级：环境变量 > 配置文件。`GITHUB_TOKEN`、`PASSWD_GITHUB_OWNER`、
+    /// `PASSWD_GITHUB_REPO` 分别覆盖 token、owner、repo，为空时视为未设置
+    pub fn with_env_overrides(&self) -> Self {
+        self.with_overrides_from(|name| std::env::var(name).ok())
+    }
+
+    /// 同 `with_env_overrides`，但从 `lookup` 读取变量
+    pub fn with_overrides_from(&self, lookup: impl Fn(&str) -> Option<String>) -> Self {
+        let lookup = |name| lookup(name).filter(|value: &String| !value.trim().is_empty());
+
+        let mut config = self.clone();
+        if let Some(token) = lookup(ENV_GITHUB_TOKEN) {
+            config.token = token;
+        }
+        if let Some(owner) = lookup(ENV_GITHUB_OWNER) {
+            config.owner = owner;
+        }
+        if let Some(repo) = lookup(ENV_GITHUB_REPO) {
+            config.repo = repo;
+        }
+        config
+    }
+
+    /// 校验并返回 API 根地址
+    ///
+    /// 必须是 https 地址；仅本机地址（localhost/127.0.0.1/::1）允许 http，便于调试
+    pub fn api_base_url(&self) -> Result<String> {
+        let Some(base_url) = self
+            .base_url
+            .as_deref()
+            .map(str::trim)
+            .filter(|s| !s.is_empty())
+        else {
+            return Ok(GITHUB_API_URL.to_string());
+        };
+
+        let url = reqwest::Url::parse(base_url)
+            .map_err(|e| anyhow!("Invalid GitHub base_url [{}]: {}", base_url, e))?;
+
+        let is_loopback = matches!(
+            url.host_str(),
+            Some("localhost") | Some("127.0.0.1") | Some("[::1]")
+        );
+        match url.scheme() {
+            "https" => {}
+            "http" if is_loopback => {}
+            scheme => {
+                return Err(anyhow!(
+                    "Invalid GitHub base_url [{}]: scheme must be https, got {}",
+                    base_url,
+                    scheme
+                ));
+            }
+        }
+        if url.host_str().is_none() || url.query().is_some() || url.fragment().is_some() {
+            return Err(anyhow!(
+                "Invalid GitHub base_url [{}]: expected a plain API root",
+                base_url
+            ));
+        }
+
+        Ok(base_url.trim_end_matches('/').to_string())
+    }
+}
+
+/// 同一条目在多个存储点中的版本不一致时的处理方式
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ConflictPolicy {
+    /// 保留 `updated_at` 较新的版本
+    #[default]
+    NewestWins,
+    /// 以本地存储点的版本为准
+    LocalWins,
+    /// 以GitHub存储点的版本为准
+    #[serde(rename = "github_wins")]
+    GitHubWins,
+    /// 不自动处理，由用户选择保留哪个版本
+    Manual,
 }
 
 // #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -33,14 +336,82 @@ pub struct GithubStorageConfig {
 //     pub double_encrypt_descriptions: bool, // 是否双重加密描述信息
 // }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+/// 硬件密钥（YubiKey等）的HMAC-SHA1挑战-响应配置
+///
+/// 挑战由前端发送给硬件密钥，这里只保存挑战和响应的校验值，不保存响应本身
+#[derive(Clone, Serialize, Deserialize)]
+pub struct HardwareFactorConfig {
+    /// 发送给硬件密钥的挑战
+    pub challenge: String,
+    /// 硬件响应的Argon2校验值
+    pub response_verifier: String,
+}
+
+// 校验值不能出现在日志中
+impl std::fmt::Debug for HardwareFactorConfig {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("HardwareFactorConfig")
+            .field("challenge", &self.challenge)
+            .field("response_verifier", &log::redact(&self.response_verifier))
+            .finish()
+    }
+}
+
+#[derive(Clone, Serialize, Deserialize)]
 pub struct Config {
     pub is_first_setup: bool,
     pub storage: StorageConfig,
+    /// 闲置多少秒后自动锁定，`None` 表示不自动锁定；旧配置中的 0 同样不自动锁定
+    #[serde(default)]
+    pub auto_lock_secs: Option<u64>,
+    /// 主密码的Argon2校验值，为空表示未设置主密码
+    #[serde(default)]
+    pub master_verifier: Option<String>,
+    /// 单个附件的最大字节数
+    #[serde(default = "default_max_attachment_bytes")]
+    pub max_attachment_bytes: usize,
+    /// 写入日志文件的最低级别
+    #[serde(default)]
+    pub log_level: LogLevel,
+    /// 加载时各存储点数据不一致的处理方式
+    #[serde(default)]
+    pub conflict_policy: ConflictPolicy,
+    /// 加载时跳过无法解析的条目，而不是整体失败
+    #[serde(default)]
+    pub lenient_load: bool,
+    /// 新加密数据使用的算法
+    #[serde(default)]
+    pub cipher: CipherAlgo,
+    /// 按URL查找条目时，是否让子域名匹配父域名下保存的条目
+    #[serde(default = "default_match_subdomains")]
+    pub match_subdomains: bool,
+    /// 缓存超过多少秒后在后台从存储点重新加载，0 表示只在启动和解锁时加载
+    #[serde(default)]
+    pub cache_ttl_secs: u64,
+    /// 只读模式：可以查看和解密，拒绝所有写入，只能通过主密码关闭
+    #[serde(default)]
+    pub read_only: bool,
+    /// 硬件密钥第二因素，设置后解锁和加解密都需要硬件密钥的响应
+    #[serde(default)]
+    pub hardware_factor: Option<HardwareFactorConfig>,
+    /// 由key和明文派生nonce，使相同的key和明文得到相同的密文，便于备份去重
+    ///
+    /// **不安全**：能看到密文的人可以判断哪些条目的密码相同，默认关闭，只在明确需要时开启。
+    /// 使用 Argon2（`KdfStrength::Strong`）的条目每次使用随机盐，不受该选项影响
+    #[serde(default)]
+    pub deterministic_nonce: bool,
     // pub security: SecurityConfig,
     pub version: String,
 }
 
+fn default_max_attachment_bytes() -> usize {
+    512 * 1024
+}
+
+fn default_match_subdomains() -> bool {
+    true
+}
+
 impl Default for Config {
     fn default() -> Self {
         // Use relative path that will be resolved by Tauri's path API when needed
@@ -49,9 +420,22 @@ impl Default for Config {
         Self {
             is_first_setup: true,
             storage: StorageConfig {
-                local_storage: Some(LocalStorageConfig { enabled: true }),
-                github_storage: None,
+                local_storages: vec![LocalStorageConfig::default()],
+                github_storages: vec![],
+                s3_storages: vec![],
             },
+            auto_lock_secs: None,
+            master_verifier: None,
+            max_attachment_bytes: default_max_attachment_bytes(),
+            log_level: LogLevel::default(),
+            conflict_policy: ConflictPolicy::default(),
+            lenient_load: false,
+            cipher: CipherAlgo::default(),
+            match_subdomains: default_match_subdomains(),
+            cache_ttl_secs: 0,
+            read_only: false,
+            hardware_factor: None,
+            deterministic_nonce: false,
             // security: SecurityConfig {
             //     encryption_salt: vec![0u8; 32],
             //     double_encrypt_descriptions: false,
@@ -61,6 +445,32 @@ impl Default for Config {
     }
 }
 
+// 主密码校验值不能出现在日志中
+impl std::fmt::Debug for Config {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Config")
+            .field("is_first_setup", &self.is_first_setup)
+            .field("read_only", &self.read_only)
+            .field("storage", &self.storage)
+            .field("auto_lock_secs", &self.auto_lock_secs)
+            .field(
+                "master_verifier",
+                &self.master_verifier.as_deref().map(log::redact),
+            )
+            .field("max_attachment_bytes", &self.max_attachment_bytes)
+            .field("log_level", &self.log_level)
+            .field("conflict_policy", &self.conflict_policy)
+            .field("lenient_load", &self.lenient_load)
+            .field("match_subdomains", &self.match_subdomains)
+            .field("cache_ttl_secs", &self.cache_ttl_secs)
+            .field("cipher", &self.cipher)
+            .field("deterministic_nonce", &self.deterministic_nonce)
+            .field("hardware_factor", &self.hardware_factor)
+            .field("version", &self.version)
+            .finish()
+    }
+}
+
 impl Config {
     // pub fn new() -> Self {
     //     Self::default()
@@ -76,6 +486,75 @@ impl Config {
         Ok(config)
     }
 
+    /// 检查配置是否完整可用
+    ///
+    /// 启用的GitHub存储必须填写 owner、repo 和 token（可由环境变量提供），
+    /// 且 base_url 合法；启用的S3存储必须填写所有字段；存储点名称不能重复
+    pub fn validate(&self) -> Result<()> {
+        for (index, github) in self.storage.github_storages.iter().enumerate() {
+            if !github.enabled {
+                continue;
+            }
+            let github = github.with_env_overrides();
+
+            let missing: Vec<&str> = [
+                ("owner", &github.owner),
+                ("repo", &github.repo),
+                ("token", &github.token),
+            ]
+            .into_iter()
+            .filter(|(_, value)| value.trim().is_empty())
+            .map(|(field, _)| field)
+            .collect();
+            if !missing.is_empty() {
+                return Err(anyhow!(
+                    "GitHub storage #{} is enabled but {} is empty",
+                    index + 1,
+                    missing.join("/")
+                ));
+            }
+
+            github.api_base_url()?;
+        }
+
+        for (index, s3) in self.storage.s3_storages.iter().enumerate() {
+            if !s3.enabled {
+                continue;
+            }
+
+            let missing: Vec<&str> = [
+                ("endpoint", &s3.endpoint),
+                ("region", &s3.region),
+                ("bucket", &s3.bucket),
+                ("key", &s3.key),
+                ("access_key_id", &s3.access_key_id),
+                ("secret_access_key", &s3.secret_access_key),
+            ]
+            .into_iter()
+            .filter(|(_, value)| value.trim().is_empty())
+            .map(|(field, _)| field)
+            .collect();
+            if !missing.is_empty() {
+                return Err(anyhow!(
+                    "S3 storage #{} is enabled but {} is empty",
+                    index + 1,
+                    missing.join("/")
+                ));
+            }
+        }
+
+        self.storage.enabled_entries()?;
+
+        Ok(())
+    }
+
+    /// 自动锁定前允许闲置的时间，不自动锁定时为 `None`
+    pub fn auto_lock_timeout(&self) -> Option<Duration> {
+        self.auto_lock_secs
+            .filter(|&secs| secs > 0)
+            .map(Duration::from_secs)
+    }
+
     pub fn save_to_file(&self, path: &PathBuf) -> Result<()> {
         let content = serde_json::to_string_pretty(self)
             .map_err(|e| anyhow!("Failed to serialize config: {}", e))?;
@@ -91,14 +570,413 @@ impl Config {
     }
 
     // Cross-platform config path using Tauri's AppConfig directory
-    pub fn get_config_path(app_handle: &tauri::AppHandle) -> tauri::Result<PathBuf> {
-        app_handle
-            .path()
-            .resolve("config.json", BaseDirectory::AppConfig)
-    }
-    pub fn get_data_path(app_handle: &tauri::AppHandle) -> tauri::Result<PathBuf> {
-        app_handle
-            .path()
-            .resolve("passwords.json", BaseDirectory::AppData)
+    // 设置了 `--config-dir` 或 `PASSWD_CONFIG_DIR` 时使用指定的目录
+    pub fn get_config_path(
+        app_handle: &tauri::AppHandle,
+        overrides: &PathOverrides,
+    ) -> Result<PathBuf> {
+        resolve_path(overrides.config_dir.as_deref(), "config.json", || {
+            app_handle
+                .path()
+                .resolve("config.json", BaseDirectory::AppConfig)
+        })
+    }
+    // 设置了 `--data-dir` 或 `PASSWD_DATA_DIR` 时使用指定的目录
+    pub fn get_data_path(
+        app_handle: &tauri::AppHandle,
+        overrides: &PathOverrides,
+    ) -> Result<PathBuf> {
+        resolve_path(overrides.data_dir.as_deref(), "passwords.json", || {
+            app_handle
+                .path()
+                .resolve("passwords.json", BaseDirectory::AppData)
+        })
+    }
+}
+
+/// 指定数据目录的环境变量
+pub const ENV_DATA_DIR: &str = "PASSWD_DATA_DIR";
+/// 指定配置目录的环境变量
+pub const ENV_CONFIG_DIR: &str = "PASSWD_CONFIG_DIR";
+/// 指定数据目录的命令行参数
+pub const ARG_DATA_DIR: &str = "--data-dir";
+/// 指定配置目录的命令行参数
+pub const ARG_CONFIG_DIR: &str = "--config-dir";
+
+/// 用户指定的数据和配置目录，用于便携版或放在加密卷中
+///
+/// 未指定的目录使用系统默认位置
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PathOverrides {
+    pub data_dir: Option<PathBuf>,
+    pub config_dir: Option<PathBuf>,
+}
+
+impl PathOverrides {
+    /// 从当前进程的命令行参数和环境变量读取
+    pub fn from_env_and_args() -> Self {
+        Self::from_sources(std::env::args().skip(1), |name| std::env::var(name).ok())
+    }
+
+    /// 同 `from_env_and_args`，但从 `args` 和 `lookup` 读取
+    ///
+    /// 优先级：命令行参数 > 环境变量。参数可以写成 `--data-dir <目录>` 或
+    /// `--data-dir=<目录>`，空值视为未设置
+    pub fn from_sources(
+        args: impl IntoIterator<Item = String>,
+        lookup: impl Fn(&str) -> Option<String>,
+    ) -> Self {
+        let args: Vec<String> = args.into_iter().collect();
+        let arg = |name: &str| {
+            args.iter().enumerate().find_map(|(i, arg)| {
+                if arg == name {
+                    args.get(i + 1).cloned()
+                } else {
+                    arg.strip_prefix(name)?
+                        .strip_prefix('=')
+                        .map(str::to_string)
+                }
+            })
+        };
+        let dir = |arg_name: &str, env_name: &str| {
+            arg(arg_name)
+                .or_else(|| lookup(env_name))
+                .filter(|value| !value.trim().is_empty())
+                .map(PathBuf::from)
+        };
+
+        Self {
+            data_dir: dir(ARG_DATA_DIR, ENV_DATA_DIR),
+            config_dir: dir(ARG_CONFIG_DIR, ENV_CONFIG_DIR),
+        }
+    }
+}
+
+// 指定了目录时返回目录下的 `file_name`，并确认目录可写；否则使用默认路径
+fn resolve_path(
+    dir: Option<&Path>,
+    file_name: &str,
+    default: impl FnOnce() -> tauri::Result<PathBuf>,
+) -> Result<PathBuf> {
+    match dir {
+        Some(dir) => {
+            ensure_writable_dir(dir)?;
+            Ok(dir.join(file_name))
+        }
+        None => Ok(default()?),
+    }
+}
+
+// 目录不存在时创建，再写入并删除一个临时文件确认可写
+fn ensure_writable_dir(dir: &Path) -> Result<()> {
+    fs::create_dir_all(dir).map_err(|e| anyhow!("无法创建目录（{}）：{}", dir.display(), e))?;
+
+    let probe = dir.join(format!(".passwd-write-test-{}", std::process::id()));
+    fs::write(&probe, b"")
+        .and_then(|_| fs::remove_file(&probe))
+        .map_err(|e| anyhow!("目录不可写（{}）：{}", dir.display(), e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn github_config(base_url: Option<&str>) -> GithubStorageConfig {
+        GithubStorageConfig {
+            name: String::new(),
+            enabled: true,
+            owner: "owner".to_string(),
+            repo: "repo".to_string(),
+            branch: "main".to_string(),
+            token: "token".to_string(),
+            file_path: "passwords.json".to_string(),
+            max_attempts: 3,
+            base_url: base_url.map(str::to_string),
+            timeout_secs: 30,
+        }
+    }
+
+    #[test]
+    fn base_url_defaults_to_public_api() {
+        assert_eq!(github_config(None).api_base_url().unwrap(), GITHUB_API_URL);
+        assert_eq!(
+            github_config(Some("  ")).api_base_url().unwrap(),
+            GITHUB_API_URL
+        );
+    }
+
+    #[test]
+    fn base_url_accepts_enterprise_https() {
+        let config = github_config(Some("https://github.mycorp.com/api/v3/"));
+        assert_eq!(
+            config.api_base_url().unwrap(),
+            "https://github.mycorp.com/api/v3"
+        );
+    }
+
+    #[test]
+    fn base_url_rejects_malformed() {
+        for bad in [
+            "github.mycorp.com/api/v3",
+            "http://github.mycorp.com/api/v3",
+            "ftp://github.mycorp.com",
+            "https://github.mycorp.com/api?x=1",
+        ] {
+            assert!(github_config(Some(bad)).api_base_url().is_err(), "{}", bad);
+        }
+        assert!(
+            github_config(Some("http://127.0.0.1:1234"))
+                .api_base_url()
+                .is_ok()
+        );
+    }
+
+    fn ids(config: &StorageConfig) -> Vec<String> {
+        config
+            .enabled_entries()
+            .unwrap()
+            .into_iter()
+            .map(|(id, _)| id.0)
+            .collect()
+    }
+
+    fn s3_config() -> S3StorageConfig {
+        S3StorageConfig {
+            name: String::new(),
+            enabled: true,
+            endpoint: "https://s3.example.com".to_string(),
+            region: "us-east-1".to_string(),
+            bucket: "vault".to_string(),
+            key: "passwords.json".to_string(),
+            access_key_id: "AKIDEXAMPLE".to_string(),
+            secret_access_key: "s3-secret".to_string(),
+        }
+    }
+
+    #[test]
+    fn validate_s3_storage() {
+        let mut config = Config::default();
+        config.storage.s3_storages.push(s3_config());
+        assert!(config.validate().is_ok());
+
+        config.storage.s3_storages[0].secret_access_key.clear();
+        let err = config.validate().unwrap_err();
+        assert!(err.to_string().contains("secret_access_key"), "{}", err);
+
+        config.storage.s3_storages[0].enabled = false;
+        assert!(config.validate().is_ok());
+    }
+
+    #[test]
+    fn s3_credentials_are_redacted() {
+        let debug = format!("{:?}", s3_config());
+        assert!(debug.contains("vault"));
+        assert!(!debug.contains("AKIDEXAMPLE"), "{}", debug);
+        assert!(!debug.contains("s3-secret"), "{}", debug);
+    }
+
+    fn config_with_github(github: GithubStorageConfig) -> Config {
+        let mut config = Config::default();
+        config.storage.github_storages.push(github);
+        config
+    }
+
+    #[test]
+    fn validate_accepts_complete_config() {
+        assert!(Config::default().validate().is_ok());
+        assert!(config_with_github(github_config(None)).validate().is_ok());
+    }
+
+    #[test]
+    fn validate_rejects_empty_github_fields() {
+        for field in ["owner", "repo", "token"] {
+            let mut github = github_config(None);
+            match field {
+                "owner" => github.owner.clear(),
+                "repo" => github.repo = "  ".to_string(),
+                _ => github.token.clear(),
+            }
+
+            let err = config_with_github(github).validate().unwrap_err();
+            assert!(err.to_string().contains(field), "{}: {}", field, err);
+        }
+
+        let mut github = github_config(None);
+        github.owner.clear();
+        github.token.clear();
+        let err = config_with_github(github).validate().unwrap_err();
+        assert!(err.to_string().contains("owner/token"), "{}", err);
+    }
+
+    #[test]
+    fn validate_ignores_disabled_github() {
+        let mut github = github_config(None);
+        github.enabled = false;
+        github.owner.clear();
+        github.repo.clear();
+        github.token.clear();
+        assert!(config_with_github(github).validate().is_ok());
+    }
+
+    #[test]
+    fn validate_rejects_bad_base_url_and_duplicate_names() {
+        let github = github_config(Some("http://github.mycorp.com/api/v3"));
+        assert!(config_with_github(github).validate().is_err());
+
+        let mut github = github_config(None);
+        github.name = "local".to_string();
+        assert!(config_with_github(github).validate().is_err());
+    }
+
+    #[test]
+    fn env_overrides_take_precedence() {
+        let config = github_config(None);
+        let overridden = config.with_overrides_from(|name| match name {
+            ENV_GITHUB_TOKEN => Some("env-token".to_string()),
+            ENV_GITHUB_OWNER => Some("env-owner".to_string()),
+            _ => None,
+        });
+
+        assert_eq!(overridden.token, "env-token");
+        assert_eq!(overridden.owner, "env-owner");
+        assert_eq!(overridden.repo, "repo");
+        // 原配置不受影响
+        assert_eq!(config.token, "token");
+        assert_eq!(config.owner, "owner");
+    }
+
+    #[test]
+    fn empty_env_values_are_ignored() {
+        let config = github_config(None);
+        let overridden = config.with_overrides_from(|_| Some("  ".to_string()));
+
+        assert_eq!(overridden.token, "token");
+        assert_eq!(overridden.owner, "owner");
+        assert_eq!(overridden.repo, "repo");
+    }
+
+    #[test]
+    fn legacy_single_storage_config_loads() {
+        let json = r#"{
+            "local_storage": { "enabled": true },
+            "github_storage": {
+                "enabled": false, "owner": "o", "repo": "r", "branch": "main",
+                "token": "t", "file_path": "passwords.json"
+            }
+        }"#;
+        let config: StorageConfig = serde_json::from_str(json).unwrap();
+
+        assert_eq!(config.local_storages.len(), 1);
+        assert_eq!(config.github_storages.len(), 1);
+        assert_eq!(ids(&config), vec!["local"]);
+
+        // 保存时使用新格式
+        let saved = serde_json::to_value(&config).unwrap();
+        assert!(saved.get("local_storage").is_none());
+        assert_eq!(saved["local_storages"].as_array().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn auto_lock_accepts_old_and_new_values() {
+        let timeout = |value: serde_json::Value| {
+            let mut json = serde_json::to_value(Config::default()).unwrap();
+            json["auto_lock_secs"] = value;
+            serde_json::from_value::<Config>(json)
+                .unwrap()
+                .auto_lock_timeout()
+        };
+
+        assert_eq!(timeout(serde_json::json!(null)), None);
+        // 旧版本用 0 表示不自动锁定
+        assert_eq!(timeout(serde_json::json!(0)), None);
+        assert_eq!(
+            timeout(serde_json::json!(300)),
+            Some(Duration::from_secs(300))
+        );
+    }
+
+    #[test]
+    fn storage_ids_follow_names_and_order() {
+        let named = |name: &str| LocalStorageConfig {
+            name: name.to_string(),
+            ..Default::default()
+        };
+        let config = StorageConfig {
+            local_storages: vec![named(""), named("usb"), named("")],
+            github_storages: vec![github_config(None)],
+            s3_storages: vec![s3_config()],
+        };
+        assert_eq!(
+            ids(&config),
+            vec!["local", "usb", "local-3", "github", "s3"]
+        );
+
+        let config = StorageConfig {
+            local_storages: vec![named("usb"), named(" usb ")],
+            github_storages: vec![],
+            s3_storages: vec![],
+        };
+        assert!(config.enabled_entries().is_err());
+    }
+
+    #[test]
+    fn path_overrides_prefer_args_then_env() {
+        let env: std::collections::HashMap<&str, &str> = std::collections::HashMap::from([
+            (ENV_DATA_DIR, "/env/data"),
+            (ENV_CONFIG_DIR, "/env/config"),
+        ]);
+        let lookup = |name: &str| env.get(name).map(|v| v.to_string());
+        let args = |list: &[&str]| list.iter().map(|s| s.to_string()).collect::<Vec<_>>();
+
+        let overrides = PathOverrides::from_sources(args(&["--data-dir", "/arg/data"]), lookup);
+        assert_eq!(overrides.data_dir, Some(PathBuf::from("/arg/data")));
+        assert_eq!(overrides.config_dir, Some(PathBuf::from("/env/config")));
+
+        let overrides = PathOverrides::from_sources(args(&["--config-dir=/arg/config"]), lookup);
+        assert_eq!(overrides.data_dir, Some(PathBuf::from("/env/data")));
+        assert_eq!(overrides.config_dir, Some(PathBuf::from("/arg/config")));
+
+        // 都未设置或为空时使用默认位置
+        let overrides = PathOverrides::from_sources(args(&["--data-dir="]), |_| None);
+        assert_eq!(overrides, PathOverrides::default());
+    }
+
+    #[test]
+    fn resolve_path_uses_override_or_default() {
+        let dir = crate::store::mock_store::temp_dir().join("portable");
+        let path = resolve_path(Some(&dir), "passwords.json", || unreachable!()).unwrap();
+        assert_eq!(path, dir.join("passwords.json"));
+        // 目录已创建，且没有留下检查用的文件
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 0);
+
+        let default = PathBuf::from("/default/passwords.json");
+        let path = resolve_path(None, "passwords.json", || Ok(default.clone())).unwrap();
+        assert_eq!(path, default);
+
+        // 已存在的可写目录优先于默认路径
+        let existing = crate::store::mock_store::temp_dir();
+        let path = resolve_path(Some(&existing), "passwords.json", || Ok(default.clone())).unwrap();
+        assert_eq!(path, existing.join("passwords.json"));
+        assert_ne!(path, default);
+
+        // 指定的路径是文件，无法作为目录使用
+        let file = crate::store::mock_store::temp_dir().join("file");
+        fs::write(&file, b"").unwrap();
+        let err = resolve_path(Some(&file), "passwords.json", || unreachable!()).unwrap_err();
+        assert!(
+            err.to_string()
+                .starts_with(&format!("无法创建目录（{}）：", file.display())),
+            "{}",
+            err
+        );
+        // 文件下的子目录同样无法创建
+        let nested = file.join("sub");
+        let err = resolve_path(Some(&nested), "passwords.json", || unreachable!()).unwrap_err();
+        assert!(
+            err.to_string()
+                .starts_with(&format!("无法创建目录（{}）：", nested.display())),
+            "{}",
+            err
+        );
     }
 }
@@ -1,19 +1,205 @@
-use aes_gcm::{
-    Aes256Gcm, Key, Nonce,
-    aead::{Aead, KeyInit},
+use aes_gcm::Aes256Gcm;
+use aes_gcm::aead::{Aead, KeyInit, Nonce};
+use argon2::password_hash::{
+    PasswordHash, PasswordHasher, PasswordVerifier, SaltString, rand_core::OsRng,
 };
+use argon2::{Algorithm, Argon2, Params, Version};
+use chacha20poly1305::XChaCha20Poly1305;
+use hmac::{Hmac, Mac};
 use rand::RngCore;
 use serde::{Deserialize, Serialize};
 use sha2::{Digest, Sha256};
+use std::sync::atomic::{AtomicBool, AtomicU8, Ordering};
 
 use anyhow::{Result, anyhow};
 
+use crate::debug;
+
+/// 加密算法
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CipherAlgo {
+    /// AES-256-GCM，密钥由密码经SHA-256派生
+    #[default]
+    Aes256Gcm,
+    /// XChaCha20-Poly1305，密钥派生方式同上，适合没有AES硬件加速的平台
+    #[serde(rename = "xchacha20_poly1305")]
+    XChaCha20Poly1305,
+}
+
+impl CipherAlgo {
+    /// 该算法的nonce长度
+    pub fn nonce_len(&self) -> usize {
+        match self {
+            CipherAlgo::Aes256Gcm => 12,
+            CipherAlgo::XChaCha20Poly1305 => 24,
+        }
+    }
+
+    fn from_u8(value: u8) -> Self {
+        match value {
+            1 => CipherAlgo::XChaCha20Poly1305,
+            _ => CipherAlgo::Aes256Gcm,
+        }
+    }
+}
+
+// 新加密数据使用的算法，由配置决定
+static PREFERRED_CIPHER: AtomicU8 = AtomicU8::new(CipherAlgo::Aes256Gcm as u8);
+
+/// 设置新加密数据使用的算法，已有数据按各自记录的算法解密，不受影响
+pub fn set_preferred_cipher(algo: CipherAlgo) {
+    PREFERRED_CIPHER.store(algo as u8, Ordering::Relaxed);
+}
+
+pub fn preferred_cipher() -> CipherAlgo {
+    CipherAlgo::from_u8(PREFERRED_CIPHER.load(Ordering::Relaxed))
+}
+
+// 是否由key和明文派生nonce，由配置决定
+static DETERMINISTIC_NONCE: AtomicBool = AtomicBool::new(false);
+
+/// 设置新加密数据是否使用确定性nonce，解密不受影响
+///
+/// **警告**：开启后相同的key和明文总是得到相同的密文，能看到密文的人可以
+/// 判断哪些条目的密码相同，只应在需要去重的备份场景中开启
+///
+/// 只对 [`KdfStrength::Fast`] 生效：[`KdfStrength::Strong`] 每次使用随机盐，密文仍然不同。
+/// 盐随密文明文保存，若由key或明文派生，就能跳过Argon2直接验证猜测，失去 Strong 的意义
+pub fn set_deterministic_nonce(enabled: bool) {
+    DETERMINISTIC_NONCE.store(enabled, Ordering::Relaxed);
+}
+
+pub fn deterministic_nonce() -> bool {
+    DETERMINISTIC_NONCE.load(Ordering::Relaxed)
+}
+
+/// 新加密数据的密钥派生强度
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum KdfStrength {
+    /// SHA-256，速度快，适合大多数条目
+    #[default]
+    Fast,
+    /// Argon2id，带随机盐，每次加解密需要更多时间和内存，适合重要的条目
+    Strong,
+}
+
+// Argon2盐的字节数
+const ARGON2_SALT_LEN: usize = 16;
+// 解密时接受的最大内存参数（KiB），避免被篡改的数据耗尽内存
+const MAX_ARGON2_M_COST: u32 = 256 * 1024;
+
+/// 加密时使用的密钥派生方式和参数，随密文一起保存，解密时按记录的参数派生密钥
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum KeyDerivation {
+    /// 旧数据没有该字段，默认为SHA-256
+    #[default]
+    Sha256,
+    Argon2id {
+        salt: Vec<u8>,
+        m_cost: u32,
+        t_cost: u32,
+        p_cost: u32,
+    },
+}
+
+impl KeyDerivation {
+    // 按强度生成新的派生参数，Argon2使用随机盐和默认参数
+    fn new(strength: KdfStrength) -> Self {
+        match strength {
+            KdfStrength::Fast => KeyDerivation::Sha256,
+            KdfStrength::Strong => {
+                let mut salt = vec![0u8; ARGON2_SALT_LEN];
+                rand::rng().fill_bytes(&mut salt);
+                KeyDerivation::Argon2id {
+                    salt,
+                    m_cost: Params::DEFAULT_M_COST,
+                    t_cost: Params::DEFAULT_T_COST,
+                    p_cost: Params::DEFAULT_P_COST,
+                }
+            }
+        }
+    }
+
+    pub fn strength(&self) -> KdfStrength {
+        match self {
+            KeyDerivation::Sha256 => KdfStrength::Fast,
+            KeyDerivation::Argon2id { .. } => KdfStrength::Strong,
+        }
+    }
+
+    // 按记录的参数把用户密码转换为32字节密钥
+    fn derive_key(&self, password: &str) -> Result<[u8; 32]> {
+        match self {
+            KeyDerivation::Sha256 => Ok(password_to_key(password)),
+            KeyDerivation::Argon2id {
+                salt,
+                m_cost,
+                t_cost,
+                p_cost,
+            } => {
+                if *m_cost > MAX_ARGON2_M_COST {
+                    return Err(anyhow!("Argon2内存参数过大：{} KiB", m_cost));
+                }
+                let params = Params::new(*m_cost, *t_cost, *p_cost, Some(32))
+                    .map_err(|e| anyhow!("Invalid Argon2 params: {}", e))?;
+
+                let mut key = [0u8; 32];
+                Argon2::new(Algorithm::Argon2id, Version::V0x13, params)
+                    .hash_password_into(password.as_bytes(), salt, &mut key)
+                    .map_err(|e| anyhow!("Failed to derive key: {}", e))?;
+                Ok(key)
+            }
+        }
+    }
+}
+
+/// 没有版本字段的旧数据
+pub const LEGACY_VERSION: u8 = 0;
+/// 新加密的数据使用的版本
+pub const CURRENT_VERSION: u8 = 1;
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct EncryptedData {
     pub ciphertext: Vec<u8>,
     pub nonce: Vec<u8>,
+    /// 加密算法，旧数据没有该字段，默认为AES-256-GCM
+    #[serde(default)]
+    pub algo: CipherAlgo,
+    /// 格式版本，旧数据没有该字段，默认为 [`LEGACY_VERSION`]
+    #[serde(default)]
+    pub version: u8,
+    /// 密钥派生方式，旧数据没有该字段，默认为SHA-256
+    #[serde(default)]
+    pub kdf: KeyDerivation,
+}
+
+/// 解密失败
+///
+/// 密码错误、nonce长度不对、密文被篡改等原因都返回同一个 `Failed`，
+/// 不向调用方区分失败原因，具体原因只记录在debug日志中
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DecryptError {
+    Failed,
+    /// 解密成功，但明文不是合法的UTF-8，应当用 [`decrypt_bytes_with_password`] 读取
+    ///
+    /// 只有密钥正确时才会出现，不泄露额外信息
+    NotText,
 }
 
+impl std::fmt::Display for DecryptError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DecryptError::Failed => write!(f, "decryption failed"),
+            DecryptError::NotText => write!(f, "decrypted data is not valid UTF-8 text"),
+        }
+    }
+}
+
+impl std::error::Error for DecryptError {}
+
 /// 将用户密码确定性转换为32字节密钥
 /// 使用SHA-256哈希，不需要任何盐值或存储
 fn password_to_key(password: &str) -> [u8; 32] {
@@ -29,8 +215,11 @@ fn password_to_key(password: &str) -> [u8; 32] {
 /// 使用密码加密数据
 ///
 /// 特点：
-/// - 用户密码通过SHA-256转换为32字节密钥
-/// - 每次加密生成随机nonce，保证语义安全
+/// - 用户密码通过SHA-256转换为32字节密钥，需要更强的派生时使用 [`encrypt_with_strength`]
+/// - 每次加密生成随机nonce，保证语义安全；随机数生成器失效（返回全零）时返回错误
+/// - 开启 [`set_deterministic_nonce`] 时nonce由密钥和明文派生，相同输入得到相同密文
+///   （仅限 [`KdfStrength::Fast`]）
+/// - 使用 [`set_preferred_cipher`] 设置的算法，默认为AES-256-GCM
 ///
 /// # 参数
 /// * `plaintext` - 要加密的明文数据
@@ -42,29 +231,134 @@ fn password_to_key(password: &str) -> [u8; 32] {
 /// # 错误
 /// * 加密过程中的任何错误都会返回
 pub fn encrypt_with_password(plaintext: &str, password: &str) -> Result<EncryptedData> {
-    // 确定性密钥派生：密码 → SHA-256 → 32字节密钥
-    let key_bytes = password_to_key(password);
-    let key = Key::<Aes256Gcm>::from(key_bytes);
+    encrypt_bytes_with_password(plaintext.as_bytes(), password)
+}
 
-    // 创建AES-256-GCM加密器
-    let cipher = Aes256Gcm::new(&key);
+/// 使用密码加密二进制数据，规则同 [`encrypt_with_password`]
+pub fn encrypt_bytes_with_password(plaintext: &[u8], password: &str) -> Result<EncryptedData> {
+    encrypt_bytes_with_strength(plaintext, password, KdfStrength::Fast)
+}
 
-    // 生成随机nonce（保证语义安全）
-    let mut nonce_bytes = [0u8; 12];
-    rand::rng().fill_bytes(&mut nonce_bytes);
-    let nonce = Nonce::from(nonce_bytes);
+/// 使用指定的密钥派生强度加密数据，派生参数记录在结果中，解密时不需要再指定
+pub fn encrypt_with_strength(
+    plaintext: &str,
+    password: &str,
+    strength: KdfStrength,
+) -> Result<EncryptedData> {
+    encrypt_bytes_with_strength(plaintext.as_bytes(), password, strength)
+}
 
-    // 加密数据
-    let ciphertext = cipher
-        .encrypt(&nonce, plaintext.as_bytes())
-        .map_err(|e| anyhow!(e.to_string()))?;
+/// 使用指定的密钥派生强度加密二进制数据，规则同 [`encrypt_with_strength`]
+pub fn encrypt_bytes_with_strength(
+    plaintext: &[u8],
+    password: &str,
+    strength: KdfStrength,
+) -> Result<EncryptedData> {
+    encrypt_bytes_with_cipher(plaintext, password, preferred_cipher(), strength)
+}
+
+/// 使用指定算法和密钥派生强度加密二进制数据
+pub fn encrypt_bytes_with_cipher(
+    plaintext: &[u8],
+    password: &str,
+    algo: CipherAlgo,
+    strength: KdfStrength,
+) -> Result<EncryptedData> {
+    encrypt_with_params(plaintext, password, algo, strength, deterministic_nonce())
+}
+
+// `deterministic` 只决定nonce；Strong 的盐总是随机的，见 `set_deterministic_nonce`
+fn encrypt_with_params(
+    plaintext: &[u8],
+    password: &str,
+    algo: CipherAlgo,
+    strength: KdfStrength,
+    deterministic: bool,
+) -> Result<EncryptedData> {
+    let kdf = KeyDerivation::new(strength);
+    let key_bytes = kdf.derive_key(password)?;
+    let (ciphertext, nonce) = match algo {
+        CipherAlgo::Aes256Gcm => encrypt_aead::<Aes256Gcm>(plaintext, &key_bytes, deterministic)?,
+        CipherAlgo::XChaCha20Poly1305 => {
+            encrypt_aead::<XChaCha20Poly1305>(plaintext, &key_bytes, deterministic)?
+        }
+    };
 
     Ok(EncryptedData {
         ciphertext,
-        nonce: nonce_bytes.to_vec(),
+        nonce,
+        algo,
+        version: CURRENT_VERSION,
+        kdf,
     })
 }
 
+// 生成nonce时最多尝试的次数
+const MAX_NONCE_ATTEMPTS: usize = 3;
+
+// 用随机数填充nonce，拒绝全零的结果
+// 正常的随机数生成器几乎不可能产生全零，出现时说明生成器已失效，重新生成，
+// 多次仍为全零则返回错误，避免用固定的nonce加密
+fn fill_nonce<R: RngCore + ?Sized>(rng: &mut R, nonce: &mut [u8]) -> Result<()> {
+    for _ in 0..MAX_NONCE_ATTEMPTS {
+        rng.fill_bytes(nonce);
+        if nonce.iter().any(|b| *b != 0) {
+            return Ok(());
+        }
+        debug!("随机数生成器返回了全零的nonce，重新生成");
+    }
+    Err(anyhow!("随机数生成器异常：无法生成有效的nonce"))
+}
+
+// 返回密文和nonce，`deterministic` 为真时nonce由key和明文派生
+fn encrypt_aead<C: Aead + KeyInit>(
+    plaintext: &[u8],
+    key_bytes: &[u8],
+    deterministic: bool,
+) -> Result<(Vec<u8>, Vec<u8>)> {
+    if !deterministic {
+        return encrypt_aead_with_rng::<C, _>(plaintext, key_bytes, &mut rand::rng());
+    }
+
+    // nonce取 HMAC-SHA256(密钥, 明文) 的前若干字节
+    let mut mac =
+        <Hmac<Sha256> as Mac>::new_from_slice(key_bytes).map_err(|e| anyhow!(e.to_string()))?;
+    mac.update(plaintext);
+    let digest = mac.finalize().into_bytes();
+
+    let mut nonce = Nonce::<C>::default();
+    let len = nonce.len();
+    nonce.copy_from_slice(&digest[..len]);
+    seal::<C>(plaintext, key_bytes, &nonce)
+}
+
+// 使用指定的随机数生成器生成nonce，测试时可以传入固定种子或模拟的生成器
+fn encrypt_aead_with_rng<C: Aead + KeyInit, R: RngCore + ?Sized>(
+    plaintext: &[u8],
+    key_bytes: &[u8],
+    rng: &mut R,
+) -> Result<(Vec<u8>, Vec<u8>)> {
+    // 生成随机nonce（保证语义安全）
+    let mut nonce = Nonce::<C>::default();
+    fill_nonce(rng, &mut nonce)?;
+
+    seal::<C>(plaintext, key_bytes, &nonce)
+}
+
+// 用给定的nonce加密，返回密文和nonce
+fn seal<C: Aead + KeyInit>(
+    plaintext: &[u8],
+    key_bytes: &[u8],
+    nonce: &Nonce<C>,
+) -> Result<(Vec<u8>, Vec<u8>)> {
+    let cipher = C::new_from_slice(key_bytes).map_err(|e| anyhow!(e.to_string()))?;
+    let ciphertext = cipher
+        .encrypt(nonce, plaintext)
+        .map_err(|e| anyhow!(e.to_string()))?;
+
+    Ok((ciphertext, nonce.to_vec()))
+}
+
 /// 使用密码解密数据
 ///
 /// # 参数
@@ -75,25 +369,133 @@ pub fn encrypt_with_password(plaintext: &str, password: &str) -> Result<Encrypte
 /// * `Result<String>` - 成功返回解密后的明文，失败返回错误
 ///
 /// # 错误
-/// * 解密过程中的任何错误都会返回，包括密码错误
+/// * 密码错误、数据损坏等原因导致的失败都返回 [`DecryptError::Failed`]
+/// * 明文是二进制数据（不是合法的UTF-8）时返回 [`DecryptError::NotText`]
 pub fn decrypt_with_password(encrypted_data: &EncryptedData, password: &str) -> Result<String> {
-    // 确定性密钥派生：密码 → SHA-256 → 32字节密钥
-    let key_bytes = password_to_key(password);
-    let key = Key::<Aes256Gcm>::from(key_bytes);
+    let plaintext = decrypt_bytes_with_password(encrypted_data, password)?;
+    String::from_utf8(plaintext).map_err(|e| {
+        debug!("明文不是合法的UTF-8：{}", e);
+        DecryptError::NotText.into()
+    })
+}
+
+/// 使用密码解密二进制数据，返回原始字节，不要求明文是文本
+///
+/// 按数据中记录的算法和版本选择解密方式，用于附件中的密钥文件等二进制内容；
+/// 失败时返回 [`DecryptError::Failed`]
+pub fn decrypt_bytes_with_password(
+    encrypted_data: &EncryptedData,
+    password: &str,
+) -> Result<Vec<u8>> {
+    match (encrypted_data.algo, encrypted_data.version) {
+        (CipherAlgo::Aes256Gcm, LEGACY_VERSION | CURRENT_VERSION) => {
+            decrypt_aead::<Aes256Gcm>(encrypted_data, password)
+        }
+        (CipherAlgo::XChaCha20Poly1305, CURRENT_VERSION) => {
+            decrypt_aead::<XChaCha20Poly1305>(encrypted_data, password)
+        }
+        (algo, version) => {
+            debug!("解密失败：不支持的算法 {:?} 或版本 {}", algo, version);
+            Err(DecryptError::Failed.into())
+        }
+    }
+}
 
-    // 创建AES-256-GCM解密器
-    let cipher = Aes256Gcm::new(&key);
+fn decrypt_aead<C: Aead + KeyInit>(
+    encrypted_data: &EncryptedData,
+    password: &str,
+) -> Result<Vec<u8>> {
+    // 按记录的参数派生密钥，旧数据为 密码 → SHA-256 → 32字节密钥
+    let key_bytes = encrypted_data.kdf.derive_key(password).map_err(|e| {
+        debug!("解密失败：{}", e);
+        DecryptError::Failed
+    })?;
+    let cipher = C::new_from_slice(&key_bytes).map_err(|_| DecryptError::Failed)?;
 
     // 使用存储的nonce
-    let nonce_bytes: [u8; 12] = encrypted_data.nonce.as_slice().try_into()?;
-    let nonce = Nonce::from(nonce_bytes);
+    // 长度不对时仍用全零nonce完成一次解密，避免提前返回让耗时暴露失败原因
+    let mut nonce = Nonce::<C>::default();
+    let nonce_valid = encrypted_data.nonce.len() == nonce.len();
+    if nonce_valid {
+        nonce.copy_from_slice(&encrypted_data.nonce);
+    }
 
     // 解密数据
-    let plaintext = cipher
-        .decrypt(&nonce, encrypted_data.ciphertext.as_ref())
-        .map_err(|e| anyhow!(e.to_string()))?;
+    let result = cipher.decrypt(&nonce, encrypted_data.ciphertext.as_ref());
+
+    match (nonce_valid, result) {
+        (true, Ok(plaintext)) => Ok(plaintext),
+        (false, _) => {
+            debug!(
+                "解密失败：nonce长度为 {}，应为 {}",
+                encrypted_data.nonce.len(),
+                nonce.len()
+            );
+            Err(DecryptError::Failed.into())
+        }
+        (true, Err(_)) => {
+            debug!("解密失败：认证标签校验失败（密码错误或数据已损坏）");
+            Err(DecryptError::Failed.into())
+        }
+    }
+}
+
+// 校验硬件响应时混入的固定key，校验值不会与任何条目key的派生结果相同
+const HARDWARE_CHECK_KEY: &str = "passwd-hardware-check";
+
+/// 把硬件密钥的挑战-响应混入条目key，得到实际用于加解密的key
+///
+/// 以响应为密钥对条目key做HMAC-SHA256，没有硬件密钥就无法从条目key得到相同的结果
+pub fn mix_hardware_response(key: &str, response: &[u8]) -> Result<String> {
+    let mut mac =
+        <Hmac<Sha256> as Mac>::new_from_slice(response).map_err(|e| anyhow!(e.to_string()))?;
+    mac.update(key.as_bytes());
 
-    Ok(String::from_utf8(plaintext)?)
+    Ok(mac
+        .finalize()
+        .into_bytes()
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect())
+}
+
+/// 生成硬件响应的校验值（Argon2id，包含随机盐）
+pub fn hash_hardware_response(response: &[u8]) -> Result<String> {
+    hash_master_password(&mix_hardware_response(HARDWARE_CHECK_KEY, response)?)
+}
+
+/// 校验硬件响应
+pub fn verify_hardware_response(response: &[u8], verifier: &str) -> Result<bool> {
+    verify_master_password(
+        &mix_hardware_response(HARDWARE_CHECK_KEY, response)?,
+        verifier,
+    )
+}
+
+/// 生成主密码的校验值（Argon2id，PHC字符串格式，包含随机盐）
+///
+/// 只用于校验主密码，不参与条目加密
+pub fn hash_master_password(password: &str) -> Result<String> {
+    let salt = SaltString::generate(&mut OsRng);
+    let hash = Argon2::default()
+        .hash_password(password.as_bytes(), &salt)
+        .map_err(|e| anyhow!("Failed to hash master password: {}", e))?;
+
+    Ok(hash.to_string())
+}
+
+/// 校验主密码
+///
+/// 哈希结果的比较由 argon2 以常量时间完成
+pub fn verify_master_password(password: &str, verifier: &str) -> Result<bool> {
+    let hash =
+        PasswordHash::new(verifier).map_err(|e| anyhow!("Invalid master verifier: {}", e))?;
+
+    match Argon2::default().verify_password(password.as_bytes(), &hash) {
+        Ok(()) => Ok(true),
+        Err(argon2::password_hash::Error::Password) => Ok(false),
+        Err(e) => Err(anyhow!("Failed to verify master password: {}", e)),
+    }
 }
 
 #[cfg(test)]
@@ -115,4 +517,358 @@ mod tests {
 
         assert!(t.eq(text))
     }
+
+    // 不同原因导致的解密失败对调用方完全相同
+    #[test]
+    fn decrypt_failures_are_indistinguishable() {
+        let encrypted = encrypt_with_password("secret", "key").unwrap();
+
+        let mut short_nonce = encrypted.clone();
+        short_nonce.nonce.truncate(5);
+        let mut tampered = encrypted.clone();
+        tampered.ciphertext[0] ^= 0xff;
+
+        let errors = [
+            decrypt_with_password(&encrypted, "wrong").unwrap_err(),
+            decrypt_with_password(&short_nonce, "key").unwrap_err(),
+            decrypt_with_password(&tampered, "key").unwrap_err(),
+        ];
+        for error in &errors {
+            assert_eq!(
+                error.downcast_ref::<DecryptError>(),
+                Some(&DecryptError::Failed)
+            );
+            assert_eq!(error.to_string(), "decryption failed");
+            assert!(error.source().is_none());
+            assert_eq!(format!("{:#}", error), format!("{:#}", errors[0]));
+        }
+
+        assert!(
+            decrypt_bytes_with_password(&short_nonce, "key")
+                .unwrap_err()
+                .is::<DecryptError>()
+        );
+        assert_eq!(decrypt_with_password(&encrypted, "key").unwrap(), "secret");
+    }
+
+    #[test]
+    fn binary_payload_decrypts_as_bytes() {
+        let secret = [0xff, 0xfe, 0x00, 0x80];
+        let encrypted = encrypt_bytes_with_password(&secret, "key").unwrap();
+
+        assert_eq!(
+            decrypt_bytes_with_password(&encrypted, "key").unwrap(),
+            secret
+        );
+        // 字符串接口给出明确的错误，而不是当作密钥错误
+        assert_eq!(
+            decrypt_with_password(&encrypted, "key")
+                .unwrap_err()
+                .downcast_ref::<DecryptError>(),
+            Some(&DecryptError::NotText)
+        );
+        assert_eq!(
+            decrypt_with_password(&encrypted, "wrong")
+                .unwrap_err()
+                .downcast_ref::<DecryptError>(),
+            Some(&DecryptError::Failed)
+        );
+    }
+
+    #[test]
+    fn decrypts_legacy_and_current_versions() {
+        let current =
+            encrypt_bytes_with_cipher(b"secret", "key", CipherAlgo::Aes256Gcm, KdfStrength::Fast)
+                .unwrap();
+        assert_eq!(current.version, CURRENT_VERSION);
+        assert_eq!(current.algo, CipherAlgo::Aes256Gcm);
+
+        // 旧数据只有 ciphertext 和 nonce 两个字段
+        let mut json = serde_json::to_value(&current).unwrap();
+        let fields = json.as_object_mut().unwrap();
+        fields.remove("algo");
+        fields.remove("version");
+        let legacy: EncryptedData = serde_json::from_value(json).unwrap();
+        assert_eq!(legacy.version, LEGACY_VERSION);
+        assert_eq!(legacy.algo, CipherAlgo::Aes256Gcm);
+
+        assert_eq!(decrypt_with_password(&legacy, "key").unwrap(), "secret");
+        let round_trip: EncryptedData =
+            serde_json::from_str(&serde_json::to_string(&current).unwrap()).unwrap();
+        assert_eq!(decrypt_with_password(&round_trip, "key").unwrap(), "secret");
+
+        // 未知版本不会按旧格式强行解密
+        let mut future = current.clone();
+        future.version = CURRENT_VERSION + 1;
+        assert!(decrypt_with_password(&future, "key").is_err());
+    }
+
+    #[test]
+    fn xchacha20_round_trip() {
+        let encrypted = encrypt_bytes_with_cipher(
+            "你好".as_bytes(),
+            "key",
+            CipherAlgo::XChaCha20Poly1305,
+            KdfStrength::Fast,
+        )
+        .unwrap();
+        assert_eq!(encrypted.algo, CipherAlgo::XChaCha20Poly1305);
+        assert_eq!(encrypted.nonce.len(), 24);
+        assert_eq!(decrypt_with_password(&encrypted, "key").unwrap(), "你好");
+
+        let error = decrypt_with_password(&encrypted, "wrong").unwrap_err();
+        assert_eq!(
+            error.downcast_ref::<DecryptError>(),
+            Some(&DecryptError::Failed)
+        );
+
+        // 算法标签决定解密方式，标错时无法解密
+        let mut mislabeled = encrypted.clone();
+        mislabeled.algo = CipherAlgo::Aes256Gcm;
+        assert!(decrypt_with_password(&mislabeled, "key").is_err());
+    }
+
+    // 同一个库中混合了两种算法加密的条目，都能按各自的标签解密
+    #[test]
+    fn mixed_vault_decrypts() {
+        let vault: Vec<(&str, EncryptedData)> = vec![
+            (
+                "aes",
+                encrypt_bytes_with_cipher(b"aes", "key", CipherAlgo::Aes256Gcm, KdfStrength::Fast)
+                    .unwrap(),
+            ),
+            (
+                "chacha",
+                encrypt_bytes_with_cipher(
+                    b"chacha",
+                    "key",
+                    CipherAlgo::XChaCha20Poly1305,
+                    KdfStrength::Fast,
+                )
+                .unwrap(),
+            ),
+        ];
+
+        let json = serde_json::to_string(&vault).unwrap();
+        assert!(json.contains("\"xchacha20_poly1305\""), "{}", json);
+        let loaded: Vec<(String, EncryptedData)> = serde_json::from_str(&json).unwrap();
+        for (plaintext, encrypted) in loaded {
+            assert_eq!(decrypt_with_password(&encrypted, "key").unwrap(), plaintext);
+        }
+    }
+
+    #[test]
+    fn master_password_verifier() {
+        let verifier = hash_master_password("correct horse").unwrap();
+
+        assert!(verifier.starts_with("$argon2id$"));
+        assert!(verify_master_password("correct horse", &verifier).unwrap());
+        assert!(!verify_master_password("wrong", &verifier).unwrap());
+        assert!(verify_master_password("correct horse", "not a hash").is_err());
+
+        // 每次生成的盐不同
+        assert_ne!(verifier, hash_master_password("correct horse").unwrap());
+    }
+
+    // 先返回若干次全零、之后返回固定字节的模拟随机数生成器
+    struct ZeroThenRng {
+        zeros: usize,
+        byte: u8,
+    }
+
+    impl RngCore for ZeroThenRng {
+        fn next_u32(&mut self) -> u32 {
+            u32::from_le_bytes([self.byte; 4])
+        }
+
+        fn next_u64(&mut self) -> u64 {
+            u64::from_le_bytes([self.byte; 8])
+        }
+
+        fn fill_bytes(&mut self, dst: &mut [u8]) {
+            if self.zeros > 0 {
+                self.zeros -= 1;
+                dst.fill(0);
+            } else {
+                dst.fill(self.byte);
+                self.byte = self.byte.wrapping_add(1);
+            }
+        }
+    }
+
+    #[test]
+    fn all_zero_nonce_is_regenerated() {
+        let mut rng = ZeroThenRng { zeros: 1, byte: 7 };
+        let (ciphertext, nonce) =
+            encrypt_aead_with_rng::<Aes256Gcm, _>(b"secret", &password_to_key("key"), &mut rng)
+                .unwrap();
+        assert_eq!(nonce, [7; 12]);
+
+        let encrypted = EncryptedData {
+            ciphertext,
+            nonce,
+            algo: CipherAlgo::Aes256Gcm,
+            version: CURRENT_VERSION,
+            kdf: KeyDerivation::Sha256,
+        };
+        assert_eq!(decrypt_with_password(&encrypted, "key").unwrap(), "secret");
+
+        // 生成器一直返回全零时拒绝加密
+        let mut broken = ZeroThenRng {
+            zeros: usize::MAX,
+            byte: 0,
+        };
+        assert!(
+            encrypt_aead_with_rng::<Aes256Gcm, _>(b"secret", &password_to_key("key"), &mut broken)
+                .is_err()
+        );
+        assert!(
+            encrypt_aead_with_rng::<XChaCha20Poly1305, _>(
+                b"secret",
+                &password_to_key("key"),
+                &mut broken
+            )
+            .is_err()
+        );
+    }
+
+    // 同一密码加密同一明文，nonce和密文都不同
+    #[test]
+    fn repeated_encryption_uses_fresh_nonces() {
+        use rand::SeedableRng;
+
+        let mut rng = rand::rngs::StdRng::seed_from_u64(42);
+        let first =
+            encrypt_aead_with_rng::<Aes256Gcm, _>(b"secret", &password_to_key("key"), &mut rng)
+                .unwrap();
+        let second =
+            encrypt_aead_with_rng::<Aes256Gcm, _>(b"secret", &password_to_key("key"), &mut rng)
+                .unwrap();
+        assert_ne!(first.1, second.1);
+        assert_ne!(first.0, second.0);
+
+        for algo in [CipherAlgo::Aes256Gcm, CipherAlgo::XChaCha20Poly1305] {
+            let first =
+                encrypt_bytes_with_cipher(b"secret", "key", algo, KdfStrength::Fast).unwrap();
+            let second =
+                encrypt_bytes_with_cipher(b"secret", "key", algo, KdfStrength::Fast).unwrap();
+            assert_eq!(first.nonce.len(), algo.nonce_len());
+            assert_ne!(first.nonce, second.nonce);
+            assert_ne!(first.ciphertext, second.ciphertext);
+        }
+    }
+
+    #[test]
+    fn hardware_response_changes_the_key() {
+        let first = mix_hardware_response("key", b"response-1").unwrap();
+        let second = mix_hardware_response("key", b"response-2").unwrap();
+        assert_ne!(first, second);
+        assert_ne!(first, "key");
+        // 相同的key和响应总是得到相同的结果
+        assert_eq!(first, mix_hardware_response("key", b"response-1").unwrap());
+        assert_ne!(
+            first,
+            mix_hardware_response("other", b"response-1").unwrap()
+        );
+
+        let encrypted = encrypt_with_password("secret", &first).unwrap();
+        assert!(decrypt_with_password(&encrypted, "key").is_err());
+        assert!(decrypt_with_password(&encrypted, &second).is_err());
+        assert_eq!(decrypt_with_password(&encrypted, &first).unwrap(), "secret");
+
+        let verifier = hash_hardware_response(b"response-1").unwrap();
+        assert!(verify_hardware_response(b"response-1", &verifier).unwrap());
+        assert!(!verify_hardware_response(b"response-2", &verifier).unwrap());
+    }
+
+    #[test]
+    fn deterministic_nonce_repeats_ciphertext() {
+        let first = encrypt_aead::<Aes256Gcm>(b"secret", &password_to_key("key"), true).unwrap();
+        let second = encrypt_aead::<Aes256Gcm>(b"secret", &password_to_key("key"), true).unwrap();
+        assert_eq!(first, second);
+        assert_eq!(first.1.len(), CipherAlgo::Aes256Gcm.nonce_len());
+
+        // 明文或key不同时nonce也不同
+        assert_ne!(
+            encrypt_aead::<Aes256Gcm>(b"other", &password_to_key("key"), true)
+                .unwrap()
+                .1,
+            first.1
+        );
+        assert_ne!(
+            encrypt_aead::<Aes256Gcm>(b"secret", &password_to_key("other"), true)
+                .unwrap()
+                .1,
+            first.1
+        );
+
+        let xchacha =
+            encrypt_aead::<XChaCha20Poly1305>(b"secret", &password_to_key("key"), true).unwrap();
+        assert_eq!(
+            xchacha,
+            encrypt_aead::<XChaCha20Poly1305>(b"secret", &password_to_key("key"), true).unwrap()
+        );
+        assert_eq!(xchacha.1.len(), CipherAlgo::XChaCha20Poly1305.nonce_len());
+
+        // 随机模式下每次都不同
+        let random = encrypt_aead::<Aes256Gcm>(b"secret", &password_to_key("key"), false).unwrap();
+        assert_ne!(
+            random,
+            encrypt_aead::<Aes256Gcm>(b"secret", &password_to_key("key"), false).unwrap()
+        );
+
+        // 解密不受影响
+        let data = EncryptedData {
+            ciphertext: first.0,
+            nonce: first.1,
+            algo: CipherAlgo::Aes256Gcm,
+            version: CURRENT_VERSION,
+            kdf: KeyDerivation::Sha256,
+        };
+        assert_eq!(decrypt_with_password(&data, "key").unwrap(), "secret");
+    }
+
+    #[test]
+    fn deterministic_nonce_only_applies_to_fast_kdf() {
+        let encrypt = |strength| {
+            encrypt_with_params(b"secret", "key", CipherAlgo::Aes256Gcm, strength, true).unwrap()
+        };
+
+        let fast = encrypt(KdfStrength::Fast);
+        assert_eq!(fast.ciphertext, encrypt(KdfStrength::Fast).ciphertext);
+
+        // Strong 使用随机盐，确定性nonce下密文也不同，但都能解密
+        let first = encrypt(KdfStrength::Strong);
+        let second = encrypt(KdfStrength::Strong);
+        assert_ne!(first.kdf, second.kdf);
+        assert_ne!(first.ciphertext, second.ciphertext);
+        assert_eq!(decrypt_with_password(&first, "key").unwrap(), "secret");
+        assert_eq!(decrypt_with_password(&second, "key").unwrap(), "secret");
+    }
+
+    #[test]
+    fn strong_kdf_records_its_params() {
+        let fast = encrypt_with_strength("fast", "key", KdfStrength::Fast).unwrap();
+        let strong = encrypt_with_strength("strong", "key", KdfStrength::Strong).unwrap();
+        assert_eq!(fast.kdf, KeyDerivation::Sha256);
+        assert_eq!(strong.kdf.strength(), KdfStrength::Strong);
+
+        // 派生参数随密文保存，反序列化后同一个key仍能解密
+        let strong: EncryptedData =
+            serde_json::from_str(&serde_json::to_string(&strong).unwrap()).unwrap();
+        assert_eq!(decrypt_with_password(&fast, "key").unwrap(), "fast");
+        assert_eq!(decrypt_with_password(&strong, "key").unwrap(), "strong");
+        assert!(decrypt_with_password(&strong, "other").is_err());
+
+        // 同样的key和盐换成SHA-256派生无法解密
+        let mut downgraded = strong.clone();
+        downgraded.kdf = KeyDerivation::Sha256;
+        assert!(decrypt_with_password(&downgraded, "key").is_err());
+
+        // 旧数据没有kdf字段，按SHA-256解密
+        let mut legacy = serde_json::to_value(&fast).unwrap();
+        legacy.as_object_mut().unwrap().remove("kdf");
+        let legacy: EncryptedData = serde_json::from_value(legacy).unwrap();
+        assert_eq!(decrypt_with_password(&legacy, "key").unwrap(), "fast");
+    }
 }
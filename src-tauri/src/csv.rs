@@ -0,0 +1,291 @@
+use anyhow::{Result, anyhow};
+use serde::{Deserialize, Serialize};
+
+use crate::crypto::KdfStrength;
+use crate::password::PasswordCreateRequest;
+
+/// CSV中的一条记录
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CsvRecord {
+    /// 记录起始行号（从1开始）
+    pub line: usize,
+    pub fields: Vec<String>,
+}
+
+/// 指定CSV表头与密码字段的对应关系
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CsvMapping {
+    pub title: String,
+    pub username: String,
+    pub password: String,
+    pub url: Option<String>,
+    pub tags: Option<String>,
+    #[serde(default)]
+    pub description: Option<String>,
+    /// 备注列，加密保存，不要映射到明文的描述
+    #[serde(default)]
+    pub notes: Option<String>,
+}
+
+/// 导入失败的行
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RowError {
+    pub line: usize,
+    pub reason: String,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ImportReport {
+    pub imported: usize,
+    pub skipped: Vec<RowError>,
+}
+
+/// 解析CSV文本
+///
+/// 支持双引号包裹的字段、字段内的 `""` 转义以及跨行的引号字段，
+/// 空行会被忽略
+pub fn parse_csv(text: &str) -> Result<Vec<CsvRecord>> {
+    let text = text.strip_prefix('\u{feff}').unwrap_or(text);
+
+    let mut records = Vec::new();
+    let mut fields = Vec::new();
+    let mut field = String::new();
+    let mut in_quotes = false;
+    let mut line = 1;
+    let mut record_line = 1;
+
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        if in_quotes {
+            match c {
+                '"' if chars.peek() == Some(&'"') => {
+                    chars.next();
+                    field.push('"');
+                }
+                '"' => in_quotes = false,
+                '\n' => {
+                    line += 1;
+                    field.push(c);
+                }
+                _ => field.push(c),
+            }
+            continue;
+        }
+
+        match c {
+            '"' if field.is_empty() => in_quotes = true,
+            ',' => fields.push(std::mem::take(&mut field)),
+            '\r' if chars.peek() == Some(&'\n') => {}
+            '\n' => {
+                fields.push(std::mem::take(&mut field));
+                push_record(&mut records, std::mem::take(&mut fields), record_line);
+                line += 1;
+                record_line = line;
+            }
+            _ => field.push(c),
+        }
+    }
+
+    if in_quotes {
+        return Err(anyhow!("第{}行：引号未闭合", record_line));
+    }
+
+    if !field.is_empty() || !fields.is_empty() {
+        fields.push(field);
+        push_record(&mut records, fields, record_line);
+    }
+
+    Ok(records)
+}
+
+#[inline]
+fn push_record(records: &mut Vec<CsvRecord>, fields: Vec<String>, line: usize) {
+    // 忽略空行
+    if fields.len() == 1 && fields[0].trim().is_empty() {
+        return;
+    }
+    records.push(CsvRecord { line, fields });
+}
+
+/// 映射解析后的列下标
+struct ColumnIndex {
+    title: usize,
+    username: usize,
+    password: usize,
+    url: Option<usize>,
+    tags: Option<usize>,
+    description: Option<usize>,
+    notes: Option<usize>,
+}
+
+impl ColumnIndex {
+    fn resolve(headers: &[String], mapping: &CsvMapping) -> Result<Self> {
+        let find = |name: &str| {
+            headers
+                .iter()
+                .position(|h| h.trim().eq_ignore_ascii_case(name.trim()))
+        };
+        let required = |name: &str| find(name).ok_or_else(|| anyhow!("缺少必需的列：{}", name));
+
+        Ok(Self {
+            title: required(&mapping.title)?,
+            username: required(&mapping.username)?,
+            password: required(&mapping.password)?,
+            url: mapping.url.as_deref().and_then(find),
+            tags: mapping.tags.as_deref().and_then(find),
+            description: mapping.description.as_deref().and_then(find),
+            notes: mapping.notes.as_deref().and_then(find),
+        })
+    }
+}
+
+/// 按映射把CSV文本转换为密码创建请求
+///
+/// 第一条记录视为表头；缺少必需的列时整体失败，
+/// 单行数据有问题时只跳过该行并记录在返回的 `RowError` 中
+pub fn csv_to_requests(
+    text: &str,
+    mapping: &CsvMapping,
+    key: &str,
+) -> Result<(Vec<PasswordCreateRequest>, Vec<RowError>)> {
+    let mut records = parse_csv(text)?.into_iter();
+    let header = records.next().ok_or_else(|| anyhow!("CSV内容为空"))?;
+    let columns = ColumnIndex::resolve(&header.fields, mapping)?;
+
+    let mut requests = Vec::new();
+    let mut skipped = Vec::new();
+
+    for record in records {
+        match record_to_request(&record, &columns, key) {
+            Ok(request) => requests.push(request),
+            Err(e) => skipped.push(RowError {
+                line: record.line,
+                reason: e.to_string(),
+            }),
+        }
+    }
+
+    Ok((requests, skipped))
+}
+
+fn record_to_request(
+    record: &CsvRecord,
+    columns: &ColumnIndex,
+    key: &str,
+) -> Result<PasswordCreateRequest> {
+    let get = |index: usize| -> Result<&str> {
+        record
+            .fields
+            .get(index)
+            .map(|s| s.as_str())
+            .ok_or_else(|| anyhow!("列数不足：期望至少{}列", index + 1))
+    };
+    let get_optional =
+        |index: Option<usize>| -> Result<Option<&str>> { index.map(get).transpose() };
+
+    let title = get(columns.title)?.trim();
+    let password = get(columns.password)?;
+    if title.is_empty() {
+        return Err(anyhow!("标题为空"));
+    }
+    if password.is_empty() {
+        return Err(anyhow!("密码为空"));
+    }
+
+    let tags = get_optional(columns.tags)?
+        .map(|s| {
+            s.split([',', ';'])
+                .map(str::trim)
+                .filter(|t| !t.is_empty())
+                .map(str::to_string)
+                .collect()
+        })
+        .unwrap_or_default();
+
+    let url = get_optional(columns.url)?
+        .map(str::trim)
+        .filter(|u| !u.is_empty())
+        .map(str::to_string);
+
+    let notes = get_optional(columns.notes)?
+        .filter(|n| !n.trim().is_empty())
+        .map(str::to_string);
+
+    Ok(PasswordCreateRequest {
+        title: title.to_string(),
+        description: get_optional(columns.description)?
+            .unwrap_or_default()
+            .to_string(),
+        tags,
+        username: get(columns.username)?.trim().to_string(),
+        password: password.to_string(),
+        url,
+        key: key.to_string(),
+        expires_at: None,
+        totp_secret: None,
+        notes,
+        client_id: None,
+        kdf_strength: KdfStrength::Fast,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn mapping() -> CsvMapping {
+        CsvMapping {
+            title: "title".to_string(),
+            username: "username".to_string(),
+            password: "password".to_string(),
+            url: Some("url".to_string()),
+            tags: Some("tags".to_string()),
+            description: None,
+            notes: None,
+        }
+    }
+
+    #[test]
+    fn parse_quoted_fields() {
+        let text = "a,b,c\r\n\"x, y\",\"say \"\"hi\"\"\",\"multi\nline\"\n\n1,2,3";
+        let records = parse_csv(text).unwrap();
+
+        assert_eq!(records.len(), 3);
+        assert_eq!(records[1].fields, vec!["x, y", "say \"hi\"", "multi\nline"]);
+        assert_eq!(records[2].line, 5);
+        assert_eq!(records[2].fields, vec!["1", "2", "3"]);
+    }
+
+    #[test]
+    fn parse_unterminated_quote() {
+        assert!(parse_csv("a,b\n\"oops,1").is_err());
+    }
+
+    #[test]
+    fn well_formed_file() {
+        let text = "title,username,password,url,tags\n\
+                    GitHub,alice,secret1,https://github.com,\"dev, work\"\n\
+                    Mail,bob,,https://mail.com,\n\
+                    Bank,carol,secret3,,finance\n\
+                    Short,dave\n";
+
+        let (requests, skipped) = csv_to_requests(text, &mapping(), "k").unwrap();
+
+        assert_eq!(requests.len(), 2);
+        assert_eq!(requests[0].title, "GitHub");
+        assert_eq!(requests[0].tags, vec!["dev", "work"]);
+        assert_eq!(requests[0].url.as_deref(), Some("https://github.com"));
+        assert_eq!(requests[1].url, None);
+        assert_eq!(requests[1].key, "k");
+
+        let lines: Vec<usize> = skipped.iter().map(|e| e.line).collect();
+        assert_eq!(lines, vec![3, 5]);
+    }
+
+    #[test]
+    fn missing_required_column() {
+        let text = "title,username,url\nGitHub,alice,https://github.com\n";
+        let err = csv_to_requests(text, &mapping(), "k").unwrap_err();
+        assert!(err.to_string().contains("password"));
+    }
+}
@@ -0,0 +1,205 @@
+use anyhow::{Result, anyhow};
+use argon2::Argon2;
+use sha2::{Digest, Sha256};
+
+use crate::password::{self, PasswordGeneratorConfig};
+use crate::search;
+
+/// 派生时加入盐的固定前缀，修改会使所有派生的密码改变
+const SALT_CONTEXT: &[u8] = b"passwd/site-password/v1";
+
+/// 由主密码、域名和计数器派生网站密码，不需要保存或同步任何数据
+///
+/// 主密码经 Argon2id 拉伸，盐由归一化后的域名和 `counter` 决定，
+/// 因此 `https://www.Example.com/login` 与 `example.com` 得到相同的密码。
+/// 网站要求改密码时增加 `counter` 即可。结果满足 `config` 的长度、字符类型、
+/// 排除字符和相邻不重复的要求，相同的输入总是得到相同的密码
+pub fn derive_site_password(
+    master: &str,
+    domain: &str,
+    counter: u32,
+    config: &PasswordGeneratorConfig,
+) -> Result<String> {
+    if master.is_empty() {
+        return Err(anyhow!("主密码不能为空"));
+    }
+    let domain = search::normalize_url(domain).ok_or_else(|| anyhow!("无效的域名：{}", domain))?;
+    let class_chars = password::generator_classes(config)?;
+    let available_chars = class_chars.concat();
+    if config.no_consecutive_repeats && available_chars.len() < 2 {
+        return Err(anyhow!("只有一个可用字符，无法避免相邻字符重复"));
+    }
+
+    let mut salt = Sha256::new();
+    salt.update(SALT_CONTEXT);
+    salt.update((domain.len() as u32).to_be_bytes());
+    salt.update(domain.as_bytes());
+    salt.update(counter.to_be_bytes());
+
+    let mut seed = [0u8; 32];
+    Argon2::default()
+        .hash_password_into(master.as_bytes(), &salt.finalize(), &mut seed)
+        .map_err(|e| anyhow!("派生密钥失败: {}", e))?;
+    let mut stream = KeyStream::new(seed);
+
+    // 与随机生成相同：每类各取一个，其余从全部字符中选，再打乱顺序
+    let mut password_chars: Vec<char> = class_chars
+        .iter()
+        .map(|chars| chars[stream.index(chars.len())])
+        .collect();
+    for _ in password_chars.len()..config.length {
+        password_chars.push(available_chars[stream.index(available_chars.len())]);
+    }
+    for i in (1..password_chars.len()).rev() {
+        password_chars.swap(i, stream.index(i + 1));
+    }
+
+    if config.no_consecutive_repeats {
+        for i in 1..password_chars.len() {
+            let previous = password_chars[i - 1];
+            if password_chars[i] != previous {
+                continue;
+            }
+            // 前一个位置是同样的字符，替换后每类字符仍然都会出现
+            let candidates: Vec<char> = available_chars
+                .iter()
+                .copied()
+                .filter(|c| *c != previous)
+                .collect();
+            // 不同类型中可能有相同的字符，去重后只剩一个字符时无法避免重复
+            if candidates.is_empty() {
+                return Err(anyhow!("无法生成相邻字符不重复的密码"));
+            }
+            password_chars[i] = candidates[stream.index(candidates.len())];
+        }
+    }
+
+    Ok(password_chars.into_iter().collect())
+}
+
+// 由种子展开的确定性字节流：SHA-256(种子 || 块序号)
+struct KeyStream {
+    seed: [u8; 32],
+    block: u32,
+    buf: [u8; 32],
+    pos: usize,
+}
+
+impl KeyStream {
+    fn new(seed: [u8; 32]) -> Self {
+        Self {
+            seed,
+            block: 0,
+            buf: [0; 32],
+            pos: 32,
+        }
+    }
+
+    fn next_u32(&mut self) -> u32 {
+        if self.pos + 4 > self.buf.len() {
+            let mut hasher = Sha256::new();
+            hasher.update(self.seed);
+            hasher.update(self.block.to_be_bytes());
+            self.buf = hasher.finalize().into();
+            self.block += 1;
+            self.pos = 0;
+        }
+        let bytes = self.buf[self.pos..self.pos + 4].try_into().unwrap();
+        self.pos += 4;
+        u32::from_be_bytes(bytes)
+    }
+
+    // 均匀地取 0..n 中的一个数，丢弃会造成偏差的值
+    fn index(&mut self, n: usize) -> usize {
+        let n = n as u32;
+        let zone = u32::MAX - u32::MAX % n;
+        loop {
+            let value = self.next_u32();
+            if value < zone {
+                return (value % n) as usize;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn same_inputs_same_password() {
+        let config = PasswordGeneratorConfig::default();
+        let password = derive_site_password("master", "example.com", 1, &config).unwrap();
+        assert_eq!(password.chars().count(), 16);
+
+        assert_eq!(
+            derive_site_password("master", "https://www.Example.com/login", 1, &config).unwrap(),
+            password
+        );
+        assert_ne!(
+            derive_site_password("master", "example.com", 2, &config).unwrap(),
+            password
+        );
+        assert_ne!(
+            derive_site_password("master", "example.org", 1, &config).unwrap(),
+            password
+        );
+        assert_ne!(
+            derive_site_password("master2", "example.com", 1, &config).unwrap(),
+            password
+        );
+    }
+
+    #[test]
+    fn honors_config() {
+        let config = PasswordGeneratorConfig {
+            length: 20,
+            exclude_chars: Some("O0l1".to_string()),
+            require_symbols: false,
+            no_consecutive_repeats: true,
+            ..Default::default()
+        };
+        for counter in 0..5 {
+            let password = derive_site_password("master", "example.com", counter, &config).unwrap();
+            let chars: Vec<char> = password.chars().collect();
+            assert_eq!(chars.len(), 20);
+            assert!(chars.iter().all(|c| c.is_ascii_alphanumeric()));
+            assert!(chars.iter().any(|c| c.is_ascii_uppercase()));
+            assert!(chars.iter().any(|c| c.is_ascii_lowercase()));
+            assert!(chars.iter().any(|c| c.is_ascii_digit()));
+            assert!(!password.contains(['O', '0', 'l', '1']));
+            assert!(chars.windows(2).all(|w| w[0] != w[1]));
+        }
+    }
+
+    #[test]
+    fn rejects_invalid_input() {
+        let config = PasswordGeneratorConfig::default();
+        assert!(derive_site_password("", "example.com", 1, &config).is_err());
+        assert!(derive_site_password("master", "https://", 1, &config).is_err());
+
+        let config = PasswordGeneratorConfig {
+            length: 2,
+            ..Default::default()
+        };
+        assert!(derive_site_password("master", "example.com", 1, &config).is_err());
+    }
+
+    #[test]
+    fn same_char_in_every_class_is_rejected() {
+        // 数字和特殊符号都只剩下 '0'，与随机生成一样返回错误而不是panic
+        let config = PasswordGeneratorConfig {
+            length: 4,
+            require_uppercase: false,
+            require_lowercase: false,
+            require_numbers: true,
+            require_symbols: true,
+            custom_symbols: Some("0".to_string()),
+            exclude_chars: Some("123456789".to_string()),
+            no_consecutive_repeats: true,
+            ..Default::default()
+        };
+        assert!(password::generate_password(&config).is_err());
+        assert!(derive_site_password("master", "example.com", 1, &config).is_err());
+    }
+}
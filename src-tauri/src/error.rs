@@ -0,0 +1,131 @@
+use crate::crypto::DecryptError;
+use std::fmt::Display;
+
+/// 返回给前端的错误，每个类型对应一个稳定的错误码
+///
+/// 管理器内部仍使用 `anyhow`，需要区分处理的错误以 `PasswordError` 返回，
+/// 通过 `anyhow::Error::downcast_ref` 取出；其余错误转换时按来源归类
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PasswordError {
+    /// 密码管理器尚未初始化
+    NotInitialized,
+    /// 存储点不存在或未启用
+    InvalidTarget(String),
+    /// 解密失败，不区分具体原因
+    DecryptFailed,
+    /// 条目或附件不存在
+    NotFound(String),
+    /// 已锁定，需要先解锁
+    Locked,
+    /// 网络请求失败
+    Network(String),
+    /// 只读模式下拒绝写入
+    ReadOnly,
+    /// 其它错误
+    Internal(String),
+}
+
+impl PasswordError {
+    /// 前端据此区分错误，已有的值不能修改
+    pub fn code(&self) -> isize {
+        match self {
+            PasswordError::NotInitialized => 500,
+            PasswordError::InvalidTarget(_) => 400,
+            PasswordError::DecryptFailed => 401,
+            PasswordError::NotFound(_) => 404,
+            PasswordError::Locked => 423,
+            PasswordError::Network(_) => 502,
+            PasswordError::ReadOnly => 403,
+            PasswordError::Internal(_) => -1,
+        }
+    }
+}
+
+impl Display for PasswordError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PasswordError::NotInitialized => write!(f, "Password manager not initialized"),
+            PasswordError::InvalidTarget(name) => write!(f, "Invalid storage target: {}", name),
+            PasswordError::DecryptFailed => write!(f, "decryption failed"),
+            PasswordError::NotFound(info) => write!(f, "{}", info),
+            PasswordError::Locked => write!(f, "password manager is locked"),
+            PasswordError::Network(info) => write!(f, "{}", info),
+            PasswordError::ReadOnly => write!(f, "password manager is read-only"),
+            PasswordError::Internal(info) => write!(f, "{}", info),
+        }
+    }
+}
+
+impl std::error::Error for PasswordError {}
+
+impl From<anyhow::Error> for PasswordError {
+    fn from(error: anyhow::Error) -> Self {
+        // 错误可能被 context 包装过，逐层查找
+        for cause in error.chain() {
+            if let Some(e) = cause.downcast_ref::<PasswordError>() {
+                return e.clone();
+            }
+            match cause.downcast_ref::<DecryptError>() {
+                Some(DecryptError::Failed) => return PasswordError::DecryptFailed,
+                // 密钥正确，只是内容不是文本，不能让前端当作密钥错误提示
+                Some(e @ DecryptError::NotText) => return PasswordError::Internal(e.to_string()),
+                None => {}
+            }
+            if cause.is::<reqwest::Error>() {
+                return PasswordError::Network(error.to_string());
+            }
+        }
+
+        PasswordError::Internal(error.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypto;
+
+    #[test]
+    fn codes_are_distinct() {
+        let errors = [
+            PasswordError::NotInitialized,
+            PasswordError::InvalidTarget("x".to_string()),
+            PasswordError::DecryptFailed,
+            PasswordError::NotFound("x".to_string()),
+            PasswordError::Locked,
+            PasswordError::Network("x".to_string()),
+            PasswordError::ReadOnly,
+            PasswordError::Internal("x".to_string()),
+        ];
+        let mut codes: Vec<isize> = errors.iter().map(PasswordError::code).collect();
+        codes.sort();
+        codes.dedup();
+        assert_eq!(codes.len(), errors.len());
+
+        assert_eq!(PasswordError::NotInitialized.code(), 500);
+        assert_eq!(PasswordError::Locked.code(), 423);
+    }
+
+    #[test]
+    fn classifies_anyhow_errors() {
+        let encrypted = crypto::encrypt_with_password("secret", "key").unwrap();
+        let error = crypto::decrypt_with_password(&encrypted, "wrong").unwrap_err();
+        assert_eq!(PasswordError::from(error), PasswordError::DecryptFailed);
+
+        let binary = crypto::encrypt_bytes_with_password(&[0xff, 0xfe], "key").unwrap();
+        let error = crypto::decrypt_with_password(&binary, "key").unwrap_err();
+        assert_eq!(PasswordError::from(error).code(), -1);
+
+        // 被 context 包装后仍能识别
+        let error = anyhow::Error::from(PasswordError::NotFound("密码 x 不存在".to_string()))
+            .context("Failed to save to local");
+        assert_eq!(PasswordError::from(error).code(), 404);
+
+        let error = reqwest::Client::new().get("not a url").build().unwrap_err();
+        assert_eq!(PasswordError::from(anyhow::Error::from(error)).code(), 502);
+
+        let error = PasswordError::from(anyhow::anyhow!("something else"));
+        assert_eq!(error, PasswordError::Internal("something else".to_string()));
+        assert_eq!(error.code(), -1);
+    }
+}
@@ -0,0 +1,146 @@
+use crate::password::Password;
+use serde::Serialize;
+use std::collections::HashMap;
+
+/// 密码库数据或配置保存成功后发给前端的事件名
+///
+/// payload 为 [`VaultChange`]，例如：
+///
+/// ```json
+/// { "kind": "added", "ids": ["id1", "id2"] }
+/// ```
+///
+/// `kind` 为 `added`、`updated`、`deleted` 或 `config`，
+/// `config` 表示配置已修改，此时 `ids` 为空，前端应重新加载全部数据
+pub const VAULT_CHANGED: &str = "vault-changed";
+
+/// 变化的类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ChangeKind {
+    Added,
+    Updated,
+    Deleted,
+    Config,
+}
+
+/// `vault-changed` 事件的内容
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct VaultChange {
+    pub kind: ChangeKind,
+    /// 受影响的条目id，已排序
+    pub ids: Vec<String>,
+}
+
+impl VaultChange {
+    pub fn new(kind: ChangeKind, mut ids: Vec<String>) -> Self {
+        ids.sort();
+        ids.dedup();
+        Self { kind, ids }
+    }
+
+    pub fn added(ids: Vec<String>) -> Self {
+        Self::new(ChangeKind::Added, ids)
+    }
+
+    pub fn updated(ids: Vec<String>) -> Self {
+        Self::new(ChangeKind::Updated, ids)
+    }
+
+    pub fn deleted(ids: Vec<String>) -> Self {
+        Self::new(ChangeKind::Deleted, ids)
+    }
+
+    pub fn config() -> Self {
+        Self::new(ChangeKind::Config, Vec::new())
+    }
+
+    /// 没有受影响的条目时不需要通知前端，配置变化除外
+    pub fn is_empty(&self) -> bool {
+        self.kind != ChangeKind::Config && self.ids.is_empty()
+    }
+
+    /// 比较变化前后的全部条目，按新增、修改、删除的顺序返回，没有变化的类型不返回
+    ///
+    /// 用于导入备份、修复等一次改动大量条目的操作，`updated_at` 不同即视为修改
+    pub fn diff(
+        before: &HashMap<String, Password>,
+        after: &HashMap<String, Password>,
+    ) -> Vec<VaultChange> {
+        let mut added = Vec::new();
+        let mut updated = Vec::new();
+        for (id, password) in after {
+            match before.get(id) {
+                None => added.push(id.clone()),
+                Some(old) if old.updated_at != password.updated_at => updated.push(id.clone()),
+                Some(_) => {}
+            }
+        }
+        let deleted = before
+            .keys()
+            .filter(|id| !after.contains_key(*id))
+            .cloned()
+            .collect();
+
+        [
+            Self::added(added),
+            Self::updated(updated),
+            Self::deleted(deleted),
+        ]
+        .into_iter()
+        .filter(|change| !change.is_empty())
+        .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::store::mock_store::password_entry;
+
+    #[test]
+    fn payload_shape() {
+        let change = VaultChange::added(vec!["b".to_string(), "a".to_string(), "b".to_string()]);
+        assert_eq!(
+            serde_json::to_value(&change).unwrap(),
+            serde_json::json!({ "kind": "added", "ids": ["a", "b"] })
+        );
+        assert_eq!(
+            serde_json::to_value(VaultChange::config()).unwrap(),
+            serde_json::json!({ "kind": "config", "ids": [] })
+        );
+
+        assert!(VaultChange::deleted(vec![]).is_empty());
+        assert!(!VaultChange::config().is_empty());
+    }
+
+    #[test]
+    fn diff_classifies_changes() {
+        let kept = password_entry("Kept");
+        let edited = password_entry("Edited");
+        let removed = password_entry("Removed");
+        let new = password_entry("New");
+
+        let before: HashMap<String, Password> = [&kept, &edited, &removed]
+            .into_iter()
+            .map(|p| (p.id.clone(), p.clone()))
+            .collect();
+
+        let mut edited_after = edited.clone();
+        edited_after.updated_at += chrono::Duration::seconds(1);
+        let after: HashMap<String, Password> = [&kept, &edited_after, &new]
+            .into_iter()
+            .map(|p| (p.id.clone(), p.clone()))
+            .collect();
+
+        assert_eq!(
+            VaultChange::diff(&before, &after),
+            [
+                VaultChange::added(vec![new.id.clone()]),
+                VaultChange::updated(vec![edited.id.clone()]),
+                VaultChange::deleted(vec![removed.id.clone()]),
+            ]
+        );
+        assert!(VaultChange::diff(&before, &before).is_empty());
+    }
+}
@@ -0,0 +1,61 @@
+use std::time::{Duration, Instant};
+
+/// 记录最近一次活动的时间，判断是否已闲置到应当锁定
+///
+/// 时间由调用方传入，不依赖 Tauri 运行时，便于测试
+#[derive(Debug, Clone, Copy)]
+pub struct IdleTracker {
+    last_activity: Instant,
+}
+
+impl IdleTracker {
+    pub fn new(now: Instant) -> Self {
+        Self { last_activity: now }
+    }
+
+    /// 记录一次活动，重新开始计算闲置时间
+    pub fn record_activity(&mut self, now: Instant) {
+        self.last_activity = now;
+    }
+
+    /// 闲置时间是否已达到 `timeout`；`timeout` 为 `None` 或 0 时不锁定
+    pub fn should_lock(&self, now: Instant, timeout: Option<Duration>) -> bool {
+        match timeout {
+            Some(timeout) if !timeout.is_zero() => {
+                now.saturating_duration_since(self.last_activity) >= timeout
+            }
+            _ => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn activity_resets_the_timer() {
+        let start = Instant::now();
+        let timeout = Some(Duration::from_secs(60));
+        let mut tracker = IdleTracker::new(start);
+
+        assert!(!tracker.should_lock(start + Duration::from_secs(59), timeout));
+        assert!(tracker.should_lock(start + Duration::from_secs(60), timeout));
+
+        tracker.record_activity(start + Duration::from_secs(50));
+        assert!(!tracker.should_lock(start + Duration::from_secs(100), timeout));
+        assert!(tracker.should_lock(start + Duration::from_secs(110), timeout));
+    }
+
+    #[test]
+    fn missing_or_zero_timeout_never_locks() {
+        let start = Instant::now();
+        let tracker = IdleTracker::new(start);
+        let later = start + Duration::from_secs(24 * 60 * 60);
+
+        assert!(!tracker.should_lock(later, None));
+        assert!(!tracker.should_lock(later, Some(Duration::ZERO)));
+        // 时钟早于最近一次活动时不算闲置
+        assert!(!tracker.should_lock(start, Some(Duration::from_secs(1))));
+    }
+}
@@ -0,0 +1,164 @@
+use anyhow::{Result, anyhow};
+
+use crate::csv::{self, CsvMapping};
+
+/// 已知的CSV导出格式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CsvFormat {
+    /// Chrome导出的 passwords.csv：name,url,username,password[,note]
+    Chrome,
+    /// KeePass 1.x：Account,Login Name,Password,Web Site,Comments
+    KeePass,
+    /// KeePassX / KeePassXC：Group,Title,Username,Password,URL,Notes
+    KeePassX,
+    Unknown,
+}
+
+impl CsvFormat {
+    /// 该格式对应的列映射
+    pub fn mapping(&self) -> Option<CsvMapping> {
+        match self {
+            CsvFormat::Chrome => Some(preset(
+                ["name", "username", "password", "url", "note"],
+                None,
+            )),
+            CsvFormat::KeePass => Some(preset(
+                ["Account", "Login Name", "Password", "Web Site", "Comments"],
+                None,
+            )),
+            CsvFormat::KeePassX => Some(preset(
+                ["Title", "Username", "Password", "URL", "Notes"],
+                Some("Group"),
+            )),
+            CsvFormat::Unknown => None,
+        }
+    }
+}
+
+// 列顺序：标题、用户名、密码、网址、备注；备注可能含有PIN等敏感内容，加密保存
+fn preset(columns: [&str; 5], tags: Option<&str>) -> CsvMapping {
+    let [title, username, password, url, notes] = columns.map(str::to_string);
+    CsvMapping {
+        title,
+        username,
+        password,
+        url: Some(url),
+        tags: tags.map(str::to_string),
+        description: None,
+        notes: Some(notes),
+    }
+}
+
+/// 根据表头识别CSV格式
+pub fn detect_csv_format(headers: &[String]) -> CsvFormat {
+    let headers: Vec<String> = headers.iter().map(|h| h.trim().to_lowercase()).collect();
+    let has_all = |names: &[&str]| names.iter().all(|n| headers.iter().any(|h| h == n));
+
+    if has_all(&["name", "url", "username", "password"]) {
+        CsvFormat::Chrome
+    } else if has_all(&["account", "login name", "password"]) {
+        CsvFormat::KeePass
+    } else if has_all(&["title", "username", "password"]) {
+        CsvFormat::KeePassX
+    } else {
+        CsvFormat::Unknown
+    }
+}
+
+/// 读取CSV的表头
+pub fn csv_headers(text: &str) -> Result<Vec<String>> {
+    csv::parse_csv(text)?
+        .into_iter()
+        .next()
+        .map(|r| r.fields)
+        .ok_or_else(|| anyhow!("CSV内容为空"))
+}
+
+/// 按格式名称（`auto`/`chrome`/`keepass`）得到列映射
+pub fn mapping_for_format(text: &str, format: &str) -> Result<CsvMapping> {
+    let detected = detect_csv_format(&csv_headers(text)?);
+
+    let format = match format {
+        "auto" => detected,
+        "chrome" => CsvFormat::Chrome,
+        // KeePass 1.x 与 KeePassX 的导出表头不同，按表头区分
+        "keepass" if detected == CsvFormat::KeePassX => CsvFormat::KeePassX,
+        "keepass" => CsvFormat::KeePass,
+        _ => return Err(anyhow!("不支持的CSV格式：{}", format)),
+    };
+
+    format
+        .mapping()
+        .ok_or_else(|| anyhow!("无法识别的CSV格式，请手动指定列映射"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CHROME: &str = "name,url,username,password,note\n\
+                          github.com,https://github.com/login,alice,s3cret,work account\n";
+
+    const KEEPASS: &str = "\"Account\",\"Login Name\",\"Password\",\"Web Site\",\"Comments\"\n\
+                           \"Bank\",\"bob\",\"p@ss\",\"https://bank.example\",\"pin inside\"\n";
+
+    const KEEPASSX: &str = "\"Group\",\"Title\",\"Username\",\"Password\",\"URL\",\"Notes\"\n\
+                            \"Root/Mail\",\"Mail\",\"carol\",\"pw\",\"https://mail.example\",\"\"\n";
+
+    fn headers(text: &str) -> Vec<String> {
+        csv_headers(text).unwrap()
+    }
+
+    #[test]
+    fn detect_known_formats() {
+        assert_eq!(detect_csv_format(&headers(CHROME)), CsvFormat::Chrome);
+        assert_eq!(
+            detect_csv_format(&headers("name,url,username,password")),
+            CsvFormat::Chrome
+        );
+        assert_eq!(detect_csv_format(&headers(KEEPASS)), CsvFormat::KeePass);
+        assert_eq!(detect_csv_format(&headers(KEEPASSX)), CsvFormat::KeePassX);
+        assert_eq!(
+            detect_csv_format(&headers("foo,bar\n1,2")),
+            CsvFormat::Unknown
+        );
+    }
+
+    #[test]
+    fn chrome_rows_map_to_requests() {
+        let mapping = mapping_for_format(CHROME, "auto").unwrap();
+        let (requests, skipped) = csv::csv_to_requests(CHROME, &mapping, "k").unwrap();
+
+        assert!(skipped.is_empty());
+        assert_eq!(requests[0].title, "github.com");
+        assert_eq!(requests[0].username, "alice");
+        assert_eq!(requests[0].password, "s3cret");
+        assert_eq!(requests[0].url.as_deref(), Some("https://github.com/login"));
+        assert_eq!(requests[0].notes.as_deref(), Some("work account"));
+        assert!(requests[0].description.is_empty());
+    }
+
+    #[test]
+    fn keepass_rows_map_to_requests() {
+        let mapping = mapping_for_format(KEEPASS, "keepass").unwrap();
+        let (requests, _) = csv::csv_to_requests(KEEPASS, &mapping, "k").unwrap();
+        assert_eq!(requests[0].title, "Bank");
+        assert_eq!(requests[0].username, "bob");
+        assert_eq!(requests[0].password, "p@ss");
+        assert_eq!(requests[0].notes.as_deref(), Some("pin inside"));
+        assert!(requests[0].description.is_empty());
+
+        let mapping = mapping_for_format(KEEPASSX, "keepass").unwrap();
+        let (requests, _) = csv::csv_to_requests(KEEPASSX, &mapping, "k").unwrap();
+        assert_eq!(requests[0].title, "Mail");
+        assert_eq!(requests[0].tags, vec!["Root/Mail"]);
+        // 空的备注列不生成备注
+        assert_eq!(requests[0].notes, None);
+    }
+
+    #[test]
+    fn auto_rejects_unknown_format() {
+        assert!(mapping_for_format("foo,bar\n1,2", "auto").is_err());
+        assert!(mapping_for_format(CHROME, "lastpass").is_err());
+    }
+}
@@ -1,42 +1,154 @@
+mod audit;
+mod backup;
+mod bitwarden;
+mod breach;
+mod clipboard;
 mod config;
 mod crypto;
+mod csv;
+mod derive;
+mod error;
+mod event;
+mod idle;
+mod import;
 mod log;
 mod manager;
 mod password;
+mod pending;
+mod reveal;
+mod search;
 mod store;
+mod totp;
 
+use audit::AuditEvent;
 use config::Config;
 use crypto::EncryptedData;
-use manager::PasswordManager;
-use password::{Password, PasswordCreateRequest, PasswordGeneratorConfig};
+use csv::{CsvMapping, ImportReport};
+use error::PasswordError;
+use manager::{
+    BatchReport, ConfigPreview, Conflict, IntegrityReport, KeyVerifyReport, PageResult,
+    PasswordManager, ReencryptReport, SelfTestReport, StorageStatus, VaultStats, WeakEntry,
+};
+use password::{
+    Password, PasswordCreateRequest, PasswordCreateRequestNoPassword, PasswordGeneratorConfig,
+    PasswordSummary, SortKey, ValidationResult,
+};
+use reveal::RevealToken;
+use search::{ScoredPassword, SearchHit, SearchScope};
+use std::collections::HashMap;
 use std::path::PathBuf;
 use std::sync::OnceLock;
+use std::time::Duration;
+use store::LoadWarning;
 use store::StorageData;
-use store::StorageTarget;
+use store::StorageId;
+use store::VersionInfo;
+use tauri::{Emitter, Manager};
+use totp::TotpCode;
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run_tauri_app() {
     tauri::Builder::default()
         .plugin(tauri_plugin_opener::init())
         .plugin(tauri_plugin_fs::init())
+        .plugin(tauri_plugin_clipboard_manager::init())
         .manage(AppState {
             password_manager: OnceLock::new(),
             // config: Arc::new(RwLock::new(Config::default())),
         })
         .setup(|app| {
             init(app.handle())?;
+            spawn_idle_lock(app.handle().clone());
+            spawn_cache_refresh(app.handle().clone());
+            spawn_pending_flush(app.handle().clone());
             Ok(())
         })
-        .invoke_handler(tauri::generate_handler![
+        .invoke_handler(with_activity(tauri::generate_handler![
             initialize_manager,
             add_password,
+            add_passwords,
+            create_with_generated,
             delete_password,
+            delete_passwords,
             search_passwords,
+            search_passwords_scoped,
+            search_passwords_ranked,
+            search_passwords_paged,
+            find_by_url,
+            get_all_passwords,
             get_all_passwords_from_storage,
+            list_summaries,
+            refresh_cache,
+            refresh_all_cache,
+            get_pending_sync,
+            retry_failed_saves,
+            flush_pending,
+            list_vault_versions,
+            restore_vault_version,
+            move_github_vault,
+            wipe_storage,
+            move_entry,
+            self_test,
+            audit_log,
+            vault_stats,
+            weak_password_report,
             decrypt_password,
+            decrypt_password_by_id,
+            request_reveal,
+            consume_reveal,
+            decrypt_many,
+            copy_password_to_clipboard,
+            lock,
+            unlock,
+            unlock_with_hardware,
+            enable_hardware_factor,
+            set_master_password,
+            complete_first_setup,
+            set_read_only,
+            verify_master,
+            reencrypt_all,
+            verify_key,
+            rekey_entry,
+            get_expiring_passwords,
+            get_stale_passwords,
+            generate_totp,
+            decrypt_notes,
+            password_breach_hashes,
+            check_breach,
+            add_attachment,
+            get_attachment,
+            remove_attachment,
             generate_password,
+            generate_passwords,
+            generate_pronounceable,
+            generate_for_policy,
+            validate_password,
+            derive_site_password,
             update_config,
-        ])
+            preview_config,
+            import_csv,
+            export_backup,
+            export_bitwarden_json,
+            import_bitwarden_json,
+            import_backup,
+            test_storage_connection,
+            get_storage_status,
+            get_conflicts,
+            resolve_conflict,
+            check_integrity,
+            repair_integrity,
+            get_load_warnings,
+            list_passwords,
+            list_tags,
+            filter_by_tags,
+            rename_tag,
+            set_entry_tags,
+            sort_all_tags_alphabetically,
+            delete_tag,
+            toggle_favorite,
+            get_favorites,
+            set_auto_lock_secs,
+        ]))
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
 }
@@ -45,13 +157,18 @@ static CONF_PATH: OnceLock<PathBuf> = OnceLock::new();
 static DATA_PATH: OnceLock<PathBuf> = OnceLock::new();
 
 fn init(app: &tauri::AppHandle) -> anyhow::Result<()> {
-    let conf_path = Config::get_config_path(app)?;
+    // 日志级别在加载配置后更新
+    let log_path = app.path().app_log_dir()?.join("passwd.log");
+    log::init(log_path, log::LogLevel::default())?;
+
+    let overrides = config::PathOverrides::from_env_and_args();
+    let conf_path = Config::get_config_path(app, &overrides)?;
 
     CONF_PATH
         .set(conf_path)
         .map_err(|_| anyhow::anyhow!("CONF_PATH已初始化"))?;
 
-    let data_path = Config::get_data_path(app)?;
+    let data_path = Config::get_data_path(app, &overrides)?;
     DATA_PATH
         .set(data_path)
         .map_err(|_| anyhow::anyhow!("DATA_PATH已初始化"))?;
@@ -69,6 +186,86 @@ fn init(app: &tauri::AppHandle) -> anyhow::Result<()> {
     Ok(())
 }
 
+// 前端每次调用命令都算一次活动，重新开始计算闲置锁定的时间
+fn with_activity<R: tauri::Runtime>(
+    handler: impl Fn(tauri::ipc::Invoke<R>) -> bool + Send + Sync + 'static,
+) -> impl Fn(tauri::ipc::Invoke<R>) -> bool + Send + Sync + 'static {
+    move |invoke| {
+        if let Some(manager) = invoke
+            .message
+            .webview_ref()
+            .state::<AppState>()
+            .password_manager
+            .get()
+        {
+            manager.record_activity();
+        }
+        handler(invoke)
+    }
+}
+
+// 闲置锁定的检查间隔
+const IDLE_CHECK_INTERVAL: Duration = Duration::from_secs(5);
+
+// 定期检查闲置时间，超过配置的 auto_lock_secs 后锁定
+fn spawn_idle_lock(app: tauri::AppHandle) {
+    tauri::async_runtime::spawn(async move {
+        let mut interval = tokio::time::interval(IDLE_CHECK_INTERVAL);
+        loop {
+            interval.tick().await;
+
+            let state = app.state::<AppState>();
+            if let Some(manager) = state.password_manager.get()
+                && manager.lock_if_idle().await
+            {
+                info!("闲置超时，已自动锁定");
+            }
+        }
+    });
+}
+
+// 缓存过期的检查间隔
+const CACHE_REFRESH_CHECK_INTERVAL: Duration = Duration::from_secs(30);
+
+// 定期检查缓存，超过配置的 cache_ttl_secs 后在后台重新加载
+fn spawn_cache_refresh(app: tauri::AppHandle) {
+    tauri::async_runtime::spawn(async move {
+        let mut interval = tokio::time::interval(CACHE_REFRESH_CHECK_INTERVAL);
+        loop {
+            interval.tick().await;
+
+            let state = app.state::<AppState>();
+            if let Some(manager) = state.password_manager.get()
+                && let Err(e) = manager.refresh_if_stale().await
+            {
+                error!("后台刷新缓存失败：{}", e);
+            }
+        }
+    });
+}
+
+// 待同步队列的重试间隔
+const PENDING_FLUSH_INTERVAL: Duration = Duration::from_secs(60);
+
+// 定期把离线期间保存失败的数据重新写入存储点，网络恢复后自动同步
+fn spawn_pending_flush(app: tauri::AppHandle) {
+    tauri::async_runtime::spawn(async move {
+        let mut interval = tokio::time::interval(PENDING_FLUSH_INTERVAL);
+        loop {
+            interval.tick().await;
+
+            let state = app.state::<AppState>();
+            if let Some(manager) = state.password_manager.get() {
+                match manager.flush_pending().await {
+                    Ok(0) => {}
+                    Ok(flushed) => info!("已同步 {} 个存储点的离线修改", flushed),
+                    Err(e) => warn!("同步离线修改失败，稍后重试：{}", e),
+                }
+            }
+        }
+    });
+}
+
 // 为什么这里需要一个OnceLock呢
 // 因为password_manager这个变量需要延迟初始化
 // 或至少等到app实例创建之后才能初始化
@@ -90,20 +287,31 @@ struct ErrorInfo {
 #[derive(serde::Serialize)]
 struct InitializeResult {
     is_first_setup: bool,
-    // has_encrypted_data: bool,
+    /// 任一存储点中已有数据
+    has_encrypted_data: bool,
+    /// 各存储点加载到的条目数，key为存储点id
+    per_target_counts: HashMap<String, usize>,
 }
 
-impl From<anyhow::Error> for ErrorInfo {
-    fn from(error: anyhow::Error) -> Self {
+// 错误码见 `PasswordError::code`，例如已锁定时为423，前端据此跳转到解锁界面
+impl From<PasswordError> for ErrorInfo {
+    fn from(error: PasswordError) -> Self {
         ErrorInfo {
-            code: -1,
+            code: error.code(),
             info: error.to_string(),
         }
     }
 }
 
+impl From<anyhow::Error> for ErrorInfo {
+    fn from(error: anyhow::Error) -> Self {
+        PasswordError::from(error).into()
+    }
+}
+
 #[tauri::command]
 async fn initialize_manager(
+    app: tauri::AppHandle,
     state: tauri::State<'_, AppState>,
 ) -> Result<InitializeResult, ErrorInfo> {
     let conf_path = CONF_PATH.get().expect("[内部错误] sys init error");
@@ -113,16 +321,25 @@ async fn initialize_manager(
     if conf_path.exists() {
         info!("配置文件存在，加载配置");
         config = Config::load_from_file(conf_path)?;
+        config.validate()?;
     } else {
         info!("配置文件不存在，创建默认配置");
         config.save_to_file(conf_path)?;
     }
 
     info!("配置：{:?}", &config);
+    log::set_level(config.log_level);
+    crypto::set_preferred_cipher(config.cipher);
+    crypto::set_deterministic_nonce(config.deterministic_nonce);
 
     let is_first_setup = config.is_first_setup;
 
     let password_manager = PasswordManager::new(config).await?;
+    password_manager.set_change_listener(move |change| {
+        if let Err(e) = app.emit(event::VAULT_CHANGED, change) {
+            error!("发送 {} 事件失败：{}", event::VAULT_CHANGED, e);
+        }
+    });
 
     info!("密码管理器初始化完成");
 
@@ -132,12 +349,19 @@ async fn initialize_manager(
     //     .await
     //     .is_first_setup;
 
+    let per_target_counts = password_manager.storage_counts();
+    let has_encrypted_data = per_target_counts.values().any(|count| *count > 0);
+
     // 更新状态
     if state.password_manager.set(password_manager).is_err() {
         panic!("[内部错误] sys init error");
     }
 
-    Ok(InitializeResult { is_first_setup })
+    Ok(InitializeResult {
+        is_first_setup,
+        has_encrypted_data,
+        per_target_counts,
+    })
 }
 
 #[tauri::command]
@@ -147,10 +371,10 @@ async fn add_password(
 ) -> Result<(), ErrorInfo> {
     info!("添加密码请求：{:?}", &request);
 
-    let manager = state.password_manager.get().ok_or_else(|| ErrorInfo {
-        code: 500,
-        info: "Password manager not initialized".to_string(),
-    })?;
+    let manager = state
+        .password_manager
+        .get()
+        .ok_or(PasswordError::NotInitialized)?;
 
     manager.add_password(request).await.map_err(ErrorInfo::from)
 }
@@ -160,10 +384,10 @@ async fn delete_password(
     password_id: String,
     state: tauri::State<'_, AppState>,
 ) -> Result<(), ErrorInfo> {
-    let manager = state.password_manager.get().ok_or_else(|| ErrorInfo {
-        code: 500,
-        info: "Password manager not initialized".to_string(),
-    })?;
+    let manager = state
+        .password_manager
+        .get()
+        .ok_or(PasswordError::NotInitialized)?;
 
     manager
         .delete_password(&password_id)
@@ -171,46 +395,255 @@ async fn delete_password(
         .map_err(ErrorInfo::from)
 }
 
+// 生成密码并直接保存，返回的条目中只有加密后的密码
+#[tauri::command]
+async fn create_with_generated(
+    request: PasswordCreateRequestNoPassword,
+    gen_config: PasswordGeneratorConfig,
+    key: String,
+    state: tauri::State<'_, AppState>,
+) -> Result<Password, ErrorInfo> {
+    info!("生成并添加密码请求：{:?}", &request);
+
+    let manager = state
+        .password_manager
+        .get()
+        .ok_or(PasswordError::NotInitialized)?;
+
+    manager
+        .create_with_generated(request, gen_config, &key)
+        .await
+        .map_err(ErrorInfo::from)
+}
+
+// 批量添加密码，只保存一次
+#[tauri::command]
+async fn add_passwords(
+    requests: Vec<PasswordCreateRequest>,
+    state: tauri::State<'_, AppState>,
+) -> Result<BatchReport, ErrorInfo> {
+    info!("批量添加密码请求：{} 条", requests.len());
+
+    let manager = state
+        .password_manager
+        .get()
+        .ok_or(PasswordError::NotInitialized)?;
+
+    manager
+        .add_passwords(requests)
+        .await
+        .map_err(ErrorInfo::from)
+}
+
+// 批量删除密码，只保存一次
+#[tauri::command]
+async fn delete_passwords(
+    password_ids: Vec<String>,
+    state: tauri::State<'_, AppState>,
+) -> Result<BatchReport, ErrorInfo> {
+    let manager = state
+        .password_manager
+        .get()
+        .ok_or(PasswordError::NotInitialized)?;
+
+    manager
+        .delete_passwords(&password_ids)
+        .await
+        .map_err(ErrorInfo::from)
+}
+
 #[tauri::command]
 async fn search_passwords(
     query: String,
     state: tauri::State<'_, AppState>,
 ) -> Result<Vec<Password>, ErrorInfo> {
-    let manager = state.password_manager.get().ok_or_else(|| ErrorInfo {
-        code: 500,
-        info: "Password manager not initialized".to_string(),
-    })?;
+    let manager = state
+        .password_manager
+        .get()
+        .ok_or(PasswordError::NotInitialized)?;
     manager
         .search_passwords(&query)
         .await
         .map_err(ErrorInfo::from)
 }
 
+// 只在指定字段中搜索，fields 为 title、description、username、url、tags 的组合
+#[tauri::command]
+async fn search_passwords_scoped(
+    query: String,
+    fields: Vec<String>,
+    state: tauri::State<'_, AppState>,
+) -> Result<Vec<Password>, ErrorInfo> {
+    let manager = state
+        .password_manager
+        .get()
+        .ok_or(PasswordError::NotInitialized)?;
+    let scope = SearchScope::from_names(&fields).map_err(ErrorInfo::from)?;
+    manager
+        .search_passwords_scoped(&query, scope)
+        .await
+        .map_err(ErrorInfo::from)
+}
+
+// 搜索并返回匹配位置，用于高亮，limit 为空时返回全部结果
+#[tauri::command]
+async fn search_passwords_paged(
+    query: String,
+    limit: Option<usize>,
+    state: tauri::State<'_, AppState>,
+) -> Result<Vec<SearchHit>, ErrorInfo> {
+    let manager = state
+        .password_manager
+        .get()
+        .ok_or(PasswordError::NotInitialized)?;
+    manager
+        .search_passwords_paged(&query, limit)
+        .await
+        .map_err(ErrorInfo::from)
+}
+
+// 按相关度排序的搜索，fuzzy 为true时允许拼写错误
+#[tauri::command]
+async fn search_passwords_ranked(
+    query: String,
+    fuzzy: bool,
+    state: tauri::State<'_, AppState>,
+) -> Result<Vec<ScoredPassword>, ErrorInfo> {
+    let manager = state
+        .password_manager
+        .get()
+        .ok_or(PasswordError::NotInitialized)?;
+    manager
+        .search_passwords_ranked(&query, fuzzy)
+        .await
+        .map_err(ErrorInfo::from)
+}
+
+// 查找与网址匹配的条目，用于自动填充
+#[tauri::command]
+async fn find_by_url(
+    url: String,
+    state: tauri::State<'_, AppState>,
+) -> Result<Vec<Password>, ErrorInfo> {
+    let manager = state
+        .password_manager
+        .get()
+        .ok_or(PasswordError::NotInitialized)?;
+    manager.find_by_url(&url).await.map_err(ErrorInfo::from)
+}
+
 #[tauri::command]
 async fn decrypt_password(
     password: EncryptedData,
     user_password: String,
     state: tauri::State<'_, AppState>,
 ) -> Result<String, ErrorInfo> {
-    let manager = state.password_manager.get().ok_or_else(|| ErrorInfo {
-        code: 500,
-        info: "Password manager not initialized".to_string(),
-    })?;
+    let manager = state
+        .password_manager
+        .get()
+        .ok_or(PasswordError::NotInitialized)?;
     manager
         .decrypt_password(&user_password, &password)
         .await
         .map_err(ErrorInfo::from)
 }
 
+// 用同一个密钥批量解密多个条目，单个失败不影响其它条目
+#[tauri::command]
+async fn decrypt_many(
+    ids: Vec<String>,
+    key: String,
+    state: tauri::State<'_, AppState>,
+) -> Result<Vec<(String, Result<String, String>)>, ErrorInfo> {
+    let manager = state
+        .password_manager
+        .get()
+        .ok_or(PasswordError::NotInitialized)?;
+    manager
+        .decrypt_many(&ids, &key)
+        .await
+        .map_err(ErrorInfo::from)
+}
+
+// 按id解密，并记录最近使用时间
+#[tauri::command]
+async fn decrypt_password_by_id(
+    password_id: String,
+    key: String,
+    state: tauri::State<'_, AppState>,
+) -> Result<String, ErrorInfo> {
+    let manager = state
+        .password_manager
+        .get()
+        .ok_or(PasswordError::NotInitialized)?;
+    manager
+        .decrypt_password_by_id(&password_id, &key)
+        .await
+        .map_err(ErrorInfo::from)
+}
+
+// 解密密码并暂存在后端，返回取走明文用的一次性凭据，明文不经过这次调用返回
+#[tauri::command]
+async fn request_reveal(
+    password_id: String,
+    key: String,
+    state: tauri::State<'_, AppState>,
+) -> Result<RevealToken, ErrorInfo> {
+    let manager = state
+        .password_manager
+        .get()
+        .ok_or(PasswordError::NotInitialized)?;
+    manager
+        .request_reveal(&password_id, &key)
+        .await
+        .map_err(ErrorInfo::from)
+}
+
+// 凭一次性凭据取走明文，取走后或过期后凭据失效
+#[tauri::command]
+async fn consume_reveal(
+    token: String,
+    state: tauri::State<'_, AppState>,
+) -> Result<String, ErrorInfo> {
+    let manager = state
+        .password_manager
+        .get()
+        .ok_or(PasswordError::NotInitialized)?;
+    manager
+        .consume_reveal(&token)
+        .await
+        .map_err(ErrorInfo::from)
+}
+
+// 解密后复制到剪贴板，clear_after_secs 秒后清空（为0则不清空）
+#[tauri::command]
+async fn copy_password_to_clipboard(
+    id: String,
+    key: String,
+    clear_after_secs: u64,
+    app: tauri::AppHandle,
+    state: tauri::State<'_, AppState>,
+) -> Result<(), ErrorInfo> {
+    let manager = state
+        .password_manager
+        .get()
+        .ok_or(PasswordError::NotInitialized)?;
+
+    let password = manager.decrypt_password_by_id(&id, &key).await?;
+
+    clipboard::copy_with_timeout(&app, password, Duration::from_secs(clear_after_secs))
+        .map_err(ErrorInfo::from)
+}
+
 #[tauri::command]
 async fn generate_password(
     config: PasswordGeneratorConfig,
     state: tauri::State<'_, AppState>,
 ) -> Result<String, ErrorInfo> {
-    let manager = state.password_manager.get().ok_or_else(|| ErrorInfo {
-        code: 500,
-        info: "Password manager not initialized".to_string(),
-    })?;
+    let manager = state
+        .password_manager
+        .get()
+        .ok_or(PasswordError::NotInitialized)?;
 
     manager
         .generate_password(&config)
@@ -218,46 +651,1103 @@ async fn generate_password(
         .map_err(ErrorInfo::from)
 }
 
+// 一次生成多个互不相同的候选密码
+#[tauri::command]
+async fn generate_passwords(
+    config: PasswordGeneratorConfig,
+    count: usize,
+    state: tauri::State<'_, AppState>,
+) -> Result<Vec<String>, ErrorInfo> {
+    let manager = state
+        .password_manager
+        .get()
+        .ok_or(PasswordError::NotInitialized)?;
+
+    manager
+        .generate_passwords(&config, count)
+        .await
+        .map_err(ErrorInfo::from)
+}
+
+// 由主密码、域名和计数器派生网站密码
+#[tauri::command]
+async fn derive_site_password(
+    master: String,
+    domain: String,
+    counter: u32,
+    config: PasswordGeneratorConfig,
+    state: tauri::State<'_, AppState>,
+) -> Result<String, ErrorInfo> {
+    let manager = state
+        .password_manager
+        .get()
+        .ok_or(PasswordError::NotInitialized)?;
+
+    manager
+        .derive_site_password(&master, &domain, counter, &config)
+        .await
+        .map_err(ErrorInfo::from)
+}
+
+// 按网站的密码策略生成密码，如 "len=16;nosymbol"
+#[tauri::command]
+async fn generate_for_policy(
+    policy: String,
+    state: tauri::State<'_, AppState>,
+) -> Result<String, ErrorInfo> {
+    let manager = state
+        .password_manager
+        .get()
+        .ok_or(PasswordError::NotInitialized)?;
+
+    manager
+        .generate_for_policy(&policy)
+        .await
+        .map_err(ErrorInfo::from)
+}
+
+// 检查用户输入的密码是否满足生成器的规则，返回每条不满足的规则
+#[tauri::command]
+async fn validate_password(
+    password: String,
+    config: PasswordGeneratorConfig,
+    state: tauri::State<'_, AppState>,
+) -> Result<ValidationResult, ErrorInfo> {
+    let manager = state
+        .password_manager
+        .get()
+        .ok_or(PasswordError::NotInitialized)?;
+
+    manager
+        .validate_password(&password, &config)
+        .await
+        .map_err(ErrorInfo::from)
+}
+
+// 生成便于朗读的密码
+#[tauri::command]
+async fn generate_pronounceable(
+    length: usize,
+    capitalize: bool,
+    with_digit: bool,
+    state: tauri::State<'_, AppState>,
+) -> Result<String, ErrorInfo> {
+    let manager = state
+        .password_manager
+        .get()
+        .ok_or(PasswordError::NotInitialized)?;
+
+    manager
+        .generate_pronounceable(length, capitalize, with_digit)
+        .await
+        .map_err(ErrorInfo::from)
+}
+
+// 解析前端传入的存储点名称
+async fn resolve_storage(
+    manager: &PasswordManager,
+    storage_target: &str,
+) -> Result<StorageId, ErrorInfo> {
+    manager
+        .resolve_storage(storage_target)
+        .await
+        .map_err(ErrorInfo::from)
+}
+
+// 获取所有存储点中的密码，同一条目只返回最新的版本
+#[tauri::command]
+async fn get_all_passwords(state: tauri::State<'_, AppState>) -> Result<Vec<Password>, ErrorInfo> {
+    let manager = state
+        .password_manager
+        .get()
+        .ok_or(PasswordError::NotInitialized)?;
+
+    manager.get_all_passwords().await.map_err(ErrorInfo::from)
+}
+
 #[tauri::command]
 async fn get_all_passwords_from_storage(
     storage_target: String,
     state: tauri::State<'_, AppState>,
 ) -> Result<StorageData, ErrorInfo> {
-    let manager = state.password_manager.get().ok_or_else(|| ErrorInfo {
-        code: 500,
-        info: "Password manager not initialized".to_string(),
-    })?;
-
-    let target = match storage_target.as_str() {
-        "local" => StorageTarget::Local,
-        "github" => StorageTarget::GitHub,
-        _ => {
-            return Err(ErrorInfo {
-                code: 400,
-                info: "Invalid storage target".to_string(),
-            });
-        }
-    };
+    let manager = state
+        .password_manager
+        .get()
+        .ok_or(PasswordError::NotInitialized)?;
+
+    let id = resolve_storage(manager, &storage_target).await?;
 
     manager
-        .get_all_passwords_from_storage(target)
+        .get_all_passwords_from_storage(&id)
         .await
         .map_err(ErrorInfo::from)
 }
 
-// 更新配置
+// 列表显示用的条目摘要，不包含密文
 #[tauri::command]
-async fn update_config(
-    new_config: Config,
+async fn list_summaries(
+    storage_target: String,
+    state: tauri::State<'_, AppState>,
+) -> Result<Vec<PasswordSummary>, ErrorInfo> {
+    let manager = state
+        .password_manager
+        .get()
+        .ok_or(PasswordError::NotInitialized)?;
+
+    let id = resolve_storage(manager, &storage_target).await?;
+
+    manager.list_summaries(&id).await.map_err(ErrorInfo::from)
+}
+
+// 从指定存储点重新加载数据，获取其它设备的修改
+#[tauri::command]
+async fn refresh_cache(
+    storage_target: String,
     state: tauri::State<'_, AppState>,
 ) -> Result<(), ErrorInfo> {
-    let manager = state.password_manager.get().ok_or_else(|| ErrorInfo {
-        code: 500,
-        info: "Password manager not initialized".to_string(),
-    })?;
+    let manager = state
+        .password_manager
+        .get()
+        .ok_or(PasswordError::NotInitialized)?;
+
+    let id = resolve_storage(manager, &storage_target).await?;
+
+    manager.refresh_cache(&id).await.map_err(ErrorInfo::from)
+}
+
+// 从所有存储点重新加载数据
+#[tauri::command]
+async fn refresh_all_cache(state: tauri::State<'_, AppState>) -> Result<(), ErrorInfo> {
+    let manager = state
+        .password_manager
+        .get()
+        .ok_or(PasswordError::NotInitialized)?;
+
+    manager.refresh_all_cache().await.map_err(ErrorInfo::from)
+}
+
+// 保存失败、等待重试的存储点
+#[tauri::command]
+async fn get_pending_sync(state: tauri::State<'_, AppState>) -> Result<Vec<StorageId>, ErrorInfo> {
+    let manager = state
+        .password_manager
+        .get()
+        .ok_or(PasswordError::NotInitialized)?;
+
+    Ok(manager.pending_sync())
+}
+
+// 把缓存重新写入之前保存失败的存储点
+#[tauri::command]
+async fn retry_failed_saves(state: tauri::State<'_, AppState>) -> Result<(), ErrorInfo> {
+    let manager = state
+        .password_manager
+        .get()
+        .ok_or(PasswordError::NotInitialized)?;
+
+    manager.retry_failed_saves().await.map_err(ErrorInfo::from)
+}
+
+// 把磁盘上待同步队列中的数据写入存储点，返回写入成功的存储点数
+#[tauri::command]
+async fn flush_pending(state: tauri::State<'_, AppState>) -> Result<usize, ErrorInfo> {
+    let manager = state
+        .password_manager
+        .get()
+        .ok_or(PasswordError::NotInitialized)?;
+
+    manager.flush_pending().await.map_err(ErrorInfo::from)
+}
+
+// 列出存储点的历史版本（目前仅 GitHub）
+#[tauri::command]
+async fn list_vault_versions(
+    storage_target: String,
+    state: tauri::State<'_, AppState>,
+) -> Result<Vec<VersionInfo>, ErrorInfo> {
+    let manager = state
+        .password_manager
+        .get()
+        .ok_or(PasswordError::NotInitialized)?;
+
+    let id = resolve_storage(manager, &storage_target).await?;
 
     manager
-        .update_config(new_config)
+        .list_vault_versions(&id)
+        .await
+        .map_err(ErrorInfo::from)
+}
+
+// 把密码库恢复为存储点的某个历史版本
+#[tauri::command]
+async fn restore_vault_version(
+    storage_target: String,
+    sha: String,
+    state: tauri::State<'_, AppState>,
+) -> Result<(), ErrorInfo> {
+    let manager = state
+        .password_manager
+        .get()
+        .ok_or(PasswordError::NotInitialized)?;
+
+    let id = resolve_storage(manager, &storage_target).await?;
+
+    manager
+        .restore_vault_version(&id, &sha)
+        .await
+        .map_err(ErrorInfo::from)
+}
+
+// 把 GitHub 存储点的数据文件移动到新路径，并保存配置
+#[tauri::command]
+async fn move_github_vault(
+    storage_target: String,
+    new_path: String,
+    state: tauri::State<'_, AppState>,
+) -> Result<(), ErrorInfo> {
+    let manager = state
+        .password_manager
+        .get()
+        .ok_or(PasswordError::NotInitialized)?;
+
+    let id = resolve_storage(manager, &storage_target).await?;
+
+    manager
+        .move_github_vault(&id, &new_path)
+        .await
+        .map_err(ErrorInfo::from)
+}
+
+// 列出强度不足的密码，不返回明文
+#[tauri::command]
+async fn weak_password_report(
+    key: String,
+    state: tauri::State<'_, AppState>,
+) -> Result<Vec<WeakEntry>, ErrorInfo> {
+    let manager = state
+        .password_manager
+        .get()
+        .ok_or(PasswordError::NotInitialized)?;
+
+    manager
+        .weak_password_report(&key)
+        .await
+        .map_err(ErrorInfo::from)
+}
+
+// 统计密码库的条目数、标签和更新时间等汇总信息
+#[tauri::command]
+async fn vault_stats(state: tauri::State<'_, AppState>) -> Result<VaultStats, ErrorInfo> {
+    let manager = state
+        .password_manager
+        .get()
+        .ok_or(PasswordError::NotInitialized)?;
+
+    manager.vault_stats().await.map_err(ErrorInfo::from)
+}
+
+// 读取最近的审计记录，按时间从旧到新
+#[tauri::command]
+async fn audit_log(
+    limit: usize,
+    state: tauri::State<'_, AppState>,
+) -> Result<Vec<AuditEvent>, ErrorInfo> {
+    let manager = state
+        .password_manager
+        .get()
+        .ok_or(PasswordError::NotInitialized)?;
+
+    manager.audit_log(limit).await.map_err(ErrorInfo::from)
+}
+
+// 自检加密、配置和各存储点的连接
+#[tauri::command]
+async fn self_test(state: tauri::State<'_, AppState>) -> Result<SelfTestReport, ErrorInfo> {
+    let manager = state
+        .password_manager
+        .get()
+        .ok_or(PasswordError::NotInitialized)?;
+
+    Ok(manager.self_test().await)
+}
+
+// 把单个条目从一个存储点移到另一个存储点
+#[tauri::command]
+async fn move_entry(
+    password_id: String,
+    from: String,
+    to: String,
+    state: tauri::State<'_, AppState>,
+) -> Result<(), ErrorInfo> {
+    let manager = state
+        .password_manager
+        .get()
+        .ok_or(PasswordError::NotInitialized)?;
+
+    let from = resolve_storage(manager, &from).await?;
+    let to = resolve_storage(manager, &to).await?;
+
+    manager
+        .move_entry(&password_id, &from, &to)
+        .await
+        .map_err(ErrorInfo::from)
+}
+
+// 清空单个存储点的全部数据，需要输入确认语
+#[tauri::command]
+async fn wipe_storage(
+    storage_target: String,
+    confirm: String,
+    state: tauri::State<'_, AppState>,
+) -> Result<(), ErrorInfo> {
+    let manager = state
+        .password_manager
+        .get()
+        .ok_or(PasswordError::NotInitialized)?;
+
+    let id = resolve_storage(manager, &storage_target).await?;
+
+    manager
+        .wipe_storage(&id, &confirm)
+        .await
+        .map_err(ErrorInfo::from)
+}
+
+// 分页列出密码
+#[tauri::command]
+async fn list_passwords(
+    storage_target: String,
+    page: usize,
+    page_size: usize,
+    sort: SortKey,
+    state: tauri::State<'_, AppState>,
+) -> Result<PageResult<Password>, ErrorInfo> {
+    let manager = state
+        .password_manager
+        .get()
+        .ok_or(PasswordError::NotInitialized)?;
+
+    let id = resolve_storage(manager, &storage_target).await?;
+
+    manager
+        .list_passwords(&id, page, page_size, sort)
+        .await
+        .map_err(ErrorInfo::from)
+}
+
+// 列出所有标签及数量
+#[tauri::command]
+async fn list_tags(state: tauri::State<'_, AppState>) -> Result<Vec<(String, usize)>, ErrorInfo> {
+    let manager = state
+        .password_manager
+        .get()
+        .ok_or(PasswordError::NotInitialized)?;
+
+    manager.list_tags().await.map_err(ErrorInfo::from)
+}
+
+// 重命名标签，返回修改的条目数
+#[tauri::command]
+async fn rename_tag(
+    old: String,
+    new: String,
+    state: tauri::State<'_, AppState>,
+) -> Result<usize, ErrorInfo> {
+    let manager = state
+        .password_manager
+        .get()
+        .ok_or(PasswordError::NotInitialized)?;
+
+    manager
+        .rename_tag(&old, &new)
+        .await
+        .map_err(ErrorInfo::from)
+}
+
+// 替换条目的标签列表，去掉空白和重复的标签
+#[tauri::command]
+async fn set_entry_tags(
+    password_id: String,
+    tags: Vec<String>,
+    state: tauri::State<'_, AppState>,
+) -> Result<(), ErrorInfo> {
+    let manager = state
+        .password_manager
+        .get()
+        .ok_or(PasswordError::NotInitialized)?;
+
+    manager
+        .set_entry_tags(&password_id, tags)
+        .await
+        .map_err(ErrorInfo::from)
+}
+
+// 把所有条目的标签按字母顺序排列，返回修改的条目数
+#[tauri::command]
+async fn sort_all_tags_alphabetically(
+    state: tauri::State<'_, AppState>,
+) -> Result<usize, ErrorInfo> {
+    let manager = state
+        .password_manager
+        .get()
+        .ok_or(PasswordError::NotInitialized)?;
+
+    manager
+        .sort_all_tags_alphabetically()
+        .await
+        .map_err(ErrorInfo::from)
+}
+
+// 从所有条目中删除标签，返回修改的条目数
+#[tauri::command]
+async fn delete_tag(tag: String, state: tauri::State<'_, AppState>) -> Result<usize, ErrorInfo> {
+    let manager = state
+        .password_manager
+        .get()
+        .ok_or(PasswordError::NotInitialized)?;
+
+    manager.delete_tag(&tag).await.map_err(ErrorInfo::from)
+}
+
+// 按标签筛选密码
+#[tauri::command]
+async fn filter_by_tags(
+    tags: Vec<String>,
+    match_all: bool,
+    state: tauri::State<'_, AppState>,
+) -> Result<Vec<Password>, ErrorInfo> {
+    let manager = state
+        .password_manager
+        .get()
+        .ok_or(PasswordError::NotInitialized)?;
+
+    manager
+        .filter_by_tags(&tags, match_all)
+        .await
+        .map_err(ErrorInfo::from)
+}
+
+// 切换收藏状态
+#[tauri::command]
+async fn toggle_favorite(
+    password_id: String,
+    state: tauri::State<'_, AppState>,
+) -> Result<bool, ErrorInfo> {
+    let manager = state
+        .password_manager
+        .get()
+        .ok_or(PasswordError::NotInitialized)?;
+
+    manager
+        .toggle_favorite(&password_id)
+        .await
+        .map_err(ErrorInfo::from)
+}
+
+// 获取收藏的密码
+#[tauri::command]
+async fn get_favorites(state: tauri::State<'_, AppState>) -> Result<Vec<Password>, ErrorInfo> {
+    let manager = state
+        .password_manager
+        .get()
+        .ok_or(PasswordError::NotInitialized)?;
+
+    manager.get_favorites().await.map_err(ErrorInfo::from)
+}
+
+// 更新配置
+#[tauri::command]
+async fn update_config(
+    new_config: Config,
+    state: tauri::State<'_, AppState>,
+) -> Result<(), ErrorInfo> {
+    let manager = state
+        .password_manager
+        .get()
+        .ok_or(PasswordError::NotInitialized)?;
+
+    manager
+        .update_config(new_config)
+        .await
+        .map_err(ErrorInfo::from)
+}
+
+// 从CSV导入密码
+// format 为 "auto"/"chrome"/"keepass"，或 "custom" 并同时提供 mapping
+#[tauri::command]
+async fn import_csv(
+    csv: String,
+    key: String,
+    format: String,
+    mapping: Option<CsvMapping>,
+    state: tauri::State<'_, AppState>,
+) -> Result<ImportReport, ErrorInfo> {
+    let manager = state
+        .password_manager
+        .get()
+        .ok_or(PasswordError::NotInitialized)?;
+
+    let mapping = match (format.as_str(), mapping) {
+        ("custom", Some(mapping)) => mapping,
+        ("custom", None) => {
+            return Err(ErrorInfo {
+                code: 400,
+                info: "Missing csv mapping".to_string(),
+            });
+        }
+        (format, _) => import::mapping_for_format(&csv, format)?,
+    };
+
+    manager
+        .import_csv(&csv, &key, mapping)
+        .await
+        .map_err(ErrorInfo::from)
+}
+
+// 导出加密备份
+#[tauri::command]
+async fn export_backup(
+    passphrase: String,
+    state: tauri::State<'_, AppState>,
+) -> Result<Vec<u8>, ErrorInfo> {
+    let manager = state
+        .password_manager
+        .get()
+        .ok_or(PasswordError::NotInitialized)?;
+
+    manager
+        .export_backup(&passphrase)
+        .await
+        .map_err(ErrorInfo::from)
+}
+
+// 导出为 Bitwarden 明文JSON，用于迁移
+#[tauri::command]
+async fn export_bitwarden_json(
+    key: String,
+    state: tauri::State<'_, AppState>,
+) -> Result<String, ErrorInfo> {
+    let manager = state
+        .password_manager
+        .get()
+        .ok_or(PasswordError::NotInitialized)?;
+
+    manager
+        .export_bitwarden_json(&key)
+        .await
+        .map_err(ErrorInfo::from)
+}
+
+// 导入 Bitwarden 明文JSON
+#[tauri::command]
+async fn import_bitwarden_json(
+    json: String,
+    key: String,
+    state: tauri::State<'_, AppState>,
+) -> Result<ImportReport, ErrorInfo> {
+    let manager = state
+        .password_manager
+        .get()
+        .ok_or(PasswordError::NotInitialized)?;
+
+    manager
+        .import_bitwarden_json(&json, &key)
+        .await
+        .map_err(ErrorInfo::from)
+}
+
+// 导入加密备份
+#[tauri::command]
+async fn import_backup(
+    bytes: Vec<u8>,
+    passphrase: String,
+    state: tauri::State<'_, AppState>,
+) -> Result<(), ErrorInfo> {
+    let manager = state
+        .password_manager
+        .get()
+        .ok_or(PasswordError::NotInitialized)?;
+
+    manager
+        .import_backup(&bytes, &passphrase)
+        .await
+        .map_err(ErrorInfo::from)
+}
+
+// 预览新配置：测试各存储点连接但不应用，确认无误后再调用 update_config
+#[tauri::command]
+async fn preview_config(
+    new_config: Config,
+    state: tauri::State<'_, AppState>,
+) -> Result<ConfigPreview, ErrorInfo> {
+    let manager = state
+        .password_manager
+        .get()
+        .ok_or(PasswordError::NotInitialized)?;
+
+    manager
+        .preview_config(new_config)
+        .await
+        .map_err(ErrorInfo::from)
+}
+
+// 获取各存储点中版本不一致的条目（冲突策略为 manual 时）
+#[tauri::command]
+async fn get_conflicts(state: tauri::State<'_, AppState>) -> Result<Vec<Conflict>, ErrorInfo> {
+    let manager = state
+        .password_manager
+        .get()
+        .ok_or(PasswordError::NotInitialized)?;
+
+    manager.get_conflicts().await.map_err(ErrorInfo::from)
+}
+
+// 以指定存储点中的版本为准处理冲突
+#[tauri::command]
+async fn resolve_conflict(
+    id: String,
+    storage_target: String,
+    state: tauri::State<'_, AppState>,
+) -> Result<(), ErrorInfo> {
+    let manager = state
+        .password_manager
+        .get()
+        .ok_or(PasswordError::NotInitialized)?;
+
+    let storage_id = resolve_storage(manager, &storage_target).await?;
+
+    manager
+        .resolve_conflict(&id, &storage_id)
+        .await
+        .map_err(ErrorInfo::from)
+}
+
+// 检查各存储点数据的一致性
+#[tauri::command]
+async fn check_integrity(state: tauri::State<'_, AppState>) -> Result<IntegrityReport, ErrorInfo> {
+    let manager = state
+        .password_manager
+        .get()
+        .ok_or(PasswordError::NotInitialized)?;
+
+    manager.check_integrity().await.map_err(ErrorInfo::from)
+}
+
+// 修复条目数并重新同步各存储点
+#[tauri::command]
+async fn repair_integrity(state: tauri::State<'_, AppState>) -> Result<IntegrityReport, ErrorInfo> {
+    let manager = state
+        .password_manager
+        .get()
+        .ok_or(PasswordError::NotInitialized)?;
+
+    manager.repair_integrity().await.map_err(ErrorInfo::from)
+}
+
+// 获取宽松加载时跳过的条目
+#[tauri::command]
+async fn get_load_warnings(
+    state: tauri::State<'_, AppState>,
+) -> Result<Vec<(StorageId, LoadWarning)>, ErrorInfo> {
+    let manager = state
+        .password_manager
+        .get()
+        .ok_or(PasswordError::NotInitialized)?;
+
+    Ok(manager.get_load_warnings().await)
+}
+
+// 测试存储点连接，用于保存配置前的检查
+#[tauri::command]
+async fn test_storage_connection(
+    storage_target: String,
+    state: tauri::State<'_, AppState>,
+) -> Result<(), ErrorInfo> {
+    let manager = state
+        .password_manager
+        .get()
+        .ok_or(PasswordError::NotInitialized)?;
+
+    let id = resolve_storage(manager, &storage_target).await?;
+
+    manager
+        .test_storage_connection(&id)
+        .await
+        .map_err(ErrorInfo::from)
+}
+
+// 获取各存储点的状态
+#[tauri::command]
+async fn get_storage_status(
+    state: tauri::State<'_, AppState>,
+) -> Result<HashMap<StorageId, StorageStatus>, ErrorInfo> {
+    let manager = state
+        .password_manager
+        .get()
+        .ok_or(PasswordError::NotInitialized)?;
+
+    Ok(manager.get_storage_status().await)
+}
+
+// 锁定密码管理器
+#[tauri::command]
+async fn lock(state: tauri::State<'_, AppState>) -> Result<(), ErrorInfo> {
+    let manager = state
+        .password_manager
+        .get()
+        .ok_or(PasswordError::NotInitialized)?;
+
+    manager.lock().await;
+    Ok(())
+}
+
+// 解锁密码管理器，重新从存储加载数据
+// 设置了主密码时需要提供 master_password
+#[tauri::command]
+async fn unlock(
+    master_password: Option<String>,
+    state: tauri::State<'_, AppState>,
+) -> Result<(), ErrorInfo> {
+    let manager = state
+        .password_manager
+        .get()
+        .ok_or(PasswordError::NotInitialized)?;
+
+    manager
+        .unlock(master_password.as_deref())
+        .await
+        .map_err(ErrorInfo::from)
+}
+
+// 启用硬件密钥后解锁，挑战由前端发送给硬件密钥，这里只校验响应
+#[tauri::command]
+async fn unlock_with_hardware(
+    master_password: Option<String>,
+    challenge_response: Vec<u8>,
+    state: tauri::State<'_, AppState>,
+) -> Result<(), ErrorInfo> {
+    let manager = state
+        .password_manager
+        .get()
+        .ok_or(PasswordError::NotInitialized)?;
+
+    manager
+        .unlock_with_hardware(master_password.as_deref(), &challenge_response)
+        .await
+        .map_err(ErrorInfo::from)
+}
+
+// 启用硬件密钥第二因素，只能在密码库为空时启用
+#[tauri::command]
+async fn enable_hardware_factor(
+    challenge: String,
+    challenge_response: Vec<u8>,
+    state: tauri::State<'_, AppState>,
+) -> Result<(), ErrorInfo> {
+    let manager = state
+        .password_manager
+        .get()
+        .ok_or(PasswordError::NotInitialized)?;
+
+    manager
+        .enable_hardware_factor(&challenge, &challenge_response)
+        .await
+        .map_err(ErrorInfo::from)
+}
+
+// 首次设置时设置主密码
+#[tauri::command]
+async fn set_master_password(
+    password: String,
+    state: tauri::State<'_, AppState>,
+) -> Result<(), ErrorInfo> {
+    let manager = state
+        .password_manager
+        .get()
+        .ok_or(PasswordError::NotInitialized)?;
+
+    manager
+        .set_master_password(&password)
+        .await
+        .map_err(ErrorInfo::from)
+}
+
+// 开启或关闭只读模式，设置了主密码时需要校验
+#[tauri::command]
+async fn set_read_only(
+    read_only: bool,
+    master_password: Option<String>,
+    state: tauri::State<'_, AppState>,
+) -> Result<(), ErrorInfo> {
+    let manager = state
+        .password_manager
+        .get()
+        .ok_or(PasswordError::NotInitialized)?;
+
+    manager
+        .set_read_only(read_only, master_password.as_deref())
+        .await
+        .map_err(ErrorInfo::from)
+}
+
+// 设置闲置多少秒后自动锁定，为空时不自动锁定
+#[tauri::command]
+async fn set_auto_lock_secs(
+    auto_lock_secs: Option<u64>,
+    state: tauri::State<'_, AppState>,
+) -> Result<(), ErrorInfo> {
+    let manager = state
+        .password_manager
+        .get()
+        .ok_or(PasswordError::NotInitialized)?;
+
+    manager
+        .set_auto_lock_secs(auto_lock_secs)
+        .await
+        .map_err(ErrorInfo::from)
+}
+
+// 引导流程结束后调用，之后启动不再视为首次设置
+#[tauri::command]
+async fn complete_first_setup(state: tauri::State<'_, AppState>) -> Result<(), ErrorInfo> {
+    let manager = state
+        .password_manager
+        .get()
+        .ok_or(PasswordError::NotInitialized)?;
+
+    manager
+        .complete_first_setup()
+        .await
+        .map_err(ErrorInfo::from)
+}
+
+// 校验主密码
+#[tauri::command]
+async fn verify_master(
+    password: String,
+    state: tauri::State<'_, AppState>,
+) -> Result<bool, ErrorInfo> {
+    let manager = state
+        .password_manager
+        .get()
+        .ok_or(PasswordError::NotInitialized)?;
+
+    manager
+        .verify_master(&password)
+        .await
+        .map_err(ErrorInfo::from)
+}
+
+// 更换加密密钥：用新密钥重新加密所有条目
+#[tauri::command]
+async fn reencrypt_all(
+    old_key: String,
+    new_key: String,
+    state: tauri::State<'_, AppState>,
+) -> Result<ReencryptReport, ErrorInfo> {
+    let manager = state
+        .password_manager
+        .get()
+        .ok_or(PasswordError::NotInitialized)?;
+
+    manager
+        .reencrypt_all(&old_key, &new_key)
+        .await
+        .map_err(ErrorInfo::from)
+}
+
+// 检查key能否解密所有条目，列出用其它key加密的条目
+#[tauri::command]
+async fn verify_key(
+    key: String,
+    state: tauri::State<'_, AppState>,
+) -> Result<KeyVerifyReport, ErrorInfo> {
+    let manager = state
+        .password_manager
+        .get()
+        .ok_or(PasswordError::NotInitialized)?;
+
+    manager.verify_key(&key).await.map_err(ErrorInfo::from)
+}
+
+// 把单个条目换用新的加密密钥
+#[tauri::command]
+async fn rekey_entry(
+    password_id: String,
+    old_key: String,
+    new_key: String,
+    state: tauri::State<'_, AppState>,
+) -> Result<(), ErrorInfo> {
+    let manager = state
+        .password_manager
+        .get()
+        .ok_or(PasswordError::NotInitialized)?;
+
+    manager
+        .rekey_entry(&password_id, &old_key, &new_key)
+        .await
+        .map_err(ErrorInfo::from)
+}
+
+// 获取 within_days 天内到期或已过期的密码
+#[tauri::command]
+async fn get_expiring_passwords(
+    within_days: i64,
+    state: tauri::State<'_, AppState>,
+) -> Result<Vec<Password>, ErrorInfo> {
+    let manager = state
+        .password_manager
+        .get()
+        .ok_or(PasswordError::NotInitialized)?;
+
+    manager
+        .get_expiring_passwords(chrono::Duration::days(within_days))
+        .await
+        .map_err(ErrorInfo::from)
+}
+
+// 获取超过 max_age_days 天未更新的密码
+#[tauri::command]
+async fn get_stale_passwords(
+    max_age_days: i64,
+    state: tauri::State<'_, AppState>,
+) -> Result<Vec<Password>, ErrorInfo> {
+    let manager = state
+        .password_manager
+        .get()
+        .ok_or(PasswordError::NotInitialized)?;
+
+    manager
+        .get_stale_passwords(chrono::Duration::days(max_age_days))
+        .await
+        .map_err(ErrorInfo::from)
+}
+
+// 生成两步验证码
+#[tauri::command]
+async fn generate_totp(
+    id: String,
+    key: String,
+    state: tauri::State<'_, AppState>,
+) -> Result<TotpCode, ErrorInfo> {
+    let manager = state
+        .password_manager
+        .get()
+        .ok_or(PasswordError::NotInitialized)?;
+
+    manager
+        .generate_totp(&id, &key)
+        .await
+        .map_err(ErrorInfo::from)
+}
+
+// 解密条目的备注
+#[tauri::command]
+async fn decrypt_notes(
+    id: String,
+    key: String,
+    state: tauri::State<'_, AppState>,
+) -> Result<Option<String>, ErrorInfo> {
+    let manager = state
+        .password_manager
+        .get()
+        .ok_or(PasswordError::NotInitialized)?;
+
+    manager
+        .decrypt_notes(&id, &key)
+        .await
+        .map_err(ErrorInfo::from)
+}
+
+// 获取用于泄露查询的 (条目id, SHA-1前5位)
+#[tauri::command]
+async fn password_breach_hashes(
+    key: String,
+    state: tauri::State<'_, AppState>,
+) -> Result<Vec<(String, String)>, ErrorInfo> {
+    let manager = state
+        .password_manager
+        .get()
+        .ok_or(PasswordError::NotInitialized)?;
+
+    manager
+        .password_breach_hashes(&key)
+        .await
+        .map_err(ErrorInfo::from)
+}
+
+// 用 range API 返回的后缀列表检查条目是否已泄露
+#[tauri::command]
+async fn check_breach(
+    id: String,
+    key: String,
+    suffixes: Vec<String>,
+    state: tauri::State<'_, AppState>,
+) -> Result<bool, ErrorInfo> {
+    let manager = state
+        .password_manager
+        .get()
+        .ok_or(PasswordError::NotInitialized)?;
+
+    manager
+        .check_breach(&id, &key, &suffixes)
+        .await
+        .map_err(ErrorInfo::from)
+}
+
+// 给条目添加加密附件
+#[tauri::command]
+async fn add_attachment(
+    id: String,
+    key: String,
+    name: String,
+    bytes: Vec<u8>,
+    state: tauri::State<'_, AppState>,
+) -> Result<(), ErrorInfo> {
+    let manager = state
+        .password_manager
+        .get()
+        .ok_or(PasswordError::NotInitialized)?;
+
+    manager
+        .add_attachment(&id, &key, &name, &bytes)
+        .await
+        .map_err(ErrorInfo::from)
+}
+
+// 解密并获取附件内容
+#[tauri::command]
+async fn get_attachment(
+    id: String,
+    attachment_name: String,
+    key: String,
+    state: tauri::State<'_, AppState>,
+) -> Result<Vec<u8>, ErrorInfo> {
+    let manager = state
+        .password_manager
+        .get()
+        .ok_or(PasswordError::NotInitialized)?;
+
+    manager
+        .get_attachment(&id, &attachment_name, &key)
+        .await
+        .map_err(ErrorInfo::from)
+}
+
+// 删除附件
+#[tauri::command]
+async fn remove_attachment(
+    id: String,
+    attachment_name: String,
+    state: tauri::State<'_, AppState>,
+) -> Result<(), ErrorInfo> {
+    let manager = state
+        .password_manager
+        .get()
+        .ok_or(PasswordError::NotInitialized)?;
+
+    manager
+        .remove_attachment(&id, &attachment_name)
         .await
         .map_err(ErrorInfo::from)
 }
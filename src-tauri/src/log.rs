@@ -1,17 +1,319 @@
-// info宏 仅在debug模式下打印
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU8, Ordering};
+use std::sync::{Mutex, OnceLock};
+
+/// 日志级别，只记录不低于配置级别的日志
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LogLevel {
+    Error,
+    Warn,
+    #[default]
+    Info,
+    Debug,
+}
+
+impl LogLevel {
+    fn as_str(&self) -> &'static str {
+        match self {
+            LogLevel::Error => "ERROR",
+            LogLevel::Warn => "WARN",
+            LogLevel::Info => "INFO",
+            LogLevel::Debug => "DEBUG",
+        }
+    }
+
+    fn from_u8(value: u8) -> Self {
+        match value {
+            0 => LogLevel::Error,
+            1 => LogLevel::Warn,
+            2 => LogLevel::Info,
+            _ => LogLevel::Debug,
+        }
+    }
+}
+
+/// 单个日志文件的默认大小上限，超过后轮转
+pub const DEFAULT_MAX_BYTES: u64 = 1024 * 1024;
+/// 保留的历史日志文件数量，如 passwd.log.1 ~ passwd.log.3
+const MAX_ROTATED_FILES: usize = 3;
+
+/// 写入文件的日志，文件超过 `max_bytes` 后轮转
+pub struct FileLogger {
+    path: PathBuf,
+    max_bytes: u64,
+    level: AtomicU8,
+    file: Mutex<Option<File>>,
+}
+
+impl FileLogger {
+    pub fn new(path: PathBuf, max_bytes: u64, level: LogLevel) -> Self {
+        Self {
+            path,
+            max_bytes,
+            level: AtomicU8::new(level as u8),
+            file: Mutex::new(None),
+        }
+    }
+
+    pub fn level(&self) -> LogLevel {
+        LogLevel::from_u8(self.level.load(Ordering::Relaxed))
+    }
+
+    pub fn set_level(&self, level: LogLevel) {
+        self.level.store(level as u8, Ordering::Relaxed);
+    }
+
+    /// 第 `index` 个历史日志文件的路径，1 为最近一次轮转
+    pub fn rotated_path(&self, index: usize) -> PathBuf {
+        let mut path = self.path.clone().into_os_string();
+        path.push(format!(".{}", index));
+        PathBuf::from(path)
+    }
+
+    /// 写入一行日志：`时间 [级别] prefix: 内容`
+    pub fn write(&self, level: LogLevel, args: fmt::Arguments) -> io::Result<()> {
+        if level > self.level() {
+            return Ok(());
+        }
+
+        let line = format!(
+            "{} [{}] prefix: {}\n",
+            chrono::Utc::now().format("%Y-%m-%dT%H:%M:%S%.3fZ"),
+            level.as_str(),
+            args
+        );
+
+        let mut file = self.file.lock().unwrap();
+        if file.is_none() {
+            *file = Some(Self::open(&self.path)?);
+        }
+
+        let size = file.as_ref().unwrap().metadata()?.len();
+        if size > 0 && size + line.len() as u64 > self.max_bytes {
+            *file = None;
+            self.rotate()?;
+            *file = Some(Self::open(&self.path)?);
+        }
+
+        file.as_mut().unwrap().write_all(line.as_bytes())
+    }
+
+    fn open(path: &Path) -> io::Result<File> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        OpenOptions::new().create(true).append(true).open(path)
+    }
+
+    // passwd.log.2 -> passwd.log.3，passwd.log.1 -> passwd.log.2，passwd.log -> passwd.log.1
+    fn rotate(&self) -> io::Result<()> {
+        for index in (1..MAX_ROTATED_FILES).rev() {
+            let from = self.rotated_path(index);
+            if from.exists() {
+                fs::rename(from, self.rotated_path(index + 1))?;
+            }
+        }
+        fs::rename(&self.path, self.rotated_path(1))
+    }
+}
+
+static LOGGER: OnceLock<FileLogger> = OnceLock::new();
+
+/// 初始化全局日志，只能调用一次
+pub fn init(path: PathBuf, level: LogLevel) -> anyhow::Result<()> {
+    LOGGER
+        .set(FileLogger::new(path, DEFAULT_MAX_BYTES, level))
+        .map_err(|_| anyhow::anyhow!("日志已初始化"))
+}
+
+/// 修改全局日志级别
+pub fn set_level(level: LogLevel) {
+    if let Some(logger) = LOGGER.get() {
+        logger.set_level(level);
+    }
+}
+
+#[doc(hidden)]
+pub fn log(level: LogLevel, args: fmt::Arguments) {
+    // debug模式下同时打印到终端
+    #[cfg(debug_assertions)]
+    match level {
+        LogLevel::Error | LogLevel::Warn => eprintln!("prefix: {}", args),
+        _ => println!("prefix: {}", args),
+    }
+
+    if let Some(logger) = LOGGER.get() {
+        // 写日志失败时没有更好的处理方式，直接忽略
+        let _ = logger.write(level, args);
+    }
+}
+
+/// 隐藏敏感内容（密码、token等），只保留是否为空的信息
+pub fn redact(secret: &str) -> &'static str {
+    if secret.is_empty() { "" } else { "***" }
+}
+
+// info宏 记录到日志文件，debug模式下同时打印
 #[macro_export]
 macro_rules! info {
     ($($arg:tt)*) => {
-        #[cfg(debug_assertions)]
-        println!("prefix: {}", format_args!($($arg)*));
+        $crate::log::log($crate::log::LogLevel::Info, format_args!($($arg)*))
+    };
+}
+
+// debug宏 只在日志级别为debug时记录，用于不能暴露给调用方的细节
+#[macro_export]
+macro_rules! debug {
+    ($($arg:tt)*) => {
+        $crate::log::log($crate::log::LogLevel::Debug, format_args!($($arg)*))
+    };
+}
+
+// warn宏 记录到日志文件，debug模式下同时打印
+#[macro_export]
+macro_rules! warn {
+    ($($arg:tt)*) => {
+        $crate::log::log($crate::log::LogLevel::Warn, format_args!($($arg)*))
     };
 }
 
-// error宏 仅在debug模式下打印
+// error宏 记录到日志文件，debug模式下同时打印
 #[macro_export]
 macro_rules! error {
     ($($arg:tt)*) => {
-        #[cfg(debug_assertions)]
-        eprintln!("prefix: {}", format_args!($($arg)*));
+        $crate::log::log($crate::log::LogLevel::Error, format_args!($($arg)*))
     };
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::store::mock_store;
+
+    #[test]
+    fn writes_level_and_timestamp() {
+        let path = mock_store::temp_dir().join("passwd.log");
+        let logger = FileLogger::new(path.clone(), DEFAULT_MAX_BYTES, LogLevel::Info);
+
+        logger
+            .write(LogLevel::Info, format_args!("hello {}", 1))
+            .unwrap();
+        logger
+            .write(LogLevel::Debug, format_args!("hidden"))
+            .unwrap();
+        logger.set_level(LogLevel::Debug);
+        logger
+            .write(LogLevel::Debug, format_args!("shown"))
+            .unwrap();
+
+        let content = fs::read_to_string(&path).unwrap();
+        let lines: Vec<&str> = content.lines().collect();
+        assert_eq!(lines.len(), 2);
+        assert!(lines[0].ends_with("[INFO] prefix: hello 1"), "{}", lines[0]);
+        assert!(lines[1].ends_with("[DEBUG] prefix: shown"), "{}", lines[1]);
+        // 时间戳形如 2024-01-01T00:00:00.000Z
+        let timestamp = lines[0].split(' ').next().unwrap();
+        assert!(chrono::DateTime::parse_from_rfc3339(timestamp).is_ok());
+    }
+
+    #[test]
+    fn rotates_at_max_bytes() {
+        let path = mock_store::temp_dir().join("passwd.log");
+        let logger = FileLogger::new(path.clone(), 200, LogLevel::Info);
+
+        for i in 0..50 {
+            logger
+                .write(LogLevel::Info, format_args!("line {:02}", i))
+                .unwrap();
+        }
+
+        assert!(fs::metadata(&path).unwrap().len() <= 200);
+        for index in 1..=MAX_ROTATED_FILES {
+            assert!(fs::metadata(logger.rotated_path(index)).unwrap().len() <= 200);
+        }
+        assert!(!logger.rotated_path(MAX_ROTATED_FILES + 1).exists());
+
+        // 最新的日志在当前文件，较早的在 .1
+        let current = fs::read_to_string(&path).unwrap();
+        assert!(current.contains("line 49"));
+        let previous = fs::read_to_string(logger.rotated_path(1)).unwrap();
+        assert!(!previous.contains("line 49"));
+        assert!(!previous.is_empty());
+    }
+
+    #[test]
+    fn secrets_are_redacted() {
+        assert_eq!(redact("ghp_secret"), "***");
+        assert_eq!(redact(""), "");
+
+        let request = crate::password::PasswordCreateRequest {
+            title: "GitHub".to_string(),
+            description: String::new(),
+            tags: vec![],
+            username: "alice".to_string(),
+            password: "hunter2".to_string(),
+            url: None,
+            key: "my-key".to_string(),
+            expires_at: None,
+            totp_secret: Some("GEZDGNBV".to_string()),
+            notes: Some("PIN 1234".to_string()),
+            client_id: None,
+            kdf_strength: crate::crypto::KdfStrength::Fast,
+        };
+        let debug = format!("{:?}", request);
+        assert!(debug.contains("alice"));
+        for secret in ["hunter2", "my-key", "GEZDGNBV", "PIN 1234"] {
+            assert!(!debug.contains(secret), "{}", debug);
+        }
+
+        let request = crate::password::PasswordCreateRequestNoPassword {
+            title: "GitHub".to_string(),
+            description: String::new(),
+            tags: vec![],
+            username: "alice".to_string(),
+            url: None,
+            expires_at: None,
+            totp_secret: Some("GEZDGNBV".to_string()),
+            notes: Some("PIN 1234".to_string()),
+            kdf_strength: crate::crypto::KdfStrength::Fast,
+        };
+        let debug = format!("{:?}", request);
+        assert!(debug.contains("alice"));
+        for secret in ["GEZDGNBV", "PIN 1234"] {
+            assert!(!debug.contains(secret), "{}", debug);
+        }
+
+        let mut config = crate::config::Config::default();
+        config
+            .storage
+            .github_storages
+            .push(crate::config::GithubStorageConfig {
+                name: String::new(),
+                enabled: true,
+                owner: "owner".to_string(),
+                repo: "repo".to_string(),
+                branch: "main".to_string(),
+                token: "ghp_secret".to_string(),
+                file_path: "passwords.json".to_string(),
+                max_attempts: 1,
+                base_url: None,
+                timeout_secs: 30,
+            });
+        config.master_verifier = Some("$argon2id$v=19$secret-hash".to_string());
+        config.hardware_factor = Some(crate::config::HardwareFactorConfig {
+            challenge: "challenge-1".to_string(),
+            response_verifier: "$argon2id$v=19$hardware-hash".to_string(),
+        });
+        let debug = format!("{:?}", config);
+        assert!(debug.contains("owner"));
+        assert!(debug.contains("challenge-1"), "{}", debug);
+        assert!(!debug.contains("ghp_secret"), "{}", debug);
+        assert!(!debug.contains("secret-hash"), "{}", debug);
+        assert!(!debug.contains("hardware-hash"), "{}", debug);
+    }
+}
@@ -1,45 +1,287 @@
 use anyhow::{Result, anyhow};
-use chrono::Utc;
-use std::collections::HashMap;
-use std::sync::Arc;
+use chrono::{DateTime, Utc};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex as StdMutex};
+use std::time::{Duration, Instant};
 use tokio::sync::RwLock;
 
-use crate::config::Config;
+use crate::audit::{self, AuditAction, AuditEvent, AuditLog};
+use crate::backup;
+use crate::bitwarden;
+use crate::config::{Config, ConflictPolicy, HardwareFactorConfig, LocalFormat, StorageEntry};
+use crate::pending::{self, PendingQueue};
+use crate::reveal::{self, RevealStore, RevealToken};
 
+use crate::breach;
 use crate::crypto::EncryptedData;
-use crate::password::{Password, PasswordCreateRequest, PasswordGeneratorConfig};
+use crate::csv::{self, CsvMapping, ImportReport};
+use crate::error::PasswordError;
+use crate::event::VaultChange;
+use crate::idle::IdleTracker;
+use crate::password::{
+    Attachment, Password, PasswordCreateRequest, PasswordCreateRequestNoPassword,
+    PasswordGeneratorConfig, PasswordSummary, SortKey, ValidationResult,
+};
+use crate::search::{self, ScoredPassword, SearchHit, SearchScope};
 use crate::store::github_store::GithubStorage;
 use crate::store::local_store::LocalStorage;
-use crate::store::{Storage, StorageData, StorageTarget};
-use crate::{CONF_PATH, DATA_PATH, crypto, info, password};
+use crate::store::s3_store::S3Storage;
+use crate::store::sqlite_store::SqliteStorage;
+use crate::store::{
+    LoadWarning, MAX_SAVE_ATTEMPTS, Storage, StorageData, StorageError, StorageId, StorageTarget,
+    VersionInfo,
+};
+use crate::totp::{self, TotpCode};
+use crate::{CONF_PATH, DATA_PATH, crypto, derive, error, info, password, warn};
 
-// #[derive(Debug, Clone, serde::Serialize)]
-// pub struct StorageStatus {
-//     pub enabled: bool,
-//     pub connected: bool,
-//     pub password_count: usize,
-//     pub last_sync: Option<DateTime<Utc>>,
-//     pub error: Option<String>,
-// }
+/// 存储点的健康状态
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct StorageStatus {
+    pub enabled: bool,
+    pub connected: bool,
+    pub password_count: usize,
+    pub last_sync: Option<DateTime<Utc>>,
+    pub error: Option<String>,
+    /// 有保存失败的修改尚未写入该存储点
+    pub pending_sync: bool,
+}
+
+/// 保存失败、数据落后于缓存的存储点
+///
+/// 缓存中的数据是准确的，`retry_failed_saves` 把缓存整体重新写入这些存储点
+pub type PendingSync = HashSet<StorageId>;
+
+// 待保存的存储点及其缓存数据的副本
+type SaveJob = (StorageId, Option<(Arc<dyn Storage>, StorageData)>);
+
+// 部分存储点保存失败时附加在错误信息后的提示
+const PENDING_SYNC_HINT: &str = "未保存成功的存储点已记录，稍后可重试";
+
+/// 清空存储点时需要输入的确认语
+pub const WIPE_CONFIRMATION: &str = "DELETE ALL PASSWORDS";
+
+/// 预览配置时单个存储点的连接结果
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct StoragePreview {
+    pub ok: bool,
+    pub error: Option<String>,
+}
+
+/// `preview_config` 的结果，`ok` 为true表示所有存储点都能正常连接
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct ConfigPreview {
+    pub ok: bool,
+    pub storages: HashMap<StorageId, StoragePreview>,
+}
+
+/// 自检中单项检查的结果
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct SelfTestCheck {
+    /// `crypto`、`config` 或 `storage:<存储点id>`
+    pub name: String,
+    pub ok: bool,
+    pub error: Option<String>,
+}
+
+impl SelfTestCheck {
+    fn from_result(name: String, result: Result<()>) -> Self {
+        match result {
+            Ok(()) => Self {
+                name,
+                ok: true,
+                error: None,
+            },
+            Err(e) => Self {
+                name,
+                ok: false,
+                error: Some(e.to_string()),
+            },
+        }
+    }
+}
+
+/// `self_test` 的结果，`ok` 为true表示所有检查都通过
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct SelfTestReport {
+    pub ok: bool,
+    pub checks: Vec<SelfTestCheck>,
+}
+
+/// 分页查询的结果，`page` 从0开始
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct PageResult<T> {
+    pub items: Vec<T>,
+    pub total: usize,
+    pub page: usize,
+    pub page_size: usize,
+}
+
+/// 批量重新加密的结果
+#[derive(Debug, Clone, Default, serde::Serialize)]
+pub struct ReencryptReport {
+    pub reencrypted: usize,
+    /// 旧密钥无法解密、保持原样的条目id
+    pub failed: Vec<String>,
+}
+
+/// 校验key能否解密整个密码库的结果，不包含明文
+#[derive(Debug, Clone, Default, serde::Serialize)]
+pub struct KeyVerifyReport {
+    pub total: usize,
+    pub succeeded: usize,
+    /// 无法用该key解密的条目id，已排序
+    pub failed_ids: Vec<String>,
+}
+
+/// 批量操作中失败的一项，`index` 为该项在请求中的下标
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct BatchError {
+    pub index: usize,
+    pub error: String,
+}
+
+/// 批量添加或删除的结果
+#[derive(Debug, Clone, Default, serde::Serialize)]
+pub struct BatchReport {
+    /// 成功的条目id
+    pub succeeded: Vec<String>,
+    pub failed: Vec<BatchError>,
+}
+
+/// 冲突策略为 `Manual` 时等待用户处理的条目，`versions` 为各存储点中的版本
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct Conflict {
+    pub id: String,
+    pub versions: Vec<(StorageId, Password)>,
+}
+
+/// 单个存储点的一致性检查结果
+#[derive(Debug, Clone, Default, serde::Serialize)]
+pub struct StorageIntegrity {
+    /// 元数据中记录的条目数
+    pub recorded_count: usize,
+    /// 实际的条目数
+    pub actual_count: usize,
+    /// nonce长度不正确、无法解密的条目id
+    pub invalid_nonce: Vec<String>,
+    /// 其它存储点中有、该存储点中缺失的条目id
+    pub missing: Vec<String>,
+    /// 读取失败时的错误
+    pub error: Option<String>,
+}
+
+impl StorageIntegrity {
+    pub fn is_ok(&self) -> bool {
+        self.recorded_count == self.actual_count
+            && self.invalid_nonce.is_empty()
+            && self.missing.is_empty()
+            && self.error.is_none()
+    }
+}
+
+/// `check_integrity` 的结果，`ok` 为true表示所有存储点都没有问题
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct IntegrityReport {
+    pub ok: bool,
+    pub storages: HashMap<StorageId, StorageIntegrity>,
+}
+
+/// `weak_password_report` 中的弱密码条目，不包含明文
+#[derive(Debug, Clone, PartialEq, serde::Serialize)]
+pub struct WeakEntry {
+    pub id: String,
+    pub title: String,
+    pub entropy_bits: f64,
+}
+
+/// `vault_stats` 的结果，只统计元数据，不需要解密
+#[derive(Debug, Clone, PartialEq, serde::Serialize)]
+pub struct VaultStats {
+    pub total: usize,
+    /// 各标签的条目数，排序同 `list_tags`
+    pub per_tag: Vec<(String, usize)>,
+    /// 各存储点缓存中的条目数
+    pub per_storage: HashMap<StorageId, usize>,
+    /// 没有填写网址的条目数
+    pub without_url: usize,
+    /// 标题的平均字符数，没有条目时为0
+    pub average_title_length: f64,
+    pub oldest_updated_at: Option<DateTime<Utc>>,
+    pub newest_updated_at: Option<DateTime<Utc>>,
+}
 
-type Storages = HashMap<StorageTarget, Arc<dyn Storage>>;
+type Storages = HashMap<StorageId, Arc<dyn Storage>>;
+
+/// 数据或配置保存成功后的回调，lib.rs 用它向前端发送 `vault-changed` 事件
+pub type ChangeListener = Arc<dyn Fn(&VaultChange) + Send + Sync>;
 
 // 每个存储点是独立的、互不干扰的(防止数据覆盖丢失)
 // 后续考虑设计存储点间的数据同步机制
 pub struct PasswordManager {
     config: RwLock<Config>,
-    storages: RwLock<Storages>,                         // 所有启用的存储点
-    cache: RwLock<HashMap<StorageTarget, StorageData>>, // 缓存策略是写透
+    storages: RwLock<Storages>,                     // 所有启用的存储点
+    cache: RwLock<HashMap<StorageId, StorageData>>, // 缓存策略是写透
+    locked: AtomicBool,                             // 锁定时缓存被清空，所有数据操作都会失败
+    idle: StdMutex<IdleTracker>,                    // 最近一次活动的时间，用于闲置锁定
+    load_warnings: RwLock<Vec<(StorageId, LoadWarning)>>, // 宽松加载时跳过的条目
+    change_listener: StdMutex<Option<ChangeListener>>, // 保存成功后通知前端
+    last_refresh: StdMutex<Instant>,                // 最近一次从所有存储点重新加载的时间
+    pending_sync: StdMutex<PendingSync>,            // 保存失败、等待重试的存储点
+    loaded_counts: StdMutex<HashMap<StorageId, usize>>, // 各存储点加载时（统一之前）的条目数
+    dirty: StdMutex<HashSet<StorageId>>,            // 缓存有改动、尚未整体写入的存储点
+    audit: Option<AuditLog>,                        // 审计日志，未设置 DATA_PATH 时不记录
+    hardware_response: StdMutex<Option<Vec<u8>>>,   // 本次解锁时硬件密钥的响应，锁定时清空
+    pending_queue: Option<PendingQueue>, // 磁盘上的待同步队列，未设置 DATA_PATH 时只在内存中记录
+    reveals: RevealStore,                // 等待前端取走的明文，锁定时清空
 }
 
 impl PasswordManager {
     pub async fn new(config: Config) -> Result<Self> {
         let storages = Self::build_storages_from_config(&config)?;
+        let manager = Self::with_storages(config, storages).await?;
+        manager.restore_pending_sync().await;
+        Ok(manager)
+    }
+
+    // 上次运行时没有同步成功的存储点仍然标记为等待重试
+    async fn restore_pending_sync(&self) {
+        let Some(queue) = &self.pending_queue else {
+            return;
+        };
+        match queue.load() {
+            Ok(queued) => {
+                let storages = self.storages.read().await;
+                for id in queued.keys().filter(|id| storages.contains_key(*id)) {
+                    self.set_pending_sync(id, true);
+                }
+            }
+            Err(e) => warn!("读取待同步队列失败：{}", e),
+        }
+    }
 
+    async fn with_storages(config: Config, storages: Storages) -> Result<Self> {
         let manager = Self {
             config: RwLock::new(config),
             storages: RwLock::new(storages),
             cache: RwLock::new(HashMap::new()),
+            locked: AtomicBool::new(false),
+            idle: StdMutex::new(IdleTracker::new(Instant::now())),
+            load_warnings: RwLock::new(Vec::new()),
+            change_listener: StdMutex::new(None),
+            last_refresh: StdMutex::new(Instant::now()),
+            pending_sync: StdMutex::new(HashSet::new()),
+            loaded_counts: StdMutex::new(HashMap::new()),
+            dirty: StdMutex::new(HashSet::new()),
+            audit: DATA_PATH
+                .get()
+                .map(|path| AuditLog::new(audit::audit_path(path))),
+            hardware_response: StdMutex::new(None),
+            pending_queue: DATA_PATH
+                .get()
+                .map(|path| PendingQueue::new(pending::pending_path(path))),
+            reveals: RevealStore::new(reveal::REVEAL_TTL),
         };
 
         // 加载数据到缓存
@@ -52,279 +294,6241 @@ impl PasswordManager {
         // 初始化所有启用的存储点
         let mut storages = HashMap::new();
 
-        // 初始化本地存储（如果启用）
-        if let Some(local_config) = &config.storage.local_storage
-            && local_config.enabled
-        {
-            let data_path = DATA_PATH
-                .get()
-                .ok_or_else(|| anyhow!("DATA_PATH not set"))?;
+        for (id, entry) in config.storage.enabled_entries()? {
+            let storage = Self::build_storage(&id, entry)?;
+            storages.insert(id, storage);
+        }
+
+        Ok(storages)
+    }
+
+    // 按配置创建单个存储点
+    fn build_storage(id: &StorageId, entry: StorageEntry) -> Result<Arc<dyn Storage>> {
+        match entry {
+            StorageEntry::Local(local_config) => {
+                let data_path = || DATA_PATH.get().ok_or_else(|| anyhow!("DATA_PATH not set"));
+
+                let path = match &local_config.data_path {
+                    Some(path) if path.is_absolute() => path.clone(),
+                    // 相对路径基于应用数据目录（DATA_PATH 所在目录）
+                    Some(path) => data_path()?
+                        .parent()
+                        .map(|dir| dir.join(path))
+                        .unwrap_or_else(|| path.clone()),
+                    None => {
+                        let path = Self::local_data_path(data_path()?, id);
+                        match local_config.format {
+                            LocalFormat::Json => path,
+                            LocalFormat::Sqlite => path.with_extension("db"),
+                        }
+                    }
+                };
 
-            let local_storage = Arc::new(LocalStorage::new(data_path.clone()));
-            storages.insert(StorageTarget::Local, local_storage as Arc<dyn Storage>);
+                match local_config.format {
+                    LocalFormat::Json => Ok(Arc::new(LocalStorage::new(path, local_config))),
+                    LocalFormat::Sqlite => Ok(Arc::new(SqliteStorage::new(path))),
+                }
+            }
+            // 环境变量中的凭据优先于配置文件
+            StorageEntry::GitHub(github_config) => Ok(Arc::new(GithubStorage::new(
+                &github_config.with_env_overrides(),
+            )?)),
+            StorageEntry::S3(s3_config) => Ok(Arc::new(S3Storage::new(s3_config)?)),
         }
+    }
 
-        // 初始化GitHub存储（如果启用）
-        if let Some(github_config) = &config.storage.github_storage
-            && github_config.enabled
-        {
-            let github_storage = Arc::new(GithubStorage::new(
-                github_config.owner.clone(),
-                github_config.repo.clone(),
-                github_config.token.clone(),
-                github_config.branch.clone(),
-                github_config.file_path.clone(),
-            ));
-            storages.insert(StorageTarget::GitHub, github_storage as Arc<dyn Storage>);
+    // 默认的本地存储点使用 DATA_PATH，
+    // 其它本地存储点在同一目录下使用各自的文件，如 passwords-usb.json
+    fn local_data_path(data_path: &Path, id: &StorageId) -> PathBuf {
+        if id.as_str() == StorageTarget::Local.name() {
+            return data_path.to_path_buf();
         }
 
-        Ok(storages)
+        let stem = data_path
+            .file_stem()
+            .unwrap_or_default()
+            .to_string_lossy()
+            .into_owned();
+        let file_name = match data_path.extension() {
+            Some(ext) => format!("{}-{}.{}", stem, id, ext.to_string_lossy()),
+            None => format!("{}-{}", stem, id),
+        };
+        data_path.with_file_name(file_name)
     }
 
-    // 更新配置
-    pub async fn update_config(&self, new_config: Config) -> Result<()> {
-        let mut config_inner = self.config.write().await;
-        let mut storage_inner = self.storages.write().await;
+    /// 把前端传入的存储点名称解析为id
+    ///
+    /// `local`/`github`/`s3` 解析为该类型第一个启用的存储点，其它名称按id匹配
+    pub async fn resolve_storage(&self, name: &str) -> Result<StorageId> {
+        let config = self.config.read().await;
+        let entries = config.storage.enabled_entries()?;
 
-        *config_inner = new_config;
-        *storage_inner = Self::build_storages_from_config(&config_inner)?;
+        let kind = StorageTarget::parse(name).ok();
 
-        // 保存新配置到文件
-        config_inner.save_to_file(
-            CONF_PATH
-                .get()
-                .ok_or_else(|| anyhow!("CONFIG_PATH not set"))?,
-        )?;
+        entries
+            .into_iter()
+            .find(|(id, entry)| match kind {
+                Some(kind) => entry.target() == kind,
+                None => id.as_str() == name,
+            })
+            .map(|(id, _)| id)
+            .ok_or_else(|| PasswordError::InvalidTarget(name.to_string()).into())
+    }
+
+    // 测试存储点能否连通
+    // 按当前配置新建存储点，不影响正在使用的存储点和缓存
+    pub async fn test_storage_connection(&self, id: &StorageId) -> Result<()> {
+        let storage = {
+            let config = self.config.read().await;
+            let (id, entry) = config
+                .storage
+                .enabled_entries()?
+                .into_iter()
+                .find(|(existing, _)| existing == id)
+                .ok_or_else(|| PasswordError::InvalidTarget(id.to_string()))?;
+            Self::build_storage(&id, entry)?
+        };
+
+        storage.test_connection().await
+    }
+
+    /// 自检：加解密往返、当前配置校验以及每个启用存储点的连接
+    ///
+    /// 只读取不写入，锁定时也可以调用
+    pub async fn self_test(&self) -> SelfTestReport {
+        let mut checks = vec![SelfTestCheck::from_result(
+            "crypto".to_string(),
+            Self::crypto_self_test(),
+        )];
+
+        checks.push(SelfTestCheck::from_result(
+            "config".to_string(),
+            self.config.read().await.validate(),
+        ));
+
+        let mut storages: Vec<(StorageId, Arc<dyn Storage>)> = self
+            .storages
+            .read()
+            .await
+            .iter()
+            .map(|(id, storage)| (id.clone(), storage.clone()))
+            .collect();
+        storages.sort_by(|(a, _), (b, _)| a.as_str().cmp(b.as_str()));
+        for (id, storage) in storages {
+            checks.push(SelfTestCheck::from_result(
+                format!("storage:{}", id),
+                storage.test_connection().await,
+            ));
+        }
+
+        let report = SelfTestReport {
+            ok: checks.iter().all(|c| c.ok),
+            checks,
+        };
+        if !report.ok {
+            warn!("自检未通过：{:?}", report);
+        }
+        report
+    }
 
+    // 用一次性的密钥加密再解密，确认加密功能正常
+    fn crypto_self_test() -> Result<()> {
+        let key = uuid::Uuid::new_v4().to_string();
+        let plaintext = "passwd self-test";
+        let encrypted = crypto::encrypt_with_password(plaintext, &key)?;
+        if crypto::decrypt_with_password(&encrypted, &key)? != plaintext {
+            return Err(anyhow!("解密结果与原文不一致"));
+        }
         Ok(())
     }
 
-    pub async fn add_password(&self, request: PasswordCreateRequest) -> Result<()> {
-        let encrypted_password = crypto::encrypt_with_password(&request.password, &request.key)?;
+    /// 按新配置创建存储点并逐个测试连接，不修改当前状态也不保存配置
+    ///
+    /// 配置本身不合法时返回错误；存储点创建或连接失败记录在结果中
+    pub async fn preview_config(&self, new_config: Config) -> Result<ConfigPreview> {
+        new_config.validate()?;
 
-        info!("加密后的密码: {:?}", encrypted_password);
+        let mut storages = HashMap::new();
+        for (id, entry) in new_config.storage.enabled_entries()? {
+            let result = match Self::build_storage(&id, entry) {
+                Ok(storage) => storage.test_connection().await,
+                Err(e) => Err(e),
+            };
+            let preview = match result {
+                Ok(()) => StoragePreview {
+                    ok: true,
+                    error: None,
+                },
+                Err(e) => StoragePreview {
+                    ok: false,
+                    error: Some(e.to_string()),
+                },
+            };
+            storages.insert(id, preview);
+        }
 
-        // 创建密码对象
-        let password = Password::new(request, encrypted_password);
-        let password_id = password.id.clone();
+        Ok(ConfigPreview {
+            ok: storages.values().all(|s| s.ok),
+            storages,
+        })
+    }
 
-        // 添加到缓存
-        let mut cache_inner = self.cache.write().await;
-        let storage_inner = self.storages.read().await;
+    // 锁定：清空缓存，解锁前所有数据操作都返回 `PasswordError::Locked`
+    pub async fn lock(&self) {
+        self.locked.store(true, Ordering::SeqCst);
+        self.cache.write().await.clear();
+        *self.hardware_response.lock().unwrap() = None;
+        self.reveals.clear();
+        info!("密码管理器已锁定");
+    }
 
-        let time_now = Utc::now();
-        for k in storage_inner.keys() {
-            if let Some(data) = cache_inner.get_mut(k) {
-                data.passwords.insert(password_id.clone(), password.clone());
-                data.metadata.password_count += 1;
-                data.metadata.last_sync = time_now;
-            } else {
-                let mut data = StorageData::new();
-                data.passwords.insert(password_id.clone(), password.clone());
-                data.metadata.password_count += 1;
-                data.metadata.last_sync = time_now;
+    // 解锁：校验主密码后从存储重新加载数据
+    // 未设置主密码时不需要 master_password；设置了硬件密钥时需要使用 unlock_with_hardware
+    pub async fn unlock(&self, master_password: Option<&str>) -> Result<()> {
+        if self.config.read().await.hardware_factor.is_some() {
+            return Err(anyhow!("已启用硬件密钥，解锁需要硬件密钥的响应"));
+        }
+        if !self
+            .verify_master(master_password.unwrap_or_default())
+            .await?
+        {
+            return Err(anyhow!("主密码错误"));
+        }
 
-                cache_inner.insert(*k, data);
-            }
+        self.finish_unlock().await
+    }
+
+    /// 校验主密码和硬件密钥的挑战-响应后解锁
+    ///
+    /// 响应只保存在内存中直到锁定，之后的加解密都会把它混入条目key
+    pub async fn unlock_with_hardware(
+        &self,
+        master_password: Option<&str>,
+        challenge_response: &[u8],
+    ) -> Result<()> {
+        let verifier = match &self.config.read().await.hardware_factor {
+            Some(factor) => factor.response_verifier.clone(),
+            None => return Err(anyhow!("未启用硬件密钥")),
+        };
+        if !self
+            .verify_master(master_password.unwrap_or_default())
+            .await?
+        {
+            return Err(anyhow!("主密码错误"));
+        }
+        if !crypto::verify_hardware_response(challenge_response, &verifier)? {
+            return Err(anyhow!("硬件密钥响应错误"));
         }
 
-        drop(cache_inner);
-        drop(storage_inner);
+        *self.hardware_response.lock().unwrap() = Some(challenge_response.to_vec());
+        self.finish_unlock().await
+    }
 
-        // 保存到存储
-        self.save_data().await?;
+    async fn finish_unlock(&self) -> Result<()> {
+        self.load_data_to_cache().await?;
+        self.locked.store(false, Ordering::SeqCst);
+        self.record_activity();
+        info!("密码管理器已解锁");
+        Ok(())
+    }
 
-        info!("密码 {} 已成功添加", password_id);
+    /// 启用硬件密钥第二因素，`challenge_response` 是硬件密钥对 `challenge` 的响应
+    ///
+    /// 已有条目用各自的key加密，无法在不知道key的情况下重新加密，所以只能在密码库为空时启用
+    pub async fn enable_hardware_factor(
+        &self,
+        challenge: &str,
+        challenge_response: &[u8],
+    ) -> Result<()> {
+        self.ensure_unlocked()?;
+        self.ensure_writable().await?;
+
+        if challenge.is_empty() || challenge_response.is_empty() {
+            return Err(anyhow!("挑战和响应不能为空"));
+        }
+        if !self.merged_passwords().await.is_empty() {
+            return Err(anyhow!("启用硬件密钥前密码库必须为空"));
+        }
+
+        let mut config_inner = self.config.write().await;
+        if config_inner.hardware_factor.is_some() {
+            return Err(anyhow!("硬件密钥已启用"));
+        }
+
+        let mut new_config = config_inner.clone();
+        new_config.hardware_factor = Some(HardwareFactorConfig {
+            challenge: challenge.to_string(),
+            response_verifier: crypto::hash_hardware_response(challenge_response)?,
+        });
+        Self::save_config(&new_config)?;
+        *config_inner = new_config;
+        *self.hardware_response.lock().unwrap() = Some(challenge_response.to_vec());
+        info!("已启用硬件密钥");
 
         Ok(())
     }
 
-    pub async fn delete_password(&self, password_id: &str) -> Result<()> {
-        let mut cache_inner = self.cache.write().await;
-        let storage_inner = self.storages.read().await;
+    // 实际用于加解密的key：启用硬件密钥时混入本次解锁的硬件响应
+    async fn entry_key(&self, key: &str) -> Result<String> {
+        if self.config.read().await.hardware_factor.is_none() {
+            return Ok(key.to_string());
+        }
+        match self.hardware_response.lock().unwrap().as_deref() {
+            Some(response) => crypto::mix_hardware_response(key, response),
+            None => Err(anyhow!("已启用硬件密钥，请先使用硬件密钥解锁")),
+        }
+    }
 
-        let time_now = Utc::now();
+    pub fn is_locked(&self) -> bool {
+        self.locked.load(Ordering::SeqCst)
+    }
 
-        // 从缓存中删除
-        for t in storage_inner.keys() {
-            if let Some(data) = cache_inner.get_mut(t)
-                && data.passwords.remove(password_id).is_some()
-            {
-                data.metadata.password_count -= 1;
-                data.metadata.last_sync = time_now;
-            }
+    /// 校验主密码，未设置主密码时总是通过
+    pub async fn verify_master(&self, password: &str) -> Result<bool> {
+        match &self.config.read().await.master_verifier {
+            Some(verifier) => crypto::verify_master_password(password, verifier),
+            None => Ok(true),
         }
+    }
 
-        drop(cache_inner);
-        drop(storage_inner);
+    /// 设置主密码，只能在尚未设置主密码时调用
+    pub async fn set_master_password(&self, password: &str) -> Result<()> {
+        if password.is_empty() {
+            return Err(anyhow!("主密码不能为空"));
+        }
+        self.ensure_unlocked()?;
+        self.ensure_writable().await?;
 
-        // 保存到存储
-        self.save_data().await?;
+        // 不依赖 is_first_setup，否则把它改回 true 就能绕过主密码
+        let mut config_inner = self.config.write().await;
+        if config_inner.master_verifier.is_some() {
+            return Err(anyhow!("主密码已设置"));
+        }
+
+        let mut new_config = config_inner.clone();
+        new_config.master_verifier = Some(crypto::hash_master_password(password)?);
+        Self::save_config(&new_config)?;
+        *config_inner = new_config;
 
         Ok(())
     }
 
-    pub async fn search_passwords(&self, query: &str) -> Result<Vec<Password>> {
-        let mut ret = HashMap::new();
+    /// 完成首次设置，之后启动不再进入引导流程
+    ///
+    /// 这是唯一修改 `is_first_setup` 的方法，完成后不能再改回首次设置
+    pub async fn complete_first_setup(&self) -> Result<()> {
+        self.ensure_unlocked()?;
+        self.ensure_writable().await?;
 
-        let cache_inner = self.cache.read().await;
-        let storage_inner = self.storages.read().await;
+        let mut config_inner = self.config.write().await;
+        if !config_inner.is_first_setup {
+            return Ok(());
+        }
 
-        // 直接从缓存中查询
-        for t in storage_inner.keys() {
-            if let Some(data) = cache_inner.get(t) {
-                let parts = Self::search_in_storagedata(query, data);
-                parts.into_iter().for_each(|p| {
-                    ret.insert(p.id.clone(), p);
-                });
-            }
+        let mut new_config = config_inner.clone();
+        new_config.is_first_setup = false;
+        Self::save_config(&new_config)?;
+        *config_inner = new_config;
+        info!("首次设置已完成");
+
+        Ok(())
+    }
+
+    /// 开启或关闭只读模式，设置了主密码时需要校验主密码
+    ///
+    /// 只读模式下可以查看和解密，所有写入存储和修改配置的操作返回 `PasswordError::ReadOnly`
+    pub async fn set_read_only(
+        &self,
+        read_only: bool,
+        master_password: Option<&str>,
+    ) -> Result<()> {
+        if !self
+            .verify_master(master_password.unwrap_or_default())
+            .await?
+        {
+            return Err(anyhow!("主密码错误"));
         }
 
-        Ok(ret.into_values().collect())
+        let mut config_inner = self.config.write().await;
+        let mut new_config = config_inner.clone();
+        new_config.read_only = read_only;
+        Self::save_config(&new_config)?;
+        *config_inner = new_config;
+
+        info!("只读模式已{}", if read_only { "开启" } else { "关闭" });
+        Ok(())
     }
 
-    #[inline]
-    fn search_in_storagedata(query: &str, data: &StorageData) -> Vec<Password> {
-        let mut ret = vec![];
+    /// 设置闲置多少秒后自动锁定并保存配置，`None` 表示不自动锁定
+    pub async fn set_auto_lock_secs(&self, auto_lock_secs: Option<u64>) -> Result<()> {
+        self.ensure_unlocked()?;
+        self.ensure_writable().await?;
 
-        for p in data.passwords.values() {
-            if Self::is_content_match(&p.title, query)
-                || Self::is_content_match(&p.description, query)
-            {
-                ret.push(p.clone());
-            }
-        }
+        let mut config_inner = self.config.write().await;
+        let mut new_config = config_inner.clone();
+        new_config.auto_lock_secs = auto_lock_secs;
+        Self::save_config(&new_config)?;
+        *config_inner = new_config;
 
-        ret
+        match auto_lock_secs.filter(|&secs| secs > 0) {
+            Some(secs) => info!("闲置 {} 秒后自动锁定", secs),
+            None => info!("已关闭自动锁定"),
+        }
+        Ok(())
     }
 
-    #[inline]
-    fn is_content_match(s: &str, p: &str) -> bool {
-        // 先简单的使用字符串全匹配
-        s.contains(p)
+    fn save_config(config: &Config) -> Result<()> {
+        config.save_to_file(
+            CONF_PATH
+                .get()
+                .ok_or_else(|| anyhow!("CONFIG_PATH not set"))?,
+        )
     }
 
-    pub async fn decrypt_password(&self, key: &str, data: &EncryptedData) -> Result<String> {
-        crypto::decrypt_with_password(data, key)
+    // 闲置超过配置的时间时锁定，返回是否执行了锁定
+    pub async fn lock_if_idle(&self) -> bool {
+        let timeout = self.config.read().await.auto_lock_timeout();
+        if self.is_locked()
+            || !self
+                .idle
+                .lock()
+                .unwrap()
+                .should_lock(Instant::now(), timeout)
+        {
+            return false;
+        }
+
+        self.lock().await;
+        true
     }
 
-    pub async fn generate_password(&self, config: &PasswordGeneratorConfig) -> Result<String> {
-        password::generate_password(config)
+    /// 设置数据变化的回调，替换之前设置的回调
+    pub fn set_change_listener(&self, listener: impl Fn(&VaultChange) + Send + Sync + 'static) {
+        *self.change_listener.lock().unwrap() = Some(Arc::new(listener));
     }
 
-    async fn load_data_to_cache(&self) -> Result<()> {
-        let mut cache_inner = self.cache.write().await;
-        let storage_inner = self.storages.read().await;
+    // 保存成功后调用；先取出回调再调用，回调中可以再访问管理器
+    fn notify(&self, change: VaultChange) {
+        if change.is_empty() {
+            return;
+        }
+        let listener = self.change_listener.lock().unwrap().clone();
+        if let Some(listener) = listener {
+            listener(&change);
+        }
+    }
 
-        for (t, s) in storage_inner.iter() {
-            let data = s.load().await?;
-            cache_inner.insert(*t, data);
+    // 检查是否已锁定，并记录一次活动
+    fn ensure_unlocked(&self) -> Result<()> {
+        if self.is_locked() {
+            return Err(PasswordError::Locked.into());
         }
+        self.record_activity();
         Ok(())
     }
 
-    async fn save_data(&self) -> Result<()> {
-        let cache_inner = self.cache.read().await;
-        let storage_inner = self.storages.read().await;
+    /// 记录一次活动，重新开始计算闲置时间
+    ///
+    /// 数据操作会自动记录；前端每次调用命令时也会记录
+    pub fn record_activity(&self) {
+        self.idle.lock().unwrap().record_activity(Instant::now());
+    }
 
-        // 保存到所有启用的存储点
-        let mut err = None;
-        for (target, data) in cache_inner.iter() {
-            if let Some(storage) = storage_inner.get(target) {
-                if let Err(e) = storage.save(data).await {
-                    err = match err {
-                        None => Some(e.context(format!("Failed to save to {}", target))),
-                        Some(_e) => Some(anyhow!("{}\nFailed to save to {}: {}", _e, target, e)),
-                    };
-                }
-            } else {
-                err = match err {
-                    None => Some(anyhow!("storage target {} is None", target)),
-                    Some(e) => Some(anyhow!("{}\nstorage target {} is None", e, target)),
-                };
-            }
+    pub async fn is_read_only(&self) -> bool {
+        self.config.read().await.read_only
+    }
+
+    // 只读模式下拒绝所有写入，返回 `PasswordError::ReadOnly`
+    async fn ensure_writable(&self) -> Result<()> {
+        if self.is_read_only().await {
+            return Err(PasswordError::ReadOnly.into());
         }
+        Ok(())
+    }
 
-        if let Some(e) = err { Err(e) } else { Ok(()) }
+    // 记录审计事件，写入失败只记录日志，不影响操作本身
+    fn audit(&self, action: AuditAction, entry_id: Option<&str>) {
+        if let Some(audit) = &self.audit
+            && let Err(e) = audit.record(action, entry_id)
+        {
+            error!("写入审计日志失败：{}", e);
+        }
     }
 
-    // 获取配置
-    // pub fn get_config_ref(&self) -> Arc<RwLock<Config>> {
-    //     self.config.clone()
-    // }
+    /// 最近的 `limit` 条审计记录，按时间从旧到新
+    pub async fn audit_log(&self, limit: usize) -> Result<Vec<AuditEvent>> {
+        self.ensure_unlocked()?;
 
-    // 获取所有启用的存储点
-    // pub fn get_enabled_storages(&self) -> Vec<(StorageTarget, Arc<dyn Storage>)> {
-    //     self.storages
-    //         .iter()
-    //         .map(|(&target, storage)| (target, storage.clone()))
-    //         .collect()
-    // }
+        match &self.audit {
+            Some(audit) => audit.recent(limit),
+            None => Ok(Vec::new()),
+        }
+    }
 
-    // 从指定存储点加载数据
-    // pub async fn load_from_storage(&self, target: StorageTarget) -> Result<StorageData> {
-    //     let storage = self
-    //         .storages
-    //         .get(&target)
-    //         .ok_or_else(|| anyhow!("Storage target {:?} is not enabled", target))?;
-    //     storage.load().await
-    // }
+    // 更新配置
+    pub async fn update_config(&self, mut new_config: Config) -> Result<()> {
+        self.ensure_unlocked()?;
+        self.ensure_writable().await?;
+        new_config.validate()?;
 
-    // 保存数据到指定存储点
-    // pub async fn save_to_storage(&self, target: StorageTarget, data: &StorageData) -> Result<()> {
-    //     let storage = self
-    //         .storages
-    //         .get(&target)
-    //         .ok_or_else(|| anyhow!("Storage target {:?} is not enabled", target))?;
-    //     storage.save(data).await
-    // }
+        let mut config_inner = self.config.write().await;
+        let mut storage_inner = self.storages.write().await;
 
-    // 同步两个存储点之间的数据
-    // pub async fn sync_storages(&self, from: StorageTarget, to: StorageTarget) -> Result<()> {
-    //     let from_data = self.load_from_storage(from).await?;
-    //     self.save_to_storage(to, &from_data).await?;
-    //
-    //     // 重新加载缓存
-    //     self.load_data_to_cache().await?;
-    //
-    //     Ok(())
-    // }
+        // 主密码只能通过 set_master_password 修改，只读模式只能通过 set_read_only 修改，
+        // 硬件密钥只能通过 enable_hardware_factor 启用，首次设置只能通过 complete_first_setup 结束
+        new_config.is_first_setup = config_inner.is_first_setup;
+        new_config.master_verifier = config_inner.master_verifier.clone();
+        new_config.read_only = config_inner.read_only;
+        new_config.hardware_factor = config_inner.hardware_factor.clone();
 
-    // 获取存储点状态信息
-    // pub async fn get_storage_status(&self) -> HashMap<StorageTarget, StorageStatus> {
-    //     let mut status = HashMap::new();
-    //
-    //     for (&target, storage) in &self.storages {
-    //         let storage_status = match storage.load().await {
-    //             Ok(data) => StorageStatus {
-    //                 enabled: true,
-    //                 connected: true,
-    //                 password_count: data.passwords.len(),
-    //                 last_sync: Some(data.metadata.last_sync),
-    //                 error: None,
-    //             },
-    //             Err(e) => StorageStatus {
-    //                 enabled: true,
-    //                 connected: false,
-    //                 password_count: 0,
-    //                 last_sync: None,
-    //                 error: Some(e.to_string()),
-    //             },
-    //         };
-    //         status.insert(target, storage_status);
-    //     }
+        *config_inner = new_config;
+        *storage_inner = Self::build_storages_from_config(&config_inner)?;
+        crate::log::set_level(config_inner.log_level);
+        crypto::set_preferred_cipher(config_inner.cipher);
+        crypto::set_deterministic_nonce(config_inner.deterministic_nonce);
+
+        // 保存新配置到文件
+        Self::save_config(&config_inner)?;
+        drop(config_inner);
+        drop(storage_inner);
+
+        self.notify(VaultChange::config());
+
+        Ok(())
+    }
+
+    /// 添加密码
+    ///
+    /// 请求带有 `client_id` 且该id的条目已存在时，视为重试：不修改条目，
+    /// 只把已有条目重新写入存储，保证上次可能失败的写入完成
+    pub async fn add_password(&self, mut request: PasswordCreateRequest) -> Result<()> {
+        self.ensure_unlocked()?;
+        self.ensure_writable().await?;
+
+        if let Some(client_id) = Self::used_client_id(
+            &request,
+            &self.merged_passwords().await,
+            &mut HashSet::new(),
+        ) {
+            self.save_entry(client_id).await?;
+            info!("密码 {} 已存在，重复添加已忽略", client_id);
+            return Ok(());
+        }
+
+        request.key = self.entry_key(&request.key).await?;
+        let password = Self::build_password(request)?;
+        let password_id = password.id.clone();
+
+        // 添加到缓存
+        self.insert_into_cache(vec![password]).await;
+
+        // 保存到存储
+        self.save_entry(&password_id).await?;
+        self.notify(VaultChange::added(vec![password_id.clone()]));
+        self.audit(AuditAction::Add, Some(&password_id));
+
+        info!("密码 {} 已成功添加", password_id);
+
+        Ok(())
+    }
+
+    /// 生成密码并直接保存，明文密码不离开后端
+    ///
+    /// 返回保存的条目，其中只有加密后的密码
+    pub async fn create_with_generated(
+        &self,
+        request: PasswordCreateRequestNoPassword,
+        gen_config: PasswordGeneratorConfig,
+        key: &str,
+    ) -> Result<Password> {
+        self.ensure_unlocked()?;
+        self.ensure_writable().await?;
+        let key = &self.entry_key(key).await?;
+
+        let generated = password::generate_password(&gen_config)?;
+        let password = Self::build_password(request.with_password(generated, key.to_string()))?;
+        let password_id = password.id.clone();
+
+        self.insert_into_cache(vec![password.clone()]).await;
+        self.save_entry(&password_id).await?;
+        self.notify(VaultChange::added(vec![password_id.clone()]));
+        self.audit(AuditAction::Add, Some(&password_id));
+
+        info!("已生成并保存密码 {}", password_id);
+
+        Ok(password)
+    }
+
+    /// 批量添加密码，所有条目加入缓存后只保存一次
+    ///
+    /// 单个条目失败（如TOTP密钥格式错误、`client_id` 已被使用）不影响其它条目，记录在返回结果中
+    pub async fn add_passwords(&self, requests: Vec<PasswordCreateRequest>) -> Result<BatchReport> {
+        self.ensure_unlocked()?;
+        self.ensure_writable().await?;
+
+        let existing = self.merged_passwords().await;
+        let mut seen = HashSet::new();
+        let mut report = BatchReport::default();
+        let mut passwords = Vec::with_capacity(requests.len());
+        for (index, mut request) in requests.into_iter().enumerate() {
+            if let Some(client_id) = Self::used_client_id(&request, &existing, &mut seen) {
+                report.failed.push(BatchError {
+                    index,
+                    error: format!("密码 {} 已存在", client_id),
+                });
+                continue;
+            }
+            request.key = self.entry_key(&request.key).await?;
+            match Self::build_password(request) {
+                Ok(password) => {
+                    report.succeeded.push(password.id.clone());
+                    passwords.push(password);
+                }
+                Err(e) => report.failed.push(BatchError {
+                    index,
+                    error: e.to_string(),
+                }),
+            }
+        }
+
+        if !passwords.is_empty() {
+            self.insert_into_cache(passwords).await;
+            self.save_data().await?;
+            self.notify(VaultChange::added(report.succeeded.clone()));
+            for id in &report.succeeded {
+                self.audit(AuditAction::Add, Some(id));
+            }
+        }
+
+        info!(
+            "批量添加完成：成功 {} 条，失败 {} 条",
+            report.succeeded.len(),
+            report.failed.len()
+        );
+
+        Ok(report)
+    }
+
+    // 请求的 `client_id` 已被已有条目或同一批中更早的请求使用时返回该id
     //
-    //     status
-    // }
+    // `Password::new` 以 `client_id` 作为条目id，这样的请求若照常添加会覆盖已有条目；
+    // `seen` 记录本批中已使用的id
+    fn used_client_id<'a>(
+        request: &'a PasswordCreateRequest,
+        existing: &HashMap<String, Password>,
+        seen: &mut HashSet<String>,
+    ) -> Option<&'a str> {
+        let client_id = request
+            .client_id
+            .as_deref()
+            .filter(|id| !id.trim().is_empty())?;
+        (existing.contains_key(client_id) || !seen.insert(client_id.to_string()))
+            .then_some(client_id)
+    }
+
+    // 导入的条目不能覆盖已有条目，也不能在同一批中重复使用 `client_id`
+    async fn ensure_new_client_ids(&self, requests: &[PasswordCreateRequest]) -> Result<()> {
+        let existing = self.merged_passwords().await;
+        let mut seen = HashSet::new();
+        match requests
+            .iter()
+            .find_map(|request| Self::used_client_id(request, &existing, &mut seen))
+        {
+            Some(client_id) => Err(anyhow!("密码 {} 已存在", client_id)),
+            None => Ok(()),
+        }
+    }
+
+    // 加密密码、TOTP密钥和备注，创建密码对象；所有字段使用请求中的密钥派生强度
+    fn build_password(request: PasswordCreateRequest) -> Result<Password> {
+        let strength = request.kdf_strength;
+        let encrypted_password =
+            crypto::encrypt_with_strength(&request.password, &request.key, strength)?;
+
+        let totp_secret = match &request.totp_secret {
+            Some(secret) => {
+                // 先校验格式，避免保存无法使用的密钥
+                totp::decode_base32(secret)?;
+                Some(crypto::encrypt_with_strength(
+                    secret,
+                    &request.key,
+                    strength,
+                )?)
+            }
+            None => None,
+        };
+
+        let encrypted_notes = match request.notes.as_deref() {
+            Some(notes) if !notes.is_empty() => Some(crypto::encrypt_with_strength(
+                notes,
+                &request.key,
+                strength,
+            )?),
+            _ => None,
+        };
+
+        let mut password = Password::new(request, encrypted_password);
+        password.totp_secret = totp_secret;
+        password.encrypted_notes = encrypted_notes;
+        Ok(password)
+    }
 
-    pub async fn get_all_passwords_from_storage(
+    /// 按映射从CSV导入密码，所有条目使用 `key` 加密，最后只保存一次
+    ///
+    /// 格式有问题的行会被跳过并记录在报告中，不会中断整个导入
+    pub async fn import_csv(
         &self,
-        target: StorageTarget,
-    ) -> Result<StorageData> {
-        if let Some(data) = self.cache.read().await.get(&target) {
-            Ok(data.clone())
+        csv: &str,
+        key: &str,
+        mapping: CsvMapping,
+    ) -> Result<ImportReport> {
+        self.ensure_unlocked()?;
+        self.ensure_writable().await?;
+        let key = &self.entry_key(key).await?;
+
+        let (requests, skipped) = csv::csv_to_requests(csv, &mapping, key)?;
+
+        self.ensure_new_client_ids(&requests).await?;
+        let passwords = requests
+            .into_iter()
+            .map(Self::build_password)
+            .collect::<Result<Vec<_>>>()?;
+
+        let imported = passwords.len();
+        if imported > 0 {
+            let ids = passwords.iter().map(|p| p.id.clone()).collect();
+            self.insert_into_cache(passwords).await;
+            self.save_data().await?;
+            self.notify(VaultChange::added(ids));
+            self.audit(AuditAction::Import, None);
+        }
+
+        info!(
+            "CSV导入完成：成功 {} 条，跳过 {} 条",
+            imported,
+            skipped.len()
+        );
+
+        Ok(ImportReport { imported, skipped })
+    }
+
+    /// 导出加密备份：合并所有存储点的数据后用 `passphrase` 整体加密
+    pub async fn export_backup(&self, passphrase: &str) -> Result<Vec<u8>> {
+        self.ensure_unlocked()?;
+
+        let mut data = StorageData::new();
+        data.passwords = self.merged_passwords().await;
+        data.metadata.password_count = data.passwords.len();
+
+        let bytes = backup::encode_backup(&data, passphrase)?;
+        self.audit(AuditAction::Export, None);
+        Ok(bytes)
+    }
+
+    /// 导入加密备份，合并到所有启用的存储点
+    ///
+    /// 同一id的条目保留 `updated_at` 较新的一份
+    pub async fn import_backup(&self, bytes: &[u8], passphrase: &str) -> Result<()> {
+        self.ensure_unlocked()?;
+        self.ensure_writable().await?;
+
+        let data = backup::decode_backup(bytes, passphrase)?;
+
+        let before = self.merged_passwords().await;
+        self.merge_into_cache(data.passwords.into_values().collect())
+            .await;
+        self.save_data().await?;
+
+        for change in VaultChange::diff(&before, &self.merged_passwords().await) {
+            self.notify(change);
+        }
+        self.audit(AuditAction::Import, None);
+
+        Ok(())
+    }
+
+    /// 导出为 Bitwarden 未加密JSON，用于迁移到其它密码管理器
+    ///
+    /// 所有条目都必须能用 `key` 解密，返回的内容为明文
+    pub async fn export_bitwarden_json(&self, key: &str) -> Result<String> {
+        self.ensure_unlocked()?;
+        let key = &self.entry_key(key).await?;
+
+        let mut passwords: Vec<Password> = self.merged_passwords().await.into_values().collect();
+        passwords.sort_by(|a, b| SortKey::TitleAsc.compare(a, b));
+
+        let json = bitwarden::export_bitwarden(&passwords, key)?;
+        self.audit(AuditAction::Export, None);
+        warn!(
+            "已导出 {} 条密码为Bitwarden明文JSON，导出文件未加密，请妥善保管并在导入后删除",
+            passwords.len()
+        );
+        Ok(json)
+    }
+
+    /// 导入 Bitwarden 未加密JSON，所有条目用 `key` 加密后一次保存
+    ///
+    /// 没有密码或两步验证密钥无效的条目会被跳过并记录在报告中
+    pub async fn import_bitwarden_json(&self, json: &str, key: &str) -> Result<ImportReport> {
+        self.ensure_unlocked()?;
+        self.ensure_writable().await?;
+        let key = &self.entry_key(key).await?;
+
+        let (requests, skipped) = bitwarden::bitwarden_to_requests(json, key)?;
+
+        self.ensure_new_client_ids(&requests).await?;
+        let passwords = requests
+            .into_iter()
+            .map(Self::build_password)
+            .collect::<Result<Vec<_>>>()?;
+
+        let imported = passwords.len();
+        if imported > 0 {
+            let ids = passwords.iter().map(|p| p.id.clone()).collect();
+            self.insert_into_cache(passwords).await;
+            self.save_data().await?;
+            self.notify(VaultChange::added(ids));
+            self.audit(AuditAction::Import, None);
+        }
+
+        info!(
+            "Bitwarden导入完成：成功 {} 条，跳过 {} 条",
+            imported,
+            skipped.len()
+        );
+
+        Ok(ImportReport { imported, skipped })
+    }
+
+    // 合并所有存储点缓存中的密码，同一id保留更新时间较新的一份
+    async fn merged_passwords(&self) -> HashMap<String, Password> {
+        let cache_inner = self.cache.read().await;
+
+        let mut ret: HashMap<String, Password> = HashMap::new();
+        for data in cache_inner.values() {
+            for p in data.passwords.values() {
+                match ret.get(&p.id) {
+                    Some(existing) if existing.updated_at >= p.updated_at => {}
+                    _ => {
+                        ret.insert(p.id.clone(), p.clone());
+                    }
+                }
+            }
+        }
+
+        ret
+    }
+
+    // 把密码合并到所有启用存储点的缓存，已存在且更新的条目不会被覆盖
+    async fn merge_into_cache(&self, passwords: Vec<Password>) {
+        let mut cache_inner = self.cache.write().await;
+        let storage_inner = self.storages.read().await;
+
+        let storage_ids: Vec<StorageId> = storage_inner.keys().cloned().collect();
+        let time_now = Utc::now();
+        for k in storage_inner.keys() {
+            let data = cache_inner
+                .entry(k.clone())
+                .or_insert_with(StorageData::new);
+            data.merge(
+                passwords
+                    .iter()
+                    .filter(|p| Self::belongs_to(p, k, &storage_ids))
+                    .cloned(),
+            );
+            data.metadata.last_sync = time_now;
+        }
+        self.mark_dirty(storage_inner.keys());
+    }
+
+    // 把密码写入所有启用存储点的缓存，条目已移出的存储点除外
+    async fn insert_into_cache(&self, passwords: Vec<Password>) {
+        let mut cache_inner = self.cache.write().await;
+        let storage_inner = self.storages.read().await;
+
+        let storage_ids: Vec<StorageId> = storage_inner.keys().cloned().collect();
+        let time_now = Utc::now();
+        for k in storage_inner.keys() {
+            let data = cache_inner
+                .entry(k.clone())
+                .or_insert_with(StorageData::new);
+            for password in &passwords {
+                if Self::belongs_to(password, k, &storage_ids) {
+                    data.passwords.insert(password.id.clone(), password.clone());
+                }
+            }
+            data.metadata.password_count = data.passwords.len();
+            data.metadata.last_sync = time_now;
+        }
+        self.mark_dirty(storage_inner.keys());
+    }
+
+    // 条目是否应同步到存储点 `sid`；排除了所有存储点时忽略排除，避免条目丢失
+    fn belongs_to(password: &Password, sid: &StorageId, storage_ids: &[StorageId]) -> bool {
+        !password.excluded_storages.contains(sid)
+            || storage_ids
+                .iter()
+                .all(|s| password.excluded_storages.contains(s))
+    }
+
+    // 修改所有存储点缓存中id对应的条目，条目不存在时报错
+    async fn update_in_cache(&self, password_id: &str, f: impl Fn(&mut Password)) -> Result<()> {
+        let mut cache_inner = self.cache.write().await;
+
+        let time_now = Utc::now();
+        let mut found = false;
+        for (id, data) in cache_inner.iter_mut() {
+            if let Some(password) = data.passwords.get_mut(password_id) {
+                f(password);
+                data.metadata.last_sync = time_now;
+                self.mark_dirty([id]);
+                found = true;
+            }
+        }
+
+        if found {
+            Ok(())
         } else {
-            Err(anyhow!("此存储点中没有数据"))
+            Err(PasswordError::NotFound(format!("密码 {} 不存在", password_id)).into())
+        }
+    }
+
+    /// 切换收藏状态，返回切换后的状态
+    pub async fn toggle_favorite(&self, password_id: &str) -> Result<bool> {
+        self.ensure_unlocked()?;
+        self.ensure_writable().await?;
+
+        // 以合并后的最新版本为准，避免各存储点状态不一致时来回翻转
+        let favorite = !self
+            .merged_passwords()
+            .await
+            .get(password_id)
+            .ok_or_else(|| PasswordError::NotFound(format!("密码 {} 不存在", password_id)))?
+            .favorite;
+
+        let time_now = Utc::now();
+        self.update_in_cache(password_id, |p| {
+            p.favorite = favorite;
+            p.updated_at = time_now;
+        })
+        .await?;
+
+        self.save_entry(password_id).await?;
+        self.notify(VaultChange::updated(vec![password_id.to_string()]));
+        self.audit(AuditAction::Update, Some(password_id));
+
+        Ok(favorite)
+    }
+
+    /// 获取所有存储点中的密码，同一id只保留 `updated_at` 较新的一份，按标题排序
+    pub async fn get_all_passwords(&self) -> Result<Vec<Password>> {
+        self.ensure_unlocked()?;
+
+        let mut ret: Vec<Password> = self.merged_passwords().await.into_values().collect();
+
+        ret.sort_by(|a, b| SortKey::TitleAsc.compare(a, b));
+        Ok(ret)
+    }
+
+    /// 获取所有收藏的密码，按标题排序
+    pub async fn get_favorites(&self) -> Result<Vec<Password>> {
+        self.ensure_unlocked()?;
+
+        let mut ret: Vec<Password> = self
+            .merged_passwords()
+            .await
+            .into_values()
+            .filter(|p| p.favorite)
+            .collect();
+
+        ret.sort_by(|a, b| SortKey::TitleAsc.compare(a, b));
+        Ok(ret)
+    }
+
+    pub async fn delete_password(&self, password_id: &str) -> Result<()> {
+        self.ensure_unlocked()?;
+        self.ensure_writable().await?;
+
+        // 从缓存中删除，写入存储前释放锁
+        {
+            let mut cache_inner = self.cache.write().await;
+            let storage_inner = self.storages.read().await;
+
+            let time_now = Utc::now();
+
+            for t in storage_inner.keys() {
+                if let Some(data) = cache_inner.get_mut(t)
+                    && data.passwords.remove(password_id).is_some()
+                {
+                    data.metadata.password_count = data.passwords.len();
+                    data.metadata.last_sync = time_now;
+                    self.mark_dirty([t]);
+                }
+            }
+        }
+
+        // 保存到存储
+        self.save_entry(password_id).await?;
+        self.notify(VaultChange::deleted(vec![password_id.to_string()]));
+        self.audit(AuditAction::Delete, Some(password_id));
+
+        Ok(())
+    }
+
+    /// 批量删除密码，只保存一次；不存在的id记录在返回结果中
+    pub async fn delete_passwords(&self, ids: &[String]) -> Result<BatchReport> {
+        self.ensure_unlocked()?;
+        self.ensure_writable().await?;
+
+        let mut report = BatchReport::default();
+        {
+            let mut cache_inner = self.cache.write().await;
+            let time_now = Utc::now();
+
+            for (index, id) in ids.iter().enumerate() {
+                let mut found = false;
+                for data in cache_inner.values_mut() {
+                    found |= data.passwords.remove(id).is_some();
+                }
+                if found {
+                    report.succeeded.push(id.clone());
+                } else {
+                    report.failed.push(BatchError {
+                        index,
+                        error: format!("密码 {} 不存在", id),
+                    });
+                }
+            }
+
+            for data in cache_inner.values_mut() {
+                data.metadata.password_count = data.passwords.len();
+                data.metadata.last_sync = time_now;
+            }
+            if !report.succeeded.is_empty() {
+                self.mark_dirty(cache_inner.keys());
+            }
+        }
+
+        if !report.succeeded.is_empty() {
+            self.save_data().await?;
+            self.notify(VaultChange::deleted(report.succeeded.clone()));
+            for id in &report.succeeded {
+                self.audit(AuditAction::Delete, Some(id));
+            }
+        }
+
+        info!(
+            "批量删除完成：成功 {} 条，失败 {} 条",
+            report.succeeded.len(),
+            report.failed.len()
+        );
+
+        Ok(report)
+    }
+
+    pub async fn search_passwords(&self, query: &str) -> Result<Vec<Password>> {
+        self.search_passwords_scoped(query, SearchScope::default())
+            .await
+    }
+
+    /// 只在 `scope` 指定的字段中搜索，匹配规则同 `search_passwords`
+    pub async fn search_passwords_scoped(
+        &self,
+        query: &str,
+        scope: SearchScope,
+    ) -> Result<Vec<Password>> {
+        self.ensure_unlocked()?;
+
+        let mut ret = HashMap::new();
+
+        let cache_inner = self.cache.read().await;
+        let storage_inner = self.storages.read().await;
+
+        // 直接从缓存中查询
+        for t in storage_inner.keys() {
+            if let Some(data) = cache_inner.get(t) {
+                let parts = Self::search_in_storagedata(query, data, scope);
+                parts.into_iter().for_each(|p| {
+                    ret.insert(p.id.clone(), p);
+                });
+            }
+        }
+
+        Ok(ret.into_values().collect())
+    }
+
+    /// 搜索并返回匹配的字段和位置，按标题排序，最多返回 `limit` 条
+    ///
+    /// 匹配规则同 `search_passwords`，`limit` 为 `None` 时返回全部结果
+    pub async fn search_passwords_paged(
+        &self,
+        query: &str,
+        limit: Option<usize>,
+    ) -> Result<Vec<SearchHit>> {
+        self.ensure_unlocked()?;
+
+        let mut hits: Vec<SearchHit> = self
+            .merged_passwords()
+            .await
+            .into_values()
+            .filter_map(|password| {
+                let (field, range) = search::find_match(&password, query)?;
+                Some(SearchHit {
+                    password,
+                    field,
+                    range,
+                })
+            })
+            .collect();
+
+        hits.sort_by(|a, b| SortKey::TitleAsc.compare(&a.password, &b.password));
+        hits.truncate(limit.unwrap_or(usize::MAX));
+        Ok(hits)
+    }
+
+    /// 按相关度排序的搜索
+    ///
+    /// `fuzzy` 为true时允许拼写错误和缺字，相关度低于阈值的结果会被丢弃
+    pub async fn search_passwords_ranked(
+        &self,
+        query: &str,
+        fuzzy: bool,
+    ) -> Result<Vec<ScoredPassword>> {
+        self.ensure_unlocked()?;
+
+        let mut ret: Vec<ScoredPassword> = self
+            .merged_passwords()
+            .await
+            .into_values()
+            .filter_map(|password| {
+                let score = search::score_password(&password, query, fuzzy);
+                (score >= search::MIN_SCORE).then_some(ScoredPassword { password, score })
+            })
+            .collect();
+
+        ret.sort_by(|a, b| {
+            b.score
+                .total_cmp(&a.score)
+                .then_with(|| SortKey::TitleAsc.compare(&a.password, &b.password))
+        });
+        Ok(ret)
+    }
+
+    /// 查找URL与 `url` 匹配的条目，用于浏览器扩展自动填充，按标题排序
+    ///
+    /// 比较归一化后的主机名，是否匹配子域名由配置 `match_subdomains` 决定
+    pub async fn find_by_url(&self, url: &str) -> Result<Vec<Password>> {
+        self.ensure_unlocked()?;
+
+        let match_subdomains = self.config.read().await.match_subdomains;
+        let mut ret: Vec<Password> = self
+            .merged_passwords()
+            .await
+            .into_values()
+            .filter(|p| {
+                p.url
+                    .as_deref()
+                    .is_some_and(|entry_url| search::url_matches(entry_url, url, match_subdomains))
+            })
+            .collect();
+
+        ret.sort_by(|a, b| SortKey::TitleAsc.compare(a, b));
+        Ok(ret)
+    }
+
+    /// 列出所有标签及使用该标签的条目数量
+    ///
+    /// 标签忽略大小写和首尾空白，按数量从多到少排序
+    pub async fn list_tags(&self) -> Result<Vec<(String, usize)>> {
+        self.ensure_unlocked()?;
+
+        let mut counts: HashMap<String, usize> = HashMap::new();
+
+        for p in self.merged_passwords().await.values() {
+            let mut tags: Vec<String> = p.tags.iter().map(|t| Self::normalize_tag(t)).collect();
+            tags.sort();
+            tags.dedup();
+
+            for tag in tags.into_iter().filter(|t| !t.is_empty()) {
+                *counts.entry(tag).or_default() += 1;
+            }
+        }
+
+        let mut ret: Vec<(String, usize)> = counts.into_iter().collect();
+        ret.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        Ok(ret)
+    }
+
+    /// 统计条目数、标签、各存储点的条目数、缺少网址的条目数和更新时间范围
+    pub async fn vault_stats(&self) -> Result<VaultStats> {
+        let per_tag = self.list_tags().await?;
+
+        let passwords = self.merged_passwords().await;
+        let per_storage = self
+            .cache
+            .read()
+            .await
+            .iter()
+            .map(|(id, data)| (id.clone(), data.passwords.len()))
+            .collect();
+
+        let total = passwords.len();
+        let title_chars: usize = passwords.values().map(|p| p.title.chars().count()).sum();
+
+        Ok(VaultStats {
+            total,
+            per_tag,
+            per_storage,
+            without_url: passwords
+                .values()
+                .filter(|p| p.url.as_deref().is_none_or(|url| url.trim().is_empty()))
+                .count(),
+            average_title_length: if total == 0 {
+                0.0
+            } else {
+                title_chars as f64 / total as f64
+            },
+            oldest_updated_at: passwords.values().map(|p| p.updated_at).min(),
+            newest_updated_at: passwords.values().map(|p| p.updated_at).max(),
+        })
+    }
+
+    /// 把所有条目中的标签 `old` 改为 `new`，返回修改的条目数
+    ///
+    /// 标签比较忽略大小写；条目已有 `new` 时去掉重复的一个。所有修改只保存一次
+    pub async fn rename_tag(&self, old: &str, new: &str) -> Result<usize> {
+        let new = new.trim();
+        if new.is_empty() {
+            return Err(anyhow!("新标签不能为空"));
+        }
+        self.replace_tag(old, Some(new)).await
+    }
+
+    /// 从所有条目中删除标签 `tag`（忽略大小写），返回修改的条目数
+    pub async fn delete_tag(&self, tag: &str) -> Result<usize> {
+        self.replace_tag(tag, None).await
+    }
+
+    /// 替换条目的标签列表并保存，保留给定的顺序
+    ///
+    /// 标签去掉首尾空白后为空的会被丢弃，忽略大小写重复的只保留第一个
+    pub async fn set_entry_tags(&self, password_id: &str, tags: Vec<String>) -> Result<()> {
+        self.ensure_unlocked()?;
+        self.ensure_writable().await?;
+
+        let mut cleaned: Vec<String> = Vec::with_capacity(tags.len());
+        for tag in &tags {
+            let tag = tag.trim();
+            if !tag.is_empty()
+                && !cleaned
+                    .iter()
+                    .any(|t| Self::normalize_tag(t) == Self::normalize_tag(tag))
+            {
+                cleaned.push(tag.to_string());
+            }
         }
+
+        let time_now = Utc::now();
+        self.update_in_cache(password_id, |p| {
+            p.tags = cleaned.clone();
+            p.updated_at = time_now;
+        })
+        .await?;
+
+        self.save_entry(password_id).await?;
+        self.notify(VaultChange::updated(vec![password_id.to_string()]));
+        self.audit(AuditAction::Update, Some(password_id));
+
+        Ok(())
+    }
+
+    /// 把所有条目的标签按字母顺序（忽略大小写）排列，返回修改的条目数
+    ///
+    /// 已经有序的条目不修改，所有修改只保存一次
+    pub async fn sort_all_tags_alphabetically(&self) -> Result<usize> {
+        self.ensure_unlocked()?;
+        self.ensure_writable().await?;
+
+        let changed: Vec<(String, Vec<String>)> = self
+            .merged_passwords()
+            .await
+            .into_values()
+            .filter_map(|p| {
+                let mut tags = p.tags.clone();
+                tags.sort_by_cached_key(|t| Self::normalize_tag(t));
+                (tags != p.tags).then_some((p.id, tags))
+            })
+            .collect();
+
+        let count = changed.len();
+        if count > 0 {
+            // 只修改顺序变化的条目，且只写入已有该条目的存储点
+            let time_now = Utc::now();
+            for (id, tags) in &changed {
+                self.update_in_cache(id, |p| {
+                    p.tags = tags.clone();
+                    p.updated_at = time_now;
+                })
+                .await?;
+            }
+            let ids: Vec<String> = changed.into_iter().map(|(id, _)| id).collect();
+            self.save_data().await?;
+            for id in &ids {
+                self.audit(AuditAction::Update, Some(id));
+            }
+            self.notify(VaultChange::updated(ids));
+        }
+
+        info!("已按字母顺序排列 {} 个条目的标签", count);
+        Ok(count)
+    }
+
+    // 把标签 `old` 替换为 `new`，`new` 为 `None` 时删除，修改过的条目一次保存
+    async fn replace_tag(&self, old: &str, new: Option<&str>) -> Result<usize> {
+        self.ensure_unlocked()?;
+        self.ensure_writable().await?;
+
+        let old = Self::normalize_tag(old);
+        if old.is_empty() {
+            return Err(anyhow!("标签不能为空"));
+        }
+
+        let changed: Vec<(String, Vec<String>)> = self
+            .merged_passwords()
+            .await
+            .into_values()
+            .filter_map(|p| {
+                if !p.tags.iter().any(|t| Self::normalize_tag(t) == old) {
+                    return None;
+                }
+
+                let mut tags: Vec<String> = Vec::with_capacity(p.tags.len());
+                for tag in &p.tags {
+                    let tag = match new {
+                        _ if Self::normalize_tag(tag) != old => tag.as_str(),
+                        Some(new) => new,
+                        None => continue,
+                    };
+                    if !tags
+                        .iter()
+                        .any(|t| Self::normalize_tag(t) == Self::normalize_tag(tag))
+                    {
+                        tags.push(tag.to_string());
+                    }
+                }
+                Some((p.id, tags))
+            })
+            .collect();
+
+        let count = changed.len();
+        if count > 0 {
+            // 只修改已有该条目的存储点，不会把条目复制到其它存储点
+            let time_now = Utc::now();
+            for (id, tags) in &changed {
+                self.update_in_cache(id, |p| {
+                    p.tags = tags.clone();
+                    p.updated_at = time_now;
+                })
+                .await?;
+            }
+            let ids: Vec<String> = changed.into_iter().map(|(id, _)| id).collect();
+            self.save_data().await?;
+            for id in &ids {
+                self.audit(AuditAction::Update, Some(id));
+            }
+            self.notify(VaultChange::updated(ids));
+        }
+
+        match new {
+            Some(new) => info!("标签 {} 已改为 {}，修改了 {} 条", old, new, count),
+            None => info!("标签 {} 已删除，修改了 {} 条", old, count),
+        }
+        Ok(count)
+    }
+
+    /// 按标签筛选密码
+    ///
+    /// `match_all` 为true时条目需包含所有标签，否则包含任一标签即可
+    pub async fn filter_by_tags(&self, tags: &[String], match_all: bool) -> Result<Vec<Password>> {
+        self.ensure_unlocked()?;
+
+        let wanted: Vec<String> = tags
+            .iter()
+            .map(|t| Self::normalize_tag(t))
+            .filter(|t| !t.is_empty())
+            .collect();
+
+        let mut ret: Vec<Password> = self
+            .merged_passwords()
+            .await
+            .into_values()
+            .filter(|p| {
+                let has = |tag: &String| p.tags.iter().any(|t| Self::normalize_tag(t) == *tag);
+                if match_all {
+                    wanted.iter().all(has)
+                } else {
+                    wanted.iter().any(has)
+                }
+            })
+            .collect();
+
+        ret.sort_by(|a, b| SortKey::TitleAsc.compare(a, b));
+        Ok(ret)
+    }
+
+    #[inline]
+    fn normalize_tag(tag: &str) -> String {
+        tag.trim().to_lowercase()
+    }
+
+    // 忽略大小写、重音符号和全角半角的差异
+    #[inline]
+    fn search_in_storagedata(query: &str, data: &StorageData, scope: SearchScope) -> Vec<Password> {
+        let query = search::normalize_for_search(query);
+        let mut ret = vec![];
+
+        for p in data.passwords.values() {
+            if scope.matches(p, &query) {
+                ret.push(p.clone());
+            }
+        }
+
+        ret
+    }
+
+    pub async fn decrypt_password(&self, key: &str, data: &EncryptedData) -> Result<String> {
+        self.ensure_unlocked()?;
+        let key = &self.entry_key(key).await?;
+
+        let plaintext = crypto::decrypt_with_password(data, key)?;
+        self.audit(AuditAction::Decrypt, None);
+        Ok(plaintext)
+    }
+
+    /// 按id解密密码，成功后记录使用时间并写回存储
+    ///
+    /// 使用时间不算修改，不更新 `updated_at`
+    pub async fn decrypt_password_by_id(&self, password_id: &str, key: &str) -> Result<String> {
+        self.ensure_unlocked()?;
+        let key = &self.entry_key(key).await?;
+
+        let encrypted = self
+            .merged_passwords()
+            .await
+            .get(password_id)
+            .ok_or_else(|| PasswordError::NotFound(format!("密码 {} 不存在", password_id)))?
+            .encrypted_password
+            .clone();
+
+        let plaintext = crypto::decrypt_with_password(&encrypted, key)?;
+        self.audit(AuditAction::Decrypt, Some(password_id));
+
+        // 只读模式下不记录使用时间
+        if self.is_read_only().await {
+            return Ok(plaintext);
+        }
+
+        let time_now = Utc::now();
+        self.update_in_cache(password_id, |p| p.last_used_at = Some(time_now))
+            .await?;
+        self.save_entry(password_id).await?;
+        self.notify(VaultChange::updated(vec![password_id.to_string()]));
+
+        Ok(plaintext)
+    }
+
+    /// 解密密码并暂存在后端，返回取走明文用的一次性凭据
+    ///
+    /// 与 `decrypt_password_by_id` 相同会记录使用时间；明文在 `reveal::REVEAL_TTL` 后过期
+    pub async fn request_reveal(&self, password_id: &str, key: &str) -> Result<RevealToken> {
+        let plaintext = self.decrypt_password_by_id(password_id, key).await?;
+        Ok(self.reveals.insert(plaintext))
+    }
+
+    /// 凭 `request_reveal` 返回的token取走明文，每个token只能取一次
+    pub async fn consume_reveal(&self, token: &str) -> Result<String> {
+        self.ensure_unlocked()?;
+        self.reveals.take(token)
+    }
+
+    /// 用同一个 `key` 批量解密多个条目，按 `ids` 的顺序返回每个条目的结果
+    ///
+    /// 单个条目失败（不存在、密钥错误）不影响其它条目，失败原因只包含
+    /// 不涉及密文的错误信息。批量解密用于列表显示，不记录使用时间
+    pub async fn decrypt_many(
+        &self,
+        ids: &[String],
+        key: &str,
+    ) -> Result<Vec<(String, Result<String, String>)>> {
+        self.ensure_unlocked()?;
+        let key = &self.entry_key(key).await?;
+
+        let passwords = self.merged_passwords().await;
+        Ok(ids
+            .iter()
+            .map(|id| {
+                let result = passwords
+                    .get(id)
+                    .ok_or_else(|| PasswordError::NotFound(format!("密码 {} 不存在", id)).into())
+                    .and_then(|p| crypto::decrypt_with_password(&p.encrypted_password, key))
+                    .map_err(|e| e.to_string());
+                if result.is_ok() {
+                    self.audit(AuditAction::Decrypt, Some(id));
+                }
+                (id.clone(), result)
+            })
+            .collect())
+    }
+
+    /// 生成条目当前的两步验证码
+    pub async fn generate_totp(&self, password_id: &str, key: &str) -> Result<TotpCode> {
+        self.ensure_unlocked()?;
+        let key = &self.entry_key(key).await?;
+
+        let password = self
+            .merged_passwords()
+            .await
+            .remove(password_id)
+            .ok_or_else(|| PasswordError::NotFound(format!("密码 {} 不存在", password_id)))?;
+        let encrypted = password
+            .totp_secret
+            .as_ref()
+            .ok_or_else(|| anyhow!("该条目没有设置两步验证"))?;
+
+        let secret = totp::decode_base32(&crypto::decrypt_with_password(encrypted, key)?)?;
+        self.audit(AuditAction::Decrypt, Some(password_id));
+        let now = Utc::now().timestamp().max(0) as u64;
+
+        totp::generate_totp(&secret, now, &password.totp_config)
+    }
+
+    /// 解密条目的备注，没有备注时返回 `None`
+    pub async fn decrypt_notes(&self, password_id: &str, key: &str) -> Result<Option<String>> {
+        self.ensure_unlocked()?;
+        let key = &self.entry_key(key).await?;
+
+        let password = self
+            .merged_passwords()
+            .await
+            .remove(password_id)
+            .ok_or_else(|| PasswordError::NotFound(format!("密码 {} 不存在", password_id)))?;
+
+        let notes = password
+            .encrypted_notes
+            .as_ref()
+            .map(|notes| crypto::decrypt_with_password(notes, key))
+            .transpose()?;
+        if notes.is_some() {
+            self.audit(AuditAction::Decrypt, Some(password_id));
+        }
+        Ok(notes)
+    }
+
+    /// 尝试用 `key` 解密每个条目的密码，找出用其它key加密的条目
+    ///
+    /// 只统计结果，不记录使用时间，也不返回明文
+    pub async fn verify_key(&self, key: &str) -> Result<KeyVerifyReport> {
+        self.ensure_unlocked()?;
+        let key = &self.entry_key(key).await?;
+
+        let passwords = self.merged_passwords().await;
+        let mut failed_ids: Vec<String> = passwords
+            .values()
+            .filter(|p| crypto::decrypt_with_password(&p.encrypted_password, key).is_err())
+            .map(|p| p.id.clone())
+            .collect();
+        failed_ids.sort();
+
+        Ok(KeyVerifyReport {
+            total: passwords.len(),
+            succeeded: passwords.len() - failed_ids.len(),
+            failed_ids,
+        })
+    }
+
+    /// 找出熵低于 `WEAK_ENTROPY_BITS` 的密码，按熵从低到高排序
+    ///
+    /// 用其它key加密、无法解密的条目会被跳过，结果中不包含明文
+    pub async fn weak_password_report(&self, key: &str) -> Result<Vec<WeakEntry>> {
+        self.ensure_unlocked()?;
+        let key = &self.entry_key(key).await?;
+
+        let mut weak: Vec<WeakEntry> = self
+            .merged_passwords()
+            .await
+            .into_values()
+            .filter_map(|p| {
+                let plaintext = crypto::decrypt_with_password(&p.encrypted_password, key).ok()?;
+                let entropy_bits = password::estimate_strength(&plaintext);
+                (entropy_bits < password::WEAK_ENTROPY_BITS).then_some(WeakEntry {
+                    id: p.id,
+                    title: p.title,
+                    entropy_bits,
+                })
+            })
+            .collect();
+        weak.sort_by(|a, b| {
+            a.entropy_bits
+                .total_cmp(&b.entropy_bits)
+                .then_with(|| a.id.cmp(&b.id))
+        });
+
+        Ok(weak)
+    }
+
+    /// 返回用 `key` 能解密的条目的 (id, SHA-1前5位)，按id排序
+    ///
+    /// 前端用前缀向 HIBP range API 查询，再调用 `check_breach` 在本地比对，
+    /// 完整的哈希不会离开后端。用其它key加密的条目会被跳过
+    pub async fn password_breach_hashes(&self, key: &str) -> Result<Vec<(String, String)>> {
+        self.ensure_unlocked()?;
+        let key = &self.entry_key(key).await?;
+
+        let mut hashes: Vec<(String, String)> = self
+            .merged_passwords()
+            .await
+            .into_values()
+            .filter_map(|p| {
+                let plaintext = crypto::decrypt_with_password(&p.encrypted_password, key).ok()?;
+                Some((p.id, breach::sha1_prefix(&breach::sha1_hex(&plaintext))))
+            })
+            .collect();
+        hashes.sort();
+
+        Ok(hashes)
+    }
+
+    /// 用 range API 返回的后缀列表检查条目是否已泄露
+    pub async fn check_breach(
+        &self,
+        password_id: &str,
+        key: &str,
+        suffixes: &[String],
+    ) -> Result<bool> {
+        self.ensure_unlocked()?;
+        let key = &self.entry_key(key).await?;
+
+        let password = self
+            .merged_passwords()
+            .await
+            .remove(password_id)
+            .ok_or_else(|| PasswordError::NotFound(format!("密码 {} 不存在", password_id)))?;
+        let plaintext = crypto::decrypt_with_password(&password.encrypted_password, key)?;
+
+        Ok(breach::matches_breach(
+            suffixes,
+            &breach::sha1_hex(&plaintext),
+        ))
+    }
+
+    /// 给条目添加加密附件，大小不能超过配置的上限，同一条目内文件名不能重复
+    pub async fn add_attachment(
+        &self,
+        password_id: &str,
+        key: &str,
+        name: &str,
+        bytes: &[u8],
+    ) -> Result<()> {
+        self.ensure_unlocked()?;
+        self.ensure_writable().await?;
+        let key = &self.entry_key(key).await?;
+
+        let name = name.trim();
+        if name.is_empty() {
+            return Err(anyhow!("附件名不能为空"));
+        }
+        let max_bytes = self.config.read().await.max_attachment_bytes;
+        if bytes.len() > max_bytes {
+            return Err(anyhow!(
+                "附件过大：{} 字节，最大允许 {} 字节",
+                bytes.len(),
+                max_bytes
+            ));
+        }
+
+        let password = self
+            .merged_passwords()
+            .await
+            .remove(password_id)
+            .ok_or_else(|| PasswordError::NotFound(format!("密码 {} 不存在", password_id)))?;
+        if password.attachments.iter().any(|a| a.name == name) {
+            return Err(anyhow!("附件 {} 已存在", name));
+        }
+        // 确认使用的是该条目的key，避免附件与密码用不同的key加密
+        crypto::decrypt_with_password(&password.encrypted_password, key)?;
+
+        let attachment = Attachment {
+            name: name.to_string(),
+            mime: Self::guess_mime(name).to_string(),
+            // 附件与密码使用相同的密钥派生强度
+            data: crypto::encrypt_bytes_with_strength(
+                bytes,
+                key,
+                password.encrypted_password.kdf.strength(),
+            )?,
+        };
+
+        let time_now = Utc::now();
+        self.update_in_cache(password_id, |p| {
+            p.attachments.push(attachment.clone());
+            p.updated_at = time_now;
+        })
+        .await?;
+        self.save_entry(password_id).await?;
+        self.notify(VaultChange::updated(vec![password_id.to_string()]));
+        self.audit(AuditAction::Update, Some(password_id));
+
+        Ok(())
+    }
+
+    /// 解密并返回附件内容
+    pub async fn get_attachment(
+        &self,
+        password_id: &str,
+        attachment_name: &str,
+        key: &str,
+    ) -> Result<Vec<u8>> {
+        self.ensure_unlocked()?;
+        let key = &self.entry_key(key).await?;
+
+        let password = self
+            .merged_passwords()
+            .await
+            .remove(password_id)
+            .ok_or_else(|| PasswordError::NotFound(format!("密码 {} 不存在", password_id)))?;
+        let attachment = password
+            .attachments
+            .iter()
+            .find(|a| a.name == attachment_name)
+            .ok_or_else(|| PasswordError::NotFound(format!("附件 {} 不存在", attachment_name)))?;
+
+        let bytes = crypto::decrypt_bytes_with_password(&attachment.data, key)?;
+        self.audit(AuditAction::Decrypt, Some(password_id));
+        Ok(bytes)
+    }
+
+    /// 删除附件
+    pub async fn remove_attachment(&self, password_id: &str, attachment_name: &str) -> Result<()> {
+        self.ensure_unlocked()?;
+        self.ensure_writable().await?;
+
+        let exists = self
+            .merged_passwords()
+            .await
+            .get(password_id)
+            .ok_or_else(|| PasswordError::NotFound(format!("密码 {} 不存在", password_id)))?
+            .attachments
+            .iter()
+            .any(|a| a.name == attachment_name);
+        if !exists {
+            return Err(PasswordError::NotFound(format!("附件 {} 不存在", attachment_name)).into());
+        }
+
+        let time_now = Utc::now();
+        self.update_in_cache(password_id, |p| {
+            p.attachments.retain(|a| a.name != attachment_name);
+            p.updated_at = time_now;
+        })
+        .await?;
+        self.save_entry(password_id).await?;
+        self.notify(VaultChange::updated(vec![password_id.to_string()]));
+        self.audit(AuditAction::Update, Some(password_id));
+
+        Ok(())
+    }
+
+    // 根据扩展名推断附件类型
+    fn guess_mime(name: &str) -> &'static str {
+        let ext = name
+            .rsplit_once('.')
+            .map(|(_, ext)| ext.to_ascii_lowercase())
+            .unwrap_or_default();
+
+        match ext.as_str() {
+            "txt" => "text/plain",
+            "json" => "application/json",
+            "pdf" => "application/pdf",
+            "png" => "image/png",
+            "jpg" | "jpeg" => "image/jpeg",
+            "pem" | "key" => "application/x-pem-file",
+            _ => "application/octet-stream",
+        }
+    }
+
+    /// 获取在 `within` 时间内到期或已经过期的密码，按到期时间排序
+    pub async fn get_expiring_passwords(&self, within: chrono::Duration) -> Result<Vec<Password>> {
+        self.ensure_unlocked()?;
+
+        let deadline = Utc::now() + within;
+        let mut ret: Vec<Password> = self
+            .merged_passwords()
+            .await
+            .into_values()
+            .filter(|p| p.expires_at.is_some_and(|t| t <= deadline))
+            .collect();
+
+        ret.sort_by(|a, b| {
+            a.expires_at
+                .cmp(&b.expires_at)
+                .then_with(|| a.id.cmp(&b.id))
+        });
+        Ok(ret)
+    }
+
+    /// 获取超过 `max_age` 未更新的密码，按更新时间排序
+    ///
+    /// 设置了过期时间的条目以过期时间为准，不在此列出
+    pub async fn get_stale_passwords(&self, max_age: chrono::Duration) -> Result<Vec<Password>> {
+        self.ensure_unlocked()?;
+
+        let cutoff = Utc::now() - max_age;
+        let mut ret: Vec<Password> = self
+            .merged_passwords()
+            .await
+            .into_values()
+            .filter(|p| p.expires_at.is_none() && p.updated_at < cutoff)
+            .collect();
+
+        ret.sort_by(|a, b| {
+            a.updated_at
+                .cmp(&b.updated_at)
+                .then_with(|| a.id.cmp(&b.id))
+        });
+        Ok(ret)
+    }
+
+    /// 把所有用 `old_key` 加密的条目改为用 `new_key` 加密，最后只保存一次
+    ///
+    /// 无法用 `old_key` 解密的条目保持不变，记录在报告中
+    pub async fn reencrypt_all(&self, old_key: &str, new_key: &str) -> Result<ReencryptReport> {
+        self.ensure_unlocked()?;
+        self.ensure_writable().await?;
+        let old_key = &self.entry_key(old_key).await?;
+        let new_key = &self.entry_key(new_key).await?;
+
+        let mut report = ReencryptReport::default();
+        let mut updated = HashMap::new();
+        for (id, p) in self.merged_passwords().await {
+            match Self::reencrypt_entry(p, old_key, new_key) {
+                Ok(entry) => {
+                    updated.insert(id, entry);
+                }
+                Err(_) => report.failed.push(id),
+            }
+        }
+        report.failed.sort();
+        report.reencrypted = updated.len();
+
+        if updated.is_empty() {
+            return Ok(report);
+        }
+
+        {
+            let mut cache_inner = self.cache.write().await;
+            let time_now = Utc::now();
+            for data in cache_inner.values_mut() {
+                for (id, entry) in &updated {
+                    if let Some(p) = data.passwords.get_mut(id) {
+                        p.encrypted_password = entry.encrypted_password.clone();
+                        p.totp_secret = entry.totp_secret.clone();
+                        p.attachments = entry.attachments.clone();
+                        p.encrypted_notes = entry.encrypted_notes.clone();
+                        p.updated_at = time_now;
+                    }
+                }
+                data.metadata.last_sync = time_now;
+            }
+            self.mark_dirty(cache_inner.keys());
+        }
+
+        self.save_data().await?;
+        self.notify(VaultChange::updated(updated.into_keys().collect()));
+        self.audit(AuditAction::Update, None);
+
+        info!(
+            "重新加密完成：成功 {} 条，失败 {} 条",
+            report.reencrypted,
+            report.failed.len()
+        );
+
+        Ok(report)
+    }
+
+    /// 把单个条目从 `old_key` 换到 `new_key`，用于不同条目使用不同key的情况
+    ///
+    /// `old_key` 错误时返回解密失败，不修改任何数据
+    pub async fn rekey_entry(&self, password_id: &str, old_key: &str, new_key: &str) -> Result<()> {
+        self.ensure_unlocked()?;
+        self.ensure_writable().await?;
+        let old_key = &self.entry_key(old_key).await?;
+        let new_key = &self.entry_key(new_key).await?;
+
+        let password = self
+            .merged_passwords()
+            .await
+            .remove(password_id)
+            .ok_or_else(|| PasswordError::NotFound(format!("密码 {} 不存在", password_id)))?;
+        let entry = Self::reencrypt_entry(password, old_key, new_key)?;
+
+        let time_now = Utc::now();
+        self.update_in_cache(password_id, |p| {
+            p.encrypted_password = entry.encrypted_password.clone();
+            p.totp_secret = entry.totp_secret.clone();
+            p.attachments = entry.attachments.clone();
+            p.encrypted_notes = entry.encrypted_notes.clone();
+            p.updated_at = time_now;
+        })
+        .await?;
+
+        self.save_entry(password_id).await?;
+        self.notify(VaultChange::updated(vec![password_id.to_string()]));
+        self.audit(AuditAction::Update, Some(password_id));
+
+        Ok(())
+    }
+
+    // 用新密钥重新加密单个条目的所有加密字段，保留各字段的密钥派生强度
+    fn reencrypt_entry(mut password: Password, old_key: &str, new_key: &str) -> Result<Password> {
+        let reencrypt = |data: &EncryptedData| -> Result<EncryptedData> {
+            let plaintext = crypto::decrypt_bytes_with_password(data, old_key)?;
+            crypto::encrypt_bytes_with_strength(&plaintext, new_key, data.kdf.strength())
+        };
+
+        password.encrypted_password = reencrypt(&password.encrypted_password)?;
+        password.totp_secret = password.totp_secret.as_ref().map(reencrypt).transpose()?;
+        for attachment in &mut password.attachments {
+            attachment.data = reencrypt(&attachment.data)?;
+        }
+        password.encrypted_notes = password
+            .encrypted_notes
+            .as_ref()
+            .map(reencrypt)
+            .transpose()?;
+        Ok(password)
+    }
+
+    pub async fn generate_password(&self, config: &PasswordGeneratorConfig) -> Result<String> {
+        password::generate_password(config)
+    }
+
+    /// 生成多个互不相同的候选密码，见 [`password::generate_passwords`]
+    pub async fn generate_passwords(
+        &self,
+        config: &PasswordGeneratorConfig,
+        count: usize,
+    ) -> Result<Vec<String>> {
+        password::generate_passwords(config, count)
+    }
+
+    /// 检查用户输入的密码是否满足生成器的规则，见 [`password::validate_against_config`]
+    pub async fn validate_password(
+        &self,
+        password: &str,
+        config: &PasswordGeneratorConfig,
+    ) -> Result<ValidationResult> {
+        password::validate_against_config(password, config)
+    }
+
+    /// 按网站的密码策略生成密码，策略格式见 [`password::parse_policy`]
+    pub async fn generate_for_policy(&self, policy: &str) -> Result<String> {
+        password::generate_password(&password::parse_policy(policy)?)
+    }
+
+    /// 由主密码、域名和计数器派生网站密码，见 [`derive::derive_site_password`]
+    pub async fn derive_site_password(
+        &self,
+        master: &str,
+        domain: &str,
+        counter: u32,
+        config: &PasswordGeneratorConfig,
+    ) -> Result<String> {
+        derive::derive_site_password(master, domain, counter, config)
+    }
+
+    pub async fn generate_pronounceable(
+        &self,
+        length: usize,
+        capitalize: bool,
+        with_digit: bool,
+    ) -> Result<String> {
+        password::generate_pronounceable(length, capitalize, with_digit)
+    }
+
+    async fn load_data_to_cache(&self) -> Result<()> {
+        let (policy, lenient) = {
+            let config = self.config.read().await;
+            (config.conflict_policy, config.lenient_load)
+        };
+        let mut cache_inner = self.cache.write().await;
+        let storage_inner = self.storages.read().await;
+
+        let mut warnings = Vec::new();
+        let mut counts = HashMap::new();
+        for (t, s) in storage_inner.iter() {
+            let (data, skipped) = Self::load_storage(t, s.as_ref(), lenient).await?;
+            warnings.extend(skipped.into_iter().map(|w| (t.clone(), w)));
+            counts.insert(t.clone(), data.passwords.len());
+            cache_inner.insert(t.clone(), data);
+        }
+        *self.load_warnings.write().await = warnings;
+        *self.loaded_counts.lock().unwrap() = counts;
+        *self.last_refresh.lock().unwrap() = Instant::now();
+
+        let targets: HashMap<StorageId, StorageTarget> = storage_inner
+            .iter()
+            .map(|(id, s)| (id.clone(), s.target()))
+            .collect();
+        // 重新加载后缓存与存储一致，只有统一时被修改的存储点需要写回
+        *self.dirty.lock().unwrap() = Self::reconcile(&mut cache_inner, &targets, policy);
+
+        Ok(())
+    }
+
+    // 读取单个存储点，宽松加载时返回跳过的条目
+    async fn load_storage(
+        id: &StorageId,
+        storage: &dyn Storage,
+        lenient: bool,
+    ) -> Result<(StorageData, Vec<LoadWarning>)> {
+        if !lenient {
+            return Ok((storage.load().await?, Vec::new()));
+        }
+
+        let (data, skipped) = storage.load_lenient().await?;
+        for warning in &skipped {
+            error!(
+                "存储点 {} 中的条目 {} 无法解析，已跳过：{}",
+                id, warning.id, warning.error
+            );
+        }
+        Ok((data, skipped))
+    }
+
+    /// 从存储点重新读取数据到缓存，用于获取其它设备推送的修改
+    ///
+    /// 读取后按冲突策略与其它存储点的缓存统一，变化的条目通过回调通知前端
+    pub async fn refresh_cache(&self, id: &StorageId) -> Result<()> {
+        self.ensure_unlocked()?;
+
+        let storage = self.storage_by_id(id).await?;
+        let (policy, lenient) = {
+            let config = self.config.read().await;
+            (config.conflict_policy, config.lenient_load)
+        };
+
+        let (data, skipped) = Self::load_storage(id, storage.as_ref(), lenient).await?;
+        self.loaded_counts
+            .lock()
+            .unwrap()
+            .insert(id.clone(), data.passwords.len());
+
+        let before = self.merged_passwords().await;
+        {
+            let mut cache_inner = self.cache.write().await;
+            cache_inner.insert(id.clone(), data);
+
+            let targets: HashMap<StorageId, StorageTarget> = self
+                .storages
+                .read()
+                .await
+                .iter()
+                .map(|(id, s)| (id.clone(), s.target()))
+                .collect();
+            let changed = Self::reconcile(&mut cache_inner, &targets, policy);
+
+            let mut dirty = self.dirty.lock().unwrap();
+            dirty.remove(id);
+            dirty.extend(changed);
+        }
+        {
+            let mut warnings = self.load_warnings.write().await;
+            warnings.retain(|(sid, _)| sid != id);
+            warnings.extend(skipped.into_iter().map(|w| (id.clone(), w)));
+        }
+
+        for change in VaultChange::diff(&before, &self.merged_passwords().await) {
+            self.notify(change);
+        }
+        Ok(())
+    }
+
+    /// 从所有存储点重新读取数据到缓存
+    pub async fn refresh_all_cache(&self) -> Result<()> {
+        self.ensure_unlocked()?;
+        self.reload_and_notify().await
+    }
+
+    /// 距上次重新加载超过配置的 `cache_ttl_secs` 时重新加载所有存储点，返回是否执行了加载
+    ///
+    /// 锁定时或 `cache_ttl_secs` 为0时不加载
+    pub async fn refresh_if_stale(&self) -> Result<bool> {
+        let ttl_secs = self.config.read().await.cache_ttl_secs;
+        if ttl_secs == 0 || self.is_locked() {
+            return Ok(false);
+        }
+
+        let elapsed = self.last_refresh.lock().unwrap().elapsed();
+        if elapsed < Duration::from_secs(ttl_secs) {
+            return Ok(false);
+        }
+
+        // 后台刷新不算用户活动，不调用 ensure_unlocked，避免推迟闲置锁定
+        self.reload_and_notify().await?;
+        Ok(true)
+    }
+
+    /// 列出存储点保存过的历史版本，从新到旧
+    ///
+    /// 目前只有 GitHub 存储点支持，其它存储点返回错误
+    pub async fn list_vault_versions(&self, id: &StorageId) -> Result<Vec<VersionInfo>> {
+        self.ensure_unlocked()?;
+
+        let storage = self.storage_by_id(id).await?;
+        storage.list_versions().await
+    }
+
+    /// 把密码库恢复为存储点的某个历史版本，并作为当前数据写入所有启用的存储点
+    ///
+    /// 历史版本之后新增的条目会被删除，恢复本身也会成为一个新的版本，可以再次撤销
+    pub async fn restore_vault_version(&self, id: &StorageId, sha: &str) -> Result<()> {
+        self.ensure_unlocked()?;
+        self.ensure_writable().await?;
+
+        let storage = self.storage_by_id(id).await?;
+        let version = storage.load_version(sha).await?;
+
+        let before = self.merged_passwords().await;
+        {
+            let mut cache_inner = self.cache.write().await;
+            for data in cache_inner.values_mut() {
+                data.passwords = version.passwords.clone();
+                data.metadata.password_count = data.passwords.len();
+            }
+            self.mark_dirty(cache_inner.keys());
+        }
+        self.save_data().await?;
+        info!(
+            "已把密码库恢复为存储点 {} 的版本 {}，共 {} 条",
+            id,
+            sha,
+            version.passwords.len()
+        );
+
+        for change in VaultChange::diff(&before, &self.merged_passwords().await) {
+            self.notify(change);
+        }
+        self.audit(AuditAction::Update, None);
+        Ok(())
+    }
+
+    /// 清空单个存储点的全部数据，用于停用设备或仓库
+    ///
+    /// `confirm` 必须等于 `WIPE_CONFIRMATION`，防止误操作。GitHub 存储点删除数据文件，
+    /// 其它存储点写入空数据，其它存储点不受影响。
+    /// 该存储点仍启用时，重新加载会从其它存储点同步回数据，清空后应在配置中停用
+    pub async fn wipe_storage(&self, id: &StorageId, confirm: &str) -> Result<()> {
+        self.ensure_unlocked()?;
+        self.ensure_writable().await?;
+
+        if confirm != WIPE_CONFIRMATION {
+            return Err(anyhow!("确认语不正确，请输入 \"{}\"", WIPE_CONFIRMATION));
+        }
+
+        let storage = self.storage_by_id(id).await?;
+        let mut data = StorageData::new();
+        data.metadata.revision = self
+            .cache
+            .read()
+            .await
+            .get(id)
+            .map_or(0, |cached| cached.metadata.revision)
+            + 1;
+        storage.save(&data).await?;
+
+        let before = self.merged_passwords().await;
+        {
+            let mut cache_inner = self.cache.write().await;
+            cache_inner.insert(id.clone(), data);
+            self.dirty.lock().unwrap().remove(id);
+        }
+        self.set_pending_sync(id, false);
+        warn!("存储点 {} 的数据已全部清空", id);
+
+        for change in VaultChange::diff(&before, &self.merged_passwords().await) {
+            self.notify(change);
+        }
+        self.audit(AuditAction::Delete, None);
+        Ok(())
+    }
+
+    /// 把 GitHub 存储点的数据文件移动到仓库中的新路径，并更新保存配置
+    ///
+    /// 先在新路径创建文件再删除旧文件，中途失败时数据仍在旧路径，配置不变
+    pub async fn move_github_vault(&self, id: &StorageId, new_path: &str) -> Result<()> {
+        self.ensure_unlocked()?;
+        self.ensure_writable().await?;
+
+        let new_path = new_path.trim();
+        if new_path.is_empty() {
+            return Err(anyhow!("文件路径不能为空"));
+        }
+
+        // 网络请求期间只持有存储点的引用，不持有配置锁，避免阻塞其它操作
+        let is_github = Self::github_storage_index(&*self.config.read().await, id)?.is_some();
+        if !is_github {
+            return Err(anyhow!("{} 不是 GitHub 存储点", id));
+        }
+        let storage = self.storage_by_id(id).await?;
+        storage.move_file(new_path).await?;
+
+        // 移动期间配置可能已被修改，重新查找该存储点
+        let mut config_inner = self.config.write().await;
+        let index = Self::github_storage_index(&config_inner, id)?.ok_or_else(|| {
+            anyhow!(
+                "数据文件已移动到 {}，但存储点 {} 已不在配置中",
+                new_path,
+                id
+            )
+        })?;
+        let mut new_config = config_inner.clone();
+        new_config.storage.github_storages[index].file_path = new_path.to_string();
+        Self::save_config(&new_config)?;
+        *config_inner = new_config;
+        drop(config_inner);
+
+        info!("存储点 {} 的数据文件已移动到 {}", id, new_path);
+        self.notify(VaultChange::config());
+        Ok(())
+    }
+
+    // 启用的 GitHub 存储点在 `github_storages` 中的下标，不是 GitHub 存储点时返回 None
+    fn github_storage_index(config: &Config, id: &StorageId) -> Result<Option<usize>> {
+        Ok(config.storage.enabled_entries()?.into_iter().find_map(
+            |(entry_id, entry)| match entry {
+                StorageEntry::GitHub(c) if entry_id == *id => config
+                    .storage
+                    .github_storages
+                    .iter()
+                    .position(|candidate| std::ptr::eq(candidate, c)),
+                _ => None,
+            },
+        ))
+    }
+
+    // 重新加载所有存储点，并通知前端变化的条目
+    async fn reload_and_notify(&self) -> Result<()> {
+        let before = self.merged_passwords().await;
+        self.load_data_to_cache().await?;
+
+        for change in VaultChange::diff(&before, &self.merged_passwords().await) {
+            self.notify(change);
+        }
+        Ok(())
+    }
+
+    async fn storage_by_id(&self, id: &StorageId) -> Result<Arc<dyn Storage>> {
+        self.storages
+            .read()
+            .await
+            .get(id)
+            .cloned()
+            .ok_or_else(|| PasswordError::InvalidTarget(id.to_string()).into())
+    }
+
+    // 按冲突策略统一各存储点缓存中的条目，使每个存储点的缓存保持一致
+    // Manual 策略下版本不同的条目保持原样，等待用户通过 resolve_conflict 选择
+    fn reconcile(
+        cache: &mut HashMap<StorageId, StorageData>,
+        targets: &HashMap<StorageId, StorageTarget>,
+        policy: ConflictPolicy,
+    ) -> HashSet<StorageId> {
+        let mut changed = HashSet::new();
+
+        // 按id排序，保证同类型的多个存储点之间结果稳定
+        let mut storage_ids: Vec<&StorageId> = cache.keys().collect();
+        storage_ids.sort_by(|a, b| a.as_str().cmp(b.as_str()));
+        let storage_ids: Vec<StorageId> = storage_ids.into_iter().cloned().collect();
+
+        let mut ids: Vec<String> = cache
+            .values()
+            .flat_map(|data| data.passwords.keys().cloned())
+            .collect();
+        ids.sort();
+        ids.dedup();
+
+        for id in ids {
+            let versions: Vec<(StorageTarget, &Password)> = storage_ids
+                .iter()
+                .filter_map(|sid| {
+                    let password = cache[sid].passwords.get(&id)?;
+                    let target = targets.get(sid).copied().unwrap_or(StorageTarget::Local);
+                    Some((target, password))
+                })
+                .collect();
+
+            let Some(winner) = Self::pick_version(&versions, policy) else {
+                continue;
+            };
+            let winner = winner.clone();
+
+            for sid in &storage_ids {
+                if let Some(data) = cache.get_mut(sid) {
+                    // 条目已从该存储点移出，删除残留的旧版本而不是同步回去
+                    if !Self::belongs_to(&winner, sid, &storage_ids) {
+                        if data.passwords.remove(&id).is_some() {
+                            changed.insert(sid.clone());
+                        }
+                        continue;
+                    }
+                    // 以更新时间判断条目是否被替换
+                    let replaced = data
+                        .passwords
+                        .insert(id.clone(), winner.clone())
+                        .is_none_or(|p| p.updated_at != winner.updated_at);
+                    if replaced {
+                        changed.insert(sid.clone());
+                    }
+                }
+            }
+        }
+
+        for (sid, data) in cache.iter_mut() {
+            if data.metadata.password_count != data.passwords.len() {
+                data.metadata.password_count = data.passwords.len();
+                changed.insert(sid.clone());
+            }
+        }
+
+        changed
+    }
+
+    // 从各存储点的版本中选出保留的一份，Manual 策略下存在冲突时返回None
+    fn pick_version<'a>(
+        versions: &[(StorageTarget, &'a Password)],
+        policy: ConflictPolicy,
+    ) -> Option<&'a Password> {
+        let (_, first) = versions.first()?;
+        let conflicting = versions
+            .iter()
+            .any(|(_, p)| p.updated_at != first.updated_at);
+        if !conflicting {
+            return Some(first);
+        }
+
+        // max_by_key 在相等时取最后一个，这里需要第一个
+        let newest = || {
+            versions
+                .iter()
+                .rev()
+                .max_by_key(|(_, p)| p.updated_at)
+                .map(|(_, p)| *p)
+        };
+        let from_target = |target: StorageTarget| {
+            versions
+                .iter()
+                .find(|(t, _)| *t == target)
+                .map(|(_, p)| *p)
+                .or_else(newest)
+        };
+
+        match policy {
+            ConflictPolicy::NewestWins => newest(),
+            ConflictPolicy::LocalWins => from_target(StorageTarget::Local),
+            ConflictPolicy::GitHubWins => from_target(StorageTarget::GitHub),
+            ConflictPolicy::Manual => None,
+        }
+    }
+
+    /// 最近一次宽松加载时跳过的条目
+    ///
+    /// 跳过的条目不在缓存中，之后的保存会把它们从存储点中移除，需要时可以从历史版本中找回
+    pub async fn get_load_warnings(&self) -> Vec<(StorageId, LoadWarning)> {
+        self.load_warnings.read().await.clone()
+    }
+
+    /// 列出各存储点中版本不一致、等待处理的条目，按id排序
+    pub async fn get_conflicts(&self) -> Result<Vec<Conflict>> {
+        self.ensure_unlocked()?;
+
+        let cache_inner = self.cache.read().await;
+        let mut storage_ids: Vec<&StorageId> = cache_inner.keys().collect();
+        storage_ids.sort_by(|a, b| a.as_str().cmp(b.as_str()));
+
+        let mut ids: Vec<&String> = cache_inner
+            .values()
+            .flat_map(|data| data.passwords.keys())
+            .collect();
+        ids.sort();
+        ids.dedup();
+
+        let mut conflicts = Vec::new();
+        for id in ids {
+            let versions: Vec<(StorageId, Password)> = storage_ids
+                .iter()
+                .filter_map(|sid| {
+                    let password = cache_inner[*sid].passwords.get(id)?;
+                    Some(((*sid).clone(), password.clone()))
+                })
+                .collect();
+
+            if versions
+                .iter()
+                .any(|(_, p)| p.updated_at != versions[0].1.updated_at)
+            {
+                conflicts.push(Conflict {
+                    id: id.clone(),
+                    versions,
+                });
+            }
+        }
+
+        Ok(conflicts)
+    }
+
+    /// 以 `storage_id` 中的版本为准处理冲突，并保存到所有存储点
+    pub async fn resolve_conflict(&self, password_id: &str, storage_id: &StorageId) -> Result<()> {
+        self.ensure_unlocked()?;
+        self.ensure_writable().await?;
+
+        {
+            let mut cache_inner = self.cache.write().await;
+            let chosen = cache_inner
+                .get(storage_id)
+                .ok_or_else(|| PasswordError::InvalidTarget(storage_id.to_string()))?
+                .passwords
+                .get(password_id)
+                .cloned()
+                .ok_or_else(|| {
+                    PasswordError::NotFound(format!(
+                        "存储点 {} 中没有密码 {}",
+                        storage_id, password_id
+                    ))
+                })?;
+
+            for data in cache_inner.values_mut() {
+                data.passwords
+                    .insert(password_id.to_string(), chosen.clone());
+                data.metadata.password_count = data.passwords.len();
+            }
+            self.mark_dirty(cache_inner.keys());
+        }
+
+        self.save_entry(password_id).await?;
+        self.notify(VaultChange::updated(vec![password_id.to_string()]));
+        self.audit(AuditAction::Update, Some(password_id));
+
+        Ok(())
+    }
+
+    /// 把单个条目从 `from` 移到 `to`，不影响其它条目和其它存储点
+    ///
+    /// 条目不在 `from` 的缓存中或存储点未启用时返回错误。先写入 `to` 再从 `from` 删除，
+    /// 中途失败时条目不会丢失。条目记录已移出 `from`，之后重新加载或修改都不会同步回去
+    pub async fn move_entry(
+        &self,
+        password_id: &str,
+        from: &StorageId,
+        to: &StorageId,
+    ) -> Result<()> {
+        self.ensure_unlocked()?;
+        self.ensure_writable().await?;
+
+        if from == to {
+            return Err(anyhow!("源存储点和目标存储点相同"));
+        }
+        let from_storage = self.storage_by_id(from).await?;
+        let to_storage = self.storage_by_id(to).await?;
+
+        let mut password = self
+            .cache
+            .read()
+            .await
+            .get(from)
+            .and_then(|data| data.passwords.get(password_id))
+            .cloned()
+            .ok_or_else(|| {
+                PasswordError::NotFound(format!("存储点 {} 中没有密码 {}", from, password_id))
+            })?;
+        // 更新时间取当前时间，使移动后的版本胜过其它存储点中的旧版本
+        password.excluded_storages.retain(|sid| sid != to);
+        password.excluded_storages.push(from.clone());
+        password.updated_at = Utc::now();
+
+        to_storage.upsert(password_id, &password).await?;
+        from_storage.remove(password_id).await?;
+
+        // 单条目写入使存储点的版本号加1，缓存同步加1
+        {
+            let mut cache_inner = self.cache.write().await;
+            if let Some(data) = cache_inner.get_mut(to) {
+                data.passwords.insert(password_id.to_string(), password);
+                data.metadata.password_count = data.passwords.len();
+                data.metadata.revision += 1;
+            }
+            if let Some(data) = cache_inner.get_mut(from) {
+                data.passwords.remove(password_id);
+                data.metadata.password_count = data.passwords.len();
+                data.metadata.revision += 1;
+            }
+        }
+
+        self.notify(VaultChange::updated(vec![password_id.to_string()]));
+        self.audit(AuditAction::Update, Some(password_id));
+        info!("密码 {} 已从存储点 {} 移到 {}", password_id, from, to);
+        Ok(())
+    }
+
+    /// 直接读取各存储点的数据，检查条目数、nonce长度以及存储点之间缺失的条目
+    pub async fn check_integrity(&self) -> Result<IntegrityReport> {
+        self.ensure_unlocked()?;
+
+        // 先复制出存储点，避免在网络请求期间持有锁
+        let storages: Vec<(StorageId, Arc<dyn Storage>)> = self
+            .storages
+            .read()
+            .await
+            .iter()
+            .map(|(id, storage)| (id.clone(), storage.clone()))
+            .collect();
+
+        let mut loaded = HashMap::new();
+        let mut storages_report = HashMap::new();
+        for (id, storage) in storages {
+            match storage.load().await {
+                Ok(data) => {
+                    let mut invalid_nonce: Vec<String> = data
+                        .passwords
+                        .values()
+                        .filter(|p| {
+                            let encrypted = &p.encrypted_password;
+                            encrypted.nonce.len() != encrypted.algo.nonce_len()
+                        })
+                        .map(|p| p.id.clone())
+                        .collect();
+                    invalid_nonce.sort();
+
+                    storages_report.insert(
+                        id.clone(),
+                        StorageIntegrity {
+                            recorded_count: data.metadata.password_count,
+                            actual_count: data.passwords.len(),
+                            invalid_nonce,
+                            ..Default::default()
+                        },
+                    );
+                    loaded.insert(id, data);
+                }
+                Err(e) => {
+                    storages_report.insert(
+                        id,
+                        StorageIntegrity {
+                            error: Some(e.to_string()),
+                            ..Default::default()
+                        },
+                    );
+                }
+            }
+        }
+
+        // 读取失败的存储点不参与缺失条目的比较，已移出某个存储点的条目在该存储点不算缺失
+        let loaded_ids: Vec<StorageId> = loaded.keys().cloned().collect();
+        let all_passwords: BTreeMap<&String, &Password> = loaded
+            .values()
+            .flat_map(|data| data.passwords.iter())
+            .collect();
+        for (id, data) in &loaded {
+            if let Some(report) = storages_report.get_mut(id) {
+                report.missing = all_passwords
+                    .iter()
+                    .filter(|(pid, p)| {
+                        !data.passwords.contains_key(**pid) && Self::belongs_to(p, id, &loaded_ids)
+                    })
+                    .map(|(pid, _)| (*pid).clone())
+                    .collect();
+            }
+        }
+
+        Ok(IntegrityReport {
+            ok: storages_report.values().all(StorageIntegrity::is_ok),
+            storages: storages_report,
+        })
+    }
+
+    /// 重新读取并统一各存储点的数据，修正条目数后写回所有存储点
+    ///
+    /// nonce长度不正确的条目无法自动修复，会保留在返回的检查结果中
+    pub async fn repair_integrity(&self) -> Result<IntegrityReport> {
+        self.ensure_unlocked()?;
+        self.ensure_writable().await?;
+
+        let before = self.merged_passwords().await;
+        self.load_data_to_cache().await?;
+        {
+            let cache_inner = self.cache.read().await;
+            self.mark_dirty(cache_inner.keys());
+        }
+        self.save_data().await?;
+
+        for change in VaultChange::diff(&before, &self.merged_passwords().await) {
+            self.notify(change);
+        }
+        info!("已修复存储点数据的一致性");
+
+        self.check_integrity().await
+    }
+
+    // 只保存缓存有改动的存储点，没有改动时不写入
+    async fn save_data(&self) -> Result<()> {
+        self.ensure_writable().await?;
+
+        let ids: Vec<StorageId> = {
+            let cache_inner = self.cache.read().await;
+            let dirty = self.dirty.lock().unwrap();
+            cache_inner
+                .keys()
+                .filter(|id| dirty.contains(*id))
+                .cloned()
+                .collect()
+        };
+        let snapshot = self.save_snapshot(ids).await;
+
+        // 保存到所有启用的存储点，失败的存储点记录下来等待重试，成功的不再需要重试
+        let mut errors = Vec::new();
+        for (target, entry) in snapshot {
+            let Some((storage, data)) = entry else {
+                errors.push(format!("storage target {} is None", target));
+                continue;
+            };
+            let result = self.save_copy(&target, storage.as_ref(), data).await;
+            self.set_pending_sync(&target, result.is_err());
+            if let Err(e) = result {
+                self.mark_dirty([&target]);
+                self.queue_pending(&target).await;
+                errors.push(format!("Failed to save to {}: {}", target, e));
+            }
+        }
+
+        Self::save_errors(errors)
+    }
+
+    // 取出各存储点要写入的存储和数据副本，避免在I/O期间持有锁
+    // 存储点或缓存已不存在时为 `None`
+    // 取出的存储点不再标记为有改动，之后的修改会重新标记，写入失败时由调用方重新标记
+    async fn save_snapshot(&self, ids: Vec<StorageId>) -> Vec<SaveJob> {
+        let cache_inner = self.cache.read().await;
+        let storage_inner = self.storages.read().await;
+        let mut dirty = self.dirty.lock().unwrap();
+        ids.into_iter()
+            .map(|id| {
+                let entry = storage_inner
+                    .get(&id)
+                    .cloned()
+                    .zip(cache_inner.get(&id).cloned());
+                if entry.is_some() {
+                    dirty.remove(&id);
+                }
+                (id, entry)
+            })
+            .collect()
+    }
+
+    // 标记缓存有改动、需要整体写入的存储点
+    // 修改缓存时应在持有写锁期间标记，避免与保存时取出副本交错
+    fn mark_dirty<'a>(&self, ids: impl IntoIterator<Item = &'a StorageId>) {
+        self.dirty.lock().unwrap().extend(ids.into_iter().cloned());
+    }
+
+    // 保存缓存数据的副本，成功后把版本号的变化和冲突时合并进来的远端条目写回缓存
+    // 保存期间缓存可能已被其他操作修改，不能直接用副本覆盖
+    async fn save_copy(
+        &self,
+        id: &StorageId,
+        storage: &dyn Storage,
+        mut data: StorageData,
+    ) -> Result<()> {
+        let base_revision = data.metadata.revision;
+        let merged = Self::save_with_merge(storage, &mut data).await?;
+
+        if let Some(cached) = self.cache.write().await.get_mut(id) {
+            cached.metadata.revision =
+                (cached.metadata.revision + data.metadata.revision).saturating_sub(base_revision);
+            if merged {
+                cached.merge(data.passwords.into_values());
+            }
+        }
+
+        Ok(())
+    }
+
+    // 把各存储点的保存错误合并为一个错误
+    fn save_errors(errors: Vec<String>) -> Result<()> {
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(anyhow!("{}\n{}", errors.join("\n"), PENDING_SYNC_HINT))
+        }
+    }
+
+    fn set_pending_sync(&self, id: &StorageId, pending: bool) {
+        let mut pending_sync = self.pending_sync.lock().unwrap();
+        if pending {
+            if pending_sync.insert(id.clone()) {
+                warn!("存储点 {} 保存失败，已记录等待重试", id);
+            }
+        } else if pending_sync.remove(id)
+            && let Some(queue) = &self.pending_queue
+            && let Err(e) = queue.remove(id)
+        {
+            warn!("从待同步队列移除存储点 {} 失败：{}", id, e);
+        }
+    }
+
+    // 把存储点当前缓存的数据记录到磁盘上的待同步队列，重启后仍可以通过 flush_pending 写入
+    async fn queue_pending(&self, id: &StorageId) {
+        let Some(queue) = &self.pending_queue else {
+            return;
+        };
+        let Some(data) = self.cache.read().await.get(id).cloned() else {
+            return;
+        };
+        if let Err(e) = queue.record(id, &data) {
+            warn!("记录存储点 {} 的待同步数据失败：{}", id, e);
+        }
+    }
+
+    /// 把磁盘上待同步队列中的数据写入对应的存储点，返回写入成功的存储点数
+    ///
+    /// 队列中是加密后的数据，锁定时也可以写入，用于网络恢复后推送离线期间的修改。
+    /// 远端已被其他设备修改时先合并再写入；仍然失败的存储点留在队列中
+    pub async fn flush_pending(&self) -> Result<usize> {
+        let Some(queue) = &self.pending_queue else {
+            return Ok(0);
+        };
+        let mut queued: Vec<(StorageId, StorageData)> = queue.load()?.into_iter().collect();
+        if queued.is_empty() {
+            return Ok(0);
+        }
+        self.ensure_writable().await?;
+        queued.sort_by(|a, b| a.0.as_str().cmp(b.0.as_str()));
+
+        let mut flushed = 0;
+        let mut errors = Vec::new();
+        for (id, mut data) in queued {
+            // 配置修改后已不存在的存储点不再重试
+            let Ok(storage) = self.storage_by_id(&id).await else {
+                queue.remove(&id)?;
+                self.set_pending_sync(&id, false);
+                continue;
+            };
+
+            match Self::save_with_merge(storage.as_ref(), &mut data).await {
+                Ok(_) => {
+                    // 锁定时缓存为空，不需要更新
+                    if let Some(cached) = self.cache.write().await.get_mut(&id) {
+                        *cached = data;
+                        self.dirty.lock().unwrap().remove(&id);
+                    }
+                    queue.remove(&id)?;
+                    self.set_pending_sync(&id, false);
+                    flushed += 1;
+                    info!("存储点 {} 的离线修改已同步", id);
+                }
+                Err(e) => errors.push(format!("Failed to save to {}: {}", id, e)),
+            }
+        }
+
+        Self::save_errors(errors)?;
+        Ok(flushed)
+    }
+
+    /// 保存失败、等待重试的存储点，按id排序
+    pub fn pending_sync(&self) -> Vec<StorageId> {
+        let mut ids: Vec<StorageId> = self.pending_sync.lock().unwrap().iter().cloned().collect();
+        ids.sort_by(|a, b| a.as_str().cmp(b.as_str()));
+        ids
+    }
+
+    /// 把缓存重新写入之前保存失败的存储点，成功的存储点从待重试中移除
+    ///
+    /// 仍然失败的存储点保留，返回的错误中列出失败原因
+    pub async fn retry_failed_saves(&self) -> Result<()> {
+        self.ensure_unlocked()?;
+        self.ensure_writable().await?;
+
+        let pending = self.pending_sync();
+        if pending.is_empty() {
+            return Ok(());
+        }
+
+        let snapshot = self.save_snapshot(pending).await;
+
+        let mut errors = Vec::new();
+        for (id, entry) in snapshot {
+            // 配置修改后已不存在的存储点不再重试
+            let Some((storage, data)) = entry else {
+                self.set_pending_sync(&id, false);
+                continue;
+            };
+
+            let result = self.save_copy(&id, storage.as_ref(), data).await;
+            self.set_pending_sync(&id, result.is_err());
+            match result {
+                Ok(()) => info!("存储点 {} 已重新同步", id),
+                Err(e) => {
+                    self.mark_dirty([&id]);
+                    self.queue_pending(&id).await;
+                    errors.push(format!("Failed to save to {}: {}", id, e));
+                }
+            }
+        }
+
+        Self::save_errors(errors)
+    }
+
+    // 把单个条目的改动写入所有存储点：缓存中有该条目时写入，没有时删除
+    async fn save_entry(&self, password_id: &str) -> Result<()> {
+        self.ensure_writable().await?;
+
+        // 先取出要写入的内容，避免在I/O期间持有锁
+        let entries: Vec<(StorageId, Arc<dyn Storage>, Option<Password>)> = {
+            let cache_inner = self.cache.read().await;
+            let storage_inner = self.storages.read().await;
+            storage_inner
+                .iter()
+                .map(|(id, storage)| {
+                    let password = cache_inner
+                        .get(id)
+                        .and_then(|data| data.passwords.get(password_id))
+                        .cloned();
+                    (id.clone(), storage.clone(), password)
+                })
+                .collect()
+        };
+
+        let mut errors = Vec::new();
+        for (id, storage, password) in entries {
+            let result = match &password {
+                Some(password) => storage.upsert(password_id, password).await,
+                None => storage.remove(password_id).await,
+            };
+            match result {
+                // 每次成功的单条目写入使存储点的版本号恰好加1，缓存同步加1，
+                // 之后整体保存时才不会被当作基于旧数据的写入
+                Ok(()) => {
+                    if let Some(data) = self.cache.write().await.get_mut(&id) {
+                        data.metadata.revision += 1;
+                    }
+                }
+                // 单条目写入成功不代表存储点已追上缓存，只有整体保存成功才清除记录
+                Err(e) => {
+                    self.set_pending_sync(&id, true);
+                    self.queue_pending(&id).await;
+                    errors.push(format!("Failed to save to {}: {}", id, e));
+                }
+            }
+        }
+
+        Self::save_errors(errors)
+    }
+
+    // 保存到单个存储点，成功后 `data` 的版本号即为写入的版本号
+    // 远端已被其他设备或其他写入修改时，先把远端数据合并进 `data` 再重试，返回是否合并过
+    // 注意：合并会让其他设备新增的条目保留下来，但也可能恢复本地刚删除的条目
+    async fn save_with_merge(storage: &dyn Storage, data: &mut StorageData) -> Result<bool> {
+        data.metadata.revision += 1;
+        let mut attempts = 0;
+        let mut merged = false;
+
+        loop {
+            attempts += 1;
+            match storage.save(data).await {
+                Err(e) => match e.downcast::<StorageError>() {
+                    Ok(StorageError::Conflict(remote)) if attempts < MAX_SAVE_ATTEMPTS => {
+                        info!("存储点数据冲突，合并远端数据后重试");
+                        data.metadata.revision = remote.metadata.revision + 1;
+                        data.merge(remote.passwords.into_values());
+                        merged = true;
+                    }
+                    Ok(conflict) => return Err(conflict.into()),
+                    Err(e) => return Err(e),
+                },
+                Ok(()) => return Ok(merged),
+            }
+        }
+    }
+
+    // 获取配置
+    // pub fn get_config_ref(&self) -> Arc<RwLock<Config>> {
+    //     self.config.clone()
+    // }
+
+    // 获取所有启用的存储点
+    // pub fn get_enabled_storages(&self) -> Vec<(StorageTarget, Arc<dyn Storage>)> {
+    //     self.storages
+    //         .iter()
+    //         .map(|(&target, storage)| (target, storage.clone()))
+    //         .collect()
+    // }
+
+    // 从指定存储点加载数据
+    // pub async fn load_from_storage(&self, target: StorageTarget) -> Result<StorageData> {
+    //     let storage = self
+    //         .storages
+    //         .get(&target)
+    //         .ok_or_else(|| anyhow!("Storage target {:?} is not enabled", target))?;
+    //     storage.load().await
+    // }
+
+    // 保存数据到指定存储点
+    // pub async fn save_to_storage(&self, target: StorageTarget, data: &StorageData) -> Result<()> {
+    //     let storage = self
+    //         .storages
+    //         .get(&target)
+    //         .ok_or_else(|| anyhow!("Storage target {:?} is not enabled", target))?;
+    //     storage.save(data).await
+    // }
+
+    // 同步两个存储点之间的数据
+    // pub async fn sync_storages(&self, from: StorageTarget, to: StorageTarget) -> Result<()> {
+    //     let from_data = self.load_from_storage(from).await?;
+    //     self.save_to_storage(to, &from_data).await?;
+    //
+    //     // 重新加载缓存
+    //     self.load_data_to_cache().await?;
+    //
+    //     Ok(())
+    // }
+
+    // 获取存储点状态信息
+    // 单个存储点失败时只记录在对应的 error 中，不影响其它存储点
+    pub async fn get_storage_status(&self) -> HashMap<StorageId, StorageStatus> {
+        // 先复制出存储点，避免在网络请求期间持有锁
+        let storages: Vec<(StorageId, Arc<dyn Storage>)> = self
+            .storages
+            .read()
+            .await
+            .iter()
+            .map(|(id, storage)| (id.clone(), storage.clone()))
+            .collect();
+
+        let mut status = HashMap::new();
+
+        for (id, storage) in storages {
+            let pending_sync = self.pending_sync.lock().unwrap().contains(&id);
+            let storage_status = match storage.load().await {
+                Ok(data) => StorageStatus {
+                    enabled: true,
+                    connected: true,
+                    password_count: data.passwords.len(),
+                    last_sync: Some(data.metadata.last_sync),
+                    error: None,
+                    pending_sync,
+                },
+                Err(e) => StorageStatus {
+                    enabled: true,
+                    connected: false,
+                    password_count: 0,
+                    last_sync: None,
+                    error: Some(e.to_string()),
+                    pending_sync,
+                },
+            };
+            status.insert(id, storage_status);
+        }
+
+        status
+    }
+
+    /// 各存储点上次加载全部数据时读到的条目数，key为存储点id
+    ///
+    /// 统计的是按冲突策略统一缓存之前的数量，用于初始化后显示各存储点实际的数据量
+    pub fn storage_counts(&self) -> HashMap<String, usize> {
+        self.loaded_counts
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|(id, count)| (id.to_string(), *count))
+            .collect()
+    }
+
+    /// 分页列出存储点中的密码
+    ///
+    /// 存储中的密码是无序的，每次都先完整排序再分页，保证翻页结果稳定
+    pub async fn list_passwords(
+        &self,
+        id: &StorageId,
+        page: usize,
+        page_size: usize,
+        sort: SortKey,
+    ) -> Result<PageResult<Password>> {
+        self.ensure_unlocked()?;
+
+        if page_size == 0 {
+            return Err(anyhow!("page_size 必须大于0"));
+        }
+
+        let cache_inner = self.cache.read().await;
+        let data = cache_inner
+            .get(id)
+            .ok_or_else(|| anyhow!("此存储点中没有数据"))?;
+
+        let mut passwords: Vec<&Password> = data.passwords.values().collect();
+        passwords.sort_by(|a, b| sort.compare(a, b));
+
+        let items = passwords
+            .into_iter()
+            .skip(page.saturating_mul(page_size))
+            .take(page_size)
+            .cloned()
+            .collect();
+
+        Ok(PageResult {
+            items,
+            total: data.passwords.len(),
+            page,
+            page_size,
+        })
+    }
+
+    /// 存储点中所有条目的摘要，按标题排序
+    ///
+    /// 列表显示只需要元数据，密文在需要解密时再按id获取
+    pub async fn list_summaries(&self, id: &StorageId) -> Result<Vec<PasswordSummary>> {
+        self.ensure_unlocked()?;
+
+        let cache_inner = self.cache.read().await;
+        let data = cache_inner
+            .get(id)
+            .ok_or_else(|| anyhow!("此存储点中没有数据"))?;
+
+        let mut passwords: Vec<&Password> = data.passwords.values().collect();
+        passwords.sort_by(|a, b| SortKey::TitleAsc.compare(a, b));
+        Ok(passwords.into_iter().map(PasswordSummary::from).collect())
+    }
+
+    pub async fn get_all_passwords_from_storage(&self, id: &StorageId) -> Result<StorageData> {
+        self.ensure_unlocked()?;
+
+        if let Some(data) = self.cache.read().await.get(id) {
+            Ok(data.clone())
+        } else {
+            Err(anyhow!("此存储点中没有数据"))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{GithubStorageConfig, LocalStorageConfig};
+    use crate::crypto::{KdfStrength, KeyDerivation};
+    use crate::store::mock_store::{self, MockStorage, create_request};
+
+    async fn manager_with(storages: Vec<(&str, Arc<MockStorage>)>) -> PasswordManager {
+        manager_from(
+            storages
+                .into_iter()
+                .map(|(id, s)| (id, s as Arc<dyn Storage>))
+                .collect(),
+        )
+        .await
+    }
+
+    async fn manager_from(storages: Vec<(&str, Arc<dyn Storage>)>) -> PasswordManager {
+        let storages = storages
+            .into_iter()
+            .map(|(id, s)| (StorageId::from(id), s))
+            .collect();
+        PasswordManager::with_storages(Config::default(), storages)
+            .await
+            .unwrap()
+    }
+
+    fn password_entry(title: &str, username: &str) -> Password {
+        Password::new(
+            PasswordCreateRequest {
+                title: title.to_string(),
+                description: String::new(),
+                tags: vec![],
+                username: username.to_string(),
+                password: "pw".to_string(),
+                url: None,
+                key: "key".to_string(),
+                expires_at: None,
+                totp_secret: None,
+                notes: None,
+                client_id: None,
+                kdf_strength: KdfStrength::Fast,
+            },
+            crypto::encrypt_with_password("pw", "key").unwrap(),
+        )
+    }
+
+    fn mapping() -> CsvMapping {
+        CsvMapping {
+            title: "name".to_string(),
+            username: "login".to_string(),
+            password: "secret".to_string(),
+            url: Some("url".to_string()),
+            tags: None,
+            description: None,
+            notes: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn import_csv_well_formed() {
+        let local = Arc::new(MockStorage::new());
+        let manager = manager_with(vec![("local", local.clone())]).await;
+
+        let csv = "name,login,secret,url\n\
+                   GitHub,alice,\"p,w\"\"1\",https://github.com\n\
+                   Broken,bob\n\
+                   Mail,carol,pw2,\n";
+        let report = manager.import_csv(csv, "key", mapping()).await.unwrap();
+
+        assert_eq!(report.imported, 2);
+        assert_eq!(report.skipped.len(), 1);
+        assert_eq!(report.skipped[0].line, 3);
+        assert_eq!(local.saves(), 1);
+
+        let stored = local.snapshot();
+        assert_eq!(stored.metadata.password_count, 2);
+        let github = stored
+            .passwords
+            .values()
+            .find(|p| p.title == "GitHub")
+            .unwrap();
+        let plain = crypto::decrypt_with_password(&github.encrypted_password, "key").unwrap();
+        assert_eq!(plain, "p,w\"1");
+    }
+
+    #[tokio::test]
+    async fn import_csv_encrypts_notes() {
+        let local = Arc::new(MockStorage::new());
+        let manager = manager_with(vec![("local", local.clone())]).await;
+
+        let csv = "name,login,secret,url,comments
+Bank,bob,pw,,pin inside
+";
+        let mapping = CsvMapping {
+            notes: Some("comments".to_string()),
+            ..mapping()
+        };
+        manager.import_csv(csv, "key", mapping).await.unwrap();
+
+        let stored = local.snapshot();
+        let bank = stored.passwords.values().next().unwrap();
+        assert!(bank.description.is_empty());
+        assert!(!serde_json::to_string(bank).unwrap().contains("pin inside"));
+        assert_eq!(
+            manager
+                .decrypt_notes(&bank.id, "key")
+                .await
+                .unwrap()
+                .as_deref(),
+            Some("pin inside")
+        );
+    }
+
+    #[tokio::test]
+    async fn backup_round_trip() {
+        let source = manager_with(vec![("local", Arc::new(MockStorage::new()))]).await;
+        let csv = "name,login,secret,url\nGitHub,alice,pw1,\nMail,bob,pw2,\n";
+        source.import_csv(csv, "key", mapping()).await.unwrap();
+
+        let bytes = source.export_backup("backup-pass").await.unwrap();
+
+        let local = Arc::new(MockStorage::new());
+        let target = manager_with(vec![("local", local.clone())]).await;
+        target.import_backup(&bytes, "backup-pass").await.unwrap();
+
+        let stored = local.snapshot();
+        assert_eq!(stored.passwords.len(), 2);
+        assert_eq!(stored.metadata.password_count, 2);
+        let mail = stored
+            .passwords
+            .values()
+            .find(|p| p.title == "Mail")
+            .unwrap();
+        let plain = crypto::decrypt_with_password(&mail.encrypted_password, "key").unwrap();
+        assert_eq!(plain, "pw2");
+    }
+
+    #[tokio::test]
+    async fn backup_wrong_passphrase() {
+        let source = manager_with(vec![("local", Arc::new(MockStorage::new()))]).await;
+        let bytes = source.export_backup("right").await.unwrap();
+
+        let local = Arc::new(MockStorage::new());
+        let target = manager_with(vec![("local", local.clone())]).await;
+        let err = target.import_backup(&bytes, "wrong").await.unwrap_err();
+
+        assert!(err.to_string().contains("备份密码错误"));
+        assert_eq!(local.saves(), 0);
+    }
+
+    #[tokio::test]
+    async fn save_merges_remote_on_conflict() {
+        use crate::store::github_store::GithubStorage;
+        use crate::store::mock_store::github_file_body;
+
+        let mut server = mockito::Server::new_async().await;
+
+        // 另一台设备已经推送了一条新密码
+        let other = password_entry("Other device", "bob");
+        let mut remote = StorageData::new();
+        remote.merge([other.clone()]);
+
+        let path = "/repos/owner/repo/contents/passwords.json";
+        // 启动加载时远端还没有文件
+        server
+            .mock("GET", path)
+            .match_query(mockito::Matcher::Any)
+            .with_status(404)
+            .expect(1)
+            .create_async()
+            .await;
+        server
+            .mock("GET", path)
+            .match_query(mockito::Matcher::Any)
+            .with_body(github_file_body(&remote, "sha-remote"))
+            .create_async()
+            .await;
+        // 第一次写入冲突，合并后第二次写入成功
+        let conflict = server
+            .mock("PUT", path)
+            .with_status(409)
+            .expect(1)
+            .create_async()
+            .await;
+        let merged = server
+            .mock("PUT", path)
+            .match_body(mockito::Matcher::PartialJsonString(
+                r#"{"sha":"sha-remote"}"#.to_string(),
+            ))
+            .with_status(200)
+            .with_body(format!(
+                r#"{{"content":{},"commit":{{}}}}"#,
+                github_file_body(&remote, "sha-new")
+            ))
+            .expect(1)
+            .create_async()
+            .await;
+
+        let github = Arc::new(GithubStorage::new_for_test(&server.url()));
+        let manager = manager_from(vec![("github", github)]).await;
+        assert!(
+            manager
+                .get_all_passwords_from_storage(&StorageId::from("github"))
+                .await
+                .unwrap()
+                .passwords
+                .is_empty()
+        );
+
+        let csv = "name,login,secret,url\nMine,alice,pw1,\n";
+        manager.import_csv(csv, "key", mapping()).await.unwrap();
+
+        conflict.assert_async().await;
+        merged.assert_async().await;
+        let data = manager
+            .get_all_passwords_from_storage(&StorageId::from("github"))
+            .await
+            .unwrap();
+        assert_eq!(data.passwords.len(), 2);
+        assert!(data.passwords.contains_key(&other.id));
+    }
+
+    fn github_config(base_url: &str) -> Config {
+        let mut config = Config::default();
+        config.storage.github_storages = vec![GithubStorageConfig {
+            name: String::new(),
+            enabled: true,
+            owner: "owner".to_string(),
+            repo: "repo".to_string(),
+            branch: "main".to_string(),
+            token: "token".to_string(),
+            file_path: "passwords.json".to_string(),
+            max_attempts: 1,
+            base_url: Some(base_url.to_string()),
+            timeout_secs: 30,
+        }];
+        config
+    }
+
+    #[tokio::test]
+    async fn test_storage_connection_github() {
+        let mut server = mockito::Server::new_async().await;
+        let manager = manager_with(vec![]).await;
+        *manager.config.write().await = github_config(&server.url());
+
+        let ok = server
+            .mock("GET", "/repos/owner/repo")
+            .with_body("{}")
+            .expect(1)
+            .create_async()
+            .await;
+        manager
+            .test_storage_connection(&StorageId::from("github"))
+            .await
+            .unwrap();
+        ok.assert_async().await;
+        ok.remove_async().await;
+
+        server
+            .mock("GET", "/repos/owner/repo")
+            .with_status(401)
+            .with_body(r#"{"message":"Bad credentials"}"#)
+            .create_async()
+            .await;
+        let err = manager
+            .test_storage_connection(&StorageId::from("github"))
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("401"));
+    }
+
+    #[tokio::test]
+    async fn move_github_vault_creates_new_file_then_deletes_old() {
+        let _guard = CONF_FILE_LOCK.lock().await;
+        conf_path();
+        let mut server = mockito::Server::new_async().await;
+        let manager = manager_with(vec![]).await;
+        let mut config = github_config(&server.url());
+        config.storage.local_storages.clear();
+        *manager.storages.write().await =
+            PasswordManager::build_storages_from_config(&config).unwrap();
+        *manager.config.write().await = config;
+
+        let mut remote = StorageData::new();
+        remote.metadata.version = "remote".to_string();
+        server
+            .mock("GET", "/repos/owner/repo/contents/passwords.json")
+            .match_query(mockito::Matcher::Any)
+            .with_body(mock_store::github_file_body(&remote, "sha-old"))
+            .create_async()
+            .await;
+        let create = server
+            .mock("PUT", "/repos/owner/repo/contents/vault/passwords.json")
+            .match_body(mockito::Matcher::PartialJson(serde_json::json!({
+                "branch": "main",
+            })))
+            .with_body(format!(
+                r#"{{"content":{},"commit":{{}}}}"#,
+                mock_store::github_file_body(&remote, "sha-new")
+            ))
+            .expect(1)
+            .create_async()
+            .await;
+        let delete = server
+            .mock("DELETE", "/repos/owner/repo/contents/passwords.json")
+            .match_body(mockito::Matcher::PartialJson(serde_json::json!({
+                "sha": "sha-old",
+            })))
+            .with_body(r#"{"commit":{}}"#)
+            .expect(1)
+            .create_async()
+            .await;
+
+        let github = StorageId::from("github");
+        manager
+            .move_github_vault(&github, " vault/passwords.json ")
+            .await
+            .unwrap();
+
+        create.assert_async().await;
+        delete.assert_async().await;
+        assert_eq!(
+            manager.config.read().await.storage.github_storages[0].file_path,
+            "vault/passwords.json"
+        );
+        assert_eq!(
+            Config::load_from_file(conf_path())
+                .unwrap()
+                .storage
+                .github_storages[0]
+                .file_path,
+            "vault/passwords.json"
+        );
+
+        // 之后的读取使用新路径
+        let moved = server
+            .mock("GET", "/repos/owner/repo/contents/vault/passwords.json")
+            .match_query(mockito::Matcher::Any)
+            .with_body(mock_store::github_file_body(&remote, "sha-new"))
+            .expect(1)
+            .create_async()
+            .await;
+        let data = manager
+            .storage_by_id(&github)
+            .await
+            .unwrap()
+            .load()
+            .await
+            .unwrap();
+        assert_eq!(data.metadata.version, "remote");
+        moved.assert_async().await;
+    }
+
+    #[tokio::test]
+    async fn move_github_vault_rejects_other_storages() {
+        let manager = manager_with(vec![("local", Arc::new(MockStorage::new()))]).await;
+        let err = manager
+            .move_github_vault(&StorageId::from("local"), "other.json")
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("GitHub"));
+    }
+
+    #[tokio::test]
+    async fn preview_config_reports_each_storage() {
+        let mut good_server = mockito::Server::new_async().await;
+        let mut bad_server = mockito::Server::new_async().await;
+        good_server
+            .mock("GET", "/repos/owner/repo")
+            .with_body("{}")
+            .create_async()
+            .await;
+        bad_server
+            .mock("GET", "/repos/owner/repo")
+            .with_status(401)
+            .with_body(r#"{"message":"Bad credentials"}"#)
+            .create_async()
+            .await;
+
+        let local = Arc::new(MockStorage::new());
+        let manager = manager_with(vec![("local", local.clone())]).await;
+        let before = manager.config.read().await.clone();
+
+        let mut candidate = github_config(&good_server.url());
+        candidate.storage.local_storages.clear();
+        let mut bad = candidate.storage.github_storages[0].clone();
+        bad.name = "backup".to_string();
+        bad.base_url = Some(bad_server.url());
+        candidate.storage.github_storages.push(bad);
+
+        let preview = manager.preview_config(candidate).await.unwrap();
+        assert!(!preview.ok);
+        assert!(preview.storages[&StorageId::from("github")].ok);
+        let failed = &preview.storages[&StorageId::from("backup")];
+        assert!(!failed.ok);
+        assert!(failed.error.as_deref().unwrap().contains("401"));
+
+        // 当前状态不受影响
+        assert_eq!(
+            serde_json::to_value(&*manager.config.read().await).unwrap(),
+            serde_json::to_value(&before).unwrap()
+        );
+        assert!(manager.resolve_storage("local").await.is_ok());
+        assert!(manager.resolve_storage("backup").await.is_err());
+        assert_eq!(local.saves(), 0);
+
+        // 只有正常的存储点时整体通过
+        let mut candidate = github_config(&good_server.url());
+        candidate.storage.local_storages.clear();
+        assert!(manager.preview_config(candidate).await.unwrap().ok);
+    }
+
+    #[tokio::test]
+    async fn test_storage_connection_disabled_target() {
+        let manager = manager_with(vec![]).await;
+        assert!(
+            manager
+                .test_storage_connection(&StorageId::from("github"))
+                .await
+                .is_err()
+        );
+    }
+
+    #[tokio::test]
+    async fn storage_status_reports_each_target() {
+        let mut data = StorageData::new();
+        data.merge([password_entry("GitHub", "alice")]);
+        let local = Arc::new(MockStorage::with_data(data));
+        let github = Arc::new(MockStorage::new());
+        let manager = manager_with(vec![("local", local), ("github", github.clone())]).await;
+        github.set_fail(true);
+
+        let status = manager.get_storage_status().await;
+
+        let healthy = &status[&StorageId::from("local")];
+        assert!(healthy.connected);
+        assert_eq!(healthy.password_count, 1);
+        assert!(healthy.last_sync.is_some());
+        assert!(healthy.error.is_none());
+
+        let unreachable = &status[&StorageId::from("github")];
+        assert!(!unreachable.connected);
+        assert_eq!(unreachable.password_count, 0);
+        assert!(
+            unreachable
+                .error
+                .as_deref()
+                .unwrap()
+                .contains("unavailable")
+        );
+
+        // 与命令中使用的名称一致
+        let json = serde_json::to_value(&status).unwrap();
+        assert_eq!(json["local"]["password_count"], 1);
+        assert_eq!(json["github"]["connected"], false);
+    }
+
+    // 测试进程共用的数据路径，放在临时目录中
+    fn data_path() -> &'static PathBuf {
+        DATA_PATH.get_or_init(|| mock_store::temp_dir().join("passwords.json"))
+    }
+
+    #[tokio::test]
+    async fn lenient_load_is_opt_in() {
+        let dir = mock_store::temp_dir();
+        let good = password_entry("GitHub", "alice");
+        let mut bad = serde_json::to_value(&good).unwrap();
+        bad.as_object_mut().unwrap().remove("encrypted_password");
+        let content = serde_json::json!({
+            "metadata": StorageData::new().metadata,
+            "passwords": { good.id.clone(): good, "broken": bad },
+        });
+        std::fs::write(dir.join("passwords.json"), content.to_string()).unwrap();
+
+        let local = || -> Arc<dyn Storage> {
+            Arc::new(LocalStorage::new(
+                dir.join("passwords.json"),
+                &LocalStorageConfig::default(),
+            ))
+        };
+        let storages = || HashMap::from([(StorageId::from("local"), local())]);
+
+        // 默认严格加载，数据文件损坏时初始化失败
+        assert!(
+            PasswordManager::with_storages(Config::default(), storages())
+                .await
+                .is_err()
+        );
+
+        let config = Config {
+            lenient_load: true,
+            ..Default::default()
+        };
+        let manager = PasswordManager::with_storages(config, storages())
+            .await
+            .unwrap();
+        let data = manager
+            .get_all_passwords_from_storage(&StorageId::from("local"))
+            .await
+            .unwrap();
+        assert_eq!(data.passwords.len(), 1);
+        assert!(data.passwords.contains_key(&good.id));
+
+        let warnings = manager.get_load_warnings().await;
+        assert_eq!(warnings.len(), 1);
+        assert_eq!(warnings[0].0, StorageId::from("local"));
+        assert_eq!(warnings[0].1.id, "broken");
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn concurrent_adds_are_not_lost() {
+        let dir = mock_store::temp_dir();
+        let path = dir.join("passwords.json");
+        let no_backups = LocalStorageConfig {
+            max_backups: 0,
+            ..Default::default()
+        };
+        let storage: Arc<dyn Storage> = Arc::new(LocalStorage::new(path.clone(), &no_backups));
+        let manager = Arc::new(manager_from(vec![("local", storage)]).await);
+
+        let tasks: Vec<_> = (0..20)
+            .map(|i| {
+                let manager = manager.clone();
+                tokio::spawn(async move {
+                    manager
+                        .add_password(create_request(&format!("entry-{}", i)))
+                        .await
+                })
+            })
+            .collect();
+        for task in tasks {
+            task.await.unwrap().unwrap();
+        }
+
+        let stored = || async {
+            LocalStorage::new(path.clone(), &no_backups)
+                .load()
+                .await
+                .unwrap()
+        };
+        assert_eq!(stored().await.passwords.len(), 20);
+        assert_eq!(stored().await.metadata.password_count, 20);
+
+        // 之后的整体保存也不会被当作基于旧数据的写入
+        manager
+            .add_passwords(vec![create_request("batch")])
+            .await
+            .unwrap();
+        assert_eq!(stored().await.passwords.len(), 21);
+        assert_eq!(manager.get_all_passwords().await.unwrap().len(), 21);
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn two_local_storages() {
+        let local = |name: &str| LocalStorageConfig {
+            name: name.to_string(),
+            ..Default::default()
+        };
+        let mut config = Config::default();
+        config.storage.local_storages = vec![local("main"), local("usb")];
+
+        let dir = data_path().parent().unwrap();
+        let manager = PasswordManager::new(config).await.unwrap();
+        manager
+            .add_password(PasswordCreateRequest {
+                title: "GitHub".to_string(),
+                description: String::new(),
+                tags: vec![],
+                username: "alice".to_string(),
+                password: "pw".to_string(),
+                url: None,
+                key: "key".to_string(),
+                expires_at: None,
+                totp_secret: None,
+                notes: None,
+                client_id: None,
+                kdf_strength: KdfStrength::Fast,
+            })
+            .await
+            .unwrap();
+
+        // 两个存储点各自写入独立的文件
+        for id in ["main", "usb"] {
+            let id = manager.resolve_storage(id).await.unwrap();
+            let data = manager.get_all_passwords_from_storage(&id).await.unwrap();
+            assert_eq!(data.passwords.len(), 1);
+            assert!(dir.join(format!("passwords-{}.json", id)).exists());
+        }
+
+        // 兼容旧的名称：解析为第一个本地存储点
+        assert_eq!(
+            manager.resolve_storage("local").await.unwrap(),
+            StorageId::from("main")
+        );
+        assert!(manager.resolve_storage("github").await.is_err());
+    }
+
+    #[tokio::test]
+    async fn configured_local_data_path() {
+        data_path();
+        let absolute = mock_store::temp_dir().join("vault.json");
+        let storage = PasswordManager::build_storage(
+            &StorageId::from("vault"),
+            StorageEntry::Local(&LocalStorageConfig {
+                data_path: Some(absolute.clone()),
+                ..Default::default()
+            }),
+        )
+        .unwrap();
+        storage.save(&StorageData::new()).await.unwrap();
+        assert!(absolute.exists());
+
+        // 相对路径放在应用数据目录下
+        let relative = PathBuf::from(format!("vaults/{}.json", uuid::Uuid::new_v4()));
+        let storage = PasswordManager::build_storage(
+            &StorageId::from("synced"),
+            StorageEntry::Local(&LocalStorageConfig {
+                data_path: Some(relative.clone()),
+                ..Default::default()
+            }),
+        )
+        .unwrap();
+        storage.save(&StorageData::new()).await.unwrap();
+        assert!(data_path().parent().unwrap().join(relative).exists());
+    }
+
+    #[tokio::test]
+    async fn sqlite_local_storage() {
+        data_path();
+        let id = format!("sqlite-{}", uuid::Uuid::new_v4());
+        let storage = PasswordManager::build_storage(
+            &StorageId::from(id.as_str()),
+            StorageEntry::Local(&LocalStorageConfig {
+                format: LocalFormat::Sqlite,
+                ..Default::default()
+            }),
+        )
+        .unwrap();
+
+        let mut data = StorageData::new();
+        data.merge([password_entry("GitHub", "alice")]);
+        storage.save(&data).await.unwrap();
+
+        let path = data_path().with_file_name(format!("passwords-{}.db", id));
+        assert!(path.exists());
+        assert_eq!(storage.load().await.unwrap().passwords.len(), 1);
+    }
+
+    #[test]
+    fn local_data_path_per_storage() {
+        let path = Path::new("/data/passwords.json");
+        assert_eq!(
+            PasswordManager::local_data_path(path, &StorageId::from("local")),
+            path
+        );
+        assert_eq!(
+            PasswordManager::local_data_path(path, &StorageId::from("usb")),
+            Path::new("/data/passwords-usb.json")
+        );
+    }
+
+    // 按顺序创建的条目，创建时间依次递增
+    fn sorted_vault() -> StorageData {
+        let base = Utc::now();
+        let mut data = StorageData::new();
+        for (i, title) in ["bravo", "Alpha", "delta", "charlie", "echo"]
+            .into_iter()
+            .enumerate()
+        {
+            let mut p = password_entry(title, "user");
+            p.created_at = base + chrono::Duration::seconds(i as i64);
+            // 更新时间与创建顺序相反
+            p.updated_at = base - chrono::Duration::seconds(i as i64);
+            data.merge([p]);
+        }
+        data
+    }
+
+    async fn titles(
+        manager: &PasswordManager,
+        page: usize,
+        size: usize,
+        sort: SortKey,
+    ) -> Vec<String> {
+        manager
+            .list_passwords(&StorageId::from("local"), page, size, sort)
+            .await
+            .unwrap()
+            .items
+            .into_iter()
+            .map(|p| p.title)
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn list_passwords_sort_keys() {
+        let local = Arc::new(MockStorage::with_data(sorted_vault()));
+        let manager = manager_with(vec![("local", local)]).await;
+
+        let cases = [
+            (
+                SortKey::TitleAsc,
+                ["Alpha", "bravo", "charlie", "delta", "echo"],
+            ),
+            (
+                SortKey::TitleDesc,
+                ["echo", "delta", "charlie", "bravo", "Alpha"],
+            ),
+            (
+                SortKey::CreatedAtAsc,
+                ["bravo", "Alpha", "delta", "charlie", "echo"],
+            ),
+            (
+                SortKey::CreatedAtDesc,
+                ["echo", "charlie", "delta", "Alpha", "bravo"],
+            ),
+            (
+                SortKey::UpdatedAtAsc,
+                ["echo", "charlie", "delta", "Alpha", "bravo"],
+            ),
+            (
+                SortKey::UpdatedAtDesc,
+                ["bravo", "Alpha", "delta", "charlie", "echo"],
+            ),
+        ];
+        for (sort, expected) in cases {
+            assert_eq!(titles(&manager, 0, 10, sort).await, expected, "{:?}", sort);
+        }
+    }
+
+    #[tokio::test]
+    async fn list_passwords_boundary_pages() {
+        let local = Arc::new(MockStorage::with_data(sorted_vault()));
+        let manager = manager_with(vec![("local", local)]).await;
+        let sort = SortKey::TitleAsc;
+
+        assert_eq!(titles(&manager, 0, 2, sort).await, ["Alpha", "bravo"]);
+        assert_eq!(titles(&manager, 2, 2, sort).await, ["echo"]);
+        assert!(titles(&manager, 3, 2, sort).await.is_empty());
+        assert!(titles(&manager, usize::MAX, 2, sort).await.is_empty());
+
+        let page = manager
+            .list_passwords(&StorageId::from("local"), 1, 2, sort)
+            .await
+            .unwrap();
+        assert_eq!(page.total, 5);
+        assert_eq!((page.page, page.page_size), (1, 2));
+
+        assert!(
+            manager
+                .list_passwords(&StorageId::from("local"), 0, 0, sort)
+                .await
+                .is_err()
+        );
+    }
+
+    #[tokio::test]
+    async fn list_passwords_ties_break_by_id() {
+        let mut data = StorageData::new();
+        let now = Utc::now();
+        for _ in 0..5 {
+            let mut p = password_entry("same", "user");
+            p.created_at = now;
+            data.merge([p]);
+        }
+        let local = Arc::new(MockStorage::with_data(data));
+        let manager = manager_with(vec![("local", local)]).await;
+
+        let ids = |page: PageResult<Password>| -> Vec<String> {
+            page.items.into_iter().map(|p| p.id).collect()
+        };
+        let first = ids(manager
+            .list_passwords(&StorageId::from("local"), 0, 5, SortKey::CreatedAtAsc)
+            .await
+            .unwrap());
+        let mut sorted = first.clone();
+        sorted.sort();
+        assert_eq!(first, sorted);
+    }
+
+    #[tokio::test]
+    async fn summaries_omit_ciphertext() {
+        let mut favorite = password_entry("b", "bob");
+        favorite.favorite = true;
+        favorite.tags = vec!["work".to_string()];
+        let mut data = StorageData::new();
+        data.merge([password_entry("a", "alice"), favorite.clone()]);
+        let manager = manager_with(vec![("local", Arc::new(MockStorage::with_data(data)))]).await;
+
+        let summaries = manager
+            .list_summaries(&StorageId::from("local"))
+            .await
+            .unwrap();
+        let titles: Vec<&str> = summaries.iter().map(|s| s.title.as_str()).collect();
+        assert_eq!(titles, ["a", "b"]);
+        assert_eq!(summaries[1], PasswordSummary::from(&favorite));
+        assert!(summaries[1].favorite);
+
+        let json = serde_json::to_value(&summaries).unwrap();
+        for summary in json.as_array().unwrap() {
+            let fields = summary.as_object().unwrap();
+            assert!(!fields.contains_key("encrypted_password"));
+            assert!(
+                fields
+                    .values()
+                    .all(|v| !v.to_string().contains("ciphertext"))
+            );
+        }
+
+        assert!(
+            manager
+                .list_summaries(&StorageId::from("missing"))
+                .await
+                .is_err()
+        );
+    }
+
+    fn tagged(title: &str, tags: &[&str]) -> Password {
+        let mut p = password_entry(title, "user");
+        p.tags = tags.iter().map(|t| t.to_string()).collect();
+        p
+    }
+
+    async fn tag_manager() -> PasswordManager {
+        let mut data = StorageData::new();
+        data.merge([
+            tagged("a", &["Work", "dev"]),
+            tagged("b", &[" work ", "Finance"]),
+            tagged("c", &["DEV", "dev"]),
+            tagged("d", &[]),
+        ]);
+        manager_with(vec![("local", Arc::new(MockStorage::with_data(data)))]).await
+    }
+
+    #[tokio::test]
+    async fn list_tags_counts_each_entry_once() {
+        let manager = tag_manager().await;
+
+        assert_eq!(
+            manager.list_tags().await.unwrap(),
+            vec![
+                ("dev".to_string(), 2),
+                ("work".to_string(), 2),
+                ("finance".to_string(), 1),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn filter_by_tags_and_or() {
+        let manager = tag_manager().await;
+        let filter = async |tags: &[&str], match_all: bool| -> Vec<String> {
+            let tags: Vec<String> = tags.iter().map(|t| t.to_string()).collect();
+            manager
+                .filter_by_tags(&tags, match_all)
+                .await
+                .unwrap()
+                .into_iter()
+                .map(|p| p.title)
+                .collect()
+        };
+
+        assert_eq!(filter(&["WORK ", "dev"], false).await, ["a", "b", "c"]);
+        assert_eq!(filter(&["WORK ", "dev"], true).await, ["a"]);
+        assert_eq!(filter(&["finance"], true).await, ["b"]);
+        assert!(filter(&["missing"], false).await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn search_ranked_prefers_exact_match() {
+        let mut data = StorageData::new();
+        data.merge([
+            password_entry("GitHub Enterprise", "a"),
+            password_entry("GitHub", "b"),
+            password_entry("Gitlab", "c"),
+            password_entry("Bank", "d"),
+        ]);
+        let manager = manager_with(vec![("local", Arc::new(MockStorage::with_data(data)))]).await;
+
+        let titles = |results: Vec<ScoredPassword>| -> Vec<String> {
+            results.into_iter().map(|r| r.password.title).collect()
+        };
+
+        let exact = manager
+            .search_passwords_ranked("github", false)
+            .await
+            .unwrap();
+        assert_eq!(titles(exact), ["GitHub", "GitHub Enterprise"]);
+
+        let fuzzy = manager
+            .search_passwords_ranked("gthub", true)
+            .await
+            .unwrap();
+        assert_eq!(fuzzy[0].password.title, "GitHub");
+        assert!(titles(fuzzy).iter().all(|t| t != "Bank"));
+
+        // 原有的搜索行为不变
+        assert!(manager.search_passwords("gthub").await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn toggle_favorite_writes_through() {
+        let entry = password_entry("GitHub", "alice");
+        let id = entry.id.clone();
+        let mut data = StorageData::new();
+        data.merge([entry, password_entry("Bank", "bob")]);
+
+        let local = Arc::new(MockStorage::with_data(data));
+        let manager = manager_with(vec![("local", local.clone())]).await;
+        assert!(manager.get_favorites().await.unwrap().is_empty());
+
+        assert!(manager.toggle_favorite(&id).await.unwrap());
+        assert!(local.snapshot().passwords[&id].favorite);
+        let favorites = manager.get_favorites().await.unwrap();
+        assert_eq!(favorites.len(), 1);
+        assert_eq!(favorites[0].id, id);
+
+        assert!(!manager.toggle_favorite(&id).await.unwrap());
+        assert!(!local.snapshot().passwords[&id].favorite);
+        assert!(manager.get_favorites().await.unwrap().is_empty());
+
+        assert!(manager.toggle_favorite("missing").await.is_err());
+    }
+
+    #[test]
+    fn password_without_favorite_field_loads() {
+        let mut json = serde_json::to_value(password_entry("Old", "user")).unwrap();
+        json.as_object_mut().unwrap().remove("favorite");
+
+        let password: Password = serde_json::from_value(json).unwrap();
+        assert!(!password.favorite);
+    }
+
+    #[tokio::test]
+    async fn decrypt_by_id_records_last_used() {
+        let entry = password_entry("GitHub", "alice");
+        let id = entry.id.clone();
+        let mut data = StorageData::new();
+        data.merge([entry]);
+
+        let local = Arc::new(MockStorage::with_data(data));
+        let manager = manager_with(vec![("local", local.clone())]).await;
+
+        // 密钥错误时不记录
+        assert!(manager.decrypt_password_by_id(&id, "wrong").await.is_err());
+        assert!(local.snapshot().passwords[&id].last_used_at.is_none());
+        assert_eq!(local.saves(), 0);
+
+        let before = Utc::now();
+        assert_eq!(
+            manager.decrypt_password_by_id(&id, "key").await.unwrap(),
+            "pw"
+        );
+        let stored = &local.snapshot().passwords[&id];
+        assert!(stored.last_used_at.unwrap() >= before);
+        assert!(stored.updated_at < before);
+
+        let first = stored.last_used_at;
+        manager.decrypt_password_by_id(&id, "key").await.unwrap();
+        assert!(local.snapshot().passwords[&id].last_used_at > first);
+    }
+
+    fn is_locked_error(result: Result<impl std::fmt::Debug>) -> bool {
+        result.unwrap_err().downcast_ref::<PasswordError>() == Some(&PasswordError::Locked)
+    }
+
+    #[tokio::test]
+    async fn locked_manager_rejects_operations() {
+        let entry = password_entry("GitHub", "alice");
+        let encrypted = entry.encrypted_password.clone();
+        let mut data = StorageData::new();
+        data.merge([entry]);
+
+        let local = Arc::new(MockStorage::with_data(data));
+        let manager = manager_with(vec![("local", local.clone())]).await;
+        let id = StorageId::from("local");
+
+        manager.lock().await;
+        assert!(manager.is_locked());
+        assert!(manager.cache.read().await.is_empty());
+
+        assert!(is_locked_error(manager.search_passwords("Git").await));
+        assert!(is_locked_error(
+            manager.get_all_passwords_from_storage(&id).await
+        ));
+        assert!(is_locked_error(
+            manager.decrypt_password("key", &encrypted).await
+        ));
+        // 锁定时的写入不能用空缓存覆盖存储
+        assert!(is_locked_error(manager.delete_password("x").await));
+        assert_eq!(local.saves(), 0);
+
+        manager.unlock(None).await.unwrap();
+        assert_eq!(manager.search_passwords("Git").await.unwrap().len(), 1);
+        assert_eq!(
+            manager
+                .get_all_passwords_from_storage(&id)
+                .await
+                .unwrap()
+                .passwords
+                .len(),
+            1
+        );
+        assert_eq!(
+            manager.decrypt_password("key", &encrypted).await.unwrap(),
+            "pw"
+        );
+    }
+
+    // 测试进程共用的配置文件路径
+    fn conf_path() -> &'static PathBuf {
+        CONF_PATH.get_or_init(|| mock_store::temp_dir().join("config.json"))
+    }
+
+    // 写配置文件的测试持有该锁，避免并行的测试互相覆盖
+    static CONF_FILE_LOCK: tokio::sync::Mutex<()> = tokio::sync::Mutex::const_new(());
+
+    #[tokio::test]
+    async fn master_password_gates_unlock() {
+        let _guard = CONF_FILE_LOCK.lock().await;
+        conf_path();
+        let manager = manager_with(vec![("local", Arc::new(MockStorage::new()))]).await;
+        manager.set_master_password("correct horse").await.unwrap();
+
+        assert!(manager.verify_master("correct horse").await.unwrap());
+        assert!(!manager.verify_master("wrong").await.unwrap());
+
+        manager.lock().await;
+        assert!(manager.unlock(Some("wrong")).await.is_err());
+        assert!(manager.unlock(None).await.is_err());
+        assert!(manager.is_locked());
+
+        manager.unlock(Some("correct horse")).await.unwrap();
+        assert!(!manager.is_locked());
+
+        // 更新配置不会覆盖主密码
+        let mut config = Config::default();
+        config.storage.local_storages.clear();
+        manager.update_config(config).await.unwrap();
+        assert!(manager.verify_master("correct horse").await.unwrap());
+
+        // 已设置主密码后不能直接重设
+        assert!(manager.set_master_password("other").await.is_err());
+    }
+
+    #[tokio::test]
+    async fn locked_vault_cannot_reset_master_password() {
+        let _guard = CONF_FILE_LOCK.lock().await;
+        conf_path();
+        let manager = manager_with(vec![("local", Arc::new(MockStorage::new()))]).await;
+        manager.set_master_password("correct horse").await.unwrap();
+        manager.lock().await;
+
+        let mut config = Config::default();
+        config.storage.local_storages.clear();
+        assert!(config.is_first_setup);
+        assert!(manager.update_config(config.clone()).await.is_err());
+        assert!(manager.set_master_password("x").await.is_err());
+        assert!(manager.unlock(Some("x")).await.is_err());
+        assert!(manager.is_locked());
+
+        // 解锁后更新配置也不能把首次设置改回 true
+        manager.unlock(Some("correct horse")).await.unwrap();
+        manager.config.write().await.is_first_setup = false;
+        manager.update_config(config).await.unwrap();
+        assert!(!manager.config.read().await.is_first_setup);
+        assert!(manager.set_master_password("x").await.is_err());
+        assert!(manager.verify_master("correct horse").await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn update_config_rejects_invalid_github() {
+        let manager = manager_with(vec![("local", Arc::new(MockStorage::new()))]).await;
+
+        let mut config = github_config("https://api.github.com");
+        config.storage.local_storages.clear();
+        config.storage.github_storages[0].token.clear();
+
+        assert!(manager.update_config(config).await.is_err());
+        // 原有存储保持不变
+        assert!(manager.resolve_storage("local").await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn unset_master_password_unlocks_freely() {
+        let manager = manager_with(vec![("local", Arc::new(MockStorage::new()))]).await;
+
+        assert!(manager.verify_master("anything").await.unwrap());
+        manager.lock().await;
+        manager.unlock(None).await.unwrap();
+        assert!(!manager.is_locked());
+    }
+
+    #[tokio::test]
+    async fn lock_if_idle_respects_config() {
+        let manager = manager_with(vec![("local", Arc::new(MockStorage::new()))]).await;
+        manager
+            .idle
+            .lock()
+            .unwrap()
+            .record_activity(Instant::now() - Duration::from_secs(120));
+
+        // 默认不自动锁定
+        assert!(!manager.lock_if_idle().await);
+
+        manager.config.write().await.auto_lock_secs = Some(300);
+        assert!(!manager.lock_if_idle().await);
+
+        manager.config.write().await.auto_lock_secs = Some(60);
+        assert!(manager.lock_if_idle().await);
+        assert!(manager.is_locked());
+        assert!(!manager.lock_if_idle().await);
+    }
+
+    #[tokio::test]
+    async fn reencrypt_all_rotates_key() {
+        let a = password_entry("a", "user");
+        let mut b = password_entry("b", "user");
+        b.totp_secret = Some(crypto::encrypt_with_password("GEZDGNBV", "key").unwrap());
+        b.attachments.push(Attachment {
+            name: "codes.txt".to_string(),
+            mime: "text/plain".to_string(),
+            data: crypto::encrypt_bytes_with_password(b"123456", "key").unwrap(),
+        });
+        let mut data = StorageData::new();
+        data.merge([a.clone(), b.clone()]);
+
+        let local = Arc::new(MockStorage::with_data(data));
+        let manager = manager_with(vec![("local", local.clone())]).await;
+
+        let report = manager.reencrypt_all("key", "new-key").await.unwrap();
+        assert_eq!(report.reencrypted, 2);
+        assert!(report.failed.is_empty());
+        assert_eq!(local.saves(), 1);
+
+        for p in local.snapshot().passwords.values() {
+            assert_eq!(
+                crypto::decrypt_with_password(&p.encrypted_password, "new-key").unwrap(),
+                "pw"
+            );
+            assert!(crypto::decrypt_with_password(&p.encrypted_password, "key").is_err());
+        }
+
+        let attachment = &local.snapshot().passwords[&b.id].attachments[0];
+        assert_eq!(
+            crypto::decrypt_bytes_with_password(&attachment.data, "new-key").unwrap(),
+            b"123456"
+        );
+
+        let totp_secret = local.snapshot().passwords[&b.id]
+            .totp_secret
+            .clone()
+            .unwrap();
+        assert_eq!(
+            crypto::decrypt_with_password(&totp_secret, "new-key").unwrap(),
+            "GEZDGNBV"
+        );
+    }
+
+    #[tokio::test]
+    async fn reencrypt_all_skips_entries_with_other_key() {
+        let same = password_entry("same", "user");
+        let mut other = password_entry("other", "user");
+        other.encrypted_password = crypto::encrypt_with_password("pw2", "another-key").unwrap();
+        let mut data = StorageData::new();
+        data.merge([same.clone(), other.clone()]);
+
+        let local = Arc::new(MockStorage::with_data(data));
+        let manager = manager_with(vec![("local", local.clone())]).await;
+
+        let report = manager.reencrypt_all("key", "new-key").await.unwrap();
+        assert_eq!(report.reencrypted, 1);
+        assert_eq!(report.failed, vec![other.id.clone()]);
+
+        let stored = local.snapshot();
+        assert_eq!(
+            crypto::decrypt_with_password(
+                &stored.passwords[&same.id].encrypted_password,
+                "new-key"
+            )
+            .unwrap(),
+            "pw"
+        );
+        // 失败的条目保持不变
+        let untouched = &stored.passwords[&other.id];
+        assert_eq!(
+            untouched.encrypted_password.ciphertext,
+            other.encrypted_password.ciphertext
+        );
+        assert_eq!(untouched.updated_at, other.updated_at);
+    }
+
+    #[tokio::test]
+    async fn rekey_entry_changes_only_that_entry() {
+        let a = password_entry("a", "user");
+        let mut b = password_entry("b", "user");
+        b.totp_secret = Some(crypto::encrypt_with_password("GEZDGNBV", "key").unwrap());
+        b.encrypted_notes = Some(crypto::encrypt_with_password("PIN 1234", "key").unwrap());
+        let mut data = StorageData::new();
+        data.merge([a.clone(), b.clone()]);
+
+        let local = Arc::new(MockStorage::with_data(data));
+        let manager = manager_with(vec![("local", local.clone())]).await;
+
+        manager.rekey_entry(&b.id, "key", "b-key").await.unwrap();
+
+        let stored = local.snapshot();
+        let rekeyed = &stored.passwords[&b.id];
+        assert_eq!(
+            crypto::decrypt_with_password(&rekeyed.encrypted_password, "b-key").unwrap(),
+            "pw"
+        );
+        assert_eq!(
+            crypto::decrypt_with_password(rekeyed.totp_secret.as_ref().unwrap(), "b-key").unwrap(),
+            "GEZDGNBV"
+        );
+        assert_eq!(
+            manager.decrypt_notes(&b.id, "b-key").await.unwrap(),
+            Some("PIN 1234".to_string())
+        );
+        assert!(rekeyed.updated_at > b.updated_at);
+
+        // 其它条目不受影响
+        assert_eq!(
+            crypto::decrypt_with_password(&stored.passwords[&a.id].encrypted_password, "key")
+                .unwrap(),
+            "pw"
+        );
+    }
+
+    #[tokio::test]
+    async fn rekey_entry_with_wrong_key_changes_nothing() {
+        let a = password_entry("a", "user");
+        let mut data = StorageData::new();
+        data.merge([a.clone()]);
+
+        let local = Arc::new(MockStorage::with_data(data));
+        let manager = manager_with(vec![("local", local.clone())]).await;
+
+        let error = manager
+            .rekey_entry(&a.id, "wrong", "new-key")
+            .await
+            .unwrap_err();
+        assert_eq!(PasswordError::from(error), PasswordError::DecryptFailed);
+        assert_eq!(local.saves(), 0);
+
+        let untouched = &local.snapshot().passwords[&a.id];
+        assert_eq!(
+            untouched.encrypted_password.ciphertext,
+            a.encrypted_password.ciphertext
+        );
+        assert_eq!(untouched.updated_at, a.updated_at);
+
+        let error = manager
+            .rekey_entry("missing", "key", "new-key")
+            .await
+            .unwrap_err();
+        assert_eq!(PasswordError::from(error).code(), 404);
+    }
+
+    #[tokio::test]
+    async fn entries_with_different_kdfs_decrypt_with_same_key() {
+        let local = Arc::new(MockStorage::new());
+        let manager = manager_with(vec![("local", local.clone())]).await;
+        manager
+            .add_passwords(vec![
+                create_request("Fast"),
+                PasswordCreateRequest {
+                    notes: Some("recovery codes".to_string()),
+                    kdf_strength: KdfStrength::Strong,
+                    ..create_request("Strong")
+                },
+            ])
+            .await
+            .unwrap();
+
+        let passwords = local.snapshot().passwords;
+        let by_title = |title: &str| passwords.values().find(|p| p.title == title).unwrap();
+        let fast = by_title("Fast");
+        let strong = by_title("Strong");
+        assert_eq!(fast.encrypted_password.kdf, KeyDerivation::Sha256);
+        assert_eq!(
+            strong.encrypted_password.kdf.strength(),
+            KdfStrength::Strong
+        );
+        assert_eq!(
+            strong.encrypted_notes.as_ref().unwrap().kdf.strength(),
+            KdfStrength::Strong
+        );
+
+        for id in [&fast.id, &strong.id] {
+            assert_eq!(
+                manager.decrypt_password_by_id(id, "key").await.unwrap(),
+                "pw"
+            );
+        }
+        assert_eq!(
+            manager.decrypt_notes(&strong.id, "key").await.unwrap(),
+            Some("recovery codes".to_string())
+        );
+
+        // 换key后保留各条目的派生强度
+        manager.reencrypt_all("key", "new-key").await.unwrap();
+        let rekeyed = &local.snapshot().passwords[&strong.id];
+        assert_eq!(
+            rekeyed.encrypted_password.kdf.strength(),
+            KdfStrength::Strong
+        );
+        assert_eq!(
+            manager
+                .decrypt_password_by_id(&strong.id, "new-key")
+                .await
+                .unwrap(),
+            "pw"
+        );
+    }
+
+    #[tokio::test]
+    async fn verify_key_finds_entries_under_other_keys() {
+        let a = password_entry("a", "user");
+        let b = password_entry("b", "user");
+        let mut data = StorageData::new();
+        data.merge([a.clone(), b.clone()]);
+        let manager = manager_with(vec![("local", Arc::new(MockStorage::with_data(data)))]).await;
+
+        let report = manager.verify_key("key").await.unwrap();
+        assert_eq!(report.total, 2);
+        assert_eq!(report.succeeded, 2);
+        assert!(report.failed_ids.is_empty());
+
+        let mut other = password_entry("c", "user");
+        other.encrypted_password = crypto::encrypt_with_password("pw", "other").unwrap();
+        manager.insert_into_cache(vec![other.clone()]).await;
+
+        let report = manager.verify_key("key").await.unwrap();
+        assert_eq!(report.total, 3);
+        assert_eq!(report.succeeded, 2);
+        assert_eq!(report.failed_ids, [other.id]);
+    }
+
+    async fn all_titles(manager: &PasswordManager) -> Vec<String> {
+        manager
+            .get_all_passwords()
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|p| p.title)
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn refresh_cache_picks_up_external_changes() {
+        let local = Arc::new(MockStorage::new());
+        let github = Arc::new(MockStorage::new().with_target(StorageTarget::GitHub));
+        let manager =
+            manager_with(vec![("local", local.clone()), ("github", github.clone())]).await;
+
+        let changes = Arc::new(StdMutex::new(Vec::new()));
+        let sink = changes.clone();
+        manager.set_change_listener(move |change| sink.lock().unwrap().push(change.clone()));
+
+        // 其它设备向 GitHub 推送了新条目
+        let pushed = password_entry("Pushed", "user");
+        let mut remote = StorageData::new();
+        remote.merge([pushed.clone()]);
+        github.save(&remote).await.unwrap();
+
+        assert!(manager.get_all_passwords().await.unwrap().is_empty());
+
+        manager
+            .refresh_cache(&StorageId::from("github"))
+            .await
+            .unwrap();
+        assert_eq!(all_titles(&manager).await, ["Pushed"]);
+        assert_eq!(
+            *changes.lock().unwrap(),
+            [VaultChange::added(vec![pushed.id.clone()])]
+        );
+
+        // 刷新全部
+        let mut local_data = StorageData::new();
+        local_data.merge([password_entry("Local", "user")]);
+        local.save(&local_data).await.unwrap();
+        manager.refresh_all_cache().await.unwrap();
+        assert_eq!(all_titles(&manager).await, ["Local", "Pushed"]);
+
+        assert!(
+            manager
+                .refresh_cache(&StorageId::from("missing"))
+                .await
+                .is_err()
+        );
+    }
+
+    #[tokio::test]
+    async fn refresh_if_stale_honors_ttl() {
+        let local = Arc::new(MockStorage::new());
+        let manager = manager_with(vec![("local", local.clone())]).await;
+
+        let mut data = StorageData::new();
+        data.merge([password_entry("External", "user")]);
+        local.save(&data).await.unwrap();
+
+        // 未开启
+        assert!(!manager.refresh_if_stale().await.unwrap());
+
+        manager.config.write().await.cache_ttl_secs = 60;
+        assert!(!manager.refresh_if_stale().await.unwrap());
+        assert!(manager.get_all_passwords().await.unwrap().is_empty());
+
+        *manager.last_refresh.lock().unwrap() = Instant::now() - Duration::from_secs(120);
+        assert!(manager.refresh_if_stale().await.unwrap());
+        assert_eq!(all_titles(&manager).await, ["External"]);
+
+        // 锁定时不加载
+        *manager.last_refresh.lock().unwrap() = Instant::now() - Duration::from_secs(120);
+        manager.lock().await;
+        assert!(!manager.refresh_if_stale().await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn expiring_and_stale_passwords() {
+        let now = Utc::now();
+        let days = chrono::Duration::days;
+        let expiring = |title: &str, at| {
+            let mut p = password_entry(title, "user");
+            p.expires_at = Some(at);
+            p
+        };
+        let updated = |title: &str, at| {
+            let mut p = password_entry(title, "user");
+            p.updated_at = at;
+            p
+        };
+
+        let mut data = StorageData::new();
+        data.merge([
+            expiring("expired", now - days(1)),
+            expiring("within", now + days(3)),
+            expiring("after", now + days(30)),
+            updated("old", now - days(200)),
+            updated("fresh", now - days(10)),
+        ]);
+        // 设置了过期时间的旧条目不算陈旧
+        let mut old_with_expiry = expiring("old but expiring later", now + days(60));
+        old_with_expiry.updated_at = now - days(400);
+        data.merge([old_with_expiry]);
+
+        let manager = manager_with(vec![("local", Arc::new(MockStorage::with_data(data)))]).await;
+        let titles =
+            |ps: Vec<Password>| -> Vec<String> { ps.into_iter().map(|p| p.title).collect() };
+
+        assert_eq!(
+            titles(manager.get_expiring_passwords(days(7)).await.unwrap()),
+            ["expired", "within"]
+        );
+        assert_eq!(
+            titles(manager.get_stale_passwords(days(90)).await.unwrap()),
+            ["old"]
+        );
+    }
+
+    #[tokio::test]
+    async fn add_password_with_totp_secret() {
+        let local = Arc::new(MockStorage::new());
+        let manager = manager_with(vec![("local", local.clone())]).await;
+
+        let request = |title: &str, totp_secret: Option<&str>| PasswordCreateRequest {
+            title: title.to_string(),
+            description: String::new(),
+            tags: vec![],
+            username: "alice".to_string(),
+            password: "pw".to_string(),
+            url: None,
+            key: "key".to_string(),
+            expires_at: None,
+            totp_secret: totp_secret.map(str::to_string),
+            notes: None,
+            client_id: None,
+            kdf_strength: KdfStrength::Fast,
+        };
+
+        assert!(
+            manager
+                .add_password(request("bad", Some("not base32!")))
+                .await
+                .is_err()
+        );
+        manager
+            .add_password(request("GitHub", Some("GEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQ")))
+            .await
+            .unwrap();
+        manager.add_password(request("plain", None)).await.unwrap();
+
+        let stored = local.snapshot();
+        let find = |title: &str| {
+            stored
+                .passwords
+                .values()
+                .find(|p| p.title == title)
+                .unwrap()
+                .id
+                .clone()
+        };
+
+        let code = manager.generate_totp(&find("GitHub"), "key").await.unwrap();
+        assert_eq!(code.code.len(), 6);
+        assert!((1..=30).contains(&code.valid_for_secs));
+
+        assert!(
+            manager
+                .generate_totp(&find("GitHub"), "wrong")
+                .await
+                .is_err()
+        );
+        assert!(manager.generate_totp(&find("plain"), "key").await.is_err());
+    }
+
+    #[tokio::test]
+    async fn breach_hashes_use_k_anonymity_prefix() {
+        let mut leaked = password_entry("Leaked", "alice");
+        leaked.encrypted_password = crypto::encrypt_with_password("password", "key").unwrap();
+        // 用其它key加密的条目不参与检查
+        let mut other_key_entry = password_entry("Other", "bob");
+        other_key_entry.encrypted_password =
+            crypto::encrypt_with_password("password", "other").unwrap();
+        let safe = password_entry("Safe", "carol");
+
+        let mut data = StorageData::new();
+        data.merge([leaked.clone(), other_key_entry, safe.clone()]);
+        let manager = manager_with(vec![("local", Arc::new(MockStorage::with_data(data)))]).await;
+
+        let hashes = manager.password_breach_hashes("key").await.unwrap();
+        assert_eq!(hashes.len(), 2);
+        let prefix = |id: &str| &hashes.iter().find(|(i, _)| i == id).unwrap().1;
+        assert_eq!(prefix(&leaked.id), "5BAA6");
+        assert_eq!(prefix(&safe.id).len(), 5);
+
+        let suffixes = vec!["1E4C9B93F3F0682250B6CF8331B7EE68FD8:9545824".to_string()];
+        assert!(
+            manager
+                .check_breach(&leaked.id, "key", &suffixes)
+                .await
+                .unwrap()
+        );
+        assert!(
+            !manager
+                .check_breach(&safe.id, "key", &suffixes)
+                .await
+                .unwrap()
+        );
+        assert!(
+            manager
+                .check_breach(&leaked.id, "wrong", &suffixes)
+                .await
+                .is_err()
+        );
+    }
+
+    // 本地和GitHub存储点中同一条目的版本不同，GitHub中的较新；本地另有一条独有的条目
+    async fn conflicting_manager(
+        policy: ConflictPolicy,
+    ) -> (PasswordManager, Arc<MockStorage>, Arc<MockStorage>, String) {
+        let base = password_entry("Original", "alice");
+        let id = base.id.clone();
+
+        let mut local_version = base.clone();
+        local_version.title = "Local".to_string();
+        let mut github_version = base.clone();
+        github_version.title = "GitHub".to_string();
+        github_version.updated_at = base.updated_at + chrono::Duration::hours(1);
+
+        let mut local_data = StorageData::new();
+        local_data.merge([local_version, password_entry("Only local", "bob")]);
+        let mut github_data = StorageData::new();
+        github_data.merge([github_version]);
+
+        let local = Arc::new(MockStorage::with_data(local_data));
+        let github =
+            Arc::new(MockStorage::with_data(github_data).with_target(StorageTarget::GitHub));
+
+        let config = Config {
+            conflict_policy: policy,
+            ..Default::default()
+        };
+        let storages: Storages = HashMap::from([
+            (StorageId::from("local"), local.clone() as Arc<dyn Storage>),
+            (
+                StorageId::from("github"),
+                github.clone() as Arc<dyn Storage>,
+            ),
+        ]);
+        let manager = PasswordManager::with_storages(config, storages)
+            .await
+            .unwrap();
+
+        (manager, local, github, id)
+    }
+
+    // 各存储点缓存中该条目的标题
+    async fn cached_titles(manager: &PasswordManager, id: &str) -> Vec<String> {
+        let mut titles = Vec::new();
+        for storage in ["local", "github"] {
+            let data = manager
+                .get_all_passwords_from_storage(&StorageId::from(storage))
+                .await
+                .unwrap();
+            // 独有的条目也同步到了每个存储点
+            assert_eq!(data.passwords.len(), 2);
+            assert_eq!(data.metadata.password_count, 2);
+            titles.push(data.passwords[id].title.clone());
+        }
+        titles
+    }
+
+    #[tokio::test]
+    async fn conflict_policy_newest_wins() {
+        let (manager, _, _, id) = conflicting_manager(ConflictPolicy::NewestWins).await;
+        assert_eq!(cached_titles(&manager, &id).await, ["GitHub", "GitHub"]);
+        assert!(manager.get_conflicts().await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn conflict_policy_local_wins() {
+        let (manager, _, _, id) = conflicting_manager(ConflictPolicy::LocalWins).await;
+        assert_eq!(cached_titles(&manager, &id).await, ["Local", "Local"]);
+    }
+
+    #[tokio::test]
+    async fn conflict_policy_github_wins() {
+        let (manager, _, _, id) = conflicting_manager(ConflictPolicy::GitHubWins).await;
+        assert_eq!(cached_titles(&manager, &id).await, ["GitHub", "GitHub"]);
+    }
+
+    #[tokio::test]
+    async fn conflict_policy_manual() {
+        let (manager, local, github, id) = conflicting_manager(ConflictPolicy::Manual).await;
+        assert_eq!(cached_titles(&manager, &id).await, ["Local", "GitHub"]);
+
+        let conflicts = manager.get_conflicts().await.unwrap();
+        assert_eq!(conflicts.len(), 1);
+        assert_eq!(conflicts[0].id, id);
+        assert_eq!(conflicts[0].versions.len(), 2);
+
+        manager
+            .resolve_conflict(&id, &StorageId::from("local"))
+            .await
+            .unwrap();
+        assert_eq!(cached_titles(&manager, &id).await, ["Local", "Local"]);
+        assert!(manager.get_conflicts().await.unwrap().is_empty());
+        assert_eq!(local.snapshot().passwords[&id].title, "Local");
+        assert_eq!(github.snapshot().passwords[&id].title, "Local");
+
+        assert!(
+            manager
+                .resolve_conflict(&id, &StorageId::from("missing"))
+                .await
+                .is_err()
+        );
+    }
+
+    #[tokio::test]
+    async fn integrity_check_and_repair() {
+        let shared = password_entry("Shared", "alice");
+        let only_local = password_entry("Only local", "bob");
+        let mut corrupted = password_entry("Corrupted", "carol");
+        corrupted.encrypted_password.nonce.truncate(3);
+
+        // 本地：条目数记录错误，含一条nonce损坏的条目，另有一条GitHub中没有的条目
+        let mut local_data = StorageData::new();
+        local_data.merge([shared.clone(), only_local.clone(), corrupted.clone()]);
+        local_data.metadata.password_count = 7;
+        let mut github_data = StorageData::new();
+        github_data.merge([shared.clone()]);
+
+        let local = Arc::new(MockStorage::with_data(local_data));
+        let github =
+            Arc::new(MockStorage::with_data(github_data).with_target(StorageTarget::GitHub));
+        let manager =
+            manager_with(vec![("local", local.clone()), ("github", github.clone())]).await;
+
+        let report = manager.check_integrity().await.unwrap();
+        assert!(!report.ok);
+        let local_report = &report.storages[&StorageId::from("local")];
+        assert_eq!(local_report.recorded_count, 7);
+        assert_eq!(local_report.actual_count, 3);
+        assert_eq!(local_report.invalid_nonce, [corrupted.id.clone()]);
+        assert!(local_report.missing.is_empty());
+        let github_report = &report.storages[&StorageId::from("github")];
+        assert_eq!(github_report.recorded_count, 1);
+        let mut missing = vec![only_local.id.clone(), corrupted.id.clone()];
+        missing.sort();
+        assert_eq!(github_report.missing, missing);
+
+        let report = manager.repair_integrity().await.unwrap();
+        for storage in [&local, &github] {
+            let data = storage.snapshot();
+            assert_eq!(data.passwords.len(), 3);
+            assert_eq!(data.metadata.password_count, 3);
+        }
+        // 条目数和缺失条目已修复，损坏的nonce仍需用户处理
+        assert!(!report.ok);
+        for storage_report in report.storages.values() {
+            assert_eq!(storage_report.recorded_count, 3);
+            assert!(storage_report.missing.is_empty());
+            assert_eq!(storage_report.invalid_nonce, [corrupted.id.clone()]);
+        }
+
+        local.set_fail(true);
+        let report = manager.check_integrity().await.unwrap();
+        assert!(report.storages[&StorageId::from("local")].error.is_some());
+        // 读取失败的存储点不参与比较，不会把其它存储点的条目误报为缺失
+        let github_report = &report.storages[&StorageId::from("github")];
+        assert!(github_report.error.is_none());
+        assert!(github_report.missing.is_empty());
+    }
+
+    #[tokio::test]
+    async fn get_all_passwords_dedups_across_storages() {
+        // Manual 策略下加载时不统一版本，两个存储点的缓存中同一id的版本不同
+        let (manager, _, _, id) = conflicting_manager(ConflictPolicy::Manual).await;
+
+        let all = manager.get_all_passwords().await.unwrap();
+        let titles: Vec<&str> = all.iter().map(|p| p.title.as_str()).collect();
+        assert_eq!(titles, ["GitHub", "Only local"]);
+        assert_eq!(all[0].id, id);
+
+        manager.lock().await;
+        assert!(is_locked_error(manager.get_all_passwords().await));
+    }
+
+    #[tokio::test]
+    async fn find_by_url_matches_hosts() {
+        let local = Arc::new(MockStorage::new());
+        let manager = manager_with(vec![("local", local.clone())]).await;
+
+        for (title, url) in [
+            ("Example", Some("https://example.com/")),
+            ("Example Login", Some("login.example.com")),
+            ("Other", Some("https://other.com")),
+            ("No URL", None),
+        ] {
+            let mut request = create_request(title);
+            request.url = url.map(str::to_string);
+            manager.add_password(request).await.unwrap();
+        }
+
+        let titles =
+            |found: Vec<Password>| -> Vec<String> { found.into_iter().map(|p| p.title).collect() };
+
+        // 主机名相同
+        let found = manager
+            .find_by_url("https://www.Example.com/login")
+            .await
+            .unwrap();
+        assert_eq!(titles(found), ["Example"]);
+
+        // 子域名同时匹配父域名下的条目
+        let found = manager
+            .find_by_url("https://login.example.com/signin")
+            .await
+            .unwrap();
+        assert_eq!(titles(found), ["Example", "Example Login"]);
+
+        // 关闭子域名匹配
+        manager.config.write().await.match_subdomains = false;
+        let found = manager
+            .find_by_url("https://login.example.com/signin")
+            .await
+            .unwrap();
+        assert_eq!(titles(found), ["Example Login"]);
+
+        assert!(
+            manager
+                .find_by_url("https://unknown.org")
+                .await
+                .unwrap()
+                .is_empty()
+        );
+    }
+
+    #[tokio::test]
+    async fn export_bitwarden_json_contains_every_entry() {
+        let local = Arc::new(MockStorage::new());
+        let manager = manager_with(vec![("local", local.clone())]).await;
+
+        let mut github = create_request("GitHub");
+        github.description = "工作账号".to_string();
+        github.tags = vec!["work".to_string(), "dev".to_string()];
+        github.url = Some("https://github.com".to_string());
+        github.notes = Some("恢复码在抽屉里".to_string());
+        github.totp_secret = Some("JBSWY3DPEHPK3PXP".to_string());
+        manager.add_password(github).await.unwrap();
+
+        let mut mail = create_request("Mail");
+        mail.password = "mail-pw".to_string();
+        manager.add_password(mail).await.unwrap();
+
+        let json = manager.export_bitwarden_json("key").await.unwrap();
+        let export: serde_json::Value = serde_json::from_str(&json).unwrap();
+
+        assert_eq!(export["encrypted"], false);
+        assert_eq!(
+            export["folders"],
+            serde_json::json!([{ "id": "folder-1", "name": "work" }])
+        );
+
+        let items = export["items"].as_array().unwrap();
+        assert_eq!(items.len(), 2);
+
+        let github = &items[0];
+        assert_eq!(github["type"], 1);
+        assert_eq!(github["name"], "GitHub");
+        assert_eq!(github["folderId"], "folder-1");
+        assert_eq!(github["notes"], "工作账号\n\n恢复码在抽屉里");
+        assert_eq!(github["login"]["username"], "alice");
+        assert_eq!(github["login"]["password"], "pw");
+        assert_eq!(
+            github["login"]["uris"],
+            serde_json::json!([{ "uri": "https://github.com" }])
+        );
+        assert_eq!(github["login"]["totp"], "JBSWY3DPEHPK3PXP");
+
+        let mail = &items[1];
+        assert_eq!(mail["name"], "Mail");
+        assert_eq!(mail["folderId"], serde_json::Value::Null);
+        assert_eq!(mail["notes"], serde_json::Value::Null);
+        assert_eq!(mail["login"]["password"], "mail-pw");
+        assert_eq!(mail["login"]["uris"], serde_json::json!([]));
+
+        // 无法解密时不导出不完整的数据
+        assert!(manager.export_bitwarden_json("wrong").await.is_err());
+    }
+
+    #[tokio::test]
+    async fn import_bitwarden_json_creates_login_entries() {
+        let local = Arc::new(MockStorage::new());
+        let manager = manager_with(vec![("local", local.clone())]).await;
+
+        let json = r#"{
+            "encrypted": false,
+            "folders": [{ "id": "f1", "name": "work" }],
+            "items": [
+                {
+                    "id": "a", "folderId": "f1", "type": 1, "name": "GitHub",
+                    "notes": "恢复码在抽屉里", "favorite": false,
+                    "login": {
+                        "username": "alice", "password": "gh-pw",
+                        "uris": [{ "match": null, "uri": "https://github.com" }],
+                        "totp": "JBSWY3DPEHPK3PXP"
+                    }
+                },
+                { "id": "b", "folderId": null, "type": 2, "name": "Secure note", "notes": "x",
+                  "secureNote": { "type": 0 } },
+                { "id": "c", "type": 1, "name": "Empty", "login": { "username": "bob" } },
+                { "id": "d", "type": 1, "name": "Mail",
+                  "login": { "username": "carol", "password": "mail-pw", "uris": [] } }
+            ]
+        }"#;
+
+        let report = manager.import_bitwarden_json(json, "key").await.unwrap();
+        assert_eq!(report.imported, 2);
+        let skipped: Vec<usize> = report.skipped.iter().map(|e| e.line).collect();
+        assert_eq!(skipped, [2, 3]);
+        // 一次保存
+        assert_eq!(local.saves(), 1);
+
+        let passwords = manager.get_all_passwords().await.unwrap();
+        assert_eq!(passwords.len(), 2);
+
+        let github = &passwords[0];
+        assert_eq!(github.title, "GitHub");
+        assert_eq!(github.username, "alice");
+        assert_eq!(github.url.as_deref(), Some("https://github.com"));
+        assert_eq!(github.tags, ["work"]);
+        assert_eq!(
+            crypto::decrypt_with_password(&github.encrypted_password, "key").unwrap(),
+            "gh-pw"
+        );
+        assert_eq!(
+            manager.decrypt_notes(&github.id, "key").await.unwrap(),
+            Some("恢复码在抽屉里".to_string())
+        );
+        assert!(github.totp_secret.is_some());
+
+        let mail = &passwords[1];
+        assert_eq!(mail.title, "Mail");
+        assert!(mail.tags.is_empty());
+        assert_eq!(mail.url, None);
+        assert!(mail.encrypted_notes.is_none());
+
+        assert!(
+            manager
+                .import_bitwarden_json("not json", "key")
+                .await
+                .is_err()
+        );
+    }
+
+    #[tokio::test]
+    async fn notes_are_encrypted_and_optional() {
+        let local = Arc::new(MockStorage::new());
+        let manager = manager_with(vec![("local", local.clone())]).await;
+
+        let mut request = create_request("Bank");
+        request.notes = Some("PIN 1234".to_string());
+        manager.add_password(request).await.unwrap();
+        manager.add_password(create_request("Mail")).await.unwrap();
+
+        let snapshot = local.snapshot();
+        let find = |title: &str| {
+            snapshot
+                .passwords
+                .values()
+                .find(|p| p.title == title)
+                .unwrap()
+                .clone()
+        };
+        let with_notes = find("Bank");
+        let without_notes = find("Mail");
+
+        let stored = &snapshot.passwords[&with_notes.id];
+        assert!(!serde_json::to_string(stored).unwrap().contains("PIN 1234"));
+        assert_eq!(
+            manager.decrypt_notes(&with_notes.id, "key").await.unwrap(),
+            Some("PIN 1234".to_string())
+        );
+        assert!(
+            manager
+                .decrypt_notes(&with_notes.id, "wrong")
+                .await
+                .is_err()
+        );
+        assert_eq!(
+            manager
+                .decrypt_notes(&without_notes.id, "key")
+                .await
+                .unwrap(),
+            None
+        );
+
+        // 旧数据没有 encrypted_notes 字段
+        let mut json = serde_json::to_value(&without_notes).unwrap();
+        json.as_object_mut().unwrap().remove("encrypted_notes");
+        let old: Password = serde_json::from_value(json).unwrap();
+        assert!(old.encrypted_notes.is_none());
+    }
+
+    #[tokio::test]
+    async fn batch_add_and_delete_save_once() {
+        let local = Arc::new(MockStorage::new());
+        let manager = manager_with(vec![("local", local.clone())]).await;
+
+        let mut requests: Vec<PasswordCreateRequest> = (0..10)
+            .map(|i| create_request(&format!("entry {}", i)))
+            .collect();
+        let mut invalid = create_request("invalid totp");
+        invalid.totp_secret = Some("not base32!".to_string());
+        requests.insert(3, invalid);
+
+        let report = manager.add_passwords(requests).await.unwrap();
+        assert_eq!(report.succeeded.len(), 10);
+        assert_eq!(report.failed.len(), 1);
+        assert_eq!(report.failed[0].index, 3);
+        assert_eq!(local.saves(), 1);
+        assert_eq!(local.snapshot().passwords.len(), 10);
+        assert_eq!(local.snapshot().metadata.password_count, 10);
+
+        let mut ids = report.succeeded.clone();
+        ids.push("missing".to_string());
+        let report = manager.delete_passwords(&ids).await.unwrap();
+        assert_eq!(report.succeeded.len(), 10);
+        assert_eq!(report.failed.len(), 1);
+        assert_eq!(report.failed[0].index, 10);
+        assert_eq!(local.saves(), 2);
+        assert!(local.snapshot().passwords.is_empty());
+        assert_eq!(local.snapshot().metadata.password_count, 0);
+
+        // 全部失败时不保存
+        manager
+            .delete_passwords(&["missing".to_string()])
+            .await
+            .unwrap();
+        assert_eq!(local.saves(), 2);
+    }
+
+    #[tokio::test]
+    async fn delete_password_recomputes_count() {
+        let local = Arc::new(MockStorage::new());
+        let manager = manager_with(vec![("local", local.clone())]).await;
+        for title in ["GitHub", "Mail"] {
+            manager.add_password(create_request(title)).await.unwrap();
+        }
+        let id = manager.get_all_passwords().await.unwrap()[0].id.clone();
+
+        // 旧文件中记录的条目数可能与实际不符，删除时不应下溢
+        let local_id = StorageId::from("local");
+        manager
+            .cache
+            .write()
+            .await
+            .get_mut(&local_id)
+            .unwrap()
+            .metadata
+            .password_count = 0;
+
+        manager.delete_password(&id).await.unwrap();
+        assert_eq!(
+            manager.cache.read().await[&local_id]
+                .metadata
+                .password_count,
+            1
+        );
+    }
+
+    #[tokio::test]
+    async fn change_listener_receives_saved_changes() {
+        let local = Arc::new(MockStorage::new());
+        let manager = manager_with(vec![("local", local.clone())]).await;
+
+        let changes = Arc::new(StdMutex::new(Vec::new()));
+        let received = changes.clone();
+        manager.set_change_listener(move |change| received.lock().unwrap().push(change.clone()));
+
+        manager
+            .add_password(create_request("GitHub"))
+            .await
+            .unwrap();
+        let id = local.snapshot().passwords.into_keys().next().unwrap();
+        manager.toggle_favorite(&id).await.unwrap();
+        manager.delete_password(&id).await.unwrap();
+
+        // 保存失败时不通知
+        local.set_fail(true);
+        assert!(manager.add_password(create_request("Mail")).await.is_err());
+        // 没有删除任何条目时也不通知
+        local.set_fail(false);
+        manager
+            .delete_passwords(&["missing".to_string()])
+            .await
+            .unwrap();
+
+        assert_eq!(
+            *changes.lock().unwrap(),
+            [
+                VaultChange::added(vec![id.clone()]),
+                VaultChange::updated(vec![id.clone()]),
+                VaultChange::deleted(vec![id.clone()]),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn create_with_generated_stores_encrypted_password() {
+        let local = Arc::new(MockStorage::new());
+        let manager = manager_with(vec![("local", local.clone())]).await;
+
+        let request = PasswordCreateRequestNoPassword {
+            title: "GitHub".to_string(),
+            description: String::new(),
+            tags: vec![],
+            username: "alice".to_string(),
+            url: None,
+            expires_at: None,
+            totp_secret: None,
+            notes: None,
+            kdf_strength: KdfStrength::Fast,
+        };
+        let gen_config = PasswordGeneratorConfig {
+            length: 24,
+            require_symbols: false,
+            exclude_chars: Some("0O1l".to_string()),
+            ..Default::default()
+        };
+
+        let created = manager
+            .create_with_generated(request, gen_config, "key")
+            .await
+            .unwrap();
+        assert_eq!(created.title, "GitHub");
+
+        let stored = &local.snapshot().passwords[&created.id];
+        let plaintext = crypto::decrypt_with_password(&stored.encrypted_password, "key").unwrap();
+        assert_eq!(plaintext.chars().count(), 24);
+        assert!(plaintext.chars().all(|c| c.is_ascii_alphanumeric()));
+        assert!(plaintext.chars().any(|c| c.is_ascii_uppercase()));
+        assert!(plaintext.chars().any(|c| c.is_ascii_lowercase()));
+        assert!(plaintext.chars().any(|c| c.is_ascii_digit()));
+        assert!(!plaintext.contains(['0', 'O', '1', 'l']));
+
+        // 返回值中不含明文
+        let json = serde_json::to_string(&created).unwrap();
+        assert!(!json.contains(&plaintext));
+    }
+
+    #[tokio::test]
+    async fn errors_carry_codes() {
+        let entry = password_entry("GitHub", "alice");
+        let id = entry.id.clone();
+        let mut data = StorageData::new();
+        data.merge([entry]);
+        let manager = manager_with(vec![("local", Arc::new(MockStorage::with_data(data)))]).await;
+
+        let code = |error: anyhow::Error| PasswordError::from(error).code();
+        assert_eq!(
+            code(
+                manager
+                    .decrypt_password_by_id(&id, "wrong")
+                    .await
+                    .unwrap_err()
+            ),
+            401
+        );
+        assert_eq!(
+            code(
+                manager
+                    .decrypt_password_by_id("missing", "key")
+                    .await
+                    .unwrap_err()
+            ),
+            404
+        );
+        assert_eq!(
+            code(manager.resolve_storage("missing").await.unwrap_err()),
+            400
+        );
+
+        manager.lock().await;
+        assert_eq!(code(manager.get_all_passwords().await.unwrap_err()), 423);
+    }
+
+    #[tokio::test]
+    async fn attachment_round_trip() {
+        let entry = password_entry("GitHub", "alice");
+        let id = entry.id.clone();
+        let mut data = StorageData::new();
+        data.merge([entry]);
+
+        let local = Arc::new(MockStorage::with_data(data));
+        let manager = manager_with(vec![("local", local.clone())]).await;
+        let bytes: Vec<u8> = (0..=255).collect();
+
+        manager
+            .add_attachment(&id, "key", "codes.txt", &bytes)
+            .await
+            .unwrap();
+        // 重名或key不对都会被拒绝
+        assert!(
+            manager
+                .add_attachment(&id, "key", "codes.txt", b"x")
+                .await
+                .is_err()
+        );
+        assert!(
+            manager
+                .add_attachment(&id, "wrong", "other.bin", b"x")
+                .await
+                .is_err()
+        );
+
+        let stored = &local.snapshot().passwords[&id].attachments;
+        assert_eq!(stored.len(), 1);
+        assert_eq!(stored[0].mime, "text/plain");
+        assert_ne!(stored[0].data.ciphertext, bytes);
+
+        assert_eq!(
+            manager
+                .get_attachment(&id, "codes.txt", "key")
+                .await
+                .unwrap(),
+            bytes
+        );
+        assert!(
+            manager
+                .get_attachment(&id, "codes.txt", "wrong")
+                .await
+                .is_err()
+        );
+
+        manager.remove_attachment(&id, "codes.txt").await.unwrap();
+        assert!(local.snapshot().passwords[&id].attachments.is_empty());
+        assert!(manager.remove_attachment(&id, "codes.txt").await.is_err());
+    }
+
+    #[tokio::test]
+    async fn attachment_size_limit() {
+        let entry = password_entry("GitHub", "alice");
+        let id = entry.id.clone();
+        let mut data = StorageData::new();
+        data.merge([entry]);
+
+        let local = Arc::new(MockStorage::with_data(data));
+        let manager = manager_with(vec![("local", local.clone())]).await;
+        manager.config.write().await.max_attachment_bytes = 16;
+
+        manager
+            .add_attachment(&id, "key", "small.bin", &[0; 16])
+            .await
+            .unwrap();
+        let err = manager
+            .add_attachment(&id, "key", "big.bin", &[0; 17])
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("17"));
+        assert_eq!(local.snapshot().passwords[&id].attachments.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn import_csv_missing_required_column() {
+        let local = Arc::new(MockStorage::new());
+        let manager = manager_with(vec![("local", local.clone())]).await;
+
+        let csv = "name,secret,url\nGitHub,pw,https://github.com\n";
+        let result = manager.import_csv(csv, "key", mapping()).await;
+
+        assert!(result.is_err());
+        assert_eq!(local.saves(), 0);
+        assert!(local.snapshot().passwords.is_empty());
+    }
+
+    #[tokio::test]
+    async fn restore_vault_version_replaces_current_data() {
+        let local = Arc::new(MockStorage::new());
+        let github = Arc::new(MockStorage::new().with_target(StorageTarget::GitHub));
+        let manager =
+            manager_with(vec![("local", local.clone()), ("github", github.clone())]).await;
+
+        manager.add_password(create_request("Old")).await.unwrap();
+        github.add_version("sha-1", github.snapshot());
+        manager.add_password(create_request("New")).await.unwrap();
+        github.add_version("sha-2", github.snapshot());
+
+        let versions = manager
+            .list_vault_versions(&StorageId::from("github"))
+            .await
+            .unwrap();
+        let shas: Vec<&str> = versions.iter().map(|v| v.sha.as_str()).collect();
+        assert_eq!(shas, ["sha-2", "sha-1"]);
+
+        let changes = Arc::new(StdMutex::new(Vec::new()));
+        let sink = changes.clone();
+        manager.set_change_listener(move |change| sink.lock().unwrap().push(change.clone()));
+
+        manager
+            .restore_vault_version(&StorageId::from("github"), "sha-1")
+            .await
+            .unwrap();
+
+        assert_eq!(all_titles(&manager).await, ["Old"]);
+        for storage in [&local, &github] {
+            let titles: Vec<String> = storage
+                .snapshot()
+                .passwords
+                .into_values()
+                .map(|p| p.title)
+                .collect();
+            assert_eq!(titles, ["Old"]);
+        }
+        let kinds: Vec<_> = changes.lock().unwrap().iter().map(|c| c.kind).collect();
+        assert_eq!(kinds, [crate::event::ChangeKind::Deleted]);
+
+        // 不支持历史版本或不存在的版本
+        assert!(
+            manager
+                .restore_vault_version(&StorageId::from("github"), "missing")
+                .await
+                .is_err()
+        );
+        assert!(
+            manager
+                .list_vault_versions(&StorageId::from("missing"))
+                .await
+                .is_err()
+        );
+    }
+
+    #[tokio::test]
+    async fn decrypt_many_reports_each_entry() {
+        let mut data = StorageData::new();
+        let good = password_entry("Good", "alice");
+        let mut other_key = password_entry("Other", "bob");
+        other_key.encrypted_password = crypto::encrypt_with_password("pw2", "other").unwrap();
+        data.merge([good.clone(), other_key.clone()]);
+        let local = Arc::new(MockStorage::with_data(data));
+        let manager = manager_with(vec![("local", local.clone())]).await;
+
+        let ids = vec![good.id.clone(), other_key.id.clone(), "missing".to_string()];
+        let results = manager.decrypt_many(&ids, "key").await.unwrap();
+
+        assert_eq!(results.len(), 3);
+        assert_eq!(results[0], (good.id.clone(), Ok("pw".to_string())));
+        assert_eq!(results[1].0, other_key.id);
+        assert_eq!(results[1].1, Err("decryption failed".to_string()));
+        assert_eq!(results[2].0, "missing");
+        assert!(results[2].1.as_ref().unwrap_err().contains("missing"));
+
+        // 批量解密不记录使用时间，也不写入存储
+        assert_eq!(local.saves(), 0);
+    }
+
+    #[tokio::test]
+    async fn auto_lock_secs_is_persisted() {
+        let _guard = CONF_FILE_LOCK.lock().await;
+        conf_path();
+        let manager = manager_with(vec![("local", Arc::new(MockStorage::new()))]).await;
+
+        manager.set_auto_lock_secs(Some(90)).await.unwrap();
+        assert_eq!(
+            Config::load_from_file(conf_path()).unwrap().auto_lock_secs,
+            Some(90)
+        );
+
+        // 有活动后重新计时
+        manager
+            .idle
+            .lock()
+            .unwrap()
+            .record_activity(Instant::now() - Duration::from_secs(120));
+        manager.record_activity();
+        assert!(!manager.lock_if_idle().await);
+
+        manager.set_auto_lock_secs(None).await.unwrap();
+        assert_eq!(
+            Config::load_from_file(conf_path()).unwrap().auto_lock_secs,
+            None
+        );
+    }
+
+    #[tokio::test]
+    async fn complete_first_setup_is_persisted() {
+        let _guard = CONF_FILE_LOCK.lock().await;
+        conf_path();
+        let manager = manager_with(vec![("local", Arc::new(MockStorage::new()))]).await;
+        assert!(manager.config.read().await.is_first_setup);
+
+        // 锁定时不能完成首次设置
+        manager.lock().await;
+        assert!(manager.complete_first_setup().await.is_err());
+        manager.unlock(None).await.unwrap();
+
+        manager.complete_first_setup().await.unwrap();
+        assert!(!manager.config.read().await.is_first_setup);
+        assert!(!Config::load_from_file(conf_path()).unwrap().is_first_setup);
+
+        // 重复调用不报错
+        manager.complete_first_setup().await.unwrap();
+
+        // 更新配置不能重新进入首次设置
+        let mut config = Config::default();
+        config.storage.local_storages.clear();
+        assert!(config.is_first_setup);
+        manager.update_config(config).await.unwrap();
+        assert!(!manager.config.read().await.is_first_setup);
+        assert!(!Config::load_from_file(conf_path()).unwrap().is_first_setup);
+    }
+
+    #[tokio::test]
+    async fn add_password_with_client_id_is_idempotent() {
+        let local = Arc::new(MockStorage::new());
+        let manager = manager_with(vec![("local", local.clone())]).await;
+
+        let changes = Arc::new(StdMutex::new(Vec::new()));
+        let sink = changes.clone();
+        manager.set_change_listener(move |change| sink.lock().unwrap().push(change.clone()));
+
+        let request = PasswordCreateRequest {
+            client_id: Some("client-1".to_string()),
+            ..create_request("GitHub")
+        };
+        manager.add_password(request.clone()).await.unwrap();
+        // 重试时即使内容不同也不会覆盖已有条目
+        let retry = PasswordCreateRequest {
+            title: "Changed".to_string(),
+            ..request
+        };
+        manager.add_password(retry).await.unwrap();
+
+        let stored = local.snapshot().passwords;
+        assert_eq!(stored.len(), 1);
+        assert_eq!(stored["client-1"].title, "GitHub");
+        assert_eq!(
+            *changes.lock().unwrap(),
+            [VaultChange::added(vec!["client-1".to_string()])]
+        );
+
+        // 没有 client_id 时每次都是新条目
+        manager.add_password(create_request("Mail")).await.unwrap();
+        manager.add_password(create_request("Mail")).await.unwrap();
+        assert_eq!(local.snapshot().passwords.len(), 3);
+    }
+
+    #[tokio::test]
+    async fn add_passwords_rejects_used_client_ids() {
+        let local = Arc::new(MockStorage::new());
+        let manager = manager_with(vec![("local", local.clone())]).await;
+        let with_id = |title: &str, id: &str| PasswordCreateRequest {
+            client_id: Some(id.to_string()),
+            ..create_request(title)
+        };
+        manager
+            .add_password(with_id("GitHub", "client-1"))
+            .await
+            .unwrap();
+        let before = local.snapshot().passwords["client-1"].clone();
+
+        let report = manager
+            .add_passwords(vec![
+                with_id("Changed", "client-1"),
+                with_id("Mail", "client-2"),
+                with_id("Mail again", "client-2"),
+            ])
+            .await
+            .unwrap();
+
+        assert_eq!(report.succeeded, ["client-2"]);
+        let failed: Vec<usize> = report.failed.iter().map(|e| e.index).collect();
+        assert_eq!(failed, [0, 2]);
+
+        let stored = local.snapshot().passwords;
+        assert_eq!(stored.len(), 2);
+        // 已有条目保持不变
+        assert_eq!(stored["client-1"].title, "GitHub");
+        assert_eq!(stored["client-1"].updated_at, before.updated_at);
+        assert_eq!(stored["client-2"].title, "Mail");
+    }
+
+    #[tokio::test]
+    async fn failed_saves_are_retried() {
+        let local = Arc::new(MockStorage::new());
+        let github = Arc::new(MockStorage::new().with_target(StorageTarget::GitHub));
+        let manager =
+            manager_with(vec![("local", local.clone()), ("github", github.clone())]).await;
+
+        github.set_fail(true);
+        let err = manager
+            .add_password(create_request("GitHub"))
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("github"));
+        assert_eq!(manager.pending_sync(), [StorageId::from("github")]);
+
+        // 单条目写入和整体保存都只有失败的存储点需要重试
+        assert!(
+            manager
+                .add_passwords(vec![create_request("Mail")])
+                .await
+                .is_err()
+        );
+        assert_eq!(local.snapshot().passwords.len(), 2);
+        assert!(github.snapshot().passwords.is_empty());
+        assert_eq!(manager.pending_sync(), [StorageId::from("github")]);
+
+        let status = manager.get_storage_status().await;
+        assert!(!status[&StorageId::from("local")].pending_sync);
+
+        // 仍然不可用时保留记录
+        assert!(manager.retry_failed_saves().await.is_err());
+        assert_eq!(manager.pending_sync(), [StorageId::from("github")]);
+
+        github.set_fail(false);
+        assert!(manager.get_storage_status().await[&StorageId::from("github")].pending_sync);
+        manager.retry_failed_saves().await.unwrap();
+        assert!(manager.pending_sync().is_empty());
+
+        let mut titles: Vec<String> = github
+            .snapshot()
+            .passwords
+            .into_values()
+            .map(|p| p.title)
+            .collect();
+        titles.sort();
+        assert_eq!(titles, ["GitHub", "Mail"]);
+    }
+
+    #[tokio::test]
+    async fn offline_saves_are_queued_on_disk_and_flushed() {
+        let local = Arc::new(MockStorage::new());
+        let github = Arc::new(MockStorage::new().with_target(StorageTarget::GitHub));
+        let mut manager =
+            manager_with(vec![("local", local.clone()), ("github", github.clone())]).await;
+        let path = mock_store::temp_dir().join("pending.json");
+        manager.pending_queue = Some(PendingQueue::new(path.clone()));
+
+        github.set_fail(true);
+        assert!(
+            manager
+                .add_password(create_request("GitHub"))
+                .await
+                .is_err()
+        );
+        assert_eq!(local.snapshot().passwords.len(), 1);
+
+        // 队列保存在磁盘上，重新打开后仍然存在
+        let queued = PendingQueue::new(path.clone()).load().unwrap();
+        assert_eq!(queued.len(), 1);
+        assert_eq!(queued[&StorageId::from("github")].passwords.len(), 1);
+
+        // 仍然离线时保留在队列中
+        assert!(manager.flush_pending().await.is_err());
+        assert!(path.exists());
+
+        // 锁定时缓存为空，也可以写入队列中的数据
+        manager.lock().await;
+        github.set_fail(false);
+        assert_eq!(manager.flush_pending().await.unwrap(), 1);
+        assert_eq!(github.snapshot().passwords.len(), 1);
+        assert!(!path.exists());
+        assert!(manager.pending_sync().is_empty());
+        assert_eq!(manager.flush_pending().await.unwrap(), 0);
+    }
+
+    #[tokio::test]
+    async fn search_passwords_paged_limits_and_locates_matches() {
+        let manager = manager_with(vec![("local", Arc::new(MockStorage::new()))]).await;
+        for title in ["Mail B", "Mail A", "Bank", "Mail C"] {
+            manager.add_password(create_request(title)).await.unwrap();
+        }
+
+        let hits = manager
+            .search_passwords_paged("Mail", Some(2))
+            .await
+            .unwrap();
+        let titles: Vec<&str> = hits.iter().map(|h| h.password.title.as_str()).collect();
+        assert_eq!(titles, ["Mail A", "Mail B"]);
+        for hit in &hits {
+            assert_eq!(hit.field, search::MatchField::Title);
+            let (start, end) = hit.range;
+            assert_eq!(&hit.password.title[start..end], "Mail");
+        }
+
+        let all = manager.search_passwords_paged("Mail", None).await.unwrap();
+        assert_eq!(all.len(), 3);
+        assert_eq!(
+            all.len(),
+            manager.search_passwords("Mail").await.unwrap().len()
+        );
+    }
+
+    #[tokio::test]
+    async fn search_ignores_accents_and_case() {
+        let manager = manager_with(vec![("local", Arc::new(MockStorage::new()))]).await;
+        manager
+            .add_password(create_request("José's Bank"))
+            .await
+            .unwrap();
+        manager
+            .add_password(create_request("ＧｉｔＨｕｂ"))
+            .await
+            .unwrap();
+
+        let titles = |found: Vec<Password>| found.into_iter().map(|p| p.title).collect::<Vec<_>>();
+        assert_eq!(
+            titles(manager.search_passwords("jose").await.unwrap()),
+            ["José's Bank"]
+        );
+        assert_eq!(
+            titles(manager.search_passwords("GITHUB").await.unwrap()),
+            ["ＧｉｔＨｕｂ"]
+        );
+    }
+
+    #[tokio::test]
+    async fn reveal_token_is_single_use_and_cleared_on_lock() {
+        let manager = manager_with(vec![("local", Arc::new(MockStorage::new()))]).await;
+        manager.add_password(create_request("Bank")).await.unwrap();
+        let id = manager.get_all_passwords().await.unwrap()[0].id.clone();
+
+        let revealed = manager.request_reveal(&id, "key").await.unwrap();
+        assert_eq!(
+            manager.consume_reveal(&revealed.token).await.unwrap(),
+            create_request("Bank").password
+        );
+        assert!(manager.consume_reveal(&revealed.token).await.is_err());
+        assert!(manager.request_reveal(&id, "wrong").await.is_err());
+
+        let pending = manager.request_reveal(&id, "key").await.unwrap();
+        manager.lock().await;
+        manager.unlock(None).await.unwrap();
+        assert!(manager.consume_reveal(&pending.token).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn scoped_search_only_checks_selected_fields() {
+        let manager = manager_with(vec![("local", Arc::new(MockStorage::new()))]).await;
+        let mut request = create_request("Bank");
+        request.description = "backup mail account".to_string();
+        manager.add_password(request).await.unwrap();
+
+        let title_only = SearchScope::from_names(&["title"]).unwrap();
+        assert!(
+            manager
+                .search_passwords_scoped("mail", title_only)
+                .await
+                .unwrap()
+                .is_empty()
+        );
+        assert_eq!(manager.search_passwords("mail").await.unwrap().len(), 1);
+
+        let scope = SearchScope::from_names(&["Title", "username"]).unwrap();
+        let found = manager
+            .search_passwords_scoped("alice", scope)
+            .await
+            .unwrap();
+        assert_eq!(found.len(), 1);
+        assert!(SearchScope::from_names(&["notes"]).is_err());
+        assert!(SearchScope::from_names::<&str>(&[]).is_err());
+    }
+
+    #[tokio::test]
+    async fn rename_tag_merges_with_existing_tag() {
+        let local = Arc::new(MockStorage::new());
+        let manager = manager_with(vec![("local", local.clone())]).await;
+        for (title, tags) in [
+            ("GitHub", vec!["Work", "dev"]),
+            ("Mail", vec!["work", "Office"]),
+            ("Bank", vec!["personal"]),
+        ] {
+            manager
+                .add_password(PasswordCreateRequest {
+                    tags: tags.into_iter().map(str::to_string).collect(),
+                    ..create_request(title)
+                })
+                .await
+                .unwrap();
+        }
+        let saves = local.saves();
+
+        assert_eq!(manager.rename_tag("WORK", "office").await.unwrap(), 2);
+        // 所有修改只保存一次
+        assert_eq!(local.saves(), saves + 1);
+
+        let tags_of = |title: &str| {
+            local
+                .snapshot()
+                .passwords
+                .into_values()
+                .find(|p| p.title == title)
+                .unwrap()
+                .tags
+        };
+        assert_eq!(tags_of("GitHub"), ["office", "dev"]);
+        // 已有的 Office 与改名后的 office 只保留一个
+        assert_eq!(tags_of("Mail"), ["office"]);
+        assert_eq!(tags_of("Bank"), ["personal"]);
+
+        assert_eq!(manager.rename_tag("missing", "other").await.unwrap(), 0);
+        assert!(manager.rename_tag("dev", " ").await.is_err());
+    }
+
+    #[tokio::test]
+    async fn rename_tag_keeps_moved_entry_out_of_source() {
+        let mut data = StorageData::new();
+        let entry = tagged("GitHub", &["work"]);
+        data.merge([entry.clone()]);
+        let local = Arc::new(MockStorage::with_data(data));
+        let github = Arc::new(MockStorage::new().with_target(StorageTarget::GitHub));
+        let manager =
+            manager_with(vec![("local", local.clone()), ("github", github.clone())]).await;
+        manager
+            .move_entry(
+                &entry.id,
+                &StorageId::from("local"),
+                &StorageId::from("github"),
+            )
+            .await
+            .unwrap();
+        let local_saves = local.saves();
+
+        assert_eq!(manager.rename_tag("work", "office").await.unwrap(), 1);
+
+        // 没有该条目的存储点不会被写入
+        assert_eq!(local.saves(), local_saves);
+        assert!(local.snapshot().passwords.is_empty());
+        assert_eq!(github.snapshot().passwords[&entry.id].tags, ["office"]);
+    }
+
+    #[tokio::test]
+    async fn set_entry_tags_cleans_the_list() {
+        let local = Arc::new(MockStorage::new());
+        let manager = manager_with(vec![("local", local.clone())]).await;
+        manager
+            .add_password(create_request("GitHub"))
+            .await
+            .unwrap();
+        let id = manager.get_all_passwords().await.unwrap()[0].id.clone();
+
+        let tags = [" work ", "dev", "", "Work", "  ", "personal", "dev"];
+        manager
+            .set_entry_tags(&id, tags.iter().map(|t| t.to_string()).collect())
+            .await
+            .unwrap();
+        assert_eq!(
+            local.snapshot().passwords[&id].tags,
+            ["work", "dev", "personal"]
+        );
+
+        assert_eq!(
+            PasswordError::from(
+                manager
+                    .set_entry_tags("missing", vec!["a".to_string()])
+                    .await
+                    .unwrap_err()
+            )
+            .code(),
+            404
+        );
+    }
+
+    #[tokio::test]
+    async fn sort_all_tags_alphabetically_saves_once() {
+        let local = Arc::new(MockStorage::new());
+        let manager = manager_with(vec![("local", local.clone())]).await;
+        for (title, tags) in [
+            ("GitHub", vec!["work", "Dev", "api"]),
+            ("Mail", vec!["a", "b"]),
+        ] {
+            manager
+                .add_password(PasswordCreateRequest {
+                    tags: tags.into_iter().map(str::to_string).collect(),
+                    ..create_request(title)
+                })
+                .await
+                .unwrap();
+        }
+        let saves = local.saves();
+
+        // 已经有序的 Mail 不修改
+        assert_eq!(manager.sort_all_tags_alphabetically().await.unwrap(), 1);
+        assert_eq!(local.saves(), saves + 1);
+        let github = local
+            .snapshot()
+            .passwords
+            .into_values()
+            .find(|p| p.title == "GitHub")
+            .unwrap();
+        assert_eq!(github.tags, ["api", "Dev", "work"]);
+
+        assert_eq!(manager.sort_all_tags_alphabetically().await.unwrap(), 0);
+        assert_eq!(local.saves(), saves + 1);
+    }
+
+    #[tokio::test]
+    async fn sort_all_tags_only_touches_storages_holding_changed_entries() {
+        let mut data = StorageData::new();
+        let moved = tagged("GitHub", &["work", "api"]);
+        data.merge([moved.clone(), tagged("Mail", &["a", "b"])]);
+        let local = Arc::new(MockStorage::with_data(data));
+        let github = Arc::new(MockStorage::new().with_target(StorageTarget::GitHub));
+        let manager =
+            manager_with(vec![("local", local.clone()), ("github", github.clone())]).await;
+        manager.save_data().await.unwrap();
+        manager
+            .move_entry(
+                &moved.id,
+                &StorageId::from("local"),
+                &StorageId::from("github"),
+            )
+            .await
+            .unwrap();
+        let (local_saves, github_saves) = (local.saves(), github.saves());
+
+        // 只有移到 github 的条目需要调整顺序，local 不会被写入
+        assert_eq!(manager.sort_all_tags_alphabetically().await.unwrap(), 1);
+        assert_eq!(local.saves(), local_saves);
+        assert_eq!(github.saves(), github_saves + 1);
+        assert!(!local.snapshot().passwords.contains_key(&moved.id));
+        assert_eq!(github.snapshot().passwords[&moved.id].tags, ["api", "work"]);
+    }
+
+    #[tokio::test]
+    async fn delete_tag_removes_it_everywhere() {
+        let local = Arc::new(MockStorage::new());
+        let github = Arc::new(MockStorage::new().with_target(StorageTarget::GitHub));
+        let manager =
+            manager_with(vec![("local", local.clone()), ("github", github.clone())]).await;
+        for (title, tags) in [("GitHub", vec!["Work", "dev"]), ("Mail", vec!["work"])] {
+            manager
+                .add_password(PasswordCreateRequest {
+                    tags: tags.into_iter().map(str::to_string).collect(),
+                    ..create_request(title)
+                })
+                .await
+                .unwrap();
+        }
+
+        assert_eq!(manager.delete_tag("work").await.unwrap(), 2);
+
+        for storage in [&local, &github] {
+            let mut tags: Vec<Vec<String>> = storage
+                .snapshot()
+                .passwords
+                .into_values()
+                .map(|p| p.tags)
+                .collect();
+            tags.sort();
+            assert_eq!(tags, [vec![], vec!["dev".to_string()]]);
+        }
+        assert_eq!(manager.list_tags().await.unwrap(), [("dev".to_string(), 1)]);
+    }
+
+    #[tokio::test]
+    async fn storage_counts_match_loaded_data() {
+        let mut data = StorageData::new();
+        data.merge([
+            password_entry("GitHub", "alice"),
+            password_entry("Mail", "alice"),
+        ]);
+        let local = Arc::new(MockStorage::with_data(data));
+        let github = Arc::new(MockStorage::new().with_target(StorageTarget::GitHub));
+        let manager =
+            manager_with(vec![("local", local.clone()), ("github", github.clone())]).await;
+
+        let counts = manager.storage_counts();
+        assert_eq!(
+            counts,
+            HashMap::from([
+                ("local".to_string(), local.snapshot().passwords.len()),
+                ("github".to_string(), 0),
+            ])
+        );
+        assert_eq!(counts["local"], 2);
+        // 缓存已统一，但统计的是各存储点实际读到的数量
+        assert_eq!(
+            manager
+                .get_all_passwords_from_storage(&StorageId::from("github"))
+                .await
+                .unwrap()
+                .passwords
+                .len(),
+            2
+        );
+    }
+
+    #[tokio::test]
+    async fn slow_save_does_not_block_search() {
+        let local = Arc::new(MockStorage::new());
+        let manager = Arc::new(manager_with(vec![("local", local.clone())]).await);
+        manager
+            .add_passwords(vec![create_request("GitHub"), create_request("Mail")])
+            .await
+            .unwrap();
+        let id = manager.search_passwords("Mail").await.unwrap()[0]
+            .id
+            .clone();
+
+        let gate = local.block_saves();
+        let saving = tokio::spawn({
+            let manager = manager.clone();
+            async move { manager.delete_passwords(&[id]).await }
+        });
+        // 让保存任务先运行到等待写入完成的位置
+        tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+
+        let found = tokio::time::timeout(
+            std::time::Duration::from_secs(1),
+            manager.search_passwords("GitHub"),
+        )
+        .await
+        .expect("保存期间搜索被阻塞")
+        .unwrap();
+        assert_eq!(found.len(), 1);
+        assert!(!saving.is_finished());
+
+        gate.notify_one();
+        saving.await.unwrap().unwrap();
+        assert_eq!(local.snapshot().passwords.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn unchanged_save_is_skipped() {
+        let local = Arc::new(MockStorage::new());
+        let manager = manager_with(vec![("local", local.clone())]).await;
+
+        manager
+            .add_passwords(vec![create_request("GitHub")])
+            .await
+            .unwrap();
+        assert_eq!(local.saves(), 1);
+
+        // 没有改动时第二次保存不写入
+        manager.save_data().await.unwrap();
+        assert_eq!(local.saves(), 1);
+
+        // 写入失败的改动保留，下次保存时重新写入
+        local.set_fail(true);
+        assert!(
+            manager
+                .add_passwords(vec![create_request("Mail")])
+                .await
+                .is_err()
+        );
+        local.set_fail(false);
+        manager.save_data().await.unwrap();
+        assert_eq!(local.saves(), 2);
+        assert_eq!(local.snapshot().passwords.len(), 2);
+
+        manager.save_data().await.unwrap();
+        assert_eq!(local.saves(), 2);
+    }
+
+    #[tokio::test]
+    async fn wipe_storage_clears_only_that_target() {
+        let local = Arc::new(MockStorage::new());
+        let github = Arc::new(MockStorage::new().with_target(StorageTarget::GitHub));
+        let manager =
+            manager_with(vec![("local", local.clone()), ("github", github.clone())]).await;
+        manager
+            .add_passwords(vec![create_request("GitHub"), create_request("Mail")])
+            .await
+            .unwrap();
+
+        let err = manager
+            .wipe_storage(&StorageId::from("local"), "yes")
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains(WIPE_CONFIRMATION));
+        assert_eq!(local.snapshot().passwords.len(), 2);
+
+        manager
+            .wipe_storage(&StorageId::from("local"), WIPE_CONFIRMATION)
+            .await
+            .unwrap();
+        assert!(local.snapshot().passwords.is_empty());
+        assert_eq!(github.snapshot().passwords.len(), 2);
+        assert_eq!(all_titles(&manager).await.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn self_test_reports_each_check() {
+        let local = Arc::new(MockStorage::new());
+        let github = Arc::new(MockStorage::new().with_target(StorageTarget::GitHub));
+        let manager =
+            manager_with(vec![("local", local.clone()), ("github", github.clone())]).await;
+
+        let report = manager.self_test().await;
+        assert!(report.ok);
+        let names: Vec<&str> = report.checks.iter().map(|c| c.name.as_str()).collect();
+        assert_eq!(
+            names,
+            ["crypto", "config", "storage:github", "storage:local"]
+        );
+
+        github.set_fail(true);
+        let report = manager.self_test().await;
+        assert!(!report.ok);
+        for check in &report.checks {
+            assert_eq!(check.ok, check.name != "storage:github", "{}", check.name);
+        }
+        assert_eq!(local.saves(), 0);
+    }
+
+    #[tokio::test]
+    async fn move_entry_between_storages() {
+        let mut data = StorageData::new();
+        let entry = password_entry("GitHub", "alice");
+        data.merge([entry.clone()]);
+        let local = Arc::new(MockStorage::with_data(data));
+        let github = Arc::new(MockStorage::new().with_target(StorageTarget::GitHub));
+        let manager =
+            manager_with(vec![("local", local.clone()), ("github", github.clone())]).await;
+        let (local_id, github_id) = (StorageId::from("local"), StorageId::from("github"));
+
+        manager
+            .move_entry(&entry.id, &local_id, &github_id)
+            .await
+            .unwrap();
+        assert!(local.snapshot().passwords.is_empty());
+        assert!(github.snapshot().passwords.contains_key(&entry.id));
+
+        // 重新加载后条目不会被同步回源存储点
+        manager.refresh_cache(&github_id).await.unwrap();
+        manager.refresh_cache(&local_id).await.unwrap();
+        assert!(
+            !manager.cache.read().await[&local_id]
+                .passwords
+                .contains_key(&entry.id)
+        );
+        manager.save_data().await.unwrap();
+        assert!(local.snapshot().passwords.is_empty());
+        assert_eq!(manager.get_all_passwords().await.unwrap().len(), 1);
+
+        // 已经不在源存储点中
+        let err = manager
+            .move_entry(&entry.id, &local_id, &github_id)
+            .await
+            .unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<PasswordError>(),
+            Some(PasswordError::NotFound(_))
+        ));
+
+        let err = manager
+            .move_entry(&entry.id, &github_id, &StorageId::from("s3"))
+            .await
+            .unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<PasswordError>(),
+            Some(PasswordError::InvalidTarget(_))
+        ));
+        assert!(github.snapshot().passwords.contains_key(&entry.id));
+    }
+
+    #[tokio::test]
+    async fn read_only_rejects_writes() {
+        let _guard = CONF_FILE_LOCK.lock().await;
+        conf_path();
+        let local = Arc::new(MockStorage::new());
+        let manager = manager_with(vec![("local", local.clone())]).await;
+        manager
+            .add_password(create_request("GitHub"))
+            .await
+            .unwrap();
+        let id = manager.get_all_passwords().await.unwrap()[0].id.clone();
+
+        manager.set_read_only(true, None).await.unwrap();
+        assert!(Config::load_from_file(conf_path()).unwrap().read_only);
+        let saves = local.saves();
+
+        let is_read_only = |e: anyhow::Error| {
+            matches!(
+                e.downcast_ref::<PasswordError>(),
+                Some(PasswordError::ReadOnly)
+            )
+        };
+        assert!(is_read_only(
+            manager
+                .add_password(create_request("Mail"))
+                .await
+                .unwrap_err()
+        ));
+        assert!(is_read_only(
+            manager.delete_password(&id).await.unwrap_err()
+        ));
+        assert!(is_read_only(
+            manager.update_config(Config::default()).await.unwrap_err()
+        ));
+
+        // 查看和解密不受影响，也不记录使用时间
+        assert_eq!(all_titles(&manager).await, ["GitHub"]);
+        assert_eq!(
+            manager.decrypt_password_by_id(&id, "key").await.unwrap(),
+            "pw"
+        );
+        assert_eq!(local.saves(), saves);
+
+        manager.set_read_only(false, None).await.unwrap();
+        manager.add_password(create_request("Mail")).await.unwrap();
+        assert_eq!(local.snapshot().passwords.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn hardware_factor_is_mixed_into_entry_keys() {
+        let _guard = CONF_FILE_LOCK.lock().await;
+        conf_path();
+        let local = Arc::new(MockStorage::new());
+        let manager = manager_with(vec![("local", local.clone())]).await;
+
+        manager
+            .enable_hardware_factor("challenge", b"response")
+            .await
+            .unwrap();
+        manager
+            .add_password(create_request("GitHub"))
+            .await
+            .unwrap();
+        let id = manager.get_all_passwords().await.unwrap()[0].id.clone();
+
+        // 存储中的密文无法只用条目key解密
+        let stored = local.snapshot().passwords[&id].encrypted_password.clone();
+        assert!(crypto::decrypt_with_password(&stored, "key").is_err());
+
+        manager.lock().await;
+        assert!(manager.unlock(None).await.is_err());
+        assert!(manager.unlock_with_hardware(None, b"wrong").await.is_err());
+        assert!(manager.is_locked());
+
+        manager
+            .unlock_with_hardware(None, b"response")
+            .await
+            .unwrap();
+        assert_eq!(
+            manager.decrypt_password_by_id(&id, "key").await.unwrap(),
+            "pw"
+        );
+
+        // 密码库不为空时不能再次启用
+        assert!(
+            manager
+                .enable_hardware_factor("other", b"other")
+                .await
+                .is_err()
+        );
+    }
+
+    #[tokio::test]
+    async fn audit_log_records_actions_in_order() {
+        let mut manager = manager_with(vec![("local", Arc::new(MockStorage::new()))]).await;
+        manager.audit = Some(AuditLog::new(mock_store::temp_dir().join("audit.jsonl")));
+
+        manager
+            .add_password(create_request("GitHub"))
+            .await
+            .unwrap();
+        let id = manager.get_all_passwords().await.unwrap()[0].id.clone();
+        manager.delete_password(&id).await.unwrap();
+
+        let events = manager.audit_log(10).await.unwrap();
+        let actions: Vec<(AuditAction, Option<&str>)> = events
+            .iter()
+            .map(|e| (e.action, e.entry_id.as_deref()))
+            .collect();
+        assert_eq!(
+            actions,
+            [
+                (AuditAction::Add, Some(id.as_str())),
+                (AuditAction::Delete, Some(id.as_str())),
+            ]
+        );
+        assert!(events[0].timestamp <= events[1].timestamp);
+
+        // 导出和解密失败不记录，成功才记录
+        manager.export_backup("passphrase").await.unwrap();
+        assert!(manager.decrypt_notes("missing", "key").await.is_err());
+        let last = manager.audit_log(1).await.unwrap();
+        assert_eq!(last[0].action, AuditAction::Export);
+        assert_eq!(last[0].entry_id, None);
+    }
+
+    #[tokio::test]
+    async fn vault_stats_match_fixture() {
+        let now = Utc::now();
+        let mut data = StorageData::new();
+        for (title, tags, url, days) in [
+            (
+                "Mail",
+                vec!["work", "Email"],
+                Some("https://mail.example.com"),
+                3,
+            ),
+            ("Bank", vec!["finance"], None, 10),
+            ("Chat", vec!["work"], Some(" "), 1),
+        ] {
+            let mut p = password_entry(title, "alice");
+            p.tags = tags.into_iter().map(str::to_string).collect();
+            p.url = url.map(str::to_string);
+            p.updated_at = now - chrono::Duration::days(days);
+            data.merge([p]);
+        }
+        let manager = manager_with(vec![
+            ("local", Arc::new(MockStorage::with_data(data))),
+            (
+                "github",
+                Arc::new(MockStorage::new().with_target(StorageTarget::GitHub)),
+            ),
+        ])
+        .await;
+
+        let stats = manager.vault_stats().await.unwrap();
+        assert_eq!(stats.total, 3);
+        assert_eq!(
+            stats.per_tag,
+            [
+                ("work".to_string(), 2),
+                ("email".to_string(), 1),
+                ("finance".to_string(), 1)
+            ]
+        );
+        assert_eq!(stats.per_storage[&StorageId::from("local")], 3);
+        assert_eq!(stats.per_storage[&StorageId::from("github")], 3);
+        assert_eq!(stats.without_url, 2);
+        assert_eq!(stats.average_title_length, 4.0);
+        assert_eq!(
+            stats.oldest_updated_at,
+            Some(now - chrono::Duration::days(10))
+        );
+        assert_eq!(
+            stats.newest_updated_at,
+            Some(now - chrono::Duration::days(1))
+        );
+    }
+
+    #[tokio::test]
+    async fn weak_password_report_lists_weak_entries() {
+        let manager = manager_with(vec![("local", Arc::new(MockStorage::new()))]).await;
+        let request = |title: &str, password: &str, key: &str| PasswordCreateRequest {
+            password: password.to_string(),
+            key: key.to_string(),
+            ..create_request(title)
+        };
+        manager
+            .add_passwords(vec![
+                request("Strong", "x7#Lq9!vR2@mZ4$w", "key"),
+                request("Short", "abc", "key"),
+                request("Common", "password1", "key"),
+                request("Other key", "abc", "other"),
+            ])
+            .await
+            .unwrap();
+
+        let report = manager.weak_password_report("key").await.unwrap();
+        let titles: Vec<&str> = report.iter().map(|w| w.title.as_str()).collect();
+        assert_eq!(titles, ["Short", "Common"]);
+        assert!(report[0].entropy_bits < report[1].entropy_bits);
+        assert!(
+            !serde_json::to_string(&report)
+                .unwrap()
+                .contains("password1")
+        );
     }
 }
@@ -1,9 +1,15 @@
 use anyhow::{Result, anyhow};
 use chrono::{DateTime, Utc};
+use rand::rngs::OsRng;
+use rand::seq::{IndexedRandom, SliceRandom};
+use rand::{Rng, TryRngCore};
 use serde::{Deserialize, Serialize};
 
 // use crate::simple_crypto::RobustEncryptedData;
-use crate::crypto::EncryptedData;
+use crate::crypto::{EncryptedData, KdfStrength};
+use crate::log;
+use crate::store::StorageId;
+use crate::totp::TotpConfig;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Password {
@@ -19,9 +25,69 @@ pub struct Password {
     pub url: Option<String>,               // 明文URL，不再加密
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
+    /// 是否收藏
+    #[serde(default)]
+    pub favorite: bool,
+    /// 最近一次成功解密的时间
+    #[serde(default)]
+    pub last_used_at: Option<DateTime<Utc>>,
+    /// 过期时间，到期后应当更换密码
+    #[serde(default)]
+    pub expires_at: Option<DateTime<Utc>>,
+    /// 两步验证的base32密钥，与密码使用同一个key加密
+    #[serde(default)]
+    pub totp_secret: Option<EncryptedData>,
+    #[serde(default)]
+    pub totp_config: TotpConfig,
+    /// 附件，与密码使用同一个key加密
+    #[serde(default)]
+    pub attachments: Vec<Attachment>,
+    /// 加密的备注，如密保问题、PIN码，与密码使用同一个key加密
+    ///
+    /// 需要搜索的说明文字放在明文的 `description` 中
+    #[serde(default)]
+    pub encrypted_notes: Option<EncryptedData>,
+    /// 不再同步到这些存储点，由 `move_entry` 设置，重新加载时也不会被同步回去
+    #[serde(default)]
+    pub excluded_storages: Vec<StorageId>,
 }
 
+/// 列表显示用的条目摘要，只包含明文元数据，不包含任何密文
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct PasswordSummary {
+    pub id: String,
+    pub title: String,
+    pub username: String,
+    pub url: Option<String>,
+    pub tags: Vec<String>,
+    pub updated_at: DateTime<Utc>,
+    pub favorite: bool,
+}
+
+impl From<&Password> for PasswordSummary {
+    fn from(password: &Password) -> Self {
+        Self {
+            id: password.id.clone(),
+            title: password.title.clone(),
+            username: password.username.clone(),
+            url: password.url.clone(),
+            tags: password.tags.clone(),
+            updated_at: password.updated_at,
+            favorite: password.favorite,
+        }
+    }
+}
+
+/// 条目的加密附件，如恢复码、密钥文件
 #[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Attachment {
+    /// 文件名，在同一条目内唯一
+    pub name: String,
+    pub mime: String,
+    pub data: EncryptedData,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
 pub struct PasswordCreateRequest {
     pub title: String,
     pub description: String,
@@ -31,6 +97,97 @@ pub struct PasswordCreateRequest {
     pub password: String,
     pub url: Option<String>,
     pub key: String, // 用于加密的密码
+    #[serde(default)]
+    pub expires_at: Option<DateTime<Utc>>,
+    /// 明文的两步验证base32密钥
+    #[serde(default)]
+    pub totp_secret: Option<String>,
+    /// 明文备注，保存时加密
+    #[serde(default)]
+    pub notes: Option<String>,
+    /// 前端生成的条目id，重试添加时使用同一个id不会产生重复条目
+    #[serde(default)]
+    pub client_id: Option<String>,
+    /// 密钥派生强度，重要的条目可以使用更慢但更安全的Argon2
+    #[serde(default)]
+    pub kdf_strength: KdfStrength,
+}
+
+// 明文密码、加密key、两步验证密钥和备注不能出现在日志中
+impl std::fmt::Debug for PasswordCreateRequest {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("PasswordCreateRequest")
+            .field("title", &self.title)
+            .field("description", &self.description)
+            .field("tags", &self.tags)
+            .field("username", &self.username)
+            .field("password", &log::redact(&self.password))
+            .field("url", &self.url)
+            .field("key", &log::redact(&self.key))
+            .field("expires_at", &self.expires_at)
+            .field("totp_secret", &self.totp_secret.as_deref().map(log::redact))
+            .field("notes", &self.notes.as_deref().map(log::redact))
+            .field("client_id", &self.client_id)
+            .field("kdf_strength", &self.kdf_strength)
+            .finish()
+    }
+}
+
+/// 由后端生成密码时的创建请求，字段同 [`PasswordCreateRequest`]，但不含密码和key
+#[derive(Clone, Serialize, Deserialize)]
+pub struct PasswordCreateRequestNoPassword {
+    pub title: String,
+    pub description: String,
+    pub tags: Vec<String>,
+    pub username: String,
+    pub url: Option<String>,
+    #[serde(default)]
+    pub expires_at: Option<DateTime<Utc>>,
+    /// 明文的两步验证base32密钥
+    #[serde(default)]
+    pub totp_secret: Option<String>,
+    /// 明文备注，保存时加密
+    #[serde(default)]
+    pub notes: Option<String>,
+    #[serde(default)]
+    pub kdf_strength: KdfStrength,
+}
+
+impl PasswordCreateRequestNoPassword {
+    /// 补上密码和key，得到完整的创建请求
+    pub fn with_password(self, password: String, key: String) -> PasswordCreateRequest {
+        PasswordCreateRequest {
+            title: self.title,
+            description: self.description,
+            tags: self.tags,
+            username: self.username,
+            password,
+            url: self.url,
+            key,
+            expires_at: self.expires_at,
+            totp_secret: self.totp_secret,
+            notes: self.notes,
+            client_id: None,
+            kdf_strength: self.kdf_strength,
+        }
+    }
+}
+
+// 两步验证密钥不能出现在日志中
+impl std::fmt::Debug for PasswordCreateRequestNoPassword {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("PasswordCreateRequestNoPassword")
+            .field("title", &self.title)
+            .field("description", &self.description)
+            .field("tags", &self.tags)
+            .field("username", &self.username)
+            .field("url", &self.url)
+            .field("expires_at", &self.expires_at)
+            .field("totp_secret", &self.totp_secret.as_deref().map(log::redact))
+            .field("notes", &self.notes.as_deref().map(log::redact))
+            .field("kdf_strength", &self.kdf_strength)
+            .finish()
+    }
 }
 
 // #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -47,8 +204,12 @@ pub struct PasswordCreateRequest {
 impl Password {
     pub fn new(request: PasswordCreateRequest, encrypted_password: EncryptedData) -> Self {
         let now = Utc::now();
+        let id = request
+            .client_id
+            .filter(|id| !id.trim().is_empty())
+            .unwrap_or_else(|| uuid::Uuid::new_v4().to_string());
         Self {
-            id: uuid::Uuid::new_v4().to_string(),
+            id,
             title: request.title,
             description: request.description,
             tags: request.tags,
@@ -57,6 +218,14 @@ impl Password {
             url: request.url,
             created_at: now,
             updated_at: now,
+            favorite: false,
+            last_used_at: None,
+            expires_at: request.expires_at,
+            totp_secret: None,
+            totp_config: TotpConfig::default(),
+            attachments: vec![],
+            encrypted_notes: None,
+            excluded_storages: vec![],
         }
     }
 
@@ -85,7 +254,43 @@ impl Password {
     // }
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+/// 密码列表的排序方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SortKey {
+    TitleAsc,
+    TitleDesc,
+    CreatedAtAsc,
+    CreatedAtDesc,
+    UpdatedAtAsc,
+    UpdatedAtDesc,
+}
+
+impl SortKey {
+    /// 比较两个条目，排序字段相同时按id比较，保证顺序稳定
+    pub fn compare(&self, a: &Password, b: &Password) -> std::cmp::Ordering {
+        let ordering = match self {
+            SortKey::TitleAsc | SortKey::TitleDesc => a
+                .title
+                .to_lowercase()
+                .cmp(&b.title.to_lowercase())
+                .then_with(|| a.title.cmp(&b.title)),
+            SortKey::CreatedAtAsc | SortKey::CreatedAtDesc => a.created_at.cmp(&b.created_at),
+            SortKey::UpdatedAtAsc | SortKey::UpdatedAtDesc => a.updated_at.cmp(&b.updated_at),
+        };
+
+        let ordering = match self {
+            SortKey::TitleDesc | SortKey::CreatedAtDesc | SortKey::UpdatedAtDesc => {
+                ordering.reverse()
+            }
+            _ => ordering,
+        };
+
+        ordering.then_with(|| a.id.cmp(&b.id))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct PasswordGeneratorConfig {
     pub length: usize,
     pub exclude_chars: Option<String>,
@@ -93,6 +298,15 @@ pub struct PasswordGeneratorConfig {
     pub require_lowercase: bool,
     pub require_numbers: bool,
     pub require_symbols: bool,
+    /// 不允许相邻字符相同，如 "aa"
+    #[serde(default)]
+    pub no_consecutive_repeats: bool,
+    /// 替换内置的特殊符号，如只允许网站接受的 "-_."
+    #[serde(default)]
+    pub custom_symbols: Option<String>,
+    /// 完全自定义的字符集，设置后取代所有内置字符类型，不再要求包含各类字符
+    #[serde(default)]
+    pub custom_alphabet: Option<String>,
 }
 
 impl Default for PasswordGeneratorConfig {
@@ -104,20 +318,97 @@ impl Default for PasswordGeneratorConfig {
             require_lowercase: true,
             require_numbers: true,
             require_symbols: true,
+            no_consecutive_repeats: false,
+            custom_symbols: None,
+            custom_alphabet: None,
         }
     }
 }
 
+/// 避免相邻字符重复时，每个位置最多重新选择的次数
+const MAX_RESAMPLE_ATTEMPTS: usize = 100;
+
+/// 一次最多生成的候选密码数量
+pub const MAX_GENERATE_COUNT: usize = 50;
+
+/// 按配置选出要求的各类字符，已去掉排除的字符和重复的字符
+///
+/// 设置了 `custom_alphabet` 时只有这一类字符。
+/// 没有选择字符类型、长度小于字符类型数量、或排除后某类字符为空时返回错误
+pub(crate) fn generator_classes(config: &PasswordGeneratorConfig) -> Result<Vec<Vec<char>>> {
+    // 定义字符集
+    const UPPERCASE: &str = "ABCDEFGHIJKLMNOPQRSTUVWXYZ";
+    const LOWERCASE: &str = "abcdefghijklmnopqrstuvwxyz";
+    const NUMBERS: &str = "0123456789";
+    const SYMBOLS: &str = "!@#$%^&*()_+-=[]{}|;:,.<>?";
+
+    // 根据配置选出要求的字符类型，自定义字符集取代所有内置类型
+    let classes: Vec<(&str, &str)> = match config.custom_alphabet.as_deref() {
+        Some(alphabet) => vec![("自定义字符", alphabet)],
+        None => [
+            (config.require_lowercase, "小写字母", LOWERCASE),
+            (config.require_uppercase, "大写字母", UPPERCASE),
+            (config.require_numbers, "数字", NUMBERS),
+            (
+                config.require_symbols,
+                "特殊符号",
+                config.custom_symbols.as_deref().unwrap_or(SYMBOLS),
+            ),
+        ]
+        .into_iter()
+        .filter(|(required, _, _)| *required)
+        .map(|(_, name, chars)| (name, chars))
+        .collect(),
+    };
+
+    // 如果没有选择任何字符类型，返回错误
+    if classes.is_empty() {
+        return Err(anyhow!("至少需要选择一种字符类型"));
+    }
+
+    // 每种字符类型至少占一位
+    if config.length < classes.len() {
+        return Err(anyhow!(
+            "密码长度 {} 小于要求的字符类型数量 {}",
+            config.length,
+            classes.len()
+        ));
+    }
+
+    // 处理排除字符，排除后每类都必须还有可用字符
+    let exclude = config.exclude_chars.as_deref().unwrap_or_default();
+    let mut class_chars = Vec::new();
+    for (name, chars) in classes {
+        // 自定义字符集中重复的字符会提高被选中的概率，只保留一个
+        let mut filtered: Vec<char> = Vec::new();
+        for c in chars.chars().filter(|c| !exclude.contains(*c)) {
+            if !filtered.contains(&c) {
+                filtered.push(c);
+            }
+        }
+        if filtered.is_empty() {
+            return Err(anyhow!("排除字符后没有可用的{}", name));
+        }
+        class_chars.push(filtered);
+    }
+
+    Ok(class_chars)
+}
+
 /// 根据配置生成复杂密码
 ///
+/// 生成的密码长度恰好为 `config.length`，且每种要求的字符类型至少出现一次；
+/// 开启 `no_consecutive_repeats` 时相邻字符不会相同
+///
 /// # 参数
 /// * `config` - 密码生成配置
 ///
 /// # 返回
-/// * `Result<String, String>` - 成功返回生成的密码，失败返回错误信息
+/// * `Result<String>` - 成功返回生成的密码；没有选择字符类型、长度小于要求的
+///   字符类型数量、排除字符后某类字符为空、或要求不重复但只有一个可用字符时返回错误
 ///
 /// # 示例
-/// ```
+/// ```ignore
 /// let config = PasswordGeneratorConfig {
 ///     length: 12,
 ///     exclude_chars: Some("O0l1".to_string()),
@@ -125,124 +416,676 @@ impl Default for PasswordGeneratorConfig {
 ///     require_lowercase: true,
 ///     require_numbers: true,
 ///     require_symbols: true,
+///     no_consecutive_repeats: false,
+///     custom_symbols: None,
+///     custom_alphabet: None,
 /// };
-/// let password = generate_password(config)?;
+/// let password = generate_password(&config)?;
 /// ```
 pub fn generate_password(config: &PasswordGeneratorConfig) -> Result<String> {
-    // 定义字符集
-    const UPPERCASE: &str = "ABCDEFGHIJKLMNOPQRSTUVWXYZ";
-    const LOWERCASE: &str = "abcdefghijklmnopqrstuvwxyz";
-    const NUMBERS: &str = "0123456789";
-    const SYMBOLS: &str = "!@#$%^&*()_+-=[]{}|;:,.<>?";
+    let class_chars = generator_classes(config)?;
+    let available_chars = class_chars.concat();
 
-    // 根据配置构建可用字符集
-    let mut available_chars = String::new();
-    let mut required_chars = Vec::new();
+    let mut rng = rand::rng();
 
-    // 添加小写字母
-    if config.require_lowercase {
-        available_chars.push_str(LOWERCASE);
-        // 确保至少包含一个小写字母
-        required_chars.push(get_random_char(LOWERCASE));
+    // 首先每类各取一个字符，确保都出现
+    let mut password_chars: Vec<char> = class_chars
+        .iter()
+        .map(|chars| *chars.choose(&mut rng).unwrap())
+        .collect();
+
+    // 剩余的位置从所有可用字符中随机选择
+    for _ in password_chars.len()..config.length {
+        password_chars.push(*available_chars.choose(&mut rng).unwrap());
     }
 
-    // 添加大写字母
-    if config.require_uppercase {
-        available_chars.push_str(UPPERCASE);
-        // 确保至少包含一个大写字母
-        required_chars.push(get_random_char(UPPERCASE));
+    // 打乱字符顺序，避免必需字符总在开头
+    password_chars.shuffle(&mut rng);
+
+    if config.no_consecutive_repeats {
+        if available_chars.len() < 2 {
+            return Err(anyhow!("只有一个可用字符，无法避免相邻字符重复"));
+        }
+
+        for i in 1..password_chars.len() {
+            let previous = password_chars[i - 1];
+            if password_chars[i] != previous {
+                continue;
+            }
+
+            // 前一个位置是同样的字符，所以替换后每类字符仍然都会出现
+            password_chars[i] = (0..MAX_RESAMPLE_ATTEMPTS)
+                .map(|_| *available_chars.choose(&mut rng).unwrap())
+                .find(|c| *c != previous)
+                .ok_or_else(|| anyhow!("无法生成相邻字符不重复的密码"))?;
+        }
     }
 
-    // 添加数字
-    if config.require_numbers {
-        available_chars.push_str(NUMBERS);
-        // 确保至少包含一个数字
-        required_chars.push(get_random_char(NUMBERS));
+    // 组合成最终密码
+    let password: String = password_chars.into_iter().collect();
+
+    Ok(password)
+}
+
+/// 按同一配置生成 `count` 个互不相同的候选密码，每个都满足配置的要求
+///
+/// `count` 超过 `MAX_GENERATE_COUNT`，或字符集太小无法生成足够多不同的密码时返回错误
+pub fn generate_passwords(config: &PasswordGeneratorConfig, count: usize) -> Result<Vec<String>> {
+    if count > MAX_GENERATE_COUNT {
+        return Err(anyhow!(
+            "一次最多生成 {} 个密码，请求了 {} 个",
+            MAX_GENERATE_COUNT,
+            count
+        ));
     }
 
-    // 添加特殊符号
-    if config.require_symbols {
-        available_chars.push_str(SYMBOLS);
-        // 确保至少包含一个特殊符号
-        required_chars.push(get_random_char(SYMBOLS));
+    let mut passwords: Vec<String> = Vec::with_capacity(count);
+    let mut attempts = 0;
+    while passwords.len() < count {
+        if attempts == count * MAX_RESAMPLE_ATTEMPTS {
+            return Err(anyhow!("可用字符太少，无法生成 {} 个不同的密码", count));
+        }
+        attempts += 1;
+
+        let password = generate_password(config)?;
+        if !passwords.contains(&password) {
+            passwords.push(password);
+        }
     }
 
-    // 如果没有选择任何字符类型，返回错误
-    if available_chars.is_empty() {
-        return Err(anyhow!("至少需要选择一种字符类型"));
+    Ok(passwords)
+}
+
+/// 用户输入的密码不满足的一条生成规则
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "rule", rename_all = "snake_case")]
+pub enum RuleViolation {
+    /// 长度不足 `config.length`
+    TooShort {
+        min: usize,
+        actual: usize,
+    },
+    MissingUppercase,
+    MissingLowercase,
+    MissingNumber,
+    MissingSymbol,
+    /// 包含排除的字符，`chars` 为出现的排除字符，按出现顺序、不重复
+    ExcludedChars {
+        chars: String,
+    },
+    /// 相邻字符相同
+    ConsecutiveRepeat {
+        ch: char,
+    },
+}
+
+/// 密码与生成配置的校验结果
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ValidationResult {
+    /// 满足所有规则
+    pub valid: bool,
+    /// 不满足的规则，按长度、字符类型、排除字符、相邻重复的顺序列出
+    pub failed: Vec<RuleViolation>,
+}
+
+/// 检查用户输入的密码是否满足生成器的规则
+///
+/// 生成器要求长度恰好为 `config.length`，这里作为最小长度；字符类型按Unicode判断，
+/// 除字母、数字和空白以外的字符都算作特殊符号。配置本身无法生成密码时返回错误
+pub fn validate_against_config(
+    password: &str,
+    config: &PasswordGeneratorConfig,
+) -> Result<ValidationResult> {
+    generator_classes(config)?;
+
+    let mut failed = Vec::new();
+
+    let actual = password.chars().count();
+    if actual < config.length {
+        failed.push(RuleViolation::TooShort {
+            min: config.length,
+            actual,
+        });
+    }
+
+    // 自定义字符集不要求包含各类字符
+    let required = config.custom_alphabet.is_none();
+    let has = |f: fn(&char) -> bool| password.chars().any(|c| f(&c));
+    if required && config.require_uppercase && !has(|c| c.is_uppercase()) {
+        failed.push(RuleViolation::MissingUppercase);
+    }
+    if required && config.require_lowercase && !has(|c| c.is_lowercase()) {
+        failed.push(RuleViolation::MissingLowercase);
+    }
+    if required && config.require_numbers && !has(|c| c.is_numeric()) {
+        failed.push(RuleViolation::MissingNumber);
+    }
+    if required && config.require_symbols && !has(|c| !c.is_alphanumeric() && !c.is_whitespace()) {
+        failed.push(RuleViolation::MissingSymbol);
     }
 
-    // 处理排除字符
-    let mut filtered_chars = available_chars.clone();
-    if let Some(exclude) = &config.exclude_chars {
-        for exclude_char in exclude.chars() {
-            filtered_chars = filtered_chars.replace(exclude_char, "");
+    let exclude = config.exclude_chars.as_deref().unwrap_or_default();
+    let mut excluded = String::new();
+    for c in password.chars().filter(|c| exclude.contains(*c)) {
+        if !excluded.contains(c) {
+            excluded.push(c);
         }
     }
+    if !excluded.is_empty() {
+        failed.push(RuleViolation::ExcludedChars { chars: excluded });
+    }
 
-    // 如果过滤后没有可用字符，返回错误
-    if filtered_chars.is_empty() {
-        return Err(anyhow!("排除字符后没有可用字符"));
+    if config.no_consecutive_repeats {
+        let chars: Vec<char> = password.chars().collect();
+        if let Some(pair) = chars.windows(2).find(|w| w[0] == w[1]) {
+            failed.push(RuleViolation::ConsecutiveRepeat { ch: pair[0] });
+        }
     }
 
-    // 生成随机密码
-    let mut password_chars = Vec::new();
+    Ok(ValidationResult {
+        valid: failed.is_empty(),
+        failed,
+    })
+}
+
+/// 解析网站的密码策略，得到对应的生成配置
+///
+/// 策略由 `;` 分隔的若干项组成，未提到的项沿用默认配置，例如
+/// `len=16;upper;lower;digit;nosymbol;exclude=O0`：
+///
+/// * `len=N` 或 `len=MIN-MAX` - 密码长度，给出范围时取最大值
+/// * `upper`、`lower`、`digit`、`symbol` - 要求包含该类字符，
+///   加 `no` 前缀（如 `nosymbol`）表示不使用该类字符
+/// * `exclude=CHARS` - 排除的字符
+/// * `norepeat` - 相邻字符不能相同
+///
+/// 无法识别的项返回错误，错误信息中包含该项的位置和内容
+pub fn parse_policy(policy: &str) -> Result<PasswordGeneratorConfig> {
+    let mut config = PasswordGeneratorConfig::default();
 
-    // 首先添加必需的字符
-    password_chars.extend(&required_chars);
+    for (index, token) in policy.split(';').enumerate() {
+        let token = token.trim();
+        if token.is_empty() {
+            continue;
+        }
+        let invalid =
+            |reason: &str| anyhow!("策略第 {} 项 `{}` 无效：{}", index + 1, token, reason);
 
-    // 计算还需要多少字符
-    let remaining_length = config.length.saturating_sub(required_chars.len());
+        let (name, value) = match token.split_once('=') {
+            Some((name, value)) => (name.trim().to_ascii_lowercase(), Some(value.trim())),
+            None => (token.to_ascii_lowercase(), None),
+        };
 
-    // 添加剩余的随机字符
-    for _ in 0..remaining_length {
-        password_chars.push(get_random_char(&filtered_chars));
+        match (name.as_str(), value) {
+            ("len" | "length", Some(value)) => {
+                let parse = |n: &str| {
+                    n.trim()
+                        .parse::<usize>()
+                        .ok()
+                        .filter(|n| *n > 0)
+                        .ok_or_else(|| invalid("长度必须是正整数"))
+                };
+                config.length = match value.split_once('-') {
+                    Some((min, max)) => {
+                        let (min, max) = (parse(min)?, parse(max)?);
+                        if min > max {
+                            return Err(invalid("最小长度大于最大长度"));
+                        }
+                        max
+                    }
+                    None => parse(value)?,
+                };
+            }
+            ("exclude", Some(value)) => {
+                if value.is_empty() {
+                    return Err(invalid("没有给出要排除的字符"));
+                }
+                config.exclude_chars = Some(value.to_string());
+            }
+            ("len" | "length" | "exclude", None) => return Err(invalid("缺少 `=` 后的值")),
+            (flag, None) => {
+                let (enabled, class) = match flag.strip_prefix("no") {
+                    Some(class) => (false, class),
+                    None => (true, flag),
+                };
+                match class {
+                    "upper" => config.require_uppercase = enabled,
+                    "lower" => config.require_lowercase = enabled,
+                    "digit" => config.require_numbers = enabled,
+                    "symbol" => config.require_symbols = enabled,
+                    "repeat" if !enabled => config.no_consecutive_repeats = true,
+                    _ => return Err(invalid("未知的选项")),
+                }
+            }
+            (_, Some(_)) => return Err(invalid("未知的选项")),
+        }
     }
 
-    // 打乱字符顺序以增加随机性
-    shuffle_chars(&mut password_chars);
+    Ok(config)
+}
 
-    // 组合成最终密码
-    let password: String = password_chars.into_iter().collect();
+/// 低于该熵值（比特）的密码视为弱密码
+pub const WEAK_ENTROPY_BITS: f64 = 50.0;
 
-    Ok(password)
-}
+/// 粗略估算密码的熵（比特）
+///
+/// 按出现的字符类型确定字符集大小，每个字符贡献 log2(字符集大小)；
+/// 与前一个字符相同的字符不计入，如 "aaaa" 只相当于一个字符
+pub fn estimate_strength(password: &str) -> f64 {
+    let chars: Vec<char> = password.chars().collect();
 
-/// 从字符串中随机选择一个字符
-fn get_random_char(chars: &str) -> char {
-    use std::time::{SystemTime, UNIX_EPOCH};
+    let has = |f: fn(&char) -> bool| chars.iter().any(f);
+    let pool = [
+        (has(|c| c.is_ascii_lowercase()), 26),
+        (has(|c| c.is_ascii_uppercase()), 26),
+        (has(|c| c.is_ascii_digit()), 10),
+        (has(|c| c.is_ascii_punctuation() || *c == ' '), 33),
+        (has(|c| !c.is_ascii()), 100),
+    ]
+    .into_iter()
+    .filter(|(present, _)| *present)
+    .map(|(_, size)| size)
+    .sum::<u32>();
 
-    let seed = SystemTime::now()
-        .duration_since(UNIX_EPOCH)
-        .unwrap()
-        .as_nanos() as u64;
+    if pool == 0 {
+        return 0.0;
+    }
 
-    let rng = simple_rng(seed);
-    let index = rng % chars.len() as u64;
-    chars.chars().nth(index as usize).unwrap_or('a')
+    let effective = 1 + chars.windows(2).filter(|w| w[0] != w[1]).count();
+    effective as f64 * f64::from(pool).log2()
 }
 
-/// 简单的线性同余随机数生成器
-fn simple_rng(mut seed: u64) -> u64 {
-    seed = seed.wrapping_mul(1103515245).wrapping_add(12345);
-    seed % (1u64 << 31)
+// 可读密码的音节组成：辅音（组合）+ 元音（组合）
+const CONSONANTS: &[&str] = &[
+    "b", "c", "d", "f", "g", "h", "j", "k", "l", "m", "n", "p", "r", "s", "t", "v", "w", "z", "br",
+    "ch", "cr", "dr", "fl", "gr", "pl", "pr", "sh", "st", "th", "tr",
+];
+const VOWELS: &[&str] = &["a", "e", "i", "o", "u", "ai", "ea", "ie", "oo", "ou"];
+
+/// 生成由音节组成、便于朗读和输入的密码
+///
+/// 辅音和元音交替出现，长度恰好为 `length`。`capitalize` 为true时首字母大写，
+/// `with_digit` 为true时在末尾加一位数字，用于满足密码策略
+pub fn generate_pronounceable(length: usize, capitalize: bool, with_digit: bool) -> Result<String> {
+    let min_length = if with_digit { 2 } else { 1 };
+    if length < min_length {
+        return Err(anyhow!("密码长度至少为 {}", min_length));
+    }
+
+    let mut rng = OsRng.unwrap_err();
+    let letters = length - usize::from(with_digit);
+
+    let mut password = String::new();
+    while password.len() < letters {
+        password.push_str(CONSONANTS.choose(&mut rng).unwrap());
+        password.push_str(VOWELS.choose(&mut rng).unwrap());
+    }
+    // 只包含ASCII字符，可以按字节截断
+    password.truncate(letters);
+
+    if capitalize {
+        password[..1].make_ascii_uppercase();
+    }
+    if with_digit {
+        password.push(char::from(b'0' + rng.random_range(0..10)));
+    }
+
+    Ok(password)
 }
 
-/// 打乱字符数组
-fn shuffle_chars(chars: &mut [char]) {
-    use std::time::{SystemTime, UNIX_EPOCH};
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn all_classes(length: usize) -> PasswordGeneratorConfig {
+        PasswordGeneratorConfig {
+            length,
+            ..Default::default()
+        }
+    }
+
+    fn has_all_classes(password: &str) -> bool {
+        password.chars().any(|c| c.is_ascii_lowercase())
+            && password.chars().any(|c| c.is_ascii_uppercase())
+            && password.chars().any(|c| c.is_ascii_digit())
+            && password.chars().any(|c| c.is_ascii_punctuation())
+    }
+
+    #[test]
+    fn length_shorter_than_required_classes_errors() {
+        assert!(generate_password(&all_classes(2)).is_err());
+        assert!(generate_password(&all_classes(0)).is_err());
+    }
+
+    #[test]
+    fn minimum_length_contains_every_class() {
+        for _ in 0..200 {
+            let password = generate_password(&all_classes(4)).unwrap();
+            assert_eq!(password.chars().count(), 4);
+            assert!(has_all_classes(&password), "{}", password);
+        }
+    }
+
+    #[test]
+    fn exact_length_and_excluded_chars() {
+        let config = PasswordGeneratorConfig {
+            length: 32,
+            exclude_chars: Some("O0l1".to_string()),
+            ..Default::default()
+        };
+        for _ in 0..50 {
+            let password = generate_password(&config).unwrap();
+            assert_eq!(password.chars().count(), 32);
+            assert!(has_all_classes(&password), "{}", password);
+            assert!(!password.contains(['O', '0', 'l', '1']), "{}", password);
+        }
+    }
+
+    #[test]
+    fn no_consecutive_repeats() {
+        // 字符集越小越容易出现重复
+        let config = PasswordGeneratorConfig {
+            length: 64,
+            exclude_chars: Some("2345678".to_string()),
+            require_uppercase: false,
+            require_lowercase: false,
+            require_numbers: true,
+            require_symbols: false,
+            no_consecutive_repeats: true,
+            custom_symbols: None,
+            custom_alphabet: None,
+        };
+        for _ in 0..500 {
+            let password: Vec<char> = generate_password(&config).unwrap().chars().collect();
+            assert_eq!(password.len(), 64);
+            assert!(
+                password.windows(2).all(|w| w[0] != w[1]),
+                "{}",
+                password.iter().collect::<String>()
+            );
+        }
+
+        let config = PasswordGeneratorConfig {
+            length: 4,
+            no_consecutive_repeats: true,
+            ..Default::default()
+        };
+        for _ in 0..200 {
+            let password = generate_password(&config).unwrap();
+            assert!(has_all_classes(&password), "{}", password);
+        }
+    }
+
+    #[test]
+    fn no_consecutive_repeats_with_single_char_errors() {
+        let config = PasswordGeneratorConfig {
+            length: 8,
+            exclude_chars: Some("012345678".to_string()),
+            require_uppercase: false,
+            require_lowercase: false,
+            require_numbers: true,
+            require_symbols: false,
+            no_consecutive_repeats: true,
+            custom_symbols: None,
+            custom_alphabet: None,
+        };
+        assert!(generate_password(&config).is_err());
+    }
+
+    #[test]
+    fn strength_estimate() {
+        assert_eq!(estimate_strength(""), 0.0);
+        assert_eq!(estimate_strength("aaaaaaaa"), estimate_strength("a"));
+        assert!(estimate_strength("password") < WEAK_ENTROPY_BITS);
+        assert!(estimate_strength("123456") < WEAK_ENTROPY_BITS);
+        assert!(estimate_strength("x7#Lq9!vR2@m") > WEAK_ENTROPY_BITS);
+
+        let generated = generate_password(&all_classes(16)).unwrap();
+        assert!(estimate_strength(&generated) > WEAK_ENTROPY_BITS);
+    }
+
+    #[test]
+    fn generate_passwords_are_distinct() {
+        let passwords = generate_passwords(&all_classes(12), 20).unwrap();
+        assert_eq!(passwords.len(), 20);
+        for password in &passwords {
+            assert_eq!(password.chars().count(), 12);
+            assert!(has_all_classes(password), "{}", password);
+        }
+        let mut unique = passwords.clone();
+        unique.sort();
+        unique.dedup();
+        assert_eq!(unique.len(), 20);
+
+        assert!(generate_passwords(&all_classes(12), MAX_GENERATE_COUNT + 1).is_err());
+
+        // 只有两种可能的密码
+        let config = PasswordGeneratorConfig {
+            length: 1,
+            custom_alphabet: Some("ab".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(generate_passwords(&config, 2).unwrap().len(), 2);
+        assert!(generate_passwords(&config, 3).is_err());
+    }
+
+    #[test]
+    fn custom_symbols_replace_builtin_symbols() {
+        let config = PasswordGeneratorConfig {
+            length: 32,
+            custom_symbols: Some("-_.".to_string()),
+            ..Default::default()
+        };
+        for _ in 0..200 {
+            let password = generate_password(&config).unwrap();
+            assert!(has_all_classes(&password), "{}", password);
+            assert!(
+                password
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || "-_.".contains(c)),
+                "{}",
+                password
+            );
+        }
 
-    let seed = SystemTime::now()
-        .duration_since(UNIX_EPOCH)
-        .unwrap()
-        .as_nanos() as u64;
+        // 排除后没有可用的符号
+        let config = PasswordGeneratorConfig {
+            exclude_chars: Some("-_.".to_string()),
+            ..config
+        };
+        assert!(generate_password(&config).is_err());
+    }
+
+    #[test]
+    fn custom_alphabet_replaces_all_classes() {
+        let config = PasswordGeneratorConfig {
+            length: 20,
+            custom_alphabet: Some("ab c".to_string()),
+            exclude_chars: Some("c".to_string()),
+            ..Default::default()
+        };
+        for _ in 0..100 {
+            let password = generate_password(&config).unwrap();
+            assert_eq!(password.chars().count(), 20);
+            assert!(password.chars().all(|c| "ab ".contains(c)), "{}", password);
+        }
+
+        // 不要求包含各类字符
+        let result = validate_against_config(&"ab ".repeat(7), &config).unwrap();
+        assert!(result.valid, "{:?}", result.failed);
+
+        let config = PasswordGeneratorConfig {
+            custom_alphabet: Some(String::new()),
+            ..config
+        };
+        assert!(generate_password(&config).is_err());
+    }
+
+    #[test]
+    fn pronounceable_length_and_chars() {
+        for length in 1..40 {
+            let password = generate_pronounceable(length, false, false).unwrap();
+            assert_eq!(password.len(), length);
+            assert!(
+                password.chars().all(|c| c.is_ascii_lowercase()),
+                "{}",
+                password
+            );
+        }
+
+        for length in 2..40 {
+            let password = generate_pronounceable(length, true, true).unwrap();
+            let chars: Vec<char> = password.chars().collect();
+            assert_eq!(chars.len(), length);
+            assert!(chars[0].is_ascii_uppercase(), "{}", password);
+            assert!(chars[length - 1].is_ascii_digit(), "{}", password);
+            assert!(
+                chars[1..length - 1].iter().all(|c| c.is_ascii_lowercase()),
+                "{}",
+                password
+            );
+        }
+    }
+
+    #[test]
+    fn pronounceable_alternates_syllables() {
+        let password = generate_pronounceable(64, false, false).unwrap();
+        // 辅音和元音组合最长两个字母，不会出现三个连续的辅音或元音
+        let is_vowel = |c: char| "aeiou".contains(c);
+        let chars: Vec<char> = password.chars().collect();
+        assert!(
+            chars
+                .windows(3)
+                .all(|w| !w.iter().all(|c| is_vowel(*c)) && !w.iter().all(|c| !is_vowel(*c))),
+            "{}",
+            password
+        );
+    }
+
+    #[test]
+    fn pronounceable_rejects_too_short() {
+        assert!(generate_pronounceable(0, false, false).is_err());
+        assert!(generate_pronounceable(1, false, true).is_err());
+    }
+
+    #[test]
+    fn fully_excluded_class_errors() {
+        let config = PasswordGeneratorConfig {
+            length: 16,
+            exclude_chars: Some("0123456789".to_string()),
+            ..Default::default()
+        };
+        assert!(generate_password(&config).is_err());
+    }
+
+    #[test]
+    fn parses_policies() {
+        let config = parse_policy("len=16;upper;lower;digit;nosymbol;exclude=O0").unwrap();
+        assert_eq!(
+            config,
+            PasswordGeneratorConfig {
+                length: 16,
+                exclude_chars: Some("O0".to_string()),
+                require_symbols: false,
+                ..Default::default()
+            }
+        );
+
+        // 范围取最大值，空白和多余的分号被忽略
+        let config = parse_policy(" len = 12-20 ; NoUpper; norepeat;").unwrap();
+        assert_eq!(config.length, 20);
+        assert!(!config.require_uppercase);
+        assert!(config.no_consecutive_repeats);
+
+        // 空策略即默认配置
+        assert_eq!(
+            parse_policy("").unwrap(),
+            PasswordGeneratorConfig::default()
+        );
+
+        let password =
+            generate_password(&parse_policy("len=12;nosymbol;exclude=O0").unwrap()).unwrap();
+        assert_eq!(password.chars().count(), 12);
+        assert!(password.chars().all(|c| c.is_ascii_alphanumeric()));
+        assert!(!password.contains(['O', '0']));
+    }
+
+    #[test]
+    fn malformed_policy_points_at_token() {
+        let cases = [
+            ("len=16;uppr", "第 2 项 `uppr`"),
+            ("len=abc", "第 1 项 `len=abc`"),
+            ("len=0", "第 1 项 `len=0`"),
+            ("upper;len=20-12", "第 2 项 `len=20-12`"),
+            ("len", "第 1 项 `len`"),
+            ("lower;exclude=", "第 2 项 `exclude=`"),
+            ("digit=1", "第 1 项 `digit=1`"),
+            ("repeat", "第 1 项 `repeat`"),
+        ];
+        for (policy, expected) in cases {
+            let error = parse_policy(policy).unwrap_err().to_string();
+            assert!(error.contains(expected), "{}: {}", policy, error);
+        }
+    }
+
+    #[test]
+    fn validation_lists_failed_rules() {
+        let config = PasswordGeneratorConfig {
+            length: 8,
+            ..Default::default()
+        };
+        let result = validate_against_config("Abcdefg1!", &config).unwrap();
+        assert_eq!(
+            result,
+            ValidationResult {
+                valid: true,
+                failed: vec![]
+            }
+        );
+
+        // 缺少特殊符号且长度不足
+        let result = validate_against_config("Abc123", &config).unwrap();
+        assert!(!result.valid);
+        assert_eq!(
+            result.failed,
+            [
+                RuleViolation::TooShort { min: 8, actual: 6 },
+                RuleViolation::MissingSymbol,
+            ]
+        );
+    }
 
-    let mut rng = simple_rng(seed);
+    #[test]
+    fn validation_reports_excluded_chars() {
+        let config = PasswordGeneratorConfig {
+            length: 8,
+            exclude_chars: Some("O0l1".to_string()),
+            no_consecutive_repeats: true,
+            ..Default::default()
+        };
+        let result = validate_against_config("Passw0rd!!1O0", &config).unwrap();
+        assert_eq!(
+            result.failed,
+            [
+                RuleViolation::ExcludedChars {
+                    chars: "01O".to_string()
+                },
+                RuleViolation::ConsecutiveRepeat { ch: 's' },
+            ]
+        );
+        assert_eq!(
+            serde_json::to_value(&result.failed[0]).unwrap(),
+            serde_json::json!({ "rule": "excluded_chars", "chars": "01O" })
+        );
 
-    // Fisher-Yates 洗牌算法
-    for i in (1..chars.len()).rev() {
-        rng = simple_rng(rng);
-        let j = (rng % (i as u64 + 1)) as usize;
-        chars.swap(i, j);
+        // 配置本身无效
+        let invalid = PasswordGeneratorConfig {
+            require_uppercase: false,
+            require_lowercase: false,
+            require_numbers: false,
+            require_symbols: false,
+            ..Default::default()
+        };
+        assert!(validate_against_config("anything", &invalid).is_err());
     }
 }
@@ -0,0 +1,113 @@
+use anyhow::Result;
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use crate::store::{StorageData, StorageId};
+
+/// 待同步队列的文件名，与数据文件放在同一目录
+const PENDING_FILE_NAME: &str = "pending.json";
+
+/// 数据文件所在目录下的待同步队列路径
+pub fn pending_path(data_path: &Path) -> PathBuf {
+    data_path.with_file_name(PENDING_FILE_NAME)
+}
+
+/// 保存失败的存储点及其应当写入的数据，保存在磁盘上，重启后仍然可以重试
+///
+/// 每个存储点只保留最近一次失败时的数据；数据已经加密，与数据文件的敏感程度相同
+pub struct PendingQueue {
+    path: PathBuf,
+    // 读取-修改-写入期间不能被其它操作打断
+    write_lock: Mutex<()>,
+}
+
+impl PendingQueue {
+    pub fn new(path: PathBuf) -> Self {
+        Self {
+            path,
+            write_lock: Mutex::new(()),
+        }
+    }
+
+    /// 队列中的所有存储点，文件不存在时返回空
+    pub fn load(&self) -> Result<HashMap<StorageId, StorageData>> {
+        let _guard = self.write_lock.lock().unwrap();
+        self.read()
+    }
+
+    /// 记录存储点应当写入的数据，替换之前记录的数据
+    pub fn record(&self, id: &StorageId, data: &StorageData) -> Result<()> {
+        let _guard = self.write_lock.lock().unwrap();
+        let mut pending = self.read()?;
+        pending.insert(id.clone(), data.clone());
+        self.write(&pending)
+    }
+
+    /// 移除存储点，没有记录时什么也不做
+    pub fn remove(&self, id: &StorageId) -> Result<()> {
+        let _guard = self.write_lock.lock().unwrap();
+        let mut pending = self.read()?;
+        if pending.remove(id).is_some() {
+            self.write(&pending)?;
+        }
+        Ok(())
+    }
+
+    fn read(&self) -> Result<HashMap<StorageId, StorageData>> {
+        match fs::read_to_string(&self.path) {
+            Ok(content) => Ok(serde_json::from_str(&content)?),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(HashMap::new()),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    // 队列为空时删除文件；否则先写临时文件再替换，避免写入中途崩溃损坏队列
+    fn write(&self, pending: &HashMap<StorageId, StorageData>) -> Result<()> {
+        if pending.is_empty() {
+            return match fs::remove_file(&self.path) {
+                Err(e) if e.kind() != io::ErrorKind::NotFound => Err(e.into()),
+                _ => Ok(()),
+            };
+        }
+
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let temp_path = self.path.with_extension("json.tmp");
+        fs::write(&temp_path, serde_json::to_vec(pending)?)?;
+        fs::rename(&temp_path, &self.path)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::store::mock_store;
+
+    #[test]
+    fn record_replaces_and_remove_deletes_file() {
+        let path = mock_store::temp_dir().join(PENDING_FILE_NAME);
+        let queue = PendingQueue::new(path.clone());
+        assert!(queue.load().unwrap().is_empty());
+
+        let github = StorageId::from("github");
+        let mut data = StorageData::new();
+        queue.record(&github, &data).unwrap();
+        data.metadata.revision = 3;
+        queue.record(&github, &data).unwrap();
+
+        // 重新打开后仍然存在，只保留最近一次记录
+        let pending = PendingQueue::new(path.clone()).load().unwrap();
+        assert_eq!(pending.len(), 1);
+        assert_eq!(pending[&github].metadata.revision, 3);
+
+        queue.remove(&StorageId::from("missing")).unwrap();
+        queue.remove(&github).unwrap();
+        assert!(!path.exists());
+        assert!(queue.load().unwrap().is_empty());
+    }
+}
@@ -0,0 +1,121 @@
+use anyhow::Result;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use crate::error::PasswordError;
+
+/// 明文在服务端保留的时间，超时后即使没有取走也会被删除
+pub const REVEAL_TTL: Duration = Duration::from_secs(30);
+
+/// 取走明文用的一次性凭据，本身不包含任何明文
+#[derive(Debug, Clone, Serialize)]
+pub struct RevealToken {
+    pub token: String,
+    /// 多少秒后过期
+    pub expires_in_secs: u64,
+}
+
+struct Revealed {
+    plaintext: String,
+    expires_at: Instant,
+}
+
+/// 暂存已解密的明文，凭一次性token取走
+///
+/// 明文不直接通过IPC返回，避免被webview记录或缓存；取走一次或超时后删除
+pub struct RevealStore {
+    entries: Arc<Mutex<HashMap<String, Revealed>>>,
+    ttl: Duration,
+}
+
+impl RevealStore {
+    pub fn new(ttl: Duration) -> Self {
+        Self {
+            entries: Arc::new(Mutex::new(HashMap::new())),
+            ttl,
+        }
+    }
+
+    /// 暂存明文，返回取走用的token，超时后由后台任务删除
+    pub fn insert(&self, plaintext: String) -> RevealToken {
+        let token = uuid::Uuid::new_v4().to_string();
+        self.entries.lock().unwrap().insert(
+            token.clone(),
+            Revealed {
+                plaintext,
+                expires_at: Instant::now() + self.ttl,
+            },
+        );
+
+        let entries = Arc::clone(&self.entries);
+        let expired = token.clone();
+        let ttl = self.ttl;
+        tokio::spawn(async move {
+            tokio::time::sleep(ttl).await;
+            entries.lock().unwrap().remove(&expired);
+        });
+
+        RevealToken {
+            token,
+            expires_in_secs: self.ttl.as_secs(),
+        }
+    }
+
+    /// 取走明文并删除；token不存在、已被取走或已过期时返回 `NotFound`
+    pub fn take(&self, token: &str) -> Result<String> {
+        self.entries
+            .lock()
+            .unwrap()
+            .remove(token)
+            .filter(|revealed| Instant::now() < revealed.expires_at)
+            .map(|revealed| revealed.plaintext)
+            .ok_or_else(|| PasswordError::NotFound("凭据无效或已过期".to_string()).into())
+    }
+
+    /// 删除所有暂存的明文，锁定时调用
+    pub fn clear(&self) {
+        self.entries.lock().unwrap().clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn token_can_be_consumed_once() {
+        let store = RevealStore::new(REVEAL_TTL);
+        let revealed = store.insert("s3cret".to_string());
+        assert_eq!(revealed.expires_in_secs, REVEAL_TTL.as_secs());
+
+        assert_eq!(store.take(&revealed.token).unwrap(), "s3cret");
+        assert!(store.take(&revealed.token).is_err());
+    }
+
+    #[tokio::test]
+    async fn unknown_and_cleared_tokens_are_rejected() {
+        let store = RevealStore::new(REVEAL_TTL);
+        let err = store.take("not-a-token").unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<PasswordError>(),
+            Some(PasswordError::NotFound(_))
+        ));
+
+        let revealed = store.insert("s3cret".to_string());
+        store.clear();
+        assert!(store.take(&revealed.token).is_err());
+    }
+
+    #[tokio::test]
+    async fn token_expires_after_ttl() {
+        let store = RevealStore::new(Duration::from_millis(20));
+        let revealed = store.insert("s3cret".to_string());
+
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        // 后台任务已删除明文
+        assert!(store.entries.lock().unwrap().is_empty());
+        assert!(store.take(&revealed.token).is_err());
+    }
+}
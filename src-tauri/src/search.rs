@@ -0,0 +1,413 @@
+use anyhow::{Result, anyhow};
+use serde::Serialize;
+use unicode_normalization::UnicodeNormalization;
+use unicode_normalization::char::is_combining_mark;
+
+use crate::password::Password;
+
+/// 低于该分数的结果会被丢弃
+pub const MIN_SCORE: f64 = 0.5;
+
+/// 带相关度分数的搜索结果，分数在 0~1 之间，越大越相关
+#[derive(Debug, Clone, Serialize)]
+pub struct ScoredPassword {
+    pub password: Password,
+    pub score: f64,
+}
+
+/// 匹配到查询的字段
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MatchField {
+    Title,
+    Description,
+}
+
+/// 带匹配位置的搜索结果，前端据此高亮匹配的文字
+#[derive(Debug, Clone, Serialize)]
+pub struct SearchHit {
+    pub password: Password,
+    pub field: MatchField,
+    /// 查询在该字段中的字节范围，左闭右开
+    pub range: (usize, usize),
+}
+
+/// 搜索的字段范围，可以用 `|` 组合，如 `SearchScope::TITLE | SearchScope::URL`
+///
+/// 默认为标题和描述，与 `search_passwords` 一致
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SearchScope(u8);
+
+impl SearchScope {
+    pub const TITLE: Self = Self(1);
+    pub const DESCRIPTION: Self = Self(1 << 1);
+    pub const USERNAME: Self = Self(1 << 2);
+    pub const URL: Self = Self(1 << 3);
+    pub const TAGS: Self = Self(1 << 4);
+
+    /// 按字段名（`title`、`description`、`username`、`url`、`tags`）组合范围，
+    /// 忽略大小写；字段名未知或列表为空时报错
+    pub fn from_names<S: AsRef<str>>(names: &[S]) -> Result<Self> {
+        if names.is_empty() {
+            return Err(anyhow!("搜索范围不能为空"));
+        }
+
+        names.iter().try_fold(Self(0), |scope, name| {
+            let field = match name.as_ref().trim().to_lowercase().as_str() {
+                "title" => Self::TITLE,
+                "description" => Self::DESCRIPTION,
+                "username" => Self::USERNAME,
+                "url" => Self::URL,
+                "tags" => Self::TAGS,
+                other => return Err(anyhow!("未知的搜索字段：{}", other)),
+            };
+            Ok(scope | field)
+        })
+    }
+
+    pub fn contains(self, other: Self) -> bool {
+        self.0 & other.0 == other.0
+    }
+
+    /// 范围内是否有字段包含 `query`，`query` 须已用 [`normalize_for_search`] 归一化
+    pub fn matches(self, password: &Password, query: &str) -> bool {
+        let has = |text: &str| normalize_for_search(text).contains(query);
+
+        (self.contains(Self::TITLE) && has(&password.title))
+            || (self.contains(Self::DESCRIPTION) && has(&password.description))
+            || (self.contains(Self::USERNAME) && has(&password.username))
+            || (self.contains(Self::URL) && password.url.as_deref().is_some_and(has))
+            || (self.contains(Self::TAGS) && password.tags.iter().any(|tag| has(tag)))
+    }
+}
+
+impl Default for SearchScope {
+    fn default() -> Self {
+        Self::TITLE | Self::DESCRIPTION
+    }
+}
+
+impl std::ops::BitOr for SearchScope {
+    type Output = Self;
+
+    fn bitor(self, rhs: Self) -> Self {
+        Self(self.0 | rhs.0)
+    }
+}
+
+/// 查找条目中第一个包含查询的字段，规则与 `search_passwords` 相同：
+/// 依次检查标题和描述，比较前用 [`normalize_for_search`] 归一化
+///
+/// 返回的范围对应原文，例如在 `José` 中搜索 `jose` 得到整个 `José` 的范围
+pub fn find_match(password: &Password, query: &str) -> Option<(MatchField, (usize, usize))> {
+    let query = normalize_for_search(query);
+    [
+        (MatchField::Title, &password.title),
+        (MatchField::Description, &password.description),
+    ]
+    .into_iter()
+    .find_map(|(field, text)| find_normalized(text, &query).map(|range| (field, range)))
+}
+
+/// 归一化文本用于搜索比较：NFKD分解、去掉组合附加符号、转为小写
+///
+/// 因此 `José` 与 `jose`、全角的 `ＧｉｔＨｕｂ` 与 `github` 都视为相同。
+/// 土耳其语的 `İ` 分解为 `I` 加附加点，归一化为 `i`；
+/// 无点的 `ı` 是独立的字母，不会与 `i` 匹配
+pub fn normalize_for_search(s: &str) -> String {
+    s.chars().flat_map(normalize_char).collect()
+}
+
+// 单个字符的归一化结果；组合附加符号会被去掉，所以逐字符分解与整体分解的结果相同
+fn normalize_char(c: char) -> impl Iterator<Item = char> {
+    std::iter::once(c)
+        .nfkd()
+        .filter(|c| !is_combining_mark(*c))
+        .flat_map(char::to_lowercase)
+}
+
+// 在归一化后的 `text` 中查找已归一化的 `query`，返回对应原文的字节范围
+fn find_normalized(text: &str, query: &str) -> Option<(usize, usize)> {
+    if query.is_empty() {
+        return Some((0, 0));
+    }
+
+    // 归一化后每个字节对应的原文字符范围
+    let mut normalized = String::new();
+    let mut origins = Vec::new();
+    for (start, c) in text.char_indices() {
+        let origin = (start, start + c.len_utf8());
+        for n in normalize_char(c) {
+            normalized.push(n);
+            origins.extend(std::iter::repeat_n(origin, n.len_utf8()));
+        }
+    }
+
+    let start = normalized.find(query)?;
+    let mut end = origins[start + query.len() - 1].1;
+    // 紧跟的组合附加符号归一化后为空，也算在匹配范围内，避免高亮时把附加符号分开
+    for c in text[end..].chars() {
+        if normalize_char(c).next().is_some() {
+            break;
+        }
+        end += c.len_utf8();
+    }
+    Some((origins[start].0, end))
+}
+
+/// 计算条目与查询的相关度，取标题和描述中较高的分数
+pub fn score_password(password: &Password, query: &str, fuzzy: bool) -> f64 {
+    // 描述的权重略低于标题
+    let title = score(&password.title, query, fuzzy);
+    let description = score(&password.description, query, fuzzy) * 0.9;
+    title.max(description)
+}
+
+/// 计算文本与查询的相关度（比较前用 [`normalize_for_search`] 归一化）
+///
+/// 完全相同为1，包含查询为 0.8~0.95；开启 `fuzzy` 时再考虑
+/// 子序列匹配（如 gthub 之于 github）和编辑距离，最高 0.8
+pub fn score(text: &str, query: &str, fuzzy: bool) -> f64 {
+    let text = normalize_for_search(text.trim());
+    let query = normalize_for_search(query.trim());
+    if text.is_empty() || query.is_empty() {
+        return 0.0;
+    }
+
+    if text == query {
+        return 1.0;
+    }
+
+    let text_len = text.chars().count();
+    let query_len = query.chars().count();
+
+    if text.contains(&query) {
+        // 查询占文本的比例越大越相关
+        return 0.8 + 0.15 * query_len as f64 / text_len as f64;
+    }
+
+    if !fuzzy {
+        return 0.0;
+    }
+
+    let subsequence = if is_subsequence(&query, &text) {
+        0.8 * query_len as f64 / text_len as f64
+    } else {
+        0.0
+    };
+
+    let max_len = text_len.max(query_len) as f64;
+    let similarity = 1.0 - levenshtein(&text, &query) as f64 / max_len;
+
+    (0.8 * similarity).max(subsequence)
+}
+
+// query 的字符是否按顺序出现在 text 中
+fn is_subsequence(query: &str, text: &str) -> bool {
+    let mut text = text.chars();
+    query.chars().all(|q| text.any(|t| t == q))
+}
+
+// 编辑距离
+fn levenshtein(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut prev: Vec<usize> = (0..=b.len()).collect();
+    let mut cur = vec![0; b.len() + 1];
+
+    for (i, ca) in a.chars().enumerate() {
+        cur[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let cost = usize::from(ca != *cb);
+            cur[j + 1] = (prev[j] + cost).min(prev[j + 1] + 1).min(cur[j] + 1);
+        }
+        std::mem::swap(&mut prev, &mut cur);
+    }
+
+    prev[b.len()]
+}
+
+/// 把URL归一化为用于匹配的主机名
+///
+/// 去掉协议、用户信息、端口、路径和结尾的斜杠，主机名转为小写并去掉 `www.` 前缀，
+/// 例如 `https://www.Example.com/login` 归一化为 `example.com`。没有主机名时返回 `None`
+pub fn normalize_url(url: &str) -> Option<String> {
+    let url = url.trim();
+    let rest = match url.find("://") {
+        Some(i) => &url[i + 3..],
+        None => url,
+    };
+    let authority = rest.split(['/', '?', '#']).next().unwrap_or_default();
+    let host = authority.rsplit('@').next().unwrap_or_default();
+    let host = match host.rsplit_once(':') {
+        Some((host, port)) if port.chars().all(|c| c.is_ascii_digit()) => host,
+        _ => host,
+    };
+
+    let host = host.trim_end_matches('.').to_lowercase();
+    let host = host.strip_prefix("www.").unwrap_or(&host);
+    if host.is_empty() {
+        None
+    } else {
+        Some(host.to_string())
+    }
+}
+
+/// 条目的URL是否与当前页面的URL匹配
+///
+/// 主机名相同即匹配；开启 `match_subdomains` 时，页面是条目的子域名也匹配，
+/// 如保存在 `example.com` 的条目可用于 `login.example.com`，反之不行
+pub fn url_matches(entry_url: &str, page_url: &str, match_subdomains: bool) -> bool {
+    let (Some(entry), Some(page)) = (normalize_url(entry_url), normalize_url(page_url)) else {
+        return false;
+    };
+
+    entry == page
+        || (match_subdomains
+            && page
+                .strip_suffix(&entry)
+                .is_some_and(|prefix| prefix.ends_with('.')))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::store::mock_store;
+
+    fn password_entry(title: &str, description: &str) -> Password {
+        Password {
+            description: description.to_string(),
+            ..mock_store::password_entry(title)
+        }
+    }
+
+    #[test]
+    fn match_range_points_at_query() {
+        let entry = password_entry("我的 GitHub 账号", "work mail");
+
+        let (field, (start, end)) = find_match(&entry, "GitHub").unwrap();
+        assert_eq!(field, MatchField::Title);
+        assert_eq!(&entry.title[start..end], "GitHub");
+
+        // 标题不匹配时检查描述
+        let (field, (start, end)) = find_match(&entry, "mail").unwrap();
+        assert_eq!(field, MatchField::Description);
+        assert_eq!(&entry.description[start..end], "mail");
+
+        // 多字节字符的范围按字节计算
+        let (_, (start, end)) = find_match(&entry, "账号").unwrap();
+        assert_eq!((start, end), (14, 20));
+        assert_eq!(&entry.title[start..end], "账号");
+
+        assert!(find_match(&entry, "gitlab").is_none());
+    }
+
+    #[test]
+    fn normalizes_accents_width_and_case() {
+        assert_eq!(normalize_for_search("José Müller"), "jose muller");
+        assert_eq!(normalize_for_search("ÅNGSTRÖM"), "angstrom");
+        // 全角字母和数字
+        assert_eq!(normalize_for_search("ＧｉｔＨｕｂ１２３"), "github123");
+        // 土耳其语：İ 去掉附加点后为 i，无点的 ı 保持不变
+        assert_eq!(normalize_for_search("İstanbul"), "istanbul");
+        assert_eq!(normalize_for_search("ılık"), "ılık");
+        assert_ne!(normalize_for_search("ılık"), normalize_for_search("ilik"));
+        // 中文不受影响
+        assert_eq!(normalize_for_search("我的账号"), "我的账号");
+    }
+
+    #[test]
+    fn normalized_match_maps_back_to_original() {
+        let entry = password_entry("Café José", "ＧｉｔＨｕｂ token");
+
+        let (field, (start, end)) = find_match(&entry, "jose").unwrap();
+        assert_eq!(field, MatchField::Title);
+        assert_eq!(&entry.title[start..end], "José");
+
+        let (_, (start, end)) = find_match(&entry, "CAFE").unwrap();
+        assert_eq!(&entry.title[start..end], "Café");
+
+        let (field, (start, end)) = find_match(&entry, "github").unwrap();
+        assert_eq!(field, MatchField::Description);
+        assert_eq!(&entry.description[start..end], "ＧｉｔＨｕｂ");
+
+        // 已分解的附加符号（e + U+0301）同样匹配
+        let decomposed = password_entry("Jose\u{301}", "");
+        let (_, (start, end)) = find_match(&decomposed, "josé").unwrap();
+        assert_eq!((start, end), (0, decomposed.title.len()));
+    }
+
+    #[test]
+    fn levenshtein_distance() {
+        assert_eq!(levenshtein("kitten", "sitting"), 3);
+        assert_eq!(levenshtein("", "abc"), 3);
+        assert_eq!(levenshtein("github", "github"), 0);
+    }
+
+    #[test]
+    fn fuzzy_matches_typo() {
+        assert!(score("GitHub", "gthub", true) >= MIN_SCORE);
+        assert!(score("GitHub", "githbu", true) >= MIN_SCORE);
+        assert_eq!(score("GitHub", "gthub", false), 0.0);
+        assert!(score("GitHub", "zzzz", true) < MIN_SCORE);
+    }
+
+    #[test]
+    fn exact_ranks_above_substring_and_fuzzy() {
+        let exact = score("GitHub", "github", true);
+        let substring = score("GitHub Enterprise", "github", true);
+        let fuzzy = score("GitHub", "gthub", true);
+
+        assert_eq!(exact, 1.0);
+        assert!(exact > substring);
+        assert!(substring > fuzzy);
+    }
+
+    #[test]
+    fn normalizes_urls() {
+        assert_eq!(
+            normalize_url("https://www.Example.com/login").as_deref(),
+            Some("example.com")
+        );
+        assert_eq!(
+            normalize_url("example.com/").as_deref(),
+            Some("example.com")
+        );
+        assert_eq!(
+            normalize_url("http://user:pw@Mail.Example.com:8080/inbox?x=1").as_deref(),
+            Some("mail.example.com")
+        );
+        assert_eq!(normalize_url("https://"), None);
+        assert_eq!(normalize_url(""), None);
+    }
+
+    #[test]
+    fn matches_hosts_and_subdomains() {
+        assert!(url_matches(
+            "example.com",
+            "https://www.Example.com/login",
+            false
+        ));
+        assert!(url_matches(
+            "https://example.com",
+            "https://login.example.com/",
+            true
+        ));
+        assert!(!url_matches(
+            "https://example.com",
+            "https://login.example.com/",
+            false
+        ));
+        // 父域名和相似的域名不匹配
+        assert!(!url_matches(
+            "https://login.example.com",
+            "https://example.com",
+            true
+        ));
+        assert!(!url_matches(
+            "https://example.com",
+            "https://badexample.com",
+            true
+        ));
+        assert!(!url_matches("", "https://example.com", true));
+    }
+}
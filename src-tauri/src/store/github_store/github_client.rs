@@ -1,6 +1,8 @@
 use anyhow::{Result, anyhow};
 use base64::{Engine as _, engine::general_purpose};
+use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
+use std::time::Duration;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct GithubFileContent {
@@ -26,58 +28,346 @@ pub struct GithubCreateUpdateResponse {
     pub commit: serde_json::Value,
 }
 
+/// 提交列表中的一项，只保留需要的字段
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GithubCommit {
+    pub sha: String,
+    pub commit: GithubCommitDetail,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GithubCommitDetail {
+    pub message: String,
+    pub committer: GithubCommitter,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GithubCommitter {
+    pub date: DateTime<Utc>,
+}
+
+/// 条件请求的结果
+#[derive(Debug)]
+pub enum FileResponse {
+    Modified {
+        file: GithubFileContent,
+        etag: Option<String>,
+    },
+    /// 304，文件自上次请求后未变化
+    NotModified,
+}
+
+/// GitHub 请求错误
+#[derive(Debug)]
+pub enum GithubError {
+    /// 请求发送失败或响应无法解析
+    Http(String),
+    /// 文件或仓库不存在（404）
+    NotFound,
+    /// GitHub 返回了其他非成功的状态码
+    Api { status: u16, body: String },
+    /// 连接或请求超时
+    Timeout,
+}
+
+impl GithubError {
+    pub fn status(&self) -> Option<u16> {
+        match self {
+            GithubError::Api { status, .. } => Some(*status),
+            GithubError::NotFound => Some(404),
+            GithubError::Http(_) | GithubError::Timeout => None,
+        }
+    }
+
+    // 把非成功的响应转换为错误
+    async fn check(response: reqwest::Response) -> Result<reqwest::Response, GithubError> {
+        let status = response.status();
+        // 304 只会出现在带 If-None-Match 的请求中，交给调用方处理
+        if status.is_success() || status == reqwest::StatusCode::NOT_MODIFIED {
+            return Ok(response);
+        }
+        if status == reqwest::StatusCode::NOT_FOUND {
+            return Err(GithubError::NotFound);
+        }
+
+        let body = response.text().await.unwrap_or_default();
+        Err(GithubError::Api {
+            status: status.as_u16(),
+            body,
+        })
+    }
+}
+
+impl std::fmt::Display for GithubError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            GithubError::Http(e) => write!(f, "GitHub request failed: {}", e),
+            GithubError::NotFound => write!(f, "GitHub API error (404): Not Found"),
+            GithubError::Api { status, body } => {
+                write!(f, "GitHub API error ({}): {}", status, body)
+            }
+            GithubError::Timeout => write!(f, "GitHub request timed out"),
+        }
+    }
+}
+
+impl std::error::Error for GithubError {}
+
+pub const GITHUB_API_URL: &str = "https://api.github.com";
+
+/// 单个请求（含读取响应）的默认超时时间
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(30);
+/// 建立连接的超时时间，不超过请求的超时时间
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// 请求失败时的重试策略
+#[derive(Debug, Clone)]
+pub struct RetryPolicy {
+    /// 最多尝试的次数（含第一次）
+    pub max_attempts: u32,
+    /// 指数退避的初始间隔
+    pub base_delay: Duration,
+    /// 单次等待的上限
+    pub max_delay: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 3,
+            base_delay: Duration::from_millis(500),
+            max_delay: Duration::from_secs(30),
+        }
+    }
+}
+
+impl RetryPolicy {
+    // 第 attempt 次失败后的退避时间：base * 2^(attempt-1)，不超过上限
+    fn backoff(&self, attempt: u32) -> Duration {
+        let factor = 2u32.saturating_pow(attempt.saturating_sub(1));
+        self.base_delay.saturating_mul(factor).min(self.max_delay)
+    }
+
+    /// 根据响应判断是否需要重试，需要时返回等待时间
+    ///
+    /// - 5xx、429 以及限流导致的 403 会重试
+    /// - 优先使用 `Retry-After`，其次是 `X-RateLimit-Reset`，否则指数退避
+    /// - 其他 4xx（如 401）不重试
+    fn retry_delay(
+        &self,
+        status: reqwest::StatusCode,
+        headers: &reqwest::header::HeaderMap,
+        attempt: u32,
+        now: DateTime<Utc>,
+    ) -> Option<Duration> {
+        let header = |name: &str| headers.get(name).and_then(|v| v.to_str().ok());
+
+        let rate_limited = status == reqwest::StatusCode::FORBIDDEN
+            && (header("x-ratelimit-remaining") == Some("0") || header("retry-after").is_some());
+        let retryable = status.is_server_error()
+            || status == reqwest::StatusCode::TOO_MANY_REQUESTS
+            || rate_limited;
+        if !retryable {
+            return None;
+        }
+
+        let delay = if let Some(secs) = header("retry-after").and_then(|v| v.parse::<u64>().ok()) {
+            Duration::from_secs(secs)
+        } else if let Some(reset) = header("x-ratelimit-reset").and_then(|v| v.parse::<i64>().ok())
+        {
+            Duration::from_secs(reset.saturating_sub(now.timestamp()).max(0) as u64)
+        } else {
+            self.backoff(attempt)
+        };
+
+        Some(delay.min(self.max_delay))
+    }
+}
+
 pub struct GithubClient {
     pub owner: String,
     pub repo: String,
     pub token: String,
     pub branch: String,
     pub client: reqwest::Client,
+    pub retry: RetryPolicy,
+    base_url: String,
 }
 
 impl GithubClient {
-    pub fn new(owner: String, repo: String, token: String, branch: String) -> Self {
-        let client = reqwest::Client::builder()
-            .user_agent("password-manager")
-            .build()
-            .unwrap();
-
+    /// `base_url` 为 API 根地址，如 GitHub Enterprise 的 `https://github.mycorp.com/api/v3`，
+    /// 为 `None` 时使用公共 API
+    pub fn new(
+        owner: String,
+        repo: String,
+        token: String,
+        branch: String,
+        base_url: Option<&str>,
+    ) -> Self {
         Self {
             owner,
             repo,
             token,
             branch,
-            client,
+            client: Self::http_client(DEFAULT_TIMEOUT),
+            retry: RetryPolicy::default(),
+            base_url: base_url
+                .unwrap_or(GITHUB_API_URL)
+                .trim_end_matches('/')
+                .to_string(),
         }
     }
 
-    pub async fn get_file(&self, path: &str) -> Result<GithubFileContent> {
-        let url = format!(
-            "https://api.github.com/repos/{}/{}/contents/{}",
-            self.owner, self.repo, path
-        );
+    /// 设置单个请求的超时时间，连接超时同时不超过该时间
+    pub fn set_timeout(&mut self, timeout: Duration) {
+        self.client = Self::http_client(timeout);
+    }
+
+    fn http_client(timeout: Duration) -> reqwest::Client {
+        reqwest::Client::builder()
+            .user_agent("password-manager")
+            .connect_timeout(CONNECT_TIMEOUT.min(timeout))
+            .timeout(timeout)
+            .build()
+            .unwrap()
+    }
+
+    // 发送请求，按重试策略处理限流、5xx和网络错误
+    // request 每次调用都要构造一个新的请求
+    async fn send(
+        &self,
+        action: &str,
+        request: impl Fn() -> reqwest::RequestBuilder,
+    ) -> Result<reqwest::Response, GithubError> {
+        let mut attempt = 0;
+        loop {
+            attempt += 1;
+            let last_attempt = attempt >= self.retry.max_attempts;
+
+            match request().send().await {
+                Ok(response) => {
+                    let delay = self.retry.retry_delay(
+                        response.status(),
+                        response.headers(),
+                        attempt,
+                        Utc::now(),
+                    );
+                    match delay {
+                        Some(delay) if !last_attempt => tokio::time::sleep(delay).await,
+                        _ => return GithubError::check(response).await,
+                    }
+                }
+                Err(e) if !last_attempt && (e.is_connect() || e.is_timeout()) => {
+                    tokio::time::sleep(self.retry.backoff(attempt)).await;
+                }
+                Err(e) if e.is_timeout() => return Err(GithubError::Timeout),
+                Err(e) => return Err(GithubError::Http(format!("Failed to {}: {}", action, e))),
+            }
+        }
+    }
+
+    pub fn repo_url(&self) -> String {
+        format!("{}/repos/{}/{}", self.base_url, self.owner, self.repo)
+    }
+
+    fn contents_url(&self, path: &str) -> String {
+        format!("{}/contents/{}", self.repo_url(), path)
+    }
+
+    /// 获取文件内容
+    ///
+    /// 传入上次响应的 `etag` 时发送 `If-None-Match`，文件未变化则返回 `NotModified`
+    pub async fn get_file(
+        &self,
+        path: &str,
+        etag: Option<&str>,
+    ) -> Result<FileResponse, GithubError> {
+        let url = self.contents_url(path);
 
         let response = self
-            .client
-            .get(&url)
-            .header("Authorization", format!("Bearer {}", self.token))
-            .header("Accept", "application/vnd.github.v3+json")
-            .query(&[("ref", &self.branch)])
-            .send()
-            .await
-            .map_err(|e| anyhow!("Failed to get file: {}", e))?;
+            .send("get file", || {
+                let request = self
+                    .client
+                    .get(&url)
+                    .header("Authorization", format!("Bearer {}", self.token))
+                    .header("Accept", "application/vnd.github.v3+json")
+                    .query(&[("ref", &self.branch)]);
+                match etag {
+                    Some(etag) => request.header("If-None-Match", etag),
+                    None => request,
+                }
+            })
+            .await?;
 
-        if !response.status().is_success() {
-            let status = response.status();
-            let text = response.text().await.unwrap_or_default();
-            return Err(anyhow!("GitHub API error ({}): {}", status, text));
+        if response.status() == reqwest::StatusCode::NOT_MODIFIED {
+            return Ok(FileResponse::NotModified);
         }
 
-        let file_content: GithubFileContent = response
+        let etag = response
+            .headers()
+            .get("etag")
+            .and_then(|v| v.to_str().ok())
+            .map(str::to_string);
+
+        let file: GithubFileContent = response
             .json()
             .await
-            .map_err(|e| anyhow!("Failed to parse response: {}", e))?;
+            .map_err(|e| GithubError::Http(format!("Failed to parse response: {}", e)))?;
 
-        Ok(file_content)
+        Ok(FileResponse::Modified { file, etag })
+    }
+
+    /// 获取文件在某次提交（或分支、标签）时的内容
+    pub async fn get_file_at(
+        &self,
+        path: &str,
+        git_ref: &str,
+    ) -> Result<GithubFileContent, GithubError> {
+        let url = self.contents_url(path);
+
+        let response = self
+            .send("get file", || {
+                self.client
+                    .get(&url)
+                    .header("Authorization", format!("Bearer {}", self.token))
+                    .header("Accept", "application/vnd.github.v3+json")
+                    .query(&[("ref", git_ref)])
+            })
+            .await?;
+
+        response
+            .json()
+            .await
+            .map_err(|e| GithubError::Http(format!("Failed to parse response: {}", e)))
+    }
+
+    /// 列出分支上修改过 `path` 的提交，从新到旧，最多 `per_page` 条
+    pub async fn list_commits(
+        &self,
+        path: &str,
+        per_page: u32,
+    ) -> Result<Vec<GithubCommit>, GithubError> {
+        let url = format!("{}/commits", self.repo_url());
+        let per_page = per_page.to_string();
+
+        let response = self
+            .send("list commits", || {
+                self.client
+                    .get(&url)
+                    .header("Authorization", format!("Bearer {}", self.token))
+                    .header("Accept", "application/vnd.github.v3+json")
+                    .query(&[
+                        ("path", path),
+                        ("sha", self.branch.as_str()),
+                        ("per_page", per_page.as_str()),
+                    ])
+            })
+            .await?;
+
+        response
+            .json()
+            .await
+            .map_err(|e| GithubError::Http(format!("Failed to parse response: {}", e)))
     }
 
     pub async fn create_or_update_file(
@@ -86,11 +376,8 @@ impl GithubClient {
         content: &str,
         message: &str,
         sha: Option<&str>,
-    ) -> Result<GithubCreateUpdateResponse> {
-        let url = format!(
-            "https://api.github.com/repos/{}/{}/contents/{}",
-            self.owner, self.repo, path
-        );
+    ) -> Result<GithubCreateUpdateResponse, GithubError> {
+        let url = self.contents_url(path);
 
         let encoded_content = general_purpose::STANDARD.encode(content);
 
@@ -102,34 +389,30 @@ impl GithubClient {
         };
 
         let response = self
-            .client
-            .put(&url)
-            .header("Authorization", format!("Bearer {}", self.token))
-            .header("Accept", "application/vnd.github.v3+json")
-            .json(&request_body)
-            .send()
-            .await
-            .map_err(|e| anyhow!("Failed to create/update file: {}", e))?;
-
-        if !response.status().is_success() {
-            let status = response.status();
-            let text = response.text().await.unwrap_or_default();
-            return Err(anyhow!("GitHub API error ({}): {}", status, text));
-        }
+            .send("create/update file", || {
+                self.client
+                    .put(&url)
+                    .header("Authorization", format!("Bearer {}", self.token))
+                    .header("Accept", "application/vnd.github.v3+json")
+                    .json(&request_body)
+            })
+            .await?;
 
         let response_data: GithubCreateUpdateResponse = response
             .json()
             .await
-            .map_err(|e| anyhow!("Failed to parse response: {}", e))?;
+            .map_err(|e| GithubError::Http(format!("Failed to parse response: {}", e)))?;
 
         Ok(response_data)
     }
 
-    pub async fn delete_file(&self, path: &str, message: &str, sha: &str) -> Result<()> {
-        let url = format!(
-            "https://api.github.com/repos/{}/{}/contents/{}",
-            self.owner, self.repo, path
-        );
+    pub async fn delete_file(
+        &self,
+        path: &str,
+        message: &str,
+        sha: &str,
+    ) -> Result<(), GithubError> {
+        let url = self.contents_url(path);
 
         #[derive(Serialize)]
         struct DeleteRequest {
@@ -144,21 +427,14 @@ impl GithubClient {
             branch: self.branch.clone(),
         };
 
-        let response = self
-            .client
-            .delete(&url)
-            .header("Authorization", format!("Bearer {}", self.token))
-            .header("Accept", "application/vnd.github.v3+json")
-            .json(&request_body)
-            .send()
-            .await
-            .map_err(|e| anyhow!("Failed to delete file: {}", e))?;
-
-        if !response.status().is_success() {
-            let status = response.status();
-            let text = response.text().await.unwrap_or_default();
-            return Err(anyhow!("GitHub API error ({}): {}", status, text));
-        }
+        self.send("delete file", || {
+            self.client
+                .delete(&url)
+                .header("Authorization", format!("Bearer {}", self.token))
+                .header("Accept", "application/vnd.github.v3+json")
+                .json(&request_body)
+        })
+        .await?;
 
         Ok(())
     }
@@ -175,3 +451,178 @@ impl GithubClient {
         String::from_utf8(decoded).map_err(|e| anyhow!("Invalid UTF-8 content: {}", e))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use reqwest::StatusCode;
+    use reqwest::header::{HeaderMap, HeaderValue};
+
+    fn headers(pairs: &[(&'static str, &str)]) -> HeaderMap {
+        let mut map = HeaderMap::new();
+        for (k, v) in pairs {
+            map.insert(*k, HeaderValue::from_str(v).unwrap());
+        }
+        map
+    }
+
+    #[test]
+    fn retry_delay_rules() {
+        let policy = RetryPolicy::default();
+        let now = Utc::now();
+
+        // 不可重试的 4xx
+        assert_eq!(
+            policy.retry_delay(StatusCode::UNAUTHORIZED, &HeaderMap::new(), 1, now),
+            None
+        );
+        assert_eq!(
+            policy.retry_delay(StatusCode::FORBIDDEN, &HeaderMap::new(), 1, now),
+            None
+        );
+
+        // 5xx 指数退避
+        assert_eq!(
+            policy.retry_delay(StatusCode::BAD_GATEWAY, &HeaderMap::new(), 3, now),
+            Some(Duration::from_secs(2))
+        );
+
+        // 限流：Retry-After 优先，其次 X-RateLimit-Reset，均不超过上限
+        let limited = headers(&[("x-ratelimit-remaining", "0"), ("retry-after", "7")]);
+        assert_eq!(
+            policy.retry_delay(StatusCode::FORBIDDEN, &limited, 1, now),
+            Some(Duration::from_secs(7))
+        );
+        let reset = (now.timestamp() + 12).to_string();
+        let limited = headers(&[
+            ("x-ratelimit-remaining", "0"),
+            ("x-ratelimit-reset", &reset),
+        ]);
+        assert_eq!(
+            policy.retry_delay(StatusCode::FORBIDDEN, &limited, 1, now),
+            Some(Duration::from_secs(12))
+        );
+        let limited = headers(&[("retry-after", "3600")]);
+        assert_eq!(
+            policy.retry_delay(StatusCode::TOO_MANY_REQUESTS, &limited, 1, now),
+            Some(policy.max_delay)
+        );
+    }
+
+    fn client_for(server: &mockito::Server) -> GithubClient {
+        let mut client = GithubClient::new(
+            "owner".to_string(),
+            "repo".to_string(),
+            "token".to_string(),
+            "main".to_string(),
+            Some(&server.url()),
+        );
+        client.retry.base_delay = Duration::from_millis(1);
+        client
+    }
+
+    #[test]
+    fn urls_use_custom_base() {
+        let client = GithubClient::new(
+            "owner".to_string(),
+            "repo".to_string(),
+            "token".to_string(),
+            "main".to_string(),
+            Some("https://github.mycorp.com/api/v3/"),
+        );
+        assert_eq!(
+            client.repo_url(),
+            "https://github.mycorp.com/api/v3/repos/owner/repo"
+        );
+        assert_eq!(
+            client.contents_url("vault/passwords.json"),
+            "https://github.mycorp.com/api/v3/repos/owner/repo/contents/vault/passwords.json"
+        );
+
+        let client = GithubClient::new(
+            "owner".to_string(),
+            "repo".to_string(),
+            "token".to_string(),
+            "main".to_string(),
+            None,
+        );
+        assert_eq!(client.repo_url(), "https://api.github.com/repos/owner/repo");
+    }
+
+    #[tokio::test]
+    async fn retries_after_rate_limit() {
+        let mut server = mockito::Server::new_async().await;
+        let path = "/repos/owner/repo/contents/passwords.json";
+
+        let limited = server
+            .mock("GET", path)
+            .match_query(mockito::Matcher::Any)
+            .with_status(403)
+            .with_header("x-ratelimit-remaining", "0")
+            .with_header("x-ratelimit-reset", &Utc::now().timestamp().to_string())
+            .expect(1)
+            .create_async()
+            .await;
+        let ok = server
+            .mock("GET", path)
+            .match_query(mockito::Matcher::Any)
+            .with_body(
+                r#"{"content":"","encoding":"base64","sha":"abc","size":0,"name":"passwords.json","path":"passwords.json"}"#,
+            )
+            .expect(1)
+            .create_async()
+            .await;
+
+        let response = client_for(&server)
+            .get_file("passwords.json", None)
+            .await
+            .unwrap();
+
+        match response {
+            FileResponse::Modified { file, .. } => assert_eq!(file.sha, "abc"),
+            FileResponse::NotModified => panic!("unexpected 304"),
+        }
+        limited.assert_async().await;
+        ok.assert_async().await;
+    }
+
+    #[tokio::test]
+    async fn unauthorized_fails_immediately() {
+        let mut server = mockito::Server::new_async().await;
+        let unauthorized = server
+            .mock("GET", "/repos/owner/repo/contents/passwords.json")
+            .match_query(mockito::Matcher::Any)
+            .with_status(401)
+            .expect(1)
+            .create_async()
+            .await;
+
+        let err = client_for(&server)
+            .get_file("passwords.json", None)
+            .await
+            .unwrap_err();
+
+        assert_eq!(err.status(), Some(401));
+        unauthorized.assert_async().await;
+    }
+
+    #[tokio::test]
+    async fn gives_up_after_max_attempts() {
+        let mut server = mockito::Server::new_async().await;
+        let failing = server
+            .mock("GET", "/repos/owner/repo/contents/passwords.json")
+            .match_query(mockito::Matcher::Any)
+            .with_status(503)
+            .expect(3)
+            .create_async()
+            .await;
+
+        let err = client_for(&server)
+            .get_file("passwords.json", None)
+            .await
+            .unwrap_err();
+
+        assert_eq!(err.status(), Some(503));
+        failing.assert_async().await;
+    }
+}
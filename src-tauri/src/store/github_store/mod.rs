@@ -1,81 +1,194 @@
 mod github_client;
 
-use crate::store::{Storage, StorageData, StorageMetadata};
+pub use github_client::GITHUB_API_URL;
+
+use crate::config::GithubStorageConfig;
+use crate::store::{
+    Storage, StorageData, StorageError, StorageMetadata, StorageTarget, VersionInfo,
+};
 use anyhow::{Result, anyhow};
 use async_trait::async_trait;
-use github_client::GithubClient;
+use github_client::{FileResponse, GithubClient, GithubError};
 use std::collections::HashMap;
+use std::sync::Mutex;
 
 pub struct GithubStorage {
     client: GithubClient,
-    file_path: String,
+    /// 数据文件在仓库中的路径，`move_file` 后会改变
+    file_path: Mutex<String>,
+    /// 上次下载的文件，配合ETag避免重复下载未变化的内容
+    cached: Mutex<Option<CachedFile>>,
+    #[cfg(test)]
+    parse_count: std::sync::atomic::AtomicUsize,
+}
+
+#[derive(Clone)]
+struct CachedFile {
+    etag: String,
+    sha: String,
+    data: StorageData,
 }
 
+/// 列出历史版本时最多返回的提交数（GitHub 单页上限）
+const MAX_VERSIONS: u32 = 100;
+
+/// 同一文件的sha已被其他设备更新时GitHub返回的状态码
+const STATUS_CONFLICT: u16 = 409;
+
 impl GithubStorage {
-    pub fn new(
-        owner: String,
-        repo: String,
-        token: String,
-        branch: String,
-        file_path: String,
-    ) -> Self {
-        let client = GithubClient::new(owner, repo, token, branch);
-        Self { client, file_path }
+    pub fn new(config: &GithubStorageConfig) -> Result<Self> {
+        let base_url = config.api_base_url()?;
+        let mut client = GithubClient::new(
+            config.owner.clone(),
+            config.repo.clone(),
+            config.token.clone(),
+            config.branch.clone(),
+            Some(&base_url),
+        );
+        client.retry.max_attempts = config.max_attempts.max(1);
+        client.set_timeout(std::time::Duration::from_secs(config.timeout_secs.max(1)));
+
+        Ok(Self {
+            client,
+            file_path: Mutex::new(config.file_path.clone()),
+            cached: Mutex::new(None),
+            #[cfg(test)]
+            parse_count: Default::default(),
+        })
+    }
+
+    /// 设置已知的ETag及对应的文件内容
+    ///
+    /// 之后的 `load` 在远端未变化（304）时直接返回这里的数据
+    pub fn seed_etag(&self, etag: String, sha: String, data: StorageData) {
+        *self.cached.lock().unwrap() = Some(CachedFile { etag, sha, data });
+    }
+
+    fn cached_file(&self) -> Option<CachedFile> {
+        self.cached.lock().unwrap().clone()
+    }
+
+    fn file_path(&self) -> String {
+        self.file_path.lock().unwrap().clone()
+    }
+
+    // 获取远端数据及其sha，文件不存在时返回None
+    async fn fetch(&self) -> Result<Option<(String, StorageData)>> {
+        let cached = self.cached_file();
+
+        match self
+            .client
+            .get_file(&self.file_path(), cached.as_ref().map(|c| c.etag.as_str()))
+            .await
+        {
+            Ok(FileResponse::NotModified) => {
+                let cached = cached.ok_or_else(|| anyhow!("GitHub returned 304 without cache"))?;
+                Ok(Some((cached.sha, cached.data)))
+            }
+            Ok(FileResponse::Modified { file, etag }) => {
+                let content = self.client.decode_file_content(&file)?;
+                #[cfg(test)]
+                self.parse_count
+                    .fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+                let data: StorageData = serde_json::from_str(&content)?;
+
+                match etag {
+                    Some(etag) => self.seed_etag(etag, file.sha.clone(), data.clone()),
+                    None => *self.cached.lock().unwrap() = None,
+                }
+                Ok(Some((file.sha, data)))
+            }
+            Err(GithubError::NotFound) => {
+                *self.cached.lock().unwrap() = None;
+                Ok(None)
+            }
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// 指向模拟服务器的存储：owner/repo/passwords.json，分支 main
+    #[cfg(test)]
+    pub fn new_for_test(base_url: &str) -> Self {
+        let mut client = GithubClient::new(
+            "owner".to_string(),
+            "repo".to_string(),
+            "token".to_string(),
+            "main".to_string(),
+            Some(base_url),
+        );
+        client.retry.base_delay = std::time::Duration::from_millis(1);
+        Self {
+            client,
+            file_path: Mutex::new("passwords.json".to_string()),
+            cached: Mutex::new(None),
+            parse_count: Default::default(),
+        }
     }
 }
 
 #[async_trait]
 impl Storage for GithubStorage {
     async fn load(&self) -> Result<StorageData> {
-        match self.client.get_file(&self.file_path).await {
-            Ok(file_content) => {
-                let content = self.client.decode_file_content(&file_content)?;
-                let data: StorageData = serde_json::from_str(&content)?;
+        match self.fetch().await? {
+            Some((_, data)) => {
+                data.validate()?;
                 Ok(data)
             }
-            Err(e) => {
-                // 如果文件不存在，返回空数据
-                if e.to_string().contains("404") {
-                    Ok(StorageData {
-                        metadata: StorageMetadata {
-                            version: "1.0.0".to_string(),
-                            last_sync: chrono::Utc::now(),
-                            password_count: 0,
-                        },
-                        passwords: HashMap::new(),
-                    })
-                } else {
-                    Err(e)
-                }
-            }
+            // 如果文件不存在，返回空数据
+            None => Ok(StorageData {
+                metadata: StorageMetadata {
+                    version: "1.0.0".to_string(),
+                    last_sync: chrono::Utc::now(),
+                    password_count: 0,
+                    revision: 0,
+                },
+                passwords: HashMap::new(),
+            }),
         }
     }
 
     async fn save(&self, data: &StorageData) -> Result<()> {
-        let content = serde_json::to_string_pretty(data)?;
-
-        // 尝试获取现有文件的SHA（如果存在）
-        let sha = match self.client.get_file(&self.file_path).await {
-            Ok(file_content) => Some(file_content.sha),
-            Err(_) => None,
+        // 尝试获取现有文件的SHA（如果存在），获取失败时直接返回错误，不会误删文件
+        let sha = match self.fetch().await? {
+            // 远端已被其他设备更新，把远端数据交给调用方合并，避免覆盖对方的提交
+            Some((_, remote)) if data.is_stale(remote.metadata.revision) => {
+                return Err(StorageError::Conflict(remote).into());
+            }
+            Some((sha, _)) => Some(sha),
+            None => None,
         };
 
-        let message = format!("Update passwords - {} items", data.metadata.password_count);
-
-        self.client
-            .create_or_update_file(&self.file_path, &content, &message, sha.as_deref())
-            .await?;
+        // 密码库已清空时删除远端文件，而不是留下一个空的数据文件
+        let result = if data.passwords.is_empty() {
+            let Some(sha) = sha else {
+                return Ok(());
+            };
+            self.client
+                .delete_file(&self.file_path(), "Delete passwords - 0 items", &sha)
+                .await
+        } else {
+            let content = serde_json::to_string_pretty(data)?;
+            let message = format!("Update passwords - {} items", data.metadata.password_count);
+            self.client
+                .create_or_update_file(&self.file_path(), &content, &message, sha.as_deref())
+                .await
+                .map(|_| ())
+        };
 
-        Ok(())
+        match result {
+            Ok(_) => Ok(()),
+            // 读取sha之后远端文件又被修改，把最新的远端数据交给调用方合并
+            Err(e) if e.status() == Some(STATUS_CONFLICT) => {
+                let remote = self.load().await?;
+                Err(StorageError::Conflict(remote).into())
+            }
+            Err(e) => Err(e.into()),
+        }
     }
 
     async fn test_connection(&self) -> Result<()> {
         // 尝试获取仓库信息来测试连接
-        let url = format!(
-            "https://api.github.com/repos/{}/{}",
-            self.client.owner.as_str(),
-            self.client.repo.as_str()
-        );
+        let url = self.client.repo_url();
 
         let response = self
             .client
@@ -102,4 +215,482 @@ impl Storage for GithubStorage {
             Err(_) => Ok(false),
         }
     }
+
+    fn target(&self) -> StorageTarget {
+        StorageTarget::GitHub
+    }
+
+    async fn list_versions(&self) -> Result<Vec<VersionInfo>> {
+        let commits = self
+            .client
+            .list_commits(&self.file_path(), MAX_VERSIONS)
+            .await?;
+        Ok(commits
+            .into_iter()
+            .map(|c| VersionInfo {
+                sha: c.sha,
+                date: c.commit.committer.date,
+                message: c.commit.message,
+            })
+            .collect())
+    }
+
+    async fn load_version(&self, sha: &str) -> Result<StorageData> {
+        let file_path = self.file_path();
+        let file = match self.client.get_file_at(&file_path, sha).await {
+            Ok(file) => file,
+            Err(GithubError::NotFound) => {
+                return Err(anyhow!("版本 {} 中没有文件 {}", sha, file_path));
+            }
+            Err(e) => return Err(e.into()),
+        };
+        let content = self.client.decode_file_content(&file)?;
+        Ok(serde_json::from_str(&content)?)
+    }
+
+    async fn move_file(&self, new_path: &str) -> Result<()> {
+        let old_path = self.file_path();
+        if new_path == old_path {
+            return Ok(());
+        }
+
+        // 先在新路径创建文件，成功后再删除旧文件，中途失败时数据仍在旧路径
+        let Some((sha, data)) = self.fetch().await? else {
+            return Err(anyhow!("文件 {} 不存在", old_path));
+        };
+        let content = serde_json::to_string_pretty(&data)?;
+        self.client
+            .create_or_update_file(
+                new_path,
+                &content,
+                &format!("Move passwords from {}", old_path),
+                None,
+            )
+            .await?;
+        self.client
+            .delete_file(&old_path, &format!("Move passwords to {}", new_path), &sha)
+            .await?;
+
+        *self.file_path.lock().unwrap() = new_path.to_string();
+        // 缓存的ETag属于旧路径
+        *self.cached.lock().unwrap() = None;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypto;
+    use crate::password::{Password, PasswordCreateRequest};
+    use crate::store::mock_store::github_file_body;
+
+    // 含一个条目的数据，保存时会写入文件而不是删除
+    fn one_entry() -> StorageData {
+        let password = Password::new(
+            PasswordCreateRequest {
+                title: "GitHub".to_string(),
+                description: String::new(),
+                tags: vec![],
+                username: "alice".to_string(),
+                password: "pw".to_string(),
+                url: None,
+                key: "key".to_string(),
+                expires_at: None,
+                totp_secret: None,
+                notes: None,
+                client_id: None,
+                kdf_strength: crypto::KdfStrength::Fast,
+            },
+            crypto::encrypt_with_password("pw", "key").unwrap(),
+        );
+        let mut data = StorageData::new();
+        data.merge([password]);
+        data
+    }
+
+    #[test]
+    fn client_uses_env_overrides() {
+        let config = GithubStorageConfig {
+            name: String::new(),
+            enabled: true,
+            owner: "owner".to_string(),
+            repo: "repo".to_string(),
+            branch: "main".to_string(),
+            token: String::new(),
+            file_path: "passwords.json".to_string(),
+            max_attempts: 1,
+            base_url: None,
+            timeout_secs: 30,
+        };
+        let env: HashMap<&str, &str> = HashMap::from([
+            ("GITHUB_TOKEN", "env-token"),
+            ("PASSWD_GITHUB_OWNER", "env-owner"),
+            ("PASSWD_GITHUB_REPO", "env-repo"),
+        ]);
+
+        let storage = GithubStorage::new(
+            &config.with_overrides_from(|name| env.get(name).map(|v| v.to_string())),
+        )
+        .unwrap();
+
+        assert_eq!(storage.client.token, "env-token");
+        assert_eq!(storage.client.owner, "env-owner");
+        assert_eq!(storage.client.repo, "env-repo");
+        assert_eq!(storage.client.branch, "main");
+    }
+
+    #[tokio::test]
+    async fn load_missing_file_returns_empty_data() {
+        let mut server = mockito::Server::new_async().await;
+        server
+            .mock("GET", "/repos/owner/repo/contents/passwords.json")
+            .match_query(mockito::Matcher::Any)
+            .with_status(404)
+            .with_body(r#"{"message":"Not Found"}"#)
+            .create_async()
+            .await;
+
+        let storage = GithubStorage::new_for_test(&server.url());
+        let data = storage.load().await.unwrap();
+        assert!(data.passwords.is_empty());
+    }
+
+    #[tokio::test]
+    async fn load_propagates_server_error() {
+        let mut server = mockito::Server::new_async().await;
+        server
+            .mock("GET", "/repos/owner/repo/contents/passwords.json")
+            .match_query(mockito::Matcher::Any)
+            .with_status(500)
+            .with_body("boom")
+            .create_async()
+            .await;
+
+        let storage = GithubStorage::new_for_test(&server.url());
+        let err = storage.load().await.unwrap_err();
+        match err.downcast_ref::<GithubError>() {
+            Some(GithubError::Api { status, body }) => {
+                assert_eq!(*status, 500);
+                assert_eq!(body, "boom");
+            }
+            other => panic!("unexpected error: {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn unchanged_file_is_not_parsed_again() {
+        let mut server = mockito::Server::new_async().await;
+        let path = "/repos/owner/repo/contents/passwords.json";
+
+        let mut remote = StorageData::new();
+        remote.metadata.version = "remote".to_string();
+        let full = server
+            .mock("GET", path)
+            .match_query(mockito::Matcher::Any)
+            .match_header("if-none-match", mockito::Matcher::Missing)
+            .with_header("etag", "\"v1\"")
+            .with_body(github_file_body(&remote, "sha-1"))
+            .expect(1)
+            .create_async()
+            .await;
+        let not_modified = server
+            .mock("GET", path)
+            .match_query(mockito::Matcher::Any)
+            .match_header("if-none-match", "\"v1\"")
+            .with_status(304)
+            .expect(1)
+            .create_async()
+            .await;
+
+        let storage = GithubStorage::new_for_test(&server.url());
+        let first = storage.load().await.unwrap();
+        let second = storage.load().await.unwrap();
+
+        full.assert_async().await;
+        not_modified.assert_async().await;
+        assert_eq!(first.metadata.version, "remote");
+        assert_eq!(second.metadata.version, "remote");
+        assert_eq!(
+            storage
+                .parse_count
+                .load(std::sync::atomic::Ordering::SeqCst),
+            1
+        );
+    }
+
+    #[tokio::test]
+    async fn seeded_etag_is_sent() {
+        let mut server = mockito::Server::new_async().await;
+        let not_modified = server
+            .mock("GET", "/repos/owner/repo/contents/passwords.json")
+            .match_query(mockito::Matcher::Any)
+            .match_header("if-none-match", "\"seeded\"")
+            .with_status(304)
+            .expect(1)
+            .create_async()
+            .await;
+
+        let storage = GithubStorage::new_for_test(&server.url());
+        let mut data = StorageData::new();
+        data.metadata.version = "seeded".to_string();
+        storage.seed_etag("\"seeded\"".to_string(), "sha".to_string(), data);
+
+        assert_eq!(storage.load().await.unwrap().metadata.version, "seeded");
+        not_modified.assert_async().await;
+    }
+
+    #[tokio::test]
+    async fn conflicting_put_returns_remote_state() {
+        let mut server = mockito::Server::new_async().await;
+
+        let mut remote = StorageData::new();
+        remote.metadata.version = "remote".to_string();
+        server
+            .mock("GET", "/repos/owner/repo/contents/passwords.json")
+            .match_query(mockito::Matcher::Any)
+            .with_body(github_file_body(&remote, "sha-remote"))
+            .create_async()
+            .await;
+        let put = server
+            .mock("PUT", "/repos/owner/repo/contents/passwords.json")
+            .with_status(409)
+            .with_body(r#"{"message":"is at sha-remote but expected sha-old"}"#)
+            .expect(1)
+            .create_async()
+            .await;
+
+        let storage = GithubStorage::new_for_test(&server.url());
+        let err = storage.save(&one_entry()).await.unwrap_err();
+
+        put.assert_async().await;
+        match err.downcast::<StorageError>() {
+            Ok(StorageError::Conflict(data)) => assert_eq!(data.metadata.version, "remote"),
+            Ok(other) => panic!("unexpected error: {}", other),
+            Err(e) => panic!("unexpected error: {}", e),
+        }
+    }
+
+    #[tokio::test]
+    async fn stale_save_returns_conflict_without_put() {
+        let mut server = mockito::Server::new_async().await;
+
+        let mut remote = one_entry();
+        remote.metadata.version = "remote".to_string();
+        remote.metadata.revision = 3;
+        server
+            .mock("GET", "/repos/owner/repo/contents/passwords.json")
+            .match_query(mockito::Matcher::Any)
+            .with_body(github_file_body(&remote, "sha-remote"))
+            .create_async()
+            .await;
+        let put = server
+            .mock("PUT", "/repos/owner/repo/contents/passwords.json")
+            .expect(0)
+            .create_async()
+            .await;
+
+        // 基于版本2修改的数据，远端已前进到版本3
+        let mut local = one_entry();
+        local.metadata.revision = 3;
+        let storage = GithubStorage::new_for_test(&server.url());
+        let err = storage.save(&local).await.unwrap_err();
+
+        put.assert_async().await;
+        match err.downcast::<StorageError>() {
+            Ok(StorageError::Conflict(data)) => {
+                assert_eq!(data.metadata.version, "remote");
+                assert_eq!(data.metadata.revision, 3);
+            }
+            Ok(other) => panic!("unexpected error: {}", other),
+            Err(e) => panic!("unexpected error: {}", e),
+        }
+    }
+
+    #[tokio::test]
+    async fn lists_and_loads_versions() {
+        let mut server = mockito::Server::new_async().await;
+        let commits = server
+            .mock("GET", "/repos/owner/repo/commits")
+            .match_query(mockito::Matcher::AllOf(vec![
+                mockito::Matcher::UrlEncoded("path".into(), "passwords.json".into()),
+                mockito::Matcher::UrlEncoded("sha".into(), "main".into()),
+            ]))
+            .with_body(
+                serde_json::json!([
+                    {
+                        "sha": "sha-2",
+                        "commit": {
+                            "message": "Update passwords - 2 items",
+                            "committer": { "date": "2024-05-02T08:00:00Z" }
+                        }
+                    },
+                    {
+                        "sha": "sha-1",
+                        "commit": {
+                            "message": "Update passwords - 1 items",
+                            "committer": { "date": "2024-05-01T08:00:00Z" }
+                        }
+                    }
+                ])
+                .to_string(),
+            )
+            .expect(1)
+            .create_async()
+            .await;
+
+        let mut old = StorageData::new();
+        old.metadata.version = "old".to_string();
+        let file = server
+            .mock("GET", "/repos/owner/repo/contents/passwords.json")
+            .match_query(mockito::Matcher::UrlEncoded("ref".into(), "sha-1".into()))
+            .with_body(github_file_body(&old, "blob-1"))
+            .expect(1)
+            .create_async()
+            .await;
+
+        let storage = GithubStorage::new_for_test(&server.url());
+        let versions = storage.list_versions().await.unwrap();
+        let shas: Vec<&str> = versions.iter().map(|v| v.sha.as_str()).collect();
+        assert_eq!(shas, ["sha-2", "sha-1"]);
+        assert_eq!(versions[1].message, "Update passwords - 1 items");
+        assert_eq!(versions[1].date.to_rfc3339(), "2024-05-01T08:00:00+00:00");
+
+        let data = storage.load_version("sha-1").await.unwrap();
+        assert_eq!(data.metadata.version, "old");
+
+        commits.assert_async().await;
+        file.assert_async().await;
+    }
+
+    #[tokio::test]
+    async fn load_version_reports_missing_file() {
+        let mut server = mockito::Server::new_async().await;
+        server
+            .mock("GET", "/repos/owner/repo/contents/passwords.json")
+            .match_query(mockito::Matcher::Any)
+            .with_status(404)
+            .with_body(r#"{"message":"Not Found"}"#)
+            .create_async()
+            .await;
+
+        let storage = GithubStorage::new_for_test(&server.url());
+        let err = storage.load_version("sha-0").await.unwrap_err();
+        assert!(err.to_string().contains("sha-0"));
+    }
+
+    #[tokio::test]
+    async fn saving_empty_data_deletes_existing_file() {
+        let mut server = mockito::Server::new_async().await;
+        let path = "/repos/owner/repo/contents/passwords.json";
+
+        let mut remote = StorageData::new();
+        remote.metadata.version = "remote".to_string();
+        server
+            .mock("GET", path)
+            .match_query(mockito::Matcher::Any)
+            .with_body(github_file_body(&remote, "sha-remote"))
+            .create_async()
+            .await;
+        let delete = server
+            .mock("DELETE", path)
+            .match_body(mockito::Matcher::PartialJson(serde_json::json!({
+                "sha": "sha-remote",
+                "branch": "main",
+            })))
+            .with_body(r#"{"commit":{}}"#)
+            .expect(1)
+            .create_async()
+            .await;
+        let put = server.mock("PUT", path).expect(0).create_async().await;
+
+        let storage = GithubStorage::new_for_test(&server.url());
+        storage.save(&StorageData::new()).await.unwrap();
+
+        delete.assert_async().await;
+        put.assert_async().await;
+    }
+
+    #[tokio::test]
+    async fn failed_move_keeps_old_file() {
+        let mut server = mockito::Server::new_async().await;
+        server
+            .mock("GET", "/repos/owner/repo/contents/passwords.json")
+            .match_query(mockito::Matcher::Any)
+            .with_body(github_file_body(&one_entry(), "sha-old"))
+            .create_async()
+            .await;
+        let create = server
+            .mock("PUT", "/repos/owner/repo/contents/moved.json")
+            .with_status(422)
+            .with_body(r#"{"message":"Invalid request"}"#)
+            .expect(1)
+            .create_async()
+            .await;
+        let delete = server
+            .mock("DELETE", "/repos/owner/repo/contents/passwords.json")
+            .expect(0)
+            .create_async()
+            .await;
+
+        let storage = GithubStorage::new_for_test(&server.url());
+        assert!(storage.move_file("moved.json").await.is_err());
+
+        // 新文件创建失败时不删除旧文件，之后仍然读写旧路径
+        create.assert_async().await;
+        delete.assert_async().await;
+        assert_eq!(storage.file_path(), "passwords.json");
+    }
+
+    #[tokio::test]
+    async fn saving_empty_data_without_file_writes_nothing() {
+        let mut server = mockito::Server::new_async().await;
+        let path = "/repos/owner/repo/contents/passwords.json";
+        server
+            .mock("GET", path)
+            .match_query(mockito::Matcher::Any)
+            .with_status(404)
+            .with_body(r#"{"message":"Not Found"}"#)
+            .create_async()
+            .await;
+        let delete = server.mock("DELETE", path).expect(0).create_async().await;
+        let put = server.mock("PUT", path).expect(0).create_async().await;
+
+        let storage = GithubStorage::new_for_test(&server.url());
+        storage.save(&StorageData::new()).await.unwrap();
+
+        delete.assert_async().await;
+        put.assert_async().await;
+    }
+
+    #[tokio::test]
+    async fn hung_server_times_out() {
+        // 接受连接但从不响应的服务器
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let server = tokio::spawn(async move {
+            let mut sockets = Vec::new();
+            loop {
+                let (socket, _) = listener.accept().await.unwrap();
+                sockets.push(socket);
+            }
+        });
+
+        let mut storage = GithubStorage::new_for_test(&format!("http://{}", addr));
+        storage.client.retry.max_attempts = 1;
+        storage
+            .client
+            .set_timeout(std::time::Duration::from_millis(200));
+
+        let err = tokio::time::timeout(std::time::Duration::from_secs(5), storage.load())
+            .await
+            .expect("请求没有在超时时间内返回")
+            .unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<GithubError>(),
+            Some(GithubError::Timeout)
+        ));
+
+        server.abort();
+    }
 }
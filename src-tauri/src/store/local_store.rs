@@ -1,17 +1,185 @@
 // use crate::password::Password;
-use anyhow::Result;
+use anyhow::{Result, anyhow};
 use async_trait::async_trait;
 // use serde::{Deserialize, Serialize};
-use super::{Storage, StorageData, StorageMetadata};
+use super::{LoadWarning, Storage, StorageData, StorageError, StorageMetadata, StorageTarget};
+use crate::config::LocalStorageConfig;
 use std::collections::HashMap;
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tokio::io::AsyncWriteExt;
+
+/// 等待文件锁的默认超时时间
+const DEFAULT_LOCK_TIMEOUT: Duration = Duration::from_secs(5);
+/// 重试获取文件锁的间隔
+const LOCK_RETRY_INTERVAL: Duration = Duration::from_millis(20);
+/// 超过该时间未释放的锁视为进程崩溃遗留，可以直接清除
+const LOCK_STALE_AFTER: Duration = Duration::from_secs(60);
+/// 序列化时的写缓冲大小，条目再多也只按该大小分块写入文件
+const WRITE_BUFFER_SIZE: usize = 64 * 1024;
 
 pub struct LocalStorage {
     data_path: std::path::PathBuf,
+    lock_timeout: Duration,
+    max_backups: usize,
+    /// 最近一次读写时数据文件的版本号，还未读写过时为None，不做检查
+    ///
+    /// 只能发现本进程内基于旧数据的写入，其它进程的修改要等下次读取才能知道
+    revision: Mutex<Option<u64>>,
 }
 
 impl LocalStorage {
-    pub fn new(data_path: std::path::PathBuf) -> Self {
-        Self { data_path }
+    pub fn new(data_path: std::path::PathBuf, config: &LocalStorageConfig) -> Self {
+        Self {
+            data_path,
+            lock_timeout: DEFAULT_LOCK_TIMEOUT,
+            max_backups: config.max_backups,
+            revision: Mutex::new(None),
+        }
+    }
+
+    /// 第 `index` 个历史版本的路径，1 为最近一次
+    ///
+    /// 用该路径创建的 `LocalStorage` 可以直接 `load` 出对应的历史数据
+    pub fn backup_path(&self, index: usize) -> PathBuf {
+        self.sibling_path(&format!(".{}", index))
+    }
+
+    // 把当前数据文件复制为 .1，已有的历史版本依次后移，超出数量的丢弃
+    async fn rotate_backups(&self) -> Result<()> {
+        if self.max_backups == 0 || !self.data_path.exists() {
+            return Ok(());
+        }
+
+        let oldest = self.backup_path(self.max_backups);
+        if oldest.exists() {
+            tokio::fs::remove_file(&oldest).await?;
+        }
+        for i in (1..self.max_backups).rev() {
+            let from = self.backup_path(i);
+            if from.exists() {
+                tokio::fs::rename(&from, self.backup_path(i + 1)).await?;
+            }
+        }
+        tokio::fs::copy(&self.data_path, self.backup_path(1)).await?;
+
+        Ok(())
+    }
+
+    // 与数据文件位于同一目录的辅助文件
+    fn sibling_path(&self, suffix: &str) -> PathBuf {
+        let mut name = self
+            .data_path
+            .file_name()
+            .unwrap_or_default()
+            .to_os_string();
+        name.push(suffix);
+        self.data_path.with_file_name(name)
+    }
+
+    // 写入时使用的临时文件，与数据文件位于同一目录，保证rename是原子的
+    fn temp_path(&self) -> PathBuf {
+        self.sibling_path(".tmp")
+    }
+
+    fn lock_path(&self) -> PathBuf {
+        self.sibling_path(".lock")
+    }
+
+    async fn lock(&self) -> Result<FileLock> {
+        FileLock::acquire(self.lock_path(), self.lock_timeout).await
+    }
+
+    // 读取并解析数据文件，记录其版本号；调用方需持有文件锁
+    async fn read_data(&self) -> Result<StorageData> {
+        let content = tokio::fs::read_to_string(&self.data_path).await?;
+        let data: StorageData = serde_json::from_str(&content)?;
+        *self.revision.lock().unwrap() = Some(data.metadata.revision);
+        Ok(data)
+    }
+}
+
+/// 基于同目录 `.lock` 文件的建议锁，防止多个进程同时读写数据文件
+///
+/// drop时删除锁文件
+struct FileLock {
+    path: PathBuf,
+}
+
+impl FileLock {
+    async fn acquire(path: PathBuf, timeout: Duration) -> Result<Self> {
+        let start = Instant::now();
+
+        loop {
+            match tokio::fs::OpenOptions::new()
+                .write(true)
+                .create_new(true)
+                .open(&path)
+                .await
+            {
+                Ok(mut file) => {
+                    file.write_all(std::process::id().to_string().as_bytes())
+                        .await?;
+                    return Ok(Self { path });
+                }
+                Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => {
+                    if Self::is_stale(&path).await {
+                        let _ = tokio::fs::remove_file(&path).await;
+                        continue;
+                    }
+                    if start.elapsed() >= timeout {
+                        return Err(anyhow!(
+                            "database is locked: {}",
+                            path.to_str().unwrap_or_default()
+                        ));
+                    }
+                    tokio::time::sleep(LOCK_RETRY_INTERVAL).await;
+                }
+                Err(e) => return Err(e.into()),
+            }
+        }
+    }
+
+    async fn is_stale(path: &Path) -> bool {
+        let Ok(metadata) = tokio::fs::metadata(path).await else {
+            return false;
+        };
+        metadata
+            .modified()
+            .ok()
+            .and_then(|t| t.elapsed().ok())
+            .is_some_and(|age| age > LOCK_STALE_AFTER)
+    }
+}
+
+impl Drop for FileLock {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.path);
+    }
+}
+
+/// 把数据序列化后分块写入 `writer`，不在内存中构造完整的JSON字符串
+fn write_data<W: Write>(writer: W, data: &StorageData) -> Result<()> {
+    let mut writer = BufWriter::with_capacity(WRITE_BUFFER_SIZE, writer);
+    serde_json::to_writer(&mut writer, data)?;
+    writer.flush()?;
+    Ok(())
+}
+
+/// 用临时文件替换目标文件
+///
+/// Windows 上目标文件被占用时rename可能失败，此时先删除目标再重试
+async fn replace_file(from: &Path, to: &Path) -> std::io::Result<()> {
+    match tokio::fs::rename(from, to).await {
+        Ok(()) => Ok(()),
+        #[cfg(windows)]
+        Err(_) if to.exists() => {
+            tokio::fs::remove_file(to).await?;
+            tokio::fs::rename(from, to).await
+        }
+        Err(e) => Err(e),
     }
 }
 
@@ -24,30 +192,73 @@ impl Storage for LocalStorage {
                     version: "1.0.0".to_string(),
                     last_sync: chrono::Utc::now(),
                     password_count: 0,
+                    revision: 0,
                 },
                 passwords: HashMap::new(),
             });
         }
 
-        let content = tokio::fs::read_to_string(&self.data_path).await?;
-        let data: StorageData = serde_json::from_str(&content)?;
+        let _lock = self.lock().await?;
+        let data = self.read_data().await?;
+        data.validate()?;
         Ok(data)
     }
 
+    async fn load_lenient(&self) -> Result<(StorageData, Vec<LoadWarning>)> {
+        if !self.data_path.exists() {
+            return Ok((self.load().await?, Vec::new()));
+        }
+
+        let _lock = self.lock().await?;
+        let content = tokio::fs::read_to_string(&self.data_path).await?;
+        let (data, warnings) = StorageData::from_json_lenient(&content)?;
+        *self.revision.lock().unwrap() = Some(data.metadata.revision);
+        Ok((data, warnings))
+    }
+
     async fn save(&self, data: &StorageData) -> Result<()> {
         if let Some(parent) = self.data_path.parent() {
             tokio::fs::create_dir_all(parent).await?;
         }
 
-        let content = serde_json::to_string_pretty(data)?;
-        tokio::fs::write(&self.data_path, content).await?;
+        let _lock = self.lock().await?;
+
+        // 持有文件锁时检查版本号，保证检查和写入之间没有其它写入
+        let known = *self.revision.lock().unwrap();
+        if known.is_some_and(|stored| data.is_stale(stored)) {
+            return Err(StorageError::Conflict(self.read_data().await?).into());
+        }
+
+        // 先完整写入临时文件并落盘，再原子地替换数据文件，
+        // 避免写入中途崩溃导致数据文件被截断
+        let temp_path = self.temp_path();
+        // 序列化和落盘在阻塞线程中进行，大密码库的 fsync 不会卡住异步运行时
+        let data_owned = data.clone();
+        let temp = temp_path.clone();
+        tokio::task::spawn_blocking(move || -> Result<()> {
+            let file = std::fs::File::create(&temp)?;
+            write_data(&file, &data_owned)?;
+            file.sync_all()?;
+            Ok(())
+        })
+        .await??;
+
+        self.rotate_backups().await?;
+        replace_file(&temp_path, &self.data_path).await?;
+        *self.revision.lock().unwrap() = Some(data.metadata.revision);
         Ok(())
     }
 
     async fn test_connection(&self) -> Result<()> {
-        if let Some(parent) = self.data_path.parent() {
+        // 写入并删除一个探测文件，确认数据目录可写
+        let probe = self.sibling_path(".probe");
+        if let Some(parent) = probe.parent() {
             tokio::fs::create_dir_all(parent).await?;
         }
+        tokio::fs::write(&probe, b"")
+            .await
+            .map_err(|e| anyhow!("数据目录不可写（{}）：{}", probe.display(), e))?;
+        tokio::fs::remove_file(&probe).await?;
         Ok(())
     }
 
@@ -56,10 +267,328 @@ impl Storage for LocalStorage {
             return Ok(false);
         }
 
-        let content = tokio::fs::read_to_string(&self.data_path).await?;
-        let data: StorageData = serde_json::from_str(&content)?;
+        let data = self.load().await?;
 
         // 如果有密码数据，说明存在加密数据
         Ok(!data.passwords.is_empty())
     }
+
+    fn target(&self) -> StorageTarget {
+        StorageTarget::Local
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::store::mock_store;
+
+    fn no_backups() -> LocalStorageConfig {
+        LocalStorageConfig {
+            max_backups: 0,
+            ..Default::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_connection_checks_directory_is_writable() {
+        let dir = mock_store::temp_dir();
+        let storage = LocalStorage::new(dir.join("nested/passwords.json"), &no_backups());
+        storage.test_connection().await.unwrap();
+        assert!(!dir.join("nested/passwords.json.probe").exists());
+
+        // 父路径是普通文件，无法创建目录
+        std::fs::write(dir.join("file"), b"").unwrap();
+        let storage = LocalStorage::new(dir.join("file/passwords.json"), &no_backups());
+        assert!(storage.test_connection().await.is_err());
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn partial_write_leaves_original_untouched() {
+        let dir = mock_store::temp_dir();
+        let storage = LocalStorage::new(dir.join("passwords.json"), &no_backups());
+
+        let mut data = StorageData::new();
+        data.metadata.version = "first".to_string();
+        storage.save(&data).await.unwrap();
+
+        // 模拟写入临时文件时崩溃
+        tokio::fs::write(storage.temp_path(), b"{\"metadata\": {trunc")
+            .await
+            .unwrap();
+        assert_eq!(storage.load().await.unwrap().metadata.version, "first");
+
+        data.metadata.version = "second".to_string();
+        storage.save(&data).await.unwrap();
+        assert_eq!(storage.load().await.unwrap().metadata.version, "second");
+        assert!(!storage.temp_path().exists());
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn concurrent_saves_are_serialized() {
+        let dir = mock_store::temp_dir();
+        let storage =
+            std::sync::Arc::new(LocalStorage::new(dir.join("passwords.json"), &no_backups()));
+
+        let tasks: Vec<_> = (0..2)
+            .map(|i| {
+                let storage = storage.clone();
+                tokio::spawn(async move {
+                    for round in 0..20 {
+                        let mut data = StorageData::new();
+                        data.metadata.version = format!("{}-{}", i, round);
+                        storage.save(&data).await.unwrap();
+                    }
+                })
+            })
+            .collect();
+        for task in tasks {
+            task.await.unwrap();
+        }
+
+        let data = storage.load().await.unwrap();
+        assert!(data.metadata.version.ends_with("-19"));
+        assert!(!storage.lock_path().exists());
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn stale_revision_is_rejected() {
+        let dir = mock_store::temp_dir();
+        let storage = LocalStorage::new(dir.join("passwords.json"), &no_backups());
+
+        let mut data = StorageData::new();
+        data.metadata.revision = 1;
+        storage.save(&data).await.unwrap();
+        data.metadata.version = "newer".to_string();
+        data.metadata.revision = 2;
+        storage.save(&data).await.unwrap();
+
+        // 基于版本1的写入晚于版本2到达
+        let mut stale = StorageData::new();
+        stale.metadata.revision = 2;
+        let error = storage.save(&stale).await.unwrap_err();
+        match error.downcast::<StorageError>() {
+            Ok(StorageError::Conflict(current)) => {
+                assert_eq!(current.metadata.revision, 2);
+                assert_eq!(current.metadata.version, "newer");
+            }
+            Ok(other) => panic!("unexpected error: {}", other),
+            Err(e) => panic!("unexpected error: {:#}", e),
+        }
+
+        // 版本号为0的写入不做检查
+        storage.save(&StorageData::new()).await.unwrap();
+        assert_eq!(storage.load().await.unwrap().metadata.revision, 0);
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn held_lock_times_out() {
+        let dir = mock_store::temp_dir();
+        let mut storage = LocalStorage::new(dir.join("passwords.json"), &no_backups());
+        storage.lock_timeout = Duration::from_millis(100);
+
+        let guard = storage.lock().await.unwrap();
+        let err = storage.save(&StorageData::new()).await.unwrap_err();
+        assert!(err.to_string().contains("database is locked"));
+
+        drop(guard);
+        storage.save(&StorageData::new()).await.unwrap();
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    // 记录每次写入的大小
+    #[derive(Default)]
+    struct RecordingWriter {
+        total: usize,
+        largest_write: usize,
+    }
+
+    impl Write for RecordingWriter {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.total += buf.len();
+            self.largest_write = self.largest_write.max(buf.len());
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    fn large_vault(count: usize) -> StorageData {
+        let template = crate::password::Password::new(
+            crate::password::PasswordCreateRequest {
+                title: String::new(),
+                description: "x".repeat(64),
+                tags: vec!["bench".to_string()],
+                username: "alice".to_string(),
+                password: "pw".to_string(),
+                url: Some("https://example.com".to_string()),
+                key: "key".to_string(),
+                expires_at: None,
+                totp_secret: None,
+                notes: None,
+                client_id: None,
+                kdf_strength: crate::crypto::KdfStrength::Fast,
+            },
+            crate::crypto::encrypt_with_password("pw", "key").unwrap(),
+        );
+
+        let mut data = StorageData::new();
+        data.merge((0..count).map(|i| {
+            let mut password = template.clone();
+            password.id = format!("id-{}", i);
+            password.title = format!("entry {}", i);
+            password
+        }));
+        data
+    }
+
+    #[tokio::test]
+    async fn large_vault_is_written_in_chunks() {
+        let data = large_vault(10_000);
+
+        // 整个文档远大于写缓冲，但每次写入都不超过缓冲大小
+        let mut writer = RecordingWriter::default();
+        write_data(&mut writer, &data).unwrap();
+        assert!(writer.total > 10 * WRITE_BUFFER_SIZE, "{}", writer.total);
+        assert!(
+            writer.largest_write <= WRITE_BUFFER_SIZE,
+            "{}",
+            writer.largest_write
+        );
+
+        let dir = mock_store::temp_dir();
+        let storage = LocalStorage::new(dir.join("passwords.json"), &no_backups());
+        storage.save(&data).await.unwrap();
+        let loaded = storage.load().await.unwrap();
+
+        assert_eq!(loaded.passwords.len(), 10_000);
+        assert_eq!(loaded.passwords["id-9999"].title, "entry 9999");
+        assert_eq!(
+            std::fs::metadata(dir.join("passwords.json")).unwrap().len() as usize,
+            writer.total
+        );
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn lenient_load_skips_malformed_entries() {
+        let dir = mock_store::temp_dir();
+        let storage = LocalStorage::new(dir.join("passwords.json"), &no_backups());
+
+        let good = large_vault(1).passwords.remove("id-0").unwrap();
+        let mut bad = serde_json::to_value(&good).unwrap();
+        bad.as_object_mut().unwrap().remove("encrypted_password");
+        let content = serde_json::json!({
+            "metadata": { "version": "1.0.0", "last_sync": chrono::Utc::now(), "password_count": 2 },
+            "passwords": { "id-0": good, "broken": bad },
+        });
+        tokio::fs::write(dir.join("passwords.json"), content.to_string())
+            .await
+            .unwrap();
+
+        // 严格加载整体失败
+        assert!(storage.load().await.is_err());
+
+        let (data, warnings) = storage.load_lenient().await.unwrap();
+        assert_eq!(data.passwords.len(), 1);
+        assert_eq!(data.metadata.password_count, 1);
+        assert_eq!(data.passwords["id-0"].title, "entry 0");
+        assert_eq!(warnings.len(), 1);
+        assert_eq!(warnings[0].id, "broken");
+        assert!(
+            warnings[0].error.contains("encrypted_password"),
+            "{}",
+            warnings[0].error
+        );
+
+        // 不是合法JSON时仍然报错
+        tokio::fs::write(dir.join("passwords.json"), b"{\"passwords\": ")
+            .await
+            .unwrap();
+        assert!(storage.load_lenient().await.is_err());
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn malformed_nonce_is_reported_on_load() {
+        let dir = mock_store::temp_dir();
+        let storage = LocalStorage::new(dir.join("passwords.json"), &no_backups());
+
+        let mut data = large_vault(2);
+        data.passwords
+            .get_mut("id-1")
+            .unwrap()
+            .encrypted_password
+            .nonce
+            .truncate(3);
+        storage.save(&data).await.unwrap();
+
+        let err = storage.load().await.unwrap_err();
+        match err.downcast_ref::<StorageError>() {
+            Some(StorageError::InvalidEntry { id, reason }) => {
+                assert_eq!(id, "id-1");
+                assert!(reason.contains("encrypted_password"), "{}", reason);
+            }
+            other => panic!("unexpected error: {:?}", other),
+        }
+
+        // 宽松加载跳过损坏的条目
+        let (loaded, warnings) = storage.load_lenient().await.unwrap();
+        assert_eq!(loaded.passwords.len(), 1);
+        assert!(loaded.passwords.contains_key("id-0"));
+        assert_eq!(warnings.len(), 1);
+        assert_eq!(warnings[0].id, "id-1");
+        assert!(warnings[0].error.contains("nonce"), "{}", warnings[0].error);
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn keeps_at_most_max_backups() {
+        let dir = mock_store::temp_dir();
+        let config = LocalStorageConfig {
+            max_backups: 2,
+            ..Default::default()
+        };
+        let storage = LocalStorage::new(dir.join("passwords.json"), &config);
+
+        for i in 1..=4 {
+            let mut data = StorageData::new();
+            data.metadata.version = format!("save-{}", i);
+            storage.save(&data).await.unwrap();
+        }
+
+        let backups = std::fs::read_dir(&dir)
+            .unwrap()
+            .filter_map(|e| e.ok())
+            .filter(|e| {
+                e.file_name()
+                    .to_string_lossy()
+                    .starts_with("passwords.json.")
+            })
+            .count();
+        assert_eq!(backups, 2);
+        assert!(!storage.backup_path(3).exists());
+
+        // 最旧的 save-1 已被丢弃
+        let load = |index| LocalStorage::new(storage.backup_path(index), &config);
+        assert_eq!(load(1).load().await.unwrap().metadata.version, "save-3");
+        assert_eq!(load(2).load().await.unwrap().metadata.version, "save-2");
+        assert_eq!(storage.load().await.unwrap().metadata.version, "save-4");
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
 }
@@ -0,0 +1,178 @@
+use super::{Storage, StorageData, StorageTarget, VersionInfo};
+use crate::crypto::{self, KdfStrength};
+use crate::password::{Password, PasswordCreateRequest};
+use anyhow::{Result, anyhow};
+use async_trait::async_trait;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use tokio::sync::Notify;
+
+/// 测试用的临时目录
+pub fn temp_dir() -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("passwd-test-{}", uuid::Uuid::new_v4()));
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+/// 测试用的新建条目请求，使用快速KDF
+pub fn create_request(title: &str) -> PasswordCreateRequest {
+    PasswordCreateRequest {
+        title: title.to_string(),
+        description: String::new(),
+        tags: vec![],
+        username: "alice".to_string(),
+        password: "pw".to_string(),
+        url: None,
+        key: "key".to_string(),
+        expires_at: None,
+        totp_secret: None,
+        notes: None,
+        client_id: None,
+        kdf_strength: KdfStrength::Fast,
+    }
+}
+
+/// 测试用的条目，密码 `pw` 用 `key` 加密
+pub fn password_entry(title: &str) -> Password {
+    Password::new(
+        create_request(title),
+        crypto::encrypt_with_password("pw", "key").unwrap(),
+    )
+}
+
+/// 模拟 GitHub contents API 返回的文件内容
+pub fn github_file_body(data: &StorageData, sha: &str) -> String {
+    use base64::{Engine as _, engine::general_purpose};
+
+    let content = serde_json::to_string(data).unwrap();
+    serde_json::json!({
+        "content": general_purpose::STANDARD.encode(content),
+        "encoding": "base64",
+        "sha": sha,
+        "size": 0,
+        "name": "passwords.json",
+        "path": "passwords.json",
+    })
+    .to_string()
+}
+
+/// 测试用的内存存储
+pub struct MockStorage {
+    pub data: Mutex<StorageData>,
+    pub save_count: AtomicUsize,
+    pub fail: AtomicBool,
+    pub target: StorageTarget,
+    /// 历史版本，从新到旧
+    pub versions: Mutex<Vec<(VersionInfo, StorageData)>>,
+    /// 设置后每次保存都要等到通知才完成
+    pub save_gate: Mutex<Option<Arc<Notify>>>,
+}
+
+impl MockStorage {
+    pub fn new() -> Self {
+        Self::with_data(StorageData::new())
+    }
+
+    pub fn with_data(data: StorageData) -> Self {
+        Self {
+            data: Mutex::new(data),
+            save_count: AtomicUsize::new(0),
+            fail: AtomicBool::new(false),
+            target: StorageTarget::Local,
+            versions: Mutex::new(Vec::new()),
+            save_gate: Mutex::new(None),
+        }
+    }
+
+    /// 模拟其它类型的存储点，默认为本地存储
+    pub fn with_target(mut self, target: StorageTarget) -> Self {
+        self.target = target;
+        self
+    }
+
+    pub fn snapshot(&self) -> StorageData {
+        self.data.lock().unwrap().clone()
+    }
+
+    /// 让之后的读写都返回错误，模拟存储点不可达
+    pub fn set_fail(&self, fail: bool) {
+        self.fail.store(fail, Ordering::SeqCst);
+    }
+
+    /// 记录一个历史版本，新记录的版本排在最前
+    pub fn add_version(&self, sha: &str, data: StorageData) {
+        let info = VersionInfo {
+            sha: sha.to_string(),
+            date: chrono::Utc::now(),
+            message: format!("Update passwords - {} items", data.passwords.len()),
+        };
+        self.versions.lock().unwrap().insert(0, (info, data));
+    }
+
+    /// 让之后的保存阻塞，直到返回的 `Notify` 每通知一次放行一次，模拟耗时的网络写入
+    pub fn block_saves(&self) -> Arc<Notify> {
+        let gate = Arc::new(Notify::new());
+        *self.save_gate.lock().unwrap() = Some(gate.clone());
+        gate
+    }
+
+    pub fn saves(&self) -> usize {
+        self.save_count.load(Ordering::SeqCst)
+    }
+
+    fn check(&self) -> Result<()> {
+        if self.fail.load(Ordering::SeqCst) {
+            Err(anyhow!("mock storage unavailable"))
+        } else {
+            Ok(())
+        }
+    }
+}
+
+#[async_trait]
+impl Storage for MockStorage {
+    async fn load(&self) -> Result<StorageData> {
+        self.check()?;
+        Ok(self.snapshot())
+    }
+
+    async fn save(&self, data: &StorageData) -> Result<()> {
+        let gate = self.save_gate.lock().unwrap().clone();
+        if let Some(gate) = gate {
+            gate.notified().await;
+        }
+        self.check()?;
+        *self.data.lock().unwrap() = data.clone();
+        self.save_count.fetch_add(1, Ordering::SeqCst);
+        Ok(())
+    }
+
+    async fn test_connection(&self) -> Result<()> {
+        self.check()
+    }
+
+    async fn has_encrypted_data(&self) -> Result<bool> {
+        Ok(!self.snapshot().passwords.is_empty())
+    }
+
+    fn target(&self) -> StorageTarget {
+        self.target
+    }
+
+    async fn list_versions(&self) -> Result<Vec<VersionInfo>> {
+        self.check()?;
+        let versions = self.versions.lock().unwrap();
+        Ok(versions.iter().map(|(info, _)| info.clone()).collect())
+    }
+
+    async fn load_version(&self, sha: &str) -> Result<StorageData> {
+        self.check()?;
+        let versions = self.versions.lock().unwrap();
+        versions
+            .iter()
+            .find(|(info, _)| info.sha == sha)
+            .map(|(_, data)| data.clone())
+            .ok_or_else(|| anyhow!("version {} not found", sha))
+    }
+}
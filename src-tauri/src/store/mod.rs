@@ -1,34 +1,102 @@
+use crate::error::PasswordError;
 use crate::password::Password;
 use anyhow::Result;
 use async_trait::async_trait;
 use chrono::Utc;
 use serde::{Deserialize, Serialize};
-use std::{collections::HashMap, fmt::Display};
+use std::{collections::HashMap, fmt::Display, str::FromStr};
 
 pub mod github_store;
 pub mod local_store;
+#[cfg(test)]
+pub mod mock_store;
+pub mod s3_store;
+pub mod sqlite_store;
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+/// 存储点类型，序列化、`Display` 和 `FromStr` 都使用 `name` 返回的小写名称
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
 pub enum StorageTarget {
     Local,
     GitHub,
+    S3,
     // All, // 查询时使用，表示查询所有存储点
 }
 
 impl Display for StorageTarget {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+impl FromStr for StorageTarget {
+    type Err = PasswordError;
+
+    /// 解析类型名，忽略大小写；未知的名称返回 `PasswordError::InvalidTarget`
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        Self::ALL
+            .into_iter()
+            .find(|t| t.name().eq_ignore_ascii_case(s.trim()))
+            .ok_or_else(|| PasswordError::InvalidTarget(s.to_string()))
+    }
+}
+
+impl StorageTarget {
+    pub const ALL: [StorageTarget; 3] = [
+        StorageTarget::Local,
+        StorageTarget::GitHub,
+        StorageTarget::S3,
+    ];
+
+    /// 类型名，也是该类型默认存储点的id
+    pub fn name(&self) -> &'static str {
         match self {
-            StorageTarget::Local => write!(f, "Local"),
-            StorageTarget::GitHub => write!(f, "GitHub"),
-            // StorageTarget::All =>
+            StorageTarget::Local => "local",
+            StorageTarget::GitHub => "github",
+            StorageTarget::S3 => "s3",
         }
     }
+
+    /// 解析前端传入的类型名，见 `FromStr`
+    pub fn parse(s: &str) -> Result<Self> {
+        Ok(s.parse()?)
+    }
+}
+
+/// 存储点的唯一标识，同一类型可以有多个存储点
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct StorageId(pub String);
+
+impl StorageId {
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl From<&str> for StorageId {
+    fn from(id: &str) -> Self {
+        StorageId(id.to_string())
+    }
+}
+
+impl Display for StorageId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.0)
+    }
 }
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct StorageMetadata {
     pub version: String,
     pub last_sync: chrono::DateTime<chrono::Utc>,
     pub password_count: usize,
+    /// 每次保存加1，用于发现基于旧数据的写入
+    ///
+    /// 保存时传入的是写入后的新版本号；能检查的存储点在已保存的版本号不小于它时
+    /// 返回 `StorageError::Conflict`。0 表示旧数据或不参与检查的写入
+    #[serde(default)]
+    pub revision: u64,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -45,12 +113,178 @@ impl StorageData {
                 version: "1".to_string(),
                 last_sync: Utc::now(),
                 password_count: 0,
+                revision: 0,
             },
             passwords: HashMap::new(),
         }
     }
+
+    /// 以 `stored` 为当前已保存的版本号，这次写入是否基于旧数据
+    pub fn is_stale(&self, stored: u64) -> bool {
+        self.metadata.revision != 0 && self.metadata.revision <= stored
+    }
+}
+
+impl StorageData {
+    /// 合并其他数据，同一id保留 `updated_at` 较新的一份
+    pub fn merge(&mut self, passwords: impl IntoIterator<Item = Password>) {
+        for password in passwords {
+            match self.passwords.get(&password.id) {
+                Some(existing) if existing.updated_at >= password.updated_at => {}
+                _ => {
+                    self.passwords.insert(password.id.clone(), password);
+                }
+            }
+        }
+        self.metadata.password_count = self.passwords.len();
+    }
+}
+
+/// 宽松加载时跳过的条目
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct LoadWarning {
+    /// `passwords` 中该条目的key
+    pub id: String,
+    pub error: String,
+}
+
+/// 存储点保存过的一个历史版本
+#[derive(Debug, Clone, Serialize)]
+pub struct VersionInfo {
+    /// 版本标识，GitHub 中为提交的sha
+    pub sha: String,
+    pub date: chrono::DateTime<Utc>,
+    pub message: String,
+}
+
+impl StorageData {
+    /// 逐条解析数据文件，跳过无法解析的条目而不是整体失败
+    ///
+    /// 文件不是合法JSON、或缺少 `passwords` 对象时仍然返回错误；
+    /// `metadata` 无法解析时使用默认值，条目数按实际解析出的条目计算
+    pub fn from_json_lenient(content: &str) -> Result<(StorageData, Vec<LoadWarning>)> {
+        let mut root: serde_json::Value = serde_json::from_str(content)?;
+
+        let entries = match root.get_mut("passwords").map(serde_json::Value::take) {
+            Some(serde_json::Value::Object(entries)) => entries,
+            _ => return Err(anyhow::anyhow!("数据文件缺少 passwords 对象")),
+        };
+
+        let mut data = StorageData::new();
+        if let Some(metadata) = root
+            .get_mut("metadata")
+            .map(serde_json::Value::take)
+            .and_then(|m| serde_json::from_value::<StorageMetadata>(m).ok())
+        {
+            data.metadata = metadata;
+        }
+
+        let mut warnings = Vec::new();
+        for (id, entry) in entries {
+            match serde_json::from_value::<Password>(entry) {
+                Ok(password) => match invalid_reason(&password) {
+                    None => {
+                        data.passwords.insert(id, password);
+                    }
+                    Some(reason) => {
+                        let error = StorageError::InvalidEntry {
+                            id: id.clone(),
+                            reason,
+                        };
+                        warnings.push(LoadWarning {
+                            id,
+                            error: error.to_string(),
+                        });
+                    }
+                },
+                Err(e) => warnings.push(LoadWarning {
+                    id,
+                    error: e.to_string(),
+                }),
+            }
+        }
+        data.metadata.password_count = data.passwords.len();
+
+        Ok((data, warnings))
+    }
+
+    /// 检查每个条目中加密数据的nonce长度，返回按id排序后第一个有问题的条目
+    ///
+    /// 手动编辑或损坏的数据文件在加载时就报错，而不是等到解密时才失败
+    pub fn validate(&self) -> Result<()> {
+        let mut ids: Vec<&String> = self.passwords.keys().collect();
+        ids.sort();
+
+        for id in ids {
+            if let Some(reason) = invalid_reason(&self.passwords[id]) {
+                return Err(StorageError::InvalidEntry {
+                    id: id.clone(),
+                    reason,
+                }
+                .into());
+            }
+        }
+        Ok(())
+    }
 }
 
+// 条目中nonce长度与算法不符的加密字段，没有问题时返回None
+fn invalid_reason(password: &Password) -> Option<String> {
+    let fields = [
+        ("encrypted_password", Some(&password.encrypted_password)),
+        ("totp_secret", password.totp_secret.as_ref()),
+        ("encrypted_notes", password.encrypted_notes.as_ref()),
+    ]
+    .into_iter()
+    .filter_map(|(name, data)| Some((name.to_string(), data?)))
+    .chain(
+        password
+            .attachments
+            .iter()
+            .map(|a| (format!("附件 {}", a.name), &a.data)),
+    );
+
+    for (name, data) in fields {
+        let expected = data.algo.nonce_len();
+        if data.nonce.len() != expected {
+            return Some(format!(
+                "{} 的nonce长度为 {}，{:?} 需要 {}",
+                name,
+                data.nonce.len(),
+                data.algo,
+                expected
+            ));
+        }
+    }
+    None
+}
+
+/// 调用方需要区分处理的存储错误
+///
+/// 通过 `anyhow::Error::downcast` 取出
+#[derive(Debug)]
+pub enum StorageError {
+    /// 远端数据已被其他设备修改，携带最新的远端数据以便合并
+    Conflict(StorageData),
+    /// 数据文件中的条目已损坏，如nonce长度不正确；开启宽松加载可以跳过该条目
+    InvalidEntry { id: String, reason: String },
+}
+
+impl Display for StorageError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            StorageError::Conflict(_) => {
+                write!(f, "remote data has been changed by another device")
+            }
+            StorageError::InvalidEntry { id, reason } => {
+                write!(f, "条目 {} 已损坏：{}", id, reason)
+            }
+        }
+    }
+}
+
+impl std::error::Error for StorageError {}
+
 #[async_trait]
 pub trait Storage: Send + Sync {
     async fn load(&self) -> Result<StorageData>;
@@ -58,4 +292,150 @@ pub trait Storage: Send + Sync {
     // #[allow(dead_code)]
     async fn test_connection(&self) -> Result<()>;
     async fn has_encrypted_data(&self) -> Result<bool>;
+    /// 存储点类型，冲突策略据此选择以哪个存储点为准
+    fn target(&self) -> StorageTarget;
+
+    /// 宽松加载：跳过无法解析的条目，并返回跳过的原因
+    ///
+    /// 默认等同于 `load`，只有能逐条解析的存储点需要覆盖
+    async fn load_lenient(&self) -> Result<(StorageData, Vec<LoadWarning>)> {
+        Ok((self.load().await?, Vec::new()))
+    }
+
+    /// 写入单个条目
+    ///
+    /// 默认读取全部数据、修改后整体保存；支持按条目写入的存储点可以覆盖
+    async fn upsert(&self, id: &str, password: &Password) -> Result<()> {
+        load_modify_save(self, |data| {
+            data.passwords.insert(id.to_string(), password.clone());
+        })
+        .await
+    }
+
+    /// 删除单个条目，条目不存在时不报错
+    ///
+    /// 默认读取全部数据、修改后整体保存；支持按条目删除的存储点可以覆盖
+    async fn remove(&self, id: &str) -> Result<()> {
+        load_modify_save(self, |data| {
+            data.passwords.remove(id);
+        })
+        .await
+    }
+
+    /// 列出历史版本，从新到旧
+    ///
+    /// 默认不支持，只有保留历史的存储点（如 GitHub）需要覆盖
+    async fn list_versions(&self) -> Result<Vec<VersionInfo>> {
+        Err(anyhow::anyhow!("{} 存储点不支持历史版本", self.target()))
+    }
+
+    /// 读取某个历史版本的数据，`sha` 来自 `list_versions`
+    async fn load_version(&self, sha: &str) -> Result<StorageData> {
+        let _ = sha;
+        Err(anyhow::anyhow!("{} 存储点不支持历史版本", self.target()))
+    }
+
+    /// 把数据文件移动到新路径：先在新路径写入，再删除旧文件
+    ///
+    /// 默认不支持，只有按路径存储文件的远端存储点（如 GitHub）需要覆盖
+    async fn move_file(&self, new_path: &str) -> Result<()> {
+        let _ = new_path;
+        Err(anyhow::anyhow!("{} 存储点不支持移动文件", self.target()))
+    }
+}
+
+/// 保存冲突时最多尝试的次数
+pub const MAX_SAVE_ATTEMPTS: usize = 16;
+
+// 读取、修改后整体保存；数据已被其他写入修改时，在最新的数据上重新修改后重试
+async fn load_modify_save<S, F>(storage: &S, modify: F) -> Result<()>
+where
+    S: Storage + ?Sized,
+    F: Fn(&mut StorageData) + Send,
+{
+    let mut data = storage.load().await?;
+    let mut attempts = 0;
+
+    loop {
+        modify(&mut data);
+        data.metadata.password_count = data.passwords.len();
+        data.metadata.revision += 1;
+        attempts += 1;
+
+        match storage.save(&data).await {
+            Err(e) => match e.downcast::<StorageError>() {
+                Ok(StorageError::Conflict(remote)) if attempts < MAX_SAVE_ATTEMPTS => data = remote,
+                Ok(conflict) => return Err(conflict.into()),
+                Err(e) => return Err(e),
+            },
+            ok => return ok,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn storage_target_round_trips_through_strings() {
+        for target in StorageTarget::ALL {
+            assert_eq!(StorageTarget::parse(&target.to_string()).unwrap(), target);
+            let json = serde_json::to_string(&target).unwrap();
+            assert_eq!(json, format!("\"{}\"", target));
+            assert_eq!(
+                serde_json::from_str::<StorageTarget>(&json).unwrap(),
+                target
+            );
+        }
+        assert_eq!(
+            StorageTarget::parse("GitHub").unwrap(),
+            StorageTarget::GitHub
+        );
+
+        let err = StorageTarget::parse("ftp").unwrap_err();
+        assert_eq!(
+            err.downcast_ref::<PasswordError>(),
+            Some(&PasswordError::InvalidTarget("ftp".to_string()))
+        );
+    }
+
+    #[test]
+    fn validate_checks_every_encrypted_field() {
+        let encrypted = crate::crypto::encrypt_with_password("pw", "key").unwrap();
+        let mut password = Password::new(
+            crate::password::PasswordCreateRequest {
+                title: "a".to_string(),
+                description: String::new(),
+                tags: Vec::new(),
+                username: "alice".to_string(),
+                password: "pw".to_string(),
+                url: None,
+                key: "key".to_string(),
+                expires_at: None,
+                totp_secret: None,
+                notes: None,
+                client_id: None,
+                kdf_strength: crate::crypto::KdfStrength::Fast,
+            },
+            encrypted.clone(),
+        );
+        let mut data = StorageData::new();
+        data.merge([password.clone()]);
+        data.validate().unwrap();
+
+        let mut totp = encrypted;
+        totp.nonce.push(0);
+        password.totp_secret = Some(totp);
+        data.passwords.insert(password.id.clone(), password.clone());
+
+        let err = data.validate().unwrap_err();
+        match err.downcast_ref::<StorageError>() {
+            Some(StorageError::InvalidEntry { id, reason }) => {
+                assert_eq!(id, &password.id);
+                assert!(reason.contains("totp_secret"), "{}", reason);
+            }
+            other => panic!("unexpected error: {:?}", other),
+        }
+    }
 }
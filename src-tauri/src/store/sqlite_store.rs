@@ -0,0 +1,322 @@
+use super::{Storage, StorageData, StorageError, StorageMetadata, StorageTarget};
+use crate::password::Password;
+use anyhow::{Result, anyhow};
+use async_trait::async_trait;
+use rusqlite::{Connection, OptionalExtension, params};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+
+/// 基于 SQLite 的本地存储
+///
+/// 每个条目单独一行，保存时只写入有变化的条目，避免每次重写整个文件
+pub struct SqliteStorage {
+    data_path: PathBuf,
+    conn: Arc<Mutex<Option<Connection>>>,
+}
+
+const SCHEMA: &str = "
+    CREATE TABLE IF NOT EXISTS passwords (
+        id   TEXT PRIMARY KEY,
+        data TEXT NOT NULL
+    );
+    CREATE TABLE IF NOT EXISTS metadata (
+        key   TEXT PRIMARY KEY,
+        value TEXT NOT NULL
+    );
+";
+
+// 按条目写入后把版本号加1
+const BUMP_REVISION: &str = "
+    INSERT INTO metadata (key, value) VALUES ('revision', '1')
+    ON CONFLICT(key) DO UPDATE SET value = CAST(value AS INTEGER) + 1
+";
+
+impl SqliteStorage {
+    pub fn new(data_path: PathBuf) -> Self {
+        Self {
+            data_path,
+            conn: Arc::new(Mutex::new(None)),
+        }
+    }
+
+    // 在阻塞线程中使用连接，首次使用时打开数据库并建表
+    async fn with_conn<T, F>(&self, f: F) -> Result<T>
+    where
+        T: Send + 'static,
+        F: FnOnce(&mut Connection) -> Result<T> + Send + 'static,
+    {
+        let conn = self.conn.clone();
+        let data_path = self.data_path.clone();
+
+        tokio::task::spawn_blocking(move || {
+            let mut guard = conn.lock().map_err(|_| anyhow!("SQLite连接锁已损坏"))?;
+            if guard.is_none() {
+                if let Some(parent) = data_path.parent() {
+                    std::fs::create_dir_all(parent)?;
+                }
+                let conn = Connection::open(&data_path)
+                    .map_err(|e| anyhow!("打开数据库失败（{}）：{}", data_path.display(), e))?;
+                conn.execute_batch(SCHEMA)?;
+                *guard = Some(conn);
+            }
+            f(guard.as_mut().unwrap())
+        })
+        .await?
+    }
+}
+
+fn read_data(conn: &Connection) -> Result<StorageData> {
+    let meta = |key: &str| -> Result<Option<String>> {
+        Ok(conn
+            .query_row(
+                "SELECT value FROM metadata WHERE key = ?1",
+                params![key],
+                |row| row.get(0),
+            )
+            .optional()?)
+    };
+
+    let mut passwords = HashMap::new();
+    let mut stmt = conn.prepare("SELECT data FROM passwords")?;
+    for row in stmt.query_map([], |row| row.get::<_, String>(0))? {
+        let password: Password = serde_json::from_str(&row?)?;
+        passwords.insert(password.id.clone(), password);
+    }
+
+    let mut data = StorageData::new();
+    if let Some(version) = meta("version")? {
+        data.metadata.version = version;
+    }
+    if let Some(last_sync) = meta("last_sync")? {
+        data.metadata.last_sync = last_sync.parse()?;
+    }
+    if let Some(revision) = meta("revision")? {
+        data.metadata.revision = revision.parse()?;
+    }
+    data.metadata.password_count = passwords.len();
+    data.passwords = passwords;
+    Ok(data)
+}
+
+// 在一个事务中把数据库更新为 `data`：新增或修改的条目写入，多余的条目删除
+// 返回写入和删除的行数；`data` 基于旧版本时返回 `StorageError::Conflict`
+fn write_diff(conn: &mut Connection, data: &StorageData) -> Result<usize> {
+    let tx = conn.transaction()?;
+    let mut changed = 0;
+
+    let stored_revision = tx
+        .query_row(
+            "SELECT value FROM metadata WHERE key = 'revision'",
+            [],
+            |row| row.get::<_, String>(0),
+        )
+        .optional()?
+        .map(|revision| revision.parse::<u64>())
+        .transpose()?
+        .unwrap_or(0);
+    if data.is_stale(stored_revision) {
+        return Err(StorageError::Conflict(read_data(&tx)?).into());
+    }
+
+    let existing: HashMap<String, String> = {
+        let mut stmt = tx.prepare("SELECT id, data FROM passwords")?;
+        stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?
+            .collect::<rusqlite::Result<_>>()?
+    };
+
+    for (id, password) in &data.passwords {
+        let json = serde_json::to_string(password)?;
+        if existing.get(id) == Some(&json) {
+            continue;
+        }
+        changed += tx.execute(
+            "INSERT INTO passwords (id, data) VALUES (?1, ?2)
+             ON CONFLICT(id) DO UPDATE SET data = excluded.data",
+            params![id, json],
+        )?;
+    }
+
+    for id in existing.keys() {
+        if !data.passwords.contains_key(id) {
+            changed += tx.execute("DELETE FROM passwords WHERE id = ?1", params![id])?;
+        }
+    }
+
+    let metadata: [(&str, String); 3] = [
+        ("version", data.metadata.version.clone()),
+        ("last_sync", data.metadata.last_sync.to_rfc3339()),
+        ("revision", data.metadata.revision.to_string()),
+    ];
+    for (key, value) in metadata {
+        tx.execute(
+            "INSERT INTO metadata (key, value) VALUES (?1, ?2)
+             ON CONFLICT(key) DO UPDATE SET value = excluded.value",
+            params![key, value],
+        )?;
+    }
+
+    tx.commit()?;
+    Ok(changed)
+}
+
+#[async_trait]
+impl Storage for SqliteStorage {
+    async fn load(&self) -> Result<StorageData> {
+        if !self.data_path.exists() {
+            return Ok(StorageData {
+                metadata: StorageMetadata {
+                    version: "1.0.0".to_string(),
+                    last_sync: chrono::Utc::now(),
+                    password_count: 0,
+                    revision: 0,
+                },
+                passwords: HashMap::new(),
+            });
+        }
+
+        self.with_conn(|conn| read_data(conn)).await
+    }
+
+    async fn save(&self, data: &StorageData) -> Result<()> {
+        let data = data.clone();
+        self.with_conn(move |conn| write_diff(conn, &data).map(|_| ()))
+            .await
+    }
+
+    async fn test_connection(&self) -> Result<()> {
+        self.with_conn(|conn| {
+            conn.query_row("SELECT 1", [], |_| Ok(()))?;
+            Ok(())
+        })
+        .await
+    }
+
+    async fn has_encrypted_data(&self) -> Result<bool> {
+        Ok(!self.load().await?.passwords.is_empty())
+    }
+
+    fn target(&self) -> StorageTarget {
+        StorageTarget::Local
+    }
+
+    async fn upsert(&self, id: &str, password: &Password) -> Result<()> {
+        let id = id.to_string();
+        let json = serde_json::to_string(password)?;
+        self.with_conn(move |conn| {
+            let tx = conn.transaction()?;
+            tx.execute(
+                "INSERT INTO passwords (id, data) VALUES (?1, ?2)
+                 ON CONFLICT(id) DO UPDATE SET data = excluded.data",
+                params![id, json],
+            )?;
+            tx.execute(BUMP_REVISION, [])?;
+            tx.commit()?;
+            Ok(())
+        })
+        .await
+    }
+
+    async fn remove(&self, id: &str) -> Result<()> {
+        let id = id.to_string();
+        self.with_conn(move |conn| {
+            let tx = conn.transaction()?;
+            tx.execute("DELETE FROM passwords WHERE id = ?1", params![id])?;
+            tx.execute(BUMP_REVISION, [])?;
+            tx.commit()?;
+            Ok(())
+        })
+        .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::store::mock_store::{self, password_entry};
+
+    #[tokio::test]
+    async fn load_save_round_trip() {
+        let path = mock_store::temp_dir().join("passwords.db");
+        let storage = SqliteStorage::new(path.clone());
+        assert!(storage.load().await.unwrap().passwords.is_empty());
+
+        let mut data = StorageData::new();
+        data.metadata.version = "sqlite".to_string();
+        data.merge([password_entry("GitHub"), password_entry("Mail")]);
+        storage.save(&data).await.unwrap();
+
+        // 重新打开数据库读取
+        let loaded = SqliteStorage::new(path).load().await.unwrap();
+        assert_eq!(loaded.metadata.version, "sqlite");
+        assert_eq!(loaded.metadata.password_count, 2);
+        assert_eq!(
+            loaded.metadata.last_sync.timestamp_micros(),
+            data.metadata.last_sync.timestamp_micros()
+        );
+        for (id, password) in &data.passwords {
+            assert_eq!(loaded.passwords[id].title, password.title);
+        }
+    }
+
+    // 同样的单条目操作，默认的整体读写和 SQLite 的按行写入结果一致
+    #[tokio::test]
+    async fn upsert_and_remove_match_default_fallback() {
+        let fallback = mock_store::MockStorage::new();
+        let sqlite = SqliteStorage::new(mock_store::temp_dir().join("passwords.db"));
+
+        let github = password_entry("GitHub");
+        let mail = password_entry("Mail");
+        let mut renamed = github.clone();
+        renamed.title = "GitHub Enterprise".to_string();
+
+        let storages: [&dyn Storage; 2] = [&fallback, &sqlite];
+        for storage in storages {
+            storage.upsert(&github.id, &github).await.unwrap();
+            storage.upsert(&mail.id, &mail).await.unwrap();
+            storage.upsert(&github.id, &renamed).await.unwrap();
+            storage.remove(&mail.id).await.unwrap();
+            // 删除不存在的条目不报错
+            storage.remove("missing").await.unwrap();
+        }
+
+        let expected = fallback.load().await.unwrap();
+        let actual = sqlite.load().await.unwrap();
+        assert_eq!(expected.passwords.len(), 1);
+        assert_eq!(expected.metadata.password_count, 1);
+        assert_eq!(actual.passwords.len(), 1);
+        assert_eq!(
+            serde_json::to_value(&expected.passwords).unwrap(),
+            serde_json::to_value(&actual.passwords).unwrap()
+        );
+        assert_eq!(actual.passwords[&github.id].title, "GitHub Enterprise");
+    }
+
+    #[tokio::test]
+    async fn save_only_writes_changed_rows() {
+        let path = mock_store::temp_dir().join("passwords.db");
+        let mut conn = Connection::open(&path).unwrap();
+        conn.execute_batch(SCHEMA).unwrap();
+
+        let github = password_entry("GitHub");
+        let mail = password_entry("Mail");
+        let bank = password_entry("Bank");
+        let mut data = StorageData::new();
+        data.merge([github.clone(), mail.clone(), bank.clone()]);
+        assert_eq!(write_diff(&mut conn, &data).unwrap(), 3);
+
+        // 没有变化时不写入任何条目
+        assert_eq!(write_diff(&mut conn, &data).unwrap(), 0);
+
+        // 修改一条、删除一条
+        data.passwords.get_mut(&github.id).unwrap().title = "GitHub Enterprise".to_string();
+        data.passwords.remove(&bank.id);
+        assert_eq!(write_diff(&mut conn, &data).unwrap(), 2);
+
+        let loaded = read_data(&conn).unwrap();
+        assert_eq!(loaded.passwords.len(), 2);
+        assert_eq!(loaded.passwords[&github.id].title, "GitHub Enterprise");
+        assert_eq!(loaded.passwords[&mail.id].title, "Mail");
+        assert!(!loaded.passwords.contains_key(&bank.id));
+    }
+}